@@ -0,0 +1,55 @@
+use chrono::DateTime;
+use serde::Serialize;
+use crate::database::{Database, SCHEMA_VERSION};
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Short hash of the commit this was built from ("unknown" outside a git checkout)
+pub const GIT_COMMIT: &str = env!("THE_MIND_GIT_COMMIT");
+// Unix seconds when build.rs last ran
+const BUILD_EPOCH: &str = env!("THE_MIND_BUILD_EPOCH");
+
+/// Result of get_app_info
+#[derive(Debug, Clone, Serialize)]
+pub struct AppInfo {
+    pub version: String,
+    pub git_commit: String,
+    /// RFC 3339
+    pub build_timestamp: String,
+    /// Schema version this build writes
+    pub schema_version: i64,
+    /// Schema version of the open database; below schema_version only when opened read-only
+    /// before migrating
+    pub database_schema_version: Option<i64>,
+    /// OS and architecture, e.g. "windows-x86_64"
+    pub platform: String,
+}
+
+pub fn build_timestamp() -> String {
+    BUILD_EPOCH.parse::<i64>().ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map(|at| at.to_rfc3339())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+pub fn collect(db: &Database) -> AppInfo {
+    AppInfo {
+        version: VERSION.to_string(),
+        git_commit: GIT_COMMIT.to_string(),
+        build_timestamp: build_timestamp(),
+        schema_version: SCHEMA_VERSION,
+        database_schema_version: db.stored_schema_version().ok(),
+        platform: platform(),
+    }
+}
+
+/// What `--version` prints: "the-mind 0.1.0 (1a2b3c4d5e6f, built 2025-01-01T00:00:00+00:00, schema 5, windows-x86_64)"
+pub fn version_line() -> String {
+    format!(
+        "the-mind {} ({}, built {}, schema {}, {})",
+        VERSION, GIT_COMMIT, build_timestamp(), SCHEMA_VERSION, platform()
+    )
+}
@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+use chrono::Utc;
+use crate::database::{Database, AUTO_CONNECTION_KIND, LINK_CONNECTION_KIND};
+use crate::utils::{extract_terms_with, extract_urls, is_phrase, link_key, shared_keywords, snippet, KeywordConfig};
+use crate::Thought;
+
+// Settings keys (also readable/writable through get_setting/set_setting)
+pub const SETTING_ENABLED: &str = "auto_connect_enabled";
+pub const SETTING_MIN_KEYWORDS: &str = "auto_connect_min_keywords";
+pub const SETTING_MAX_PER_THOUGHT: &str = "auto_connect_max_per_thought";
+pub const SETTING_MODE: &str = "auto_connect_mode";
+pub const SETTING_MAX_CONNECTIONS: &str = "max_connections_per_thought";
+
+/// auto_connect_mode value that queues qualifying pairs as suggestions instead of connecting them
+pub const MODE_SUGGEST: &str = "suggest";
+
+// A phrase's IDF weight is multiplied by this. A phrase is never more common than its
+// rarer word, so with the squared weights in the overlap score a shared phrase counts
+// for more than its two words shared separately (1.5² > 2).
+const PHRASE_BOOST: f64 = 1.5;
+
+/// Tunables for keyword-overlap auto-connection
+#[derive(Debug, Clone)]
+pub struct AutoConnectConfig {
+    pub enabled: bool,
+    /// Shared keywords required before two thoughts are linked
+    pub min_keywords: usize,
+    /// Keep only this many of the highest-overlap candidates per new thought
+    pub max_per_thought: usize,
+    /// Store candidates as connection suggestions for review rather than connecting them
+    pub suggest: bool,
+    /// Most connections any thought may have after an auto-connection pass (see
+    /// Database::insert_connections_capped)
+    pub max_connections: usize,
+    /// User stopwords, which never count as shared keywords
+    pub keywords: KeywordConfig,
+}
+
+impl Default for AutoConnectConfig {
+    fn default() -> Self {
+        AutoConnectConfig {
+            enabled: true,
+            min_keywords: 2,
+            max_per_thought: 10,
+            suggest: false,
+            max_connections: 20,
+            keywords: KeywordConfig::default(),
+        }
+    }
+}
+
+impl AutoConnectConfig {
+    /// Load from the settings table, using defaults for anything unset
+    pub fn load(db: &Database) -> Self {
+        let defaults = AutoConnectConfig::default();
+        AutoConnectConfig {
+            enabled: db.setting_or(SETTING_ENABLED, defaults.enabled),
+            min_keywords: db.setting_or(SETTING_MIN_KEYWORDS, defaults.min_keywords).max(1),
+            max_per_thought: db.setting_or(SETTING_MAX_PER_THOUGHT, defaults.max_per_thought),
+            suggest: db.setting_or(SETTING_MODE, String::new()).eq_ignore_ascii_case(MODE_SUGGEST),
+            max_connections: db.setting_or(SETTING_MAX_CONNECTIONS, defaults.max_connections).max(1),
+            keywords: KeywordConfig::load(db),
+        }
+    }
+}
+
+/// A thought an auto-connection pass linked to
+#[derive(Debug)]
+pub struct ConnectedThought {
+    pub snippet: String,
+    /// The keywords both thoughts contain
+    pub shared: Vec<String>,
+    /// Phrases both thoughts contain, as written in the new thought
+    pub phrases: Vec<String>,
+}
+
+/// What an auto-connection pass did for one thought
+#[derive(Debug, Default)]
+pub struct AutoConnectOutcome {
+    /// Every thought that was linked, strongest first
+    pub connected: Vec<ConnectedThought>,
+    /// In suggest mode, every thought a connection was suggested to instead, strongest first
+    pub suggested: Vec<ConnectedThought>,
+    /// Candidates that qualified but were dropped by the per-thought cap
+    pub suppressed: usize,
+    /// Candidates skipped because an endpoint was at max_connections with nothing weaker to replace
+    pub capped: usize,
+    /// Weaker automatic connections removed to make room under max_connections
+    pub evicted: usize,
+}
+
+// Strength of a "shares-link" connection: the same page is a strong hint the thoughts belong together
+const LINK_CONNECTION_STRENGTH: f64 = 0.8;
+
+/// Inverse document frequency: rare keywords weigh more than ones most thoughts contain
+pub fn idf(doc_count: i64, total_docs: i64) -> f64 {
+    ((total_docs as f64 + 1.0) / (doc_count as f64 + 1.0)).ln() + 1.0
+}
+
+/// Cosine similarity of two term sets (keywords and phrase keys) with every term weighted
+/// by its IDF, phrases boosted by PHRASE_BOOST (0..1). A phrase without a looked-up weight
+/// takes its rarer word's, which is what it would be at least.
+pub fn weighted_overlap(a: &HashSet<String>, b: &HashSet<String>, weights: &HashMap<String, f64>) -> f64 {
+    let word_weight = |k: &str| weights.get(k).copied().unwrap_or(1.0);
+    let weight = |k: &String| {
+        if !is_phrase(k) {
+            return word_weight(k);
+        }
+        let idf = weights.get(k).copied()
+            .unwrap_or_else(|| k.split(' ').map(word_weight).fold(1.0, f64::max));
+        idf * PHRASE_BOOST
+    };
+    let norm = |set: &HashSet<String>| set.iter().map(|k| weight(k).powi(2)).sum::<f64>().sqrt();
+    let shared: f64 = a.intersection(b).map(|k| weight(k).powi(2)).sum();
+    let denominator = norm(a) * norm(b);
+    if denominator > 0.0 {
+        (shared / denominator).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Connect a freshly inserted thought to existing thoughts that share keywords with it.
+/// Candidates need `min_keywords` shared keywords; strength is their IDF-weighted overlap,
+/// so sharing rare terms counts for more than sharing words most thoughts contain, and a
+/// shared phrase ("machine learning") for more than the same words used apart.
+pub fn auto_connect(db: &Database, thought: &Thought, config: &AutoConnectConfig) -> rusqlite::Result<AutoConnectOutcome> {
+    let mut outcome = AutoConnectOutcome::default();
+    if !config.enabled || db.is_read_only() {
+        return Ok(outcome);
+    }
+
+    let new_terms = extract_terms_with(&thought.content, &config.keywords);
+    let new_set: HashSet<String> = new_terms.keywords.iter()
+        .chain(new_terms.phrases.iter().map(|p| &p.key))
+        .cloned()
+        .collect();
+    let candidates: Vec<(Vec<String>, HashSet<String>, Thought)> = db.get_all_thoughts()?
+        .into_iter()
+        .filter(|t| t.id != thought.id)
+        .filter_map(|existing| {
+            let terms = extract_terms_with(&existing.content, &config.keywords);
+            let shared = shared_keywords(&new_terms.keywords, &terms.keywords);
+            if shared.len() < config.min_keywords {
+                return None;
+            }
+            let set = terms.keywords.into_iter().chain(terms.phrases.into_iter().map(|p| p.key)).collect();
+            Some((shared, set, existing))
+        })
+        .collect();
+    // A pair linked some other way already (say, by connect_shared_links) isn't linked again,
+    // nor is a pair suggested before suggested again
+    let mut unlinked = Vec::with_capacity(candidates.len());
+    for candidate in candidates {
+        let id = &candidate.2.id;
+        if db.connection_exists(&thought.id, id)? || (config.suggest && db.suggestion_exists(&thought.id, id)?) {
+            continue;
+        }
+        unlinked.push(candidate);
+    }
+    let candidates = unlinked;
+
+    // Only the terms involved in some candidate need a document frequency. Phrases can
+    // only be shared if the new thought has them, so other phrases go without one.
+    let mut involved: HashSet<String> = new_set.clone();
+    for (_, terms, _) in &candidates {
+        involved.extend(terms.iter().filter(|t| !is_phrase(t)).cloned());
+    }
+    let involved: Vec<String> = involved.into_iter().collect();
+    let (total_docs, doc_counts) = db.keyword_document_frequencies(&involved)?;
+    let weights: HashMap<String, f64> = doc_counts.into_iter()
+        .map(|(k, count)| (k, idf(count, total_docs)))
+        .collect();
+
+    let mut candidates: Vec<(Vec<String>, Vec<String>, f64, Thought)> = candidates.into_iter()
+        .map(|(shared, terms, existing)| {
+            let phrases = new_terms.phrases.iter()
+                .filter(|p| terms.contains(&p.key))
+                .map(|p| p.text.clone())
+                .collect();
+            (shared, phrases, weighted_overlap(&new_set, &terms, &weights), existing)
+        })
+        .collect();
+
+    // Keep the strongest overlaps when over the cap
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2).then(b.0.len().cmp(&a.0.len())));
+    if candidates.len() > config.max_per_thought {
+        outcome.suppressed = candidates.len() - config.max_per_thought;
+        candidates.truncate(config.max_per_thought);
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let connections: Vec<crate::Connection> = candidates.iter()
+        .map(|(shared, phrases, score, existing)| crate::Connection {
+            id: Uuid::new_v4().to_string(),
+            from_thought: thought.id.clone(),
+            to_thought: existing.id.clone(),
+            strength: *score,
+            reason: connection_reason(shared, phrases),
+            created_at: now.clone(),
+            kind: AUTO_CONNECTION_KIND.to_string(),
+        })
+        .collect();
+
+    // One transaction for the whole pass instead of a commit per edge
+    if config.suggest {
+        db.insert_suggestions(&connections)?;
+    } else {
+        let (inserted, evicted) = db.insert_connections_capped(&connections, config.max_connections)?;
+        let inserted: HashSet<&str> = inserted.iter().map(|c| c.to_thought.as_str()).collect();
+        outcome.capped = connections.len() - inserted.len();
+        outcome.evicted = evicted;
+        candidates.retain(|c| inserted.contains(c.3.id.as_str()));
+    }
+
+    let linked = candidates.into_iter()
+        .map(|(shared, phrases, _, existing)| ConnectedThought {
+            snippet: snippet(&existing.content, 40),
+            shared,
+            phrases,
+        })
+        .collect();
+    if config.suggest {
+        outcome.suggested = linked;
+    } else {
+        outcome.connected = linked;
+    }
+    Ok(outcome)
+}
+
+/// "Auto-connected via: ..." naming shared phrases first, then the shared keywords
+pub fn connection_reason(shared: &[String], phrases: &[String]) -> String {
+    if phrases.is_empty() {
+        return format!("Auto-connected via: {}", shared.join(", "));
+    }
+    let quoted: Vec<String> = phrases.iter().map(|p| format!("'{}'", p)).collect();
+    format!("Auto-connected via: shared phrase {}; keywords {}", quoted.join(", "), shared.join(", "))
+}
+
+/// Connect a freshly inserted thought to existing thoughts that mention the same page
+/// (same host and path, see link_key). Returns the linked thoughts' snippets with the
+/// shared page. Already-connected pairs are left alone, and max_connections is kept.
+pub fn connect_shared_links(db: &Database, thought: &Thought, config: &AutoConnectConfig) -> rusqlite::Result<Vec<(String, String)>> {
+    if !config.enabled || db.is_read_only() {
+        return Ok(Vec::new());
+    }
+    let keys: HashSet<String> = extract_urls(&thought.content).iter().map(|u| link_key(u)).collect();
+
+    let now = Utc::now().to_rfc3339();
+    let mut connections = Vec::new();
+    let mut linked = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    for key in &keys {
+        // The host narrows the query; the full key decides
+        let host = key.split('/').next().unwrap_or_default();
+        for existing in db.get_thoughts_with_links(Some(host))? {
+            if existing.thought.id == thought.id || seen.contains(&existing.thought.id) {
+                continue;
+            }
+            if !existing.links.iter().any(|u| link_key(u) == *key) {
+                continue;
+            }
+            seen.insert(existing.thought.id.clone());
+            if db.connection_exists(&thought.id, &existing.thought.id)? {
+                continue;
+            }
+            connections.push(crate::Connection {
+                id: Uuid::new_v4().to_string(),
+                from_thought: thought.id.clone(),
+                to_thought: existing.thought.id.clone(),
+                strength: LINK_CONNECTION_STRENGTH,
+                reason: format!("Shares link: {}", key),
+                created_at: now.clone(),
+                kind: LINK_CONNECTION_KIND.to_string(),
+            });
+            linked.push((existing.thought.id.clone(), snippet(&existing.thought.content, 40), key.clone()));
+        }
+    }
+
+    let (inserted, _) = db.insert_connections_capped(&connections, config.max_connections)?;
+    let inserted: HashSet<&str> = inserted.iter().map(|c| c.to_thought.as_str()).collect();
+    Ok(linked.into_iter()
+        .filter(|(id, ..)| inserted.contains(id.as_str()))
+        .map(|(_, snippet, key)| (snippet, key))
+        .collect())
+}
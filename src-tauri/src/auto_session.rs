@@ -0,0 +1,74 @@
+use chrono::{DateTime, Local, Utc};
+use uuid::Uuid;
+use crate::database::Database;
+use crate::Thought;
+
+// Settings keys (also readable/writable through get_setting/set_setting)
+pub const SETTING_ENABLED: &str = "auto_session_enabled";
+pub const SETTING_GAP_MINUTES: &str = "auto_session_gap_minutes";
+// Id of the currently open auto-detected session (empty when none)
+const SETTING_ACTIVE: &str = "auto_session_active";
+
+const DEFAULT_GAP_MINUTES: i64 = 45;
+
+/// Group MCP-logged thoughts into sessions by time gaps. Call after inserting a new thought.
+///
+/// A gap longer than the configured threshold since the previous thought closes the open
+/// auto session (ending it at that previous thought) and opens a new one; every new
+/// thought is then attached to the open session. Returns the session id the thought joined.
+pub fn track_thought(db: &Database, thought: &Thought) -> rusqlite::Result<Option<String>> {
+    if !db.setting_or(SETTING_ENABLED, true) {
+        return Ok(None);
+    }
+
+    let now = parse_time(&thought.created_at).unwrap_or_else(Utc::now);
+    let gap = chrono::Duration::minutes(db.setting_or(SETTING_GAP_MINUTES, DEFAULT_GAP_MINUTES).max(1));
+    let previous = db.latest_thought_created_at(&thought.id)?;
+    let after_gap = previous.as_deref()
+        .and_then(parse_time)
+        .is_none_or(|prev| now - prev > gap);
+
+    let mut active = active_session(db)?;
+    if after_gap {
+        if let Some(id) = active.take() {
+            let ended_at = previous.unwrap_or_else(|| now.to_rfc3339());
+            db.end_session(&id, &ended_at)?;
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let title = format!("Session {}", now.with_timezone(&Local).format("%Y-%m-%d %H:%M"));
+        db.open_session(&id, &title, &thought.created_at)?;
+        db.set_setting(SETTING_ACTIVE, &id)?;
+        active = Some(id);
+    }
+
+    let Some(session_id) = active else {
+        return Ok(None);
+    };
+    let position = db.next_session_position(&session_id)?;
+    db.link_session_thought(&session_id, &thought.id, position)?;
+    Ok(Some(session_id))
+}
+
+/// Close the open auto session so a summary can be attached to it. Returns its id, if any.
+pub fn take_active_session(db: &Database) -> rusqlite::Result<Option<String>> {
+    let active = active_session(db)?;
+    if active.is_some() {
+        db.set_setting(SETTING_ACTIVE, "")?;
+    }
+    Ok(active)
+}
+
+/// The open auto session, ignoring one that was deleted or ended elsewhere
+fn active_session(db: &Database) -> rusqlite::Result<Option<String>> {
+    let Some(id) = db.get_setting(SETTING_ACTIVE)?.filter(|id| !id.is_empty()) else {
+        return Ok(None);
+    };
+    Ok(db.find_session(&id)?
+        .filter(|s| s.id == id && s.ended_at.is_none())
+        .map(|s| s.id))
+}
+
+fn parse_time(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp).ok().map(|t| t.with_timezone(&Utc))
+}
@@ -0,0 +1,318 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+
+/// Passed by the login entry so the app starts hidden (see main)
+pub const MINIMIZED_FLAG: &str = "--minimized";
+
+#[cfg(windows)]
+const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+#[cfg(windows)]
+const RUN_VALUE: &str = "The Mind";
+#[cfg(target_os = "macos")]
+const LAUNCH_AGENT_LABEL: &str = "com.jaketerrell.themind";
+#[cfg(target_os = "linux")]
+const DESKTOP_FILE: &str = "the-mind.desktop";
+
+/// Whether the app launches at login, and with which executable
+#[derive(Debug, Clone, Serialize)]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    /// The executable the login entry starts
+    pub executable: Option<String>,
+    /// The executable the entry pointed at before it was rewritten for this one
+    /// (the app was moved, updated to a new path, or run from another build)
+    pub repaired_from: Option<String>,
+}
+
+/// Current login entry. One pointing at a different executable is rewritten for this one.
+pub fn status() -> Result<AutostartStatus, String> {
+    let current = current_exe()?;
+    let Some(target) = read_entry()? else {
+        return Ok(AutostartStatus { enabled: false, executable: None, repaired_from: None });
+    };
+    let repaired_from = if same_executable(&target, &current) {
+        None
+    } else {
+        write_entry(&current)?;
+        Some(target)
+    };
+    Ok(AutostartStatus { enabled: true, executable: Some(display(&current)), repaired_from })
+}
+
+/// Create or remove the login entry. Enabling always rewrites it, so a stale one is fixed.
+pub fn set_enabled(enabled: bool) -> Result<AutostartStatus, String> {
+    let current = current_exe()?;
+    let previous = read_entry()?;
+    if !enabled {
+        if previous.is_some() {
+            remove_entry()?;
+        }
+        return Ok(AutostartStatus { enabled: false, executable: None, repaired_from: None });
+    }
+
+    write_entry(&current)?;
+    Ok(AutostartStatus {
+        enabled: true,
+        executable: Some(display(&current)),
+        repaired_from: previous.filter(|target| !same_executable(target, &current)),
+    })
+}
+
+/// The executable a login entry should start. An AppImage runs from a temporary mount
+/// that changes every launch, so the entry must point at the .AppImage file itself.
+fn current_exe() -> Result<PathBuf, String> {
+    #[cfg(target_os = "linux")]
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        return Ok(PathBuf::from(appimage));
+    }
+    std::env::current_exe().map_err(|e| format!("Failed to locate the app executable: {}", e))
+}
+
+fn display(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn same_executable(target: &str, current: &Path) -> bool {
+    let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    let (a, b) = (canonical(Path::new(target)), canonical(current));
+    if cfg!(windows) {
+        display(&a).eq_ignore_ascii_case(&display(&b))
+    } else {
+        a == b
+    }
+}
+
+// Windows: a value under HKCU\...\CurrentVersion\Run holding the quoted command line
+
+#[cfg(windows)]
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+#[cfg(windows)]
+fn read_entry() -> Result<Option<String>, String> {
+    use windows_sys::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+    use windows_sys::Win32::System::Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_SZ};
+
+    let (key, value) = (wide(RUN_KEY), wide(RUN_VALUE));
+    let mut size = 0u32;
+    let status = unsafe {
+        RegGetValueW(HKEY_CURRENT_USER, key.as_ptr(), value.as_ptr(), RRF_RT_REG_SZ,
+            std::ptr::null_mut(), std::ptr::null_mut(), &mut size)
+    };
+    if status == ERROR_FILE_NOT_FOUND {
+        return Ok(None);
+    }
+    if status != ERROR_SUCCESS {
+        return Err(format!("Failed to read the login entry (error {})", status));
+    }
+
+    let mut buffer = vec![0u16; (size as usize).div_ceil(2)];
+    let status = unsafe {
+        RegGetValueW(HKEY_CURRENT_USER, key.as_ptr(), value.as_ptr(), RRF_RT_REG_SZ,
+            std::ptr::null_mut(), buffer.as_mut_ptr().cast(), &mut size)
+    };
+    if status != ERROR_SUCCESS {
+        return Err(format!("Failed to read the login entry (error {})", status));
+    }
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    let command = String::from_utf16_lossy(&buffer[..len]);
+
+    // "C:\path\the-mind.exe" --minimized
+    let executable = match command.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or_default(),
+        None => command.split(" --").next().unwrap_or_default(),
+    };
+    Ok(Some(executable.trim().to_string()))
+}
+
+#[cfg(windows)]
+fn write_entry(executable: &Path) -> Result<(), String> {
+    use windows_sys::Win32::Foundation::ERROR_SUCCESS;
+    use windows_sys::Win32::System::Registry::{RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ};
+
+    let command = wide(&format!("\"{}\" {}", display(executable), MINIMIZED_FLAG));
+    let (key, value) = (wide(RUN_KEY), wide(RUN_VALUE));
+    let status = unsafe {
+        RegSetKeyValueW(HKEY_CURRENT_USER, key.as_ptr(), value.as_ptr(), REG_SZ,
+            command.as_ptr().cast(), (command.len() * 2) as u32)
+    };
+    if status != ERROR_SUCCESS {
+        return Err(format!("Failed to write the login entry (error {})", status));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn remove_entry() -> Result<(), String> {
+    use windows_sys::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+    use windows_sys::Win32::System::Registry::{RegDeleteKeyValueW, HKEY_CURRENT_USER};
+
+    let (key, value) = (wide(RUN_KEY), wide(RUN_VALUE));
+    let status = unsafe { RegDeleteKeyValueW(HKEY_CURRENT_USER, key.as_ptr(), value.as_ptr()) };
+    if status != ERROR_SUCCESS && status != ERROR_FILE_NOT_FOUND {
+        return Err(format!("Failed to remove the login entry (error {})", status));
+    }
+    Ok(())
+}
+
+// macOS: a LaunchAgent plist, loaded by launchd at the next login
+
+#[cfg(target_os = "macos")]
+fn entry_path() -> Result<PathBuf, String> {
+    dirs::home_dir()
+        .map(|home| home.join("Library/LaunchAgents").join(format!("{}.plist", LAUNCH_AGENT_LABEL)))
+        .ok_or_else(|| "Could not find the home directory".to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn read_entry() -> Result<Option<String>, String> {
+    let Some(plist) = read_entry_file()? else {
+        return Ok(None);
+    };
+    // The first ProgramArguments string is the executable
+    let executable = plist.split_once("<key>ProgramArguments</key>")
+        .and_then(|(_, rest)| rest.split_once("<string>"))
+        .and_then(|(_, rest)| rest.split_once("</string>"))
+        .map(|(path, _)| path.replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&"))
+        .unwrap_or_default();
+    Ok(Some(executable))
+}
+
+#[cfg(target_os = "macos")]
+fn write_entry(executable: &Path) -> Result<(), String> {
+    let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let plist = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+        LAUNCH_AGENT_LABEL,
+        escape(&display(executable)),
+        MINIMIZED_FLAG,
+    );
+    write_entry_file(&plist)
+}
+
+// Linux: an XDG autostart .desktop file
+
+#[cfg(target_os = "linux")]
+fn entry_path() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|config| config.join("autostart").join(DESKTOP_FILE))
+        .ok_or_else(|| "Could not find the config directory".to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn read_entry() -> Result<Option<String>, String> {
+    let Some(desktop) = read_entry_file()? else {
+        return Ok(None);
+    };
+    let exec = desktop.lines()
+        .find_map(|line| line.strip_prefix("Exec="))
+        .unwrap_or_default();
+    Ok(Some(unquote_exec(exec)))
+}
+
+#[cfg(target_os = "linux")]
+fn write_entry(executable: &Path) -> Result<(), String> {
+    let desktop = format!(
+        "[Desktop Entry]\nType=Application\nName=The Mind\nExec={} {}\nX-GNOME-Autostart-enabled=true\n",
+        quote_exec(&display(executable)),
+        MINIMIZED_FLAG,
+    );
+    write_entry_file(&desktop)
+}
+
+/// Quote a path for an Exec key: inside double quotes `"`, `` ` ``, `$` and `\` are
+/// backslash-escaped, then the value itself doubles backslashes and percent signs
+#[cfg(target_os = "linux")]
+fn quote_exec(path: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in path.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted.replace('\\', "\\\\").replace('%', "%%")
+}
+
+/// The executable of an Exec value written by quote_exec (or a plain unquoted one)
+#[cfg(target_os = "linux")]
+fn unquote_exec(exec: &str) -> String {
+    let exec = exec.trim().replace("\\\\", "\\").replace("%%", "%");
+    let Some(rest) = exec.strip_prefix('"') else {
+        return exec.split_whitespace().next().unwrap_or_default().to_string();
+    };
+    let mut path = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => path.extend(chars.next()),
+            '"' => break,
+            c => path.push(c),
+        }
+    }
+    path
+}
+
+// macOS and Linux keep the entry in a file
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn read_entry_file() -> Result<Option<String>, String> {
+    let path = entry_path()?;
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn write_entry_file(content: &str) -> Result<(), String> {
+    let path = entry_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn remove_entry() -> Result<(), String> {
+    let path = entry_path()?;
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("Failed to remove {}: {}", path.display(), e))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+fn read_entry() -> Result<Option<String>, String> {
+    Ok(None)
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+fn write_entry(_executable: &Path) -> Result<(), String> {
+    Err("Launch at login is not supported on this platform".to_string())
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+fn remove_entry() -> Result<(), String> {
+    Ok(())
+}
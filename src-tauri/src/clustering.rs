@@ -0,0 +1,168 @@
+// Settings key (also readable/writable through get_setting/set_setting)
+pub const SETTING_MAX_SIZE: &str = "cluster_max_size";
+
+/// Clusters with more thoughts than this are split into sub-clusters
+pub const DEFAULT_MAX_SIZE: usize = 150;
+/// Levels of clusters, counting the top-level category clusters
+pub const MAX_DEPTH: usize = 3;
+
+// Lloyd iterations per split; positions settle well before this
+const ITERATIONS: usize = 30;
+
+type Point = [f64; 3];
+
+fn distance_sq(a: &Point, b: &Point) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/// Mean of the points, or the origin for none
+pub fn centroid(points: &[Point]) -> Point {
+    if points.is_empty() {
+        return [0.0; 3];
+    }
+    let n = points.len() as f64;
+    let mut sum = [0.0; 3];
+    for p in points {
+        for axis in 0..3 {
+            sum[axis] += p[axis];
+        }
+    }
+    sum.map(|s| s / n)
+}
+
+/// Group points into at most `k` clusters by k-means, returning the group of each point.
+/// Seeds are picked farthest-first so the same positions always split the same way; groups
+/// that end up empty are dropped and the rest numbered from 0.
+pub fn kmeans(points: &[Point], k: usize) -> Vec<usize> {
+    if points.is_empty() || k <= 1 {
+        return vec![0; points.len()];
+    }
+
+    // Farthest from the mean first, then whichever point is farthest from every seed so far
+    let mean = centroid(points);
+    let mut centers: Vec<Point> = Vec::with_capacity(k);
+    let mut nearest: Vec<f64> = points.iter().map(|p| distance_sq(p, &mean)).collect();
+    while centers.len() < k {
+        let (index, farthest) = nearest.iter().enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, d)| (i, *d))
+            .unwrap_or((0, 0.0));
+        if !centers.is_empty() && farthest <= 0.0 {
+            break; // Fewer distinct positions than k
+        }
+        let seed = points[index];
+        centers.push(seed);
+        for (d, p) in nearest.iter_mut().zip(points) {
+            *d = d.min(distance_sq(p, &seed));
+        }
+    }
+
+    let mut groups = vec![0; points.len()];
+    for _ in 0..ITERATIONS {
+        let mut changed = false;
+        for (group, p) in groups.iter_mut().zip(points) {
+            let closest = centers.iter().enumerate()
+                .min_by(|a, b| distance_sq(p, a.1).total_cmp(&distance_sq(p, b.1)))
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            if *group != closest {
+                *group = closest;
+                changed = true;
+            }
+        }
+        for (i, center) in centers.iter_mut().enumerate() {
+            let members: Vec<Point> = points.iter().zip(&groups)
+                .filter(|(_, g)| **g == i)
+                .map(|(p, _)| *p)
+                .collect();
+            if !members.is_empty() {
+                *center = centroid(&members);
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // Renumber so only non-empty groups are left
+    let mut renumbered: Vec<Option<usize>> = vec![None; centers.len()];
+    let mut next = 0;
+    groups.into_iter()
+        .map(|g| {
+            *renumbered[g].get_or_insert_with(|| {
+                next += 1;
+                next - 1
+            })
+        })
+        .collect()
+}
+
+/// Split an oversized cluster into enough groups to bring each near `max_size`, as indexes
+/// into `points`. Empty when the points can't be separated (e.g. all at one position).
+pub fn split(points: &[Point], max_size: usize) -> Vec<Vec<usize>> {
+    let k = points.len().div_ceil(max_size.max(1)).max(2);
+    let assignment = kmeans(points, k);
+    let count = assignment.iter().max().map_or(0, |m| m + 1);
+    if count < 2 {
+        return Vec::new();
+    }
+    let mut groups = vec![Vec::new(); count];
+    for (index, group) in assignment.into_iter().enumerate() {
+        groups[group].push(index);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{centroid, kmeans, split, Point};
+
+    /// Four points around each of three far-apart centers, interleaved so no blob is contiguous
+    fn blobs() -> Vec<Point> {
+        let centers = [[0.0, 0.0, 0.0], [100.0, 0.0, 0.0], [0.0, 100.0, 0.0]];
+        let offsets = [[1.0, 0.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]];
+        offsets.iter()
+            .flat_map(|o| centers.iter().map(move |c| [c[0] + o[0], c[1] + o[1], c[2] + o[2]]))
+            .collect()
+    }
+
+    #[test]
+    fn kmeans_finds_well_separated_groups() {
+        let points = blobs();
+
+        let groups = kmeans(&points, 3);
+
+        for (i, group) in groups.iter().enumerate() {
+            // Point i belongs to blob i % 3
+            for (j, other) in groups.iter().enumerate() {
+                assert_eq!(group == other, i % 3 == j % 3, "points {} and {}: {:?}", i, j, groups);
+            }
+        }
+        assert_eq!(kmeans(&points, 3), groups, "the same points always split the same way");
+    }
+
+    #[test]
+    fn kmeans_never_returns_empty_groups() {
+        let same = vec![[5.0, 5.0, 5.0]; 6];
+        assert_eq!(kmeans(&same, 3), vec![0; 6]);
+        assert_eq!(kmeans(&blobs(), 1), vec![0; 12]);
+        assert!(kmeans(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn split_brings_groups_down_to_the_max_size() {
+        let points = blobs();
+
+        let mut groups = split(&points, 4);
+
+        groups.sort();
+        assert_eq!(groups, [vec![0, 3, 6, 9], vec![1, 4, 7, 10], vec![2, 5, 8, 11]]);
+        let members: Vec<Point> = groups[1].iter().map(|&i| points[i]).collect();
+        assert_eq!(centroid(&members), [100.0, 0.25, -0.25]);
+    }
+
+    #[test]
+    fn split_gives_up_on_points_that_cannot_be_separated() {
+        assert!(split(&vec![[1.0, 2.0, 3.0]; 10], 4).is_empty());
+    }
+}
@@ -1,34 +1,743 @@
-use rusqlite::{Connection, Result, params};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use rusqlite::{Connection, DatabaseName, OpenFlags, OptionalExtension, Result, params};
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{Datelike, NaiveDate, Utc};
 use crate::{Thought, Connection as ThoughtConnection};
+use crate::clustering;
+use crate::encryption;
+use crate::utils::{extract_terms, lock_recovering, normalize_keyword};
 
+// Idle read-only connections kept around for reuse
+const MAX_IDLE_READERS: usize = 4;
+
+// Column list matching row_to_thought
+const THOUGHT_COLUMNS: &str =
+    "id, content, role, category, importance, position_x, position_y, position_z, created_at, last_referenced, pinned, favorite, archived";
+
+// Column list matching row_to_session
+const SESSION_COLUMNS: &str = "id, title, summary, started_at, ended_at";
+
+// Column list matching row_to_cluster
+const CLUSTER_COLUMNS: &str = "id, name, category, center_x, center_y, center_z, thought_count, created_at, keywords, parent_id, \
+     (SELECT COUNT(*) FROM clusters children WHERE children.parent_id = clusters.id), manual, \
+     (SELECT color FROM categories WHERE categories.name = clusters.category)";
+
+// Column list matching row_to_connection
+const CONNECTION_COLUMNS: &str = "id, from_thought, to_thought, strength, reason, created_at, kind";
+
+/// Allowed connection kinds; "keyword-overlap" and "shares-link" are reserved for auto-connections
+pub const CONNECTION_KINDS: [&str; 8] = [
+    "related", "supports", "contradicts", "caused-by", "leads-to", "example-of", "keyword-overlap", "shares-link",
+];
+pub const AUTO_CONNECTION_KIND: &str = "keyword-overlap";
+/// Links thoughts that mention the same page (see utils::link_key)
+pub const LINK_CONNECTION_KIND: &str = "shares-link";
+
+/// Connections made automatically (auto-connect and shared links), as opposed to by hand
+pub const AUTO_CREATED_KINDS: [&str; 2] = [AUTO_CONNECTION_KIND, LINK_CONNECTION_KIND];
+
+// How much a connection strengthens each time its two thoughts are recalled together
+const REINFORCE_STEP: f64 = 0.02;
+
+// When decay_connection_strength last ran; later runs only decay the time since
+const CONNECTION_DECAY_RUN_KEY: &str = "connection_decay_last_run";
+
+// Rows of the counters table: writes the frontend should notice (kept by triggers), and
+// compute_clusters runs
+const MUTATIONS_COUNTER: &str = "mutations";
+const CLUSTER_GENERATION_COUNTER: &str = "cluster_generation";
+// Highest change_log seq removed by prune_change_log; deltas from before it are incomplete
+const CHANGE_LOG_PRUNED_COUNTER: &str = "change_log_pruned";
+
+// Tables whose writes bump the mutations counter, with the columns whose updates count and
+// the entity type their changes are recorded as in change_log (for get_graph_delta).
+// Bookkeeping updates (last_referenced, reference counts, computed importance, cluster
+// membership, connection strength from reinforcement and decay) are left out, since reads
+// make them and the frontend would otherwise reload after every fetch.
+const WATCHED_TABLES: [(&str, Option<&str>, Option<&str>); 7] = [
+    ("thoughts", Some("content, role, category, importance, position_x, position_y, position_z, metadata, pinned, favorite, archived"), Some("thought")),
+    ("connections", Some("from_thought, to_thought, reason, kind"), Some("connection")),
+    ("sessions", None, Some("session")),
+    ("session_thoughts", None, None),
+    ("clusters", None, Some("cluster")),
+    ("categories", None, None),
+    ("attachments", None, None),
+];
+
+/// Setting that opens the database read-only on every launch (see Database::open)
+pub const SETTING_READ_ONLY: &str = "read_only";
+
+/// Who a thought came from
+pub const THOUGHT_ROLES: [&str; 3] = ["user", "assistant", "system"];
+
+/// What an attachment points at: a local file, a local image the frontend can preview, or a web link
+pub const ATTACHMENT_KINDS: [&str; 3] = ["file", "image", "url"];
+/// Setting: whether add_attachment copies local files into the attachments folder by default
+pub const SETTING_COPY_ATTACHMENTS: &str = "attachments_copy_files";
+/// Setting: how close (in scene units) a new thought may be placed to an existing one
+pub const SETTING_MIN_PLACEMENT_DISTANCE: &str = "placement_min_distance";
+
+// Set once thoughts saved before roles were recorded have been marked as the assistant's
+const ROLE_BACKFILL_KEY: &str = "role_backfill_done";
+
+// Set once thought_links has been filled in for thoughts saved before links were extracted
+const LINKS_BACKFILL_KEY: &str = "links_backfill_done";
+
+// Set once thoughts saved before code detection have their has_code/code_language metadata
+const CODE_BACKFILL_KEY: &str = "code_backfill_done";
+
+// Keyword-overlap score below which get_similar_thoughts leaves a thought out
+const SIMILAR_MIN_KEYWORD_SCORE: f64 = 0.1;
+
+// Bump when extract_terms changes so keyword_stats and thought_keywords are rebuilt on next start
+const KEYWORD_STATS_VERSION: &str = "5";
+const KEYWORD_STATS_VERSION_KEY: &str = "keyword_stats_version";
+
+/// A keyword_stats row: keyword or phrase key, document count, whether it's a phrase
+pub type KeywordCount = (String, i64, bool);
+
+// Built-in categories and their default colors and icons (matches the frontend palette)
+const DEFAULT_CATEGORIES: [(&str, &str, &str); 5] = [
+    ("work", "#3B82F6", "💼"),
+    ("personal", "#8B5CF6", "👤"),
+    ("technical", "#10B981", "⚙️"),
+    ("creative", "#F59E0B", "✨"),
+    ("other", "#6B7280", "💭"),
+];
+
+/// Substring LIKE pattern with `%`, `_`, and `\` in the query matched literally (use with ESCAPE '\')
+pub fn like_pattern(query: &str) -> String {
+    let escaped = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// SQL condition (numbered placeholders from ?1) and values for a thought filter; "1" when empty
+fn filter_clause(filter: &crate::ThoughtFilter) -> (String, Vec<rusqlite::types::Value>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+    let mut push = |condition: &str, value: rusqlite::types::Value| {
+        values.push(value);
+        conditions.push(condition.replace('?', &format!("?{}", values.len())));
+    };
+
+    if let Some(category) = &filter.category {
+        push("category = ?", category.clone().into());
+    }
+    if let Some(before) = &filter.created_before {
+        push("julianday(created_at) < julianday(?)", before.clone().into());
+    }
+    if let Some(after) = &filter.created_after {
+        push("julianday(created_at) > julianday(?)", after.clone().into());
+    }
+    if let Some(min) = filter.min_importance {
+        push("importance >= ?", min.into());
+    }
+    if let Some(max) = filter.max_importance {
+        push("importance <= ?", max.into());
+    }
+    if let Some(content) = &filter.content {
+        push("content LIKE ? ESCAPE '\\'", like_pattern(content).into());
+    }
+    if let Some(role) = &filter.role {
+        push("role = ?", role.clone().into());
+    }
+
+    if conditions.is_empty() {
+        ("1".to_string(), values)
+    } else {
+        (conditions.join(" AND "), values)
+    }
+}
+
+/// SQL condition (numbered placeholders from ?1) and values for a saved or ad-hoc query; "1"
+/// when it sets no conditions. Every value is bound, so nothing in the definition reaches the
+/// SQL text.
+fn query_clause(query: &crate::QueryDefinition) -> (String, Vec<rusqlite::types::Value>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+    let mut push = |condition: &str, value: rusqlite::types::Value| {
+        values.push(value);
+        conditions.push(condition.replace('?', &format!("?{}", values.len())));
+    };
+
+    if !query.categories.is_empty() {
+        push("category IN (SELECT value FROM json_each(?))", serde_json::json!(query.categories).to_string().into());
+    }
+    for tag in &query.tags {
+        push(
+            "EXISTS (SELECT 1 FROM json_each(CASE WHEN json_valid(metadata) THEN metadata ELSE '{}' END, '$.tags') WHERE lower(value) = ?)",
+            tag.clone().into(),
+        );
+    }
+    if let Some(after) = &query.created_after {
+        push("julianday(created_at) > julianday(?)", after.clone().into());
+    }
+    if let Some(before) = &query.created_before {
+        push("julianday(created_at) < julianday(?)", before.clone().into());
+    }
+    if let Some(days) = query.within_days {
+        push("julianday(created_at) >= julianday('now') - ?", days.into());
+    }
+    if let Some(min) = query.min_importance {
+        push("importance >= ?", min.into());
+    }
+    if let Some(max) = query.max_importance {
+        push("importance <= ?", max.into());
+    }
+    if let Some(has) = query.has_connections {
+        push(
+            "EXISTS (SELECT 1 FROM connections c WHERE c.from_thought = thoughts.id OR c.to_thought = thoughts.id) = ?",
+            has.into(),
+        );
+    }
+    if let Some(role) = &query.role {
+        push("role = ?", role.clone().into());
+    }
+    if let Some(text) = &query.text {
+        push("content LIKE ? ESCAPE '\\'", like_pattern(text).into());
+    }
+    if !query.include_archived {
+        conditions.push("archived = 0".to_string());
+    }
+
+    if conditions.is_empty() {
+        ("1".to_string(), values)
+    } else {
+        (conditions.join(" AND "), values)
+    }
+}
+
+/// Trim and lowercase a category name; None if nothing is left
+pub fn normalize_category(name: &str) -> Option<String> {
+    let name = name.trim().to_lowercase();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Calendar granularity for time-bucketed queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl TimeBucket {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "day" => Some(TimeBucket::Day),
+            "week" => Some(TimeBucket::Week),
+            "month" => Some(TimeBucket::Month),
+            _ => None,
+        }
+    }
+
+    /// SQL expression mapping a timestamp column to its bucket's start date (YYYY-MM-DD).
+    /// `?1` is the timezone modifier (e.g. "+120 minutes"). Weeks start on Monday.
+    fn sql_start(&self, column: &str) -> String {
+        match self {
+            TimeBucket::Day => format!("date({}, ?1)", column),
+            TimeBucket::Week => format!("date({}, ?1, 'weekday 0', '-6 days')", column),
+            TimeBucket::Month => format!("date({}, ?1, 'start of month')", column),
+        }
+    }
+
+    /// Start date of the bucket containing `day`
+    pub fn start_of(&self, day: NaiveDate) -> NaiveDate {
+        match self {
+            TimeBucket::Day => day,
+            TimeBucket::Week => day - chrono::Duration::days(day.weekday().num_days_from_monday() as i64),
+            TimeBucket::Month => day.with_day(1).unwrap_or(day),
+        }
+    }
+
+    /// Start date of the bucket after the one starting at `start`
+    pub fn next(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            TimeBucket::Day => start + chrono::Duration::days(1),
+            TimeBucket::Week => start + chrono::Duration::days(7),
+            TimeBucket::Month => start.checked_add_months(chrono::Months::new(1)).unwrap_or(start),
+        }
+    }
+
+    /// Start date of the bucket before the one starting at `start`
+    pub fn prev(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            TimeBucket::Day => start - chrono::Duration::days(1),
+            TimeBucket::Week => start - chrono::Duration::days(7),
+            TimeBucket::Month => start.checked_sub_months(chrono::Months::new(1)).unwrap_or(start),
+        }
+    }
+}
+
+// New thoughts are placed between these distances from the center
+const MIN_PLACEMENT_RADIUS: f64 = 10.0;
+const MAX_PLACEMENT_RADIUS: f64 = 40.0;
+const DEFAULT_MIN_PLACEMENT_DISTANCE: f64 = 1.5;
+// Outward nudges tried before a crowded position is accepted anyway
+const PLACEMENT_RETRIES: usize = 5;
+// Angle around the vertical axis given to each category's sector
+const CATEGORY_SECTOR_WIDTH: f64 = std::f64::consts::PI / 3.0;
+// How far a new thought's distance from the center strays from its category's
+const CATEGORY_RADIUS_JITTER: f64 = 5.0;
+
+/// Middle of a category's sector, in radians: an FNV-1a hash of the name, so it's the same on
+/// every run and platform (std's hasher makes no such promise)
+fn category_sector_center(category: &str) -> f64 {
+    (crate::utils::stable_hash(category.as_bytes()) % 3600) as f64 / 3600.0 * 2.0 * std::f64::consts::PI
+}
+
+/// Where the randomness for placing a new thought comes from: the clock-seeded generator, or
+/// one seeded from the thought itself when deterministic_positions is on
+pub enum PositionRandom {
+    Clock,
+    Seeded(crate::utils::SeededRandom),
+}
+
+impl PositionRandom {
+    fn next(&mut self) -> f64 {
+        match self {
+            PositionRandom::Clock => rand::random(),
+            PositionRandom::Seeded(seeded) => seeded.next_f64(),
+        }
+    }
+}
+
+/// Setting: place new thoughts from a hash of their content and category instead of at random,
+/// so importing the same thoughts into an empty mind always gives the same scene
+pub const SETTING_DETERMINISTIC_POSITIONS: &str = "deterministic_positions";
+
+/// Most buckets get_timeline will return for one range
+pub const MAX_TIMELINE_BUCKETS: usize = 1000;
+
+/// Largest page a single paged query will return
+pub const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Thoughts in a graph snapshot when the caller doesn't say
+pub const DEFAULT_SNAPSHOT_NODES: usize = 500;
+/// Most thoughts a graph snapshot will hold (every id is bound in the connections query)
+pub const MAX_SNAPSHOT_NODES: usize = 5000;
+// Keywords get_stats includes
+const STATS_TOP_KEYWORDS: i64 = 10;
+// Ids bound per query when fetching or matching many rows by id
+const ID_QUERY_CHUNK: usize = 500;
+
+/// Longest thought content a graph snapshot carries, in characters
+pub const SNAPSHOT_CONTENT_CHARS: usize = 300;
+
+/// Column a thought page is ordered by (ties are always broken by id)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThoughtSort {
+    CreatedAt,
+    LastReferenced,
+    Importance,
+    Content,
+}
+
+impl ThoughtSort {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "created_at" => Some(ThoughtSort::CreatedAt),
+            "last_referenced" => Some(ThoughtSort::LastReferenced),
+            "importance" => Some(ThoughtSort::Importance),
+            "content" => Some(ThoughtSort::Content),
+            _ => None,
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            ThoughtSort::CreatedAt => "created_at",
+            ThoughtSort::LastReferenced => "last_referenced",
+            ThoughtSort::Importance => "importance",
+            ThoughtSort::Content => "content",
+        }
+    }
+}
+
+/// Column expression used when ordering by importance
+fn importance_expr(by_computed: bool) -> &'static str {
+    if by_computed {
+        "COALESCE(computed_importance, importance)"
+    } else {
+        "importance"
+    }
+}
+
+/// 1-based rank of each (id, value) pair by value descending (ties by id), in input order
+fn rank_positions<'a>(values: impl Iterator<Item = (&'a str, f64)>) -> Vec<usize> {
+    let mut order: Vec<(usize, &str, f64)> = values.enumerate().map(|(i, (id, v))| (i, id, v)).collect();
+    order.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.1.cmp(b.1)));
+
+    let mut ranks = vec![0; order.len()];
+    for (rank, (i, ..)) in order.into_iter().enumerate() {
+        ranks[i] = rank + 1;
+    }
+    ranks
+}
+
+// Keywords kept per cluster, and how many of them make up its name
+const CLUSTER_KEYWORDS: usize = 5;
+const CLUSTER_NAME_KEYWORDS: usize = 3;
+const CLUSTER_NAME_SEPARATOR: &str = " · ";
+
+// Add ?2 to the thought count of cluster ?1 and every cluster above it
+const ADJUST_CLUSTER_CHAIN_COUNTS: &str = r#"UPDATE clusters SET thought_count = MAX(thought_count + ?2, 0)
+    WHERE id IN (
+        WITH RECURSIVE chain(id) AS (
+            SELECT ?1
+            UNION ALL
+            SELECT c.parent_id FROM clusters c JOIN chain ON c.id = chain.id
+            WHERE c.parent_id IS NOT NULL
+        )
+        SELECT id FROM chain
+    )"#;
+
+// Clusters compute_clusters keeps: made with create_cluster or holding locked thoughts
+const KEPT_CLUSTER: &str =
+    "manual = 1 OR EXISTS (SELECT 1 FROM thoughts WHERE cluster_id = clusters.id AND cluster_locked = 1)";
+
+// (id, content, position) of a thought being clustered
+type ClusterMember = (String, String, [f64; 3]);
+
+/// The keywords that best set a group of thoughts apart: those in the largest share of the
+/// group weighted by how rare they are overall (IDF from `doc_count`, out of `total_docs`).
+/// Keywords in only one thought of a larger group are ignored. Each is returned as most often
+/// written in the group rather than as its stem.
+fn cluster_keywords(contents: &[String], mut doc_count: impl FnMut(&str) -> i64, total_docs: i64) -> Vec<String> {
+    let mut group_counts: HashMap<String, usize> = HashMap::new();
+    for content in contents {
+        for keyword in extract_terms(content).keywords {
+            *group_counts.entry(keyword).or_default() += 1;
+        }
+    }
+    let written = written_forms(contents.iter().map(String::as_str));
+
+    let min_count = if contents.len() > 1 { 2 } else { 1 };
+    let mut scored: Vec<(String, usize, f64)> = group_counts.into_iter()
+        .filter(|(_, count)| *count >= min_count)
+        .map(|(keyword, count)| {
+            let share = count as f64 / contents.len() as f64;
+            let score = share * crate::auto_connect::idf(doc_count(&keyword), total_docs);
+            (keyword, count, score)
+        })
+        .collect();
+    scored.sort_by(|a, b| b.2.total_cmp(&a.2).then(b.1.cmp(&a.1)).then_with(|| a.0.cmp(&b.0)));
+
+    scored.into_iter()
+        .take(CLUSTER_KEYWORDS)
+        .map(|(stem, ..)| display_form(&written, stem))
+        .collect()
+}
+
+// stem -> (as written, lowercased -> occurrences)
+type WrittenForms = HashMap<String, HashMap<String, usize>>;
+
+/// How each stem is actually written across `contents`, for showing keywords to people
+fn written_forms<'a>(contents: impl IntoIterator<Item = &'a str>) -> WrittenForms {
+    let mut written: WrittenForms = HashMap::new();
+    for content in contents {
+        for word in content.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+            let word = word.to_lowercase();
+            *written.entry(normalize_keyword(&word)).or_default().entry(word).or_default() += 1;
+        }
+    }
+    written
+}
+
+/// The most common written form of `stem` (alphabetically first on ties), or the stem itself
+fn display_form(written: &WrittenForms, stem: String) -> String {
+    written.get(&stem)
+        .and_then(|forms| forms.iter().max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0))))
+        .map(|(form, _)| form.clone())
+        .unwrap_or(stem)
+}
+
+/// A cluster name from its top keywords ("wallpaper · workerw · monitors"). A name in
+/// `previous` whose keywords are all still among the top keywords is reused (and taken out of
+/// `previous`), so small shifts in the keyword set don't rename the cluster.
+fn cluster_name(category: &str, keywords: &[String], previous: &mut Vec<String>) -> String {
+    let kept = previous.iter().position(|name| {
+        name.contains(CLUSTER_NAME_SEPARATOR)
+            && name.split(CLUSTER_NAME_SEPARATOR).all(|k| keywords.iter().any(|kw| kw == k))
+    });
+    if let Some(index) = kept {
+        return previous.remove(index);
+    }
+    if keywords.is_empty() {
+        return format!("{} cluster", category);
+    }
+    keywords[..keywords.len().min(CLUSTER_NAME_KEYWORDS)].join(CLUSTER_NAME_SEPARATOR)
+}
+
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Schema changes after the CREATE TABLE statements in init_schema, in order. A database's
+/// schema_version is how many have been applied. Each must also run cleanly on a new
+/// database, where init_schema may already have created what it adds; append new ones,
+/// never reorder or edit applied ones.
+const MIGRATIONS: [(&str, Migration); 7] = [
+    ("connection kinds, reference counts, phrases, thought flags", migrate_thought_flags),
+    ("cluster keywords, hierarchy and locked membership", migrate_cluster_hierarchy),
+    ("connection reinforcement", migrate_connection_reinforcement),
+    ("change counters and change log triggers", migrate_change_triggers),
+    // Same triggers, created for the tables added to WATCHED_TABLES since
+    ("attachment change triggers", migrate_change_triggers),
+    ("category icons", migrate_category_icons),
+    ("thought locks", migrate_thought_locks),
+];
+
+/// The schema_version this build writes; newer databases are refused
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
+
+fn migrate_thought_flags(conn: &Connection) -> Result<()> {
+    if Database::ensure_column(conn, "connections", "kind", "TEXT DEFAULT 'related'")? {
+        // Older auto-connections are only recognizable by their reason text
+        conn.execute(
+            "UPDATE connections SET kind = ?1 WHERE reason LIKE 'Auto-connected%'",
+            [AUTO_CONNECTION_KIND],
+        )?;
+    }
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_connections_kind ON connections(kind);")?;
+    Database::ensure_column(conn, "thoughts", "reference_count", "INTEGER DEFAULT 0")?;
+    Database::ensure_column(conn, "thoughts", "computed_importance", "REAL")?;
+    Database::ensure_column(conn, "keyword_stats", "is_phrase", "INTEGER NOT NULL DEFAULT 0")?;
+    Database::ensure_column(conn, "thoughts", "pinned", "INTEGER NOT NULL DEFAULT 0")?;
+    Database::ensure_column(conn, "thoughts", "favorite", "INTEGER NOT NULL DEFAULT 0")?;
+    Database::ensure_column(conn, "thoughts", "archived", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+fn migrate_cluster_hierarchy(conn: &Connection) -> Result<()> {
+    Database::ensure_column(conn, "clusters", "keywords", "TEXT NOT NULL DEFAULT '[]'")?;
+    Database::ensure_column(conn, "clusters", "parent_id", "TEXT")?;
+    Database::ensure_column(conn, "thoughts", "cluster_id", "TEXT")?;
+    Database::ensure_column(conn, "thoughts", "cluster_locked", "INTEGER NOT NULL DEFAULT 0")?;
+    Database::ensure_column(conn, "clusters", "manual", "INTEGER NOT NULL DEFAULT 0")?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_clusters_parent ON clusters(parent_id);
+         CREATE INDEX IF NOT EXISTS idx_thoughts_cluster ON thoughts(cluster_id);",
+    )
+}
+
+fn migrate_connection_reinforcement(conn: &Connection) -> Result<()> {
+    if Database::ensure_column(conn, "connections", "last_reinforced", "TEXT")? {
+        conn.execute("UPDATE connections SET last_reinforced = created_at", [])?;
+    }
+    Ok(())
+}
+
+/// Seed the counters and add the triggers that bump the mutations counter on every insert,
+/// delete and watched update, whichever process makes it, and record them in change_log
+fn migrate_change_triggers(conn: &Connection) -> Result<()> {
+    let mut sql = format!(
+        "INSERT OR IGNORE INTO counters (name, value) VALUES ('{}', 0), ('{}', 0), ('{}', 0);\n",
+        MUTATIONS_COUNTER, CLUSTER_GENERATION_COUNTER, CHANGE_LOG_PRUNED_COUNTER
+    );
+    for (table, columns, entity) in WATCHED_TABLES {
+        let update = match columns {
+            Some(columns) => format!("UPDATE OF {}", columns),
+            None => "UPDATE".to_string(),
+        };
+        for (suffix, event, row) in [("insert", "INSERT".to_string(), "NEW"), ("delete", "DELETE".to_string(), "OLD"), ("update", update, "NEW")] {
+            sql.push_str(&format!(
+                "CREATE TRIGGER IF NOT EXISTS count_{table}_{suffix} AFTER {event} ON {table}
+                 BEGIN UPDATE counters SET value = value + 1 WHERE name = '{counter}'; END;\n",
+                counter = MUTATIONS_COUNTER,
+            ));
+            if let Some(entity) = entity {
+                sql.push_str(&format!(
+                    "CREATE TRIGGER IF NOT EXISTS log_{table}_{suffix} AFTER {event} ON {table}
+                     BEGIN INSERT INTO change_log (entity_type, entity_id, op) VALUES ('{entity}', {row}.id, '{suffix}'); END;\n",
+                ));
+            }
+        }
+    }
+    conn.execute_batch(&sql)
+}
+
+/// Give the built-in categories their default icon, and color where it was never set
+fn migrate_category_icons(conn: &Connection) -> Result<()> {
+    Database::ensure_column(conn, "categories", "icon", "TEXT")?;
+    for (name, color, icon) in DEFAULT_CATEGORIES {
+        conn.execute(
+            "UPDATE categories SET color = COALESCE(color, ?2), icon = COALESCE(icon, ?3) WHERE name = ?1",
+            params![name, color, icon],
+        )?;
+    }
+    Ok(())
+}
+
+fn migrate_thought_locks(conn: &Connection) -> Result<()> {
+    Database::ensure_column(conn, "thoughts", "locked", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+/// SQLite access split into one writer and a pool of read-only connections.
+/// In WAL mode readers never block on the writer, so the frontend can keep
+/// fetching while an import or auto-connect batch is writing.
 pub struct Database {
-    conn: Connection,
+    path: PathBuf,
+    read_only: bool,
+    writer: Mutex<Connection>,
+    readers: Mutex<Vec<Connection>>,
+    // SQLCipher key every connection is opened with; None for an unencrypted file
+    key: Mutex<Option<String>>,
+    // Bumped when the file is replaced, so readers checked out before aren't pooled again
+    epoch: AtomicU64,
+}
+
+/// A read-only connection checked out of the pool; returned on drop
+pub struct PooledReader<'a> {
+    db: &'a Database,
+    conn: Option<Connection>,
+    epoch: u64,
+}
+
+impl Deref for PooledReader<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("reader used after release")
+    }
+}
+
+impl Drop for PooledReader<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            let mut idle = lock_recovering(&self.db.readers);
+            if idle.len() < MAX_IDLE_READERS && self.epoch == self.db.epoch.load(Ordering::SeqCst) {
+                idle.push(conn);
+            }
+        }
+    }
 }
 
 impl Database {
     pub fn new() -> Result<Self> {
-        // Store in user's app data directory
-        let db_path = dirs::data_dir()
+        Self::open(false)
+    }
+
+    /// Where the database lives: the-mind/mind.db in the user's app data directory
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
             .map(|p| p.join("the-mind").join("mind.db"))
-            .unwrap_or_else(|| std::path::PathBuf::from("mind.db"));
-        
-        // Ensure directory exists
-        if let Some(parent) = db_path.parent() {
-            std::fs::create_dir_all(parent).ok();
+            .unwrap_or_else(|| PathBuf::from("mind.db"))
+    }
+
+    /// Open the database. Read-only opens it with SQLITE_OPEN_READONLY and skips schema setup,
+    /// so every write fails in SQLite itself; the file must already exist.
+    pub fn open(read_only: bool) -> Result<Self> {
+        Self::open_at(Self::default_path(), read_only)
+    }
+
+    /// Open (or create, unless read-only) the database at `db_path`
+    #[tracing::instrument(level = "debug")]
+    pub fn open_at(db_path: PathBuf, read_only: bool) -> Result<Self> {
+        if !read_only {
+            // Ensure directory exists
+            if let Some(parent) = db_path.parent() {
+                std::fs::create_dir_all(parent).ok();
+            }
         }
+        let key = encryption::key_for(&db_path, read_only)
+            .map_err(|message| Self::failure(rusqlite::ffi::SQLITE_AUTH, message))?;
+        let conn = Self::connect(&db_path, read_only, key.as_deref())?;
         
-        let conn = Connection::open(&db_path)?;
-        
-        let db = Database { conn };
-        db.init_schema()?;
+        // A newer build may have changed tables in ways this one would misread or undo
+        let version = Self::schema_version(&conn)?;
+        if version > SCHEMA_VERSION {
+            return Err(Self::failure(
+                rusqlite::ffi::SQLITE_CANTOPEN,
+                format!(
+                    "database schema version {} is newer than this version of The Mind supports ({}); update the app to open it",
+                    version, SCHEMA_VERSION
+                ),
+            ));
+        }
+
+        let db = Database {
+            path: db_path,
+            read_only,
+            writer: Mutex::new(conn),
+            readers: Mutex::new(Vec::new()),
+            key: Mutex::new(key),
+            epoch: AtomicU64::new(0),
+        };
+        if !read_only {
+            db.init_schema()?;
+        }
         
         Ok(db)
     }
+
+    /// Whether the read_only setting is on, checked without opening the database for writing.
+    /// False when there is no database yet or it can't be read.
+    pub fn read_only_setting() -> bool {
+        Self::default_path().is_file()
+            && Self::open(true).is_ok_and(|db| db.setting_or(SETTING_READ_ONLY, false))
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Whether the file is encrypted with SQLCipher
+    pub fn is_encrypted(&self) -> bool {
+        lock_recovering(&self.key).is_some()
+    }
+
+    fn failure(code: std::os::raw::c_int, message: String) -> rusqlite::Error {
+        rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(code), Some(message))
+    }
+
+    /// Open one connection to `path`, unlocked with `key` when the file is encrypted
+    fn connect(path: &std::path::Path, read_only: bool, key: Option<&str>) -> Result<Connection> {
+        let conn = if read_only {
+            Connection::open_with_flags(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX | OpenFlags::SQLITE_OPEN_URI,
+            )?
+        } else {
+            Connection::open(path)?
+        };
+        encryption::apply_key(&conn, key, path)?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        if !read_only {
+            // WAL lets the read-only connections run alongside the writer
+            conn.pragma_update(None, "journal_mode", "WAL")?;
+        }
+        Ok(conn)
+    }
+
+    /// The single connection used for every mutation
+    fn writer(&self) -> MutexGuard<'_, Connection> {
+        lock_recovering(&self.writer)
+    }
+
+    /// Check out a read-only connection, opening a new one if the pool is empty
+    fn reader(&self) -> Result<PooledReader<'_>> {
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        let idle = lock_recovering(&self.readers).pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => {
+                let key = lock_recovering(&self.key).clone();
+                Self::connect(&self.path, true, key.as_deref())?
+            }
+        };
+        Ok(PooledReader { db: self, conn: Some(conn), epoch })
+    }
     
     fn init_schema(&self) -> Result<()> {
-        self.conn.execute_batch(
+        self.writer().execute_batch(
             r#"
             -- Thoughts: Every idea, message, concept
             CREATE TABLE IF NOT EXISTS thoughts (
@@ -86,6 +795,120 @@ impl Database {
                 center_y REAL DEFAULT 0.0,
                 center_z REAL DEFAULT 0.0,
                 thought_count INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL,
+                keywords TEXT NOT NULL DEFAULT '[]',
+                parent_id TEXT,
+                manual INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Change log: one row per insert, update or delete of a thought, connection,
+            -- session or cluster, written by triggers, for get_graph_delta
+            CREATE TABLE IF NOT EXISTS change_log (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                entity_type TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                op TEXT NOT NULL,
+                changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+            );
+
+            -- Counters: monotonically increasing change counts for get_db_version
+            CREATE TABLE IF NOT EXISTS counters (
+                name TEXT PRIMARY KEY,
+                value INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Settings: User preferences as key/value strings
+            CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            -- Categories: Names available to thoughts, with display color
+            CREATE TABLE IF NOT EXISTS categories (
+                name TEXT PRIMARY KEY,
+                color TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            -- Keyword stats: how many thoughts contain each (stemmed) keyword or two-keyword
+            -- phrase, for IDF weighting
+            CREATE TABLE IF NOT EXISTS keyword_stats (
+                keyword TEXT PRIMARY KEY,
+                doc_count INTEGER NOT NULL DEFAULT 0,
+                is_phrase INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Thought keywords: which keywords and phrase keys each thought contains (the same
+            -- terms keyword_stats counts), for finding thoughts that share terms
+            CREATE TABLE IF NOT EXISTS thought_keywords (
+                thought_id TEXT NOT NULL,
+                keyword TEXT NOT NULL,
+                PRIMARY KEY (thought_id, keyword),
+                FOREIGN KEY (thought_id) REFERENCES thoughts(id)
+            );
+
+            -- Embeddings: optional vector per thought (little-endian f32s) from the named model
+            CREATE TABLE IF NOT EXISTS embeddings (
+                thought_id TEXT PRIMARY KEY,
+                model TEXT NOT NULL,
+                vector BLOB NOT NULL,
+                FOREIGN KEY (thought_id) REFERENCES thoughts(id)
+            );
+
+            -- Views: named camera bookmarks (where the camera is and what it looks at)
+            CREATE TABLE IF NOT EXISTS views (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                position_x REAL NOT NULL,
+                position_y REAL NOT NULL,
+                position_z REAL NOT NULL,
+                target_x REAL NOT NULL,
+                target_y REAL NOT NULL,
+                target_z REAL NOT NULL,
+                zoom REAL NOT NULL DEFAULT 1.0,
+                created_at TEXT NOT NULL
+            );
+
+            -- Thought links: every URL mentioned in a thought's content (see utils::extract_urls)
+            CREATE TABLE IF NOT EXISTS thought_links (
+                thought_id TEXT NOT NULL,
+                url TEXT NOT NULL,
+                PRIMARY KEY (thought_id, url),
+                FOREIGN KEY (thought_id) REFERENCES thoughts(id)
+            );
+
+            -- Connection suggestions: proposed connections awaiting review. A pair is suggested
+            -- at most once; status is pending, accepted, or rejected.
+            CREATE TABLE IF NOT EXISTS connection_suggestions (
+                id TEXT PRIMARY KEY,
+                from_thought TEXT NOT NULL,
+                to_thought TEXT NOT NULL,
+                strength REAL NOT NULL,
+                reason TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                FOREIGN KEY (from_thought) REFERENCES thoughts(id),
+                FOREIGN KEY (to_thought) REFERENCES thoughts(id)
+            );
+
+            -- Attachments: files, images and links associated with a thought. Local files may
+            -- be copied into the attachments folder beside the database (see attachments_dir)
+            CREATE TABLE IF NOT EXISTS attachments (
+                id TEXT PRIMARY KEY,
+                thought_id TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                path_or_url TEXT NOT NULL,
+                label TEXT,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (thought_id) REFERENCES thoughts(id)
+            );
+
+            -- Saved queries: named query definitions (crate::QueryDefinition, as JSON) for run_query
+            CREATE TABLE IF NOT EXISTS saved_queries (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                definition TEXT NOT NULL,
                 created_at TEXT NOT NULL
             );
 
@@ -94,324 +917,3315 @@ impl Database {
             CREATE INDEX IF NOT EXISTS idx_thoughts_content ON thoughts(content);
             CREATE INDEX IF NOT EXISTS idx_connections_from ON connections(from_thought);
             CREATE INDEX IF NOT EXISTS idx_connections_to ON connections(to_thought);
+            CREATE INDEX IF NOT EXISTS idx_thought_links_url ON thought_links(url);
+            CREATE INDEX IF NOT EXISTS idx_thought_keywords_keyword ON thought_keywords(keyword);
+            CREATE INDEX IF NOT EXISTS idx_attachments_thought ON attachments(thought_id);
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_suggestions_pair
+                ON connection_suggestions(min(from_thought, to_thought), max(from_thought, to_thought));
             "#
         )?;
+
+        self.run_migrations()?;
+
+        self.seed_categories()?;
+
+        // Every thought without a role predates roles and came from the assistant via mind_log
+        if self.get_setting(ROLE_BACKFILL_KEY)?.is_none() {
+            self.writer().execute("UPDATE thoughts SET role = 'assistant' WHERE role IS NULL", [])?;
+            self.set_setting(ROLE_BACKFILL_KEY, "1")?;
+        }
+
+        if self.get_setting(LINKS_BACKFILL_KEY)?.is_none() {
+            self.backfill_links()?;
+            self.set_setting(LINKS_BACKFILL_KEY, "1")?;
+        }
+
+        if self.get_setting(CODE_BACKFILL_KEY)?.is_none() {
+            self.backfill_code_metadata()?;
+            self.set_setting(CODE_BACKFILL_KEY, "1")?;
+        }
+
+        // Stats are stemmed keywords, so they're rebuilt whenever extraction changes
+        if self.get_setting(KEYWORD_STATS_VERSION_KEY)?.as_deref() != Some(KEYWORD_STATS_VERSION) {
+            self.rebuild_keyword_stats()?;
+            self.set_setting(KEYWORD_STATS_VERSION_KEY, KEYWORD_STATS_VERSION)?;
+        }
         
         Ok(())
     }
-    
-    pub fn insert_thought(&self, thought: &Thought) -> Result<()> {
-        self.conn.execute(
-            r#"INSERT OR REPLACE INTO thoughts 
-               (id, content, role, category, importance, position_x, position_y, position_z, created_at, last_referenced)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"#,
-            params![
-                thought.id,
-                thought.content,
-                thought.role,
-                thought.category,
-                thought.importance,
-                thought.position_x,
-                thought.position_y,
-                thought.position_z,
-                thought.created_at,
-                thought.last_referenced,
-            ],
-        )?;
+
+    /// Apply the migrations after the database's schema_version (PRAGMA user_version), each
+    /// in its own transaction that also records the new version
+    fn run_migrations(&self) -> Result<()> {
+        let mut conn = self.writer();
+        let current = Self::schema_version(&conn)?;
+        for (index, (name, migrate)) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+            let version = index as i64 + 1;
+            let tx = conn.transaction()?;
+            migrate(&tx)?;
+            tx.pragma_update(None, "user_version", version)?;
+            tx.commit()?;
+            tracing::info!("Applied schema migration {} ({})", version, name);
+        }
         Ok(())
     }
-    
-    pub fn insert_connection(&self, conn: &ThoughtConnection) -> Result<()> {
-        self.conn.execute(
-            r#"INSERT OR REPLACE INTO connections 
-               (id, from_thought, to_thought, strength, reason, created_at)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
+
+    fn schema_version(conn: &Connection) -> Result<i64> {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+    }
+
+    /// How many MIGRATIONS the file has had applied
+    pub fn stored_schema_version(&self) -> Result<i64> {
+        Self::schema_version(&*self.reader()?)
+    }
+
+    /// Add a column to an existing table if an older database doesn't have it yet.
+    /// Returns true when the column was added.
+    fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<bool> {
+        let exists: bool = conn.query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1)", table),
+            [column],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))?;
+        }
+        Ok(!exists)
+    }
+
+    fn row_to_thought(row: &rusqlite::Row) -> Result<Thought> {
+        Ok(Thought {
+            id: row.get(0)?,
+            content: row.get(1)?,
+            role: row.get(2)?,
+            category: row.get(3)?,
+            importance: row.get(4)?,
+            position_x: row.get(5)?,
+            position_y: row.get(6)?,
+            position_z: row.get(7)?,
+            created_at: row.get(8)?,
+            last_referenced: row.get(9)?,
+            pinned: row.get(10)?,
+            favorite: row.get(11)?,
+            archived: row.get(12)?,
+        })
+    }
+
+    fn row_to_connection(row: &rusqlite::Row) -> Result<ThoughtConnection> {
+        Ok(ThoughtConnection {
+            id: row.get(0)?,
+            from_thought: row.get(1)?,
+            to_thought: row.get(2)?,
+            strength: row.get(3)?,
+            reason: row.get(4)?,
+            created_at: row.get(5)?,
+            kind: row.get::<_, Option<String>>(6)?.unwrap_or_else(crate::default_connection_kind),
+        })
+    }
+
+    fn row_to_session(row: &rusqlite::Row) -> Result<crate::Session> {
+        Ok(crate::Session {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            summary: row.get(2)?,
+            started_at: row.get(3)?,
+            ended_at: row.get(4)?,
+        })
+    }
+
+    /// Seed the built-in categories on first run, and register any category already used by a thought
+    fn seed_categories(&self) -> Result<()> {
+        let conn = self.writer();
+        let now = Utc::now().to_rfc3339();
+        let empty: bool = conn.query_row("SELECT NOT EXISTS(SELECT 1 FROM categories)", [], |row| row.get(0))?;
+        if empty {
+            for (name, color, icon) in DEFAULT_CATEGORIES {
+                conn.execute(
+                    "INSERT OR IGNORE INTO categories (name, color, icon, created_at) VALUES (?1, ?2, ?3, ?4)",
+                    params![name, color, icon, now],
+                )?;
+            }
+        }
+        conn.execute(
+            "INSERT OR IGNORE INTO categories (name, created_at)
+             SELECT DISTINCT category, ?1 FROM thoughts WHERE category IS NOT NULL",
+            [&now],
+        )?;
+        Ok(())
+    }
+    
+    /// Insert a thought or update the one with its id; a locked thought is left as it is
+    #[tracing::instrument(level = "debug", skip_all, fields(id = %thought.id))]
+    pub fn insert_thought(&self, thought: &Thought) -> Result<()> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        Self::insert_thought_with(&tx, thought)?;
+        tx.commit()
+    }
+
+    /// Insert many thoughts in one transaction, skipping any whose id is already taken
+    #[tracing::instrument(level = "debug", skip_all, fields(count = thoughts.len()))]
+    pub fn insert_thoughts_batch(&self, thoughts: &[Thought]) -> Result<crate::BatchInsert> {
+        self.insert_tagged_thoughts_batch(thoughts.iter().map(|thought| (thought, None)))
+    }
+
+    /// insert_thoughts_batch, also merging the JSON object given with each thought into its
+    /// metadata (as set_thought_metadata does)
+    pub fn insert_tagged_thoughts_batch<'a>(
+        &self,
+        thoughts: impl IntoIterator<Item = (&'a Thought, Option<&'a str>)>,
+    ) -> Result<crate::BatchInsert> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        let mut result = crate::BatchInsert::default();
+        for (thought, metadata) in thoughts {
+            if Self::thought_content(&tx, &thought.id)?.is_some() {
+                result.skipped += 1;
+                continue;
+            }
+            Self::insert_thought_with(&tx, thought)?;
+            if let Some(metadata) = metadata {
+                Self::merge_metadata_with(&tx, &thought.id, metadata)?;
+            }
+            result.inserted += 1;
+        }
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// Insert `thoughts` and link them to a session in order from `first_position`, all in one
+    /// transaction: either the whole batch lands or none of it does
+    #[tracing::instrument(level = "debug", skip(self, thoughts), fields(count = thoughts.len()))]
+    pub fn insert_session_thoughts(&self, session_id: &str, thoughts: &[Thought], first_position: i64) -> Result<()> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        for (offset, thought) in thoughts.iter().enumerate() {
+            Self::insert_thought_with(&tx, thought)?;
+            tx.prepare_cached(
+                "INSERT OR REPLACE INTO session_thoughts (session_id, thought_id, position) VALUES (?1, ?2, ?3)",
+            )?.execute(params![session_id, thought.id, first_position + offset as i64])?;
+        }
+        tx.commit()
+    }
+
+    /// Insert a thought, or update the content columns of the one with its id. Metadata,
+    /// reference counts, computed importance, cluster membership, created_at and the lock
+    /// survive; a locked thought isn't touched at all. False if it was locked.
+    fn insert_thought_with(tx: &Connection, thought: &Thought) -> Result<bool> {
+        let previous: Option<(String, bool)> = tx
+            .prepare_cached("SELECT content, locked FROM thoughts WHERE id = ?1")?
+            .query_row([&thought.id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?;
+        // A replaced thought's old keywords and embedding no longer count
+        match previous {
+            Some((_, true)) => {
+                tracing::debug!(id = %thought.id, "left locked thought as it is");
+                return Ok(false);
+            }
+            Some((previous, false)) => {
+                Self::adjust_keyword_stats(tx, &thought.id, &previous, -1)?;
+                tx.prepare_cached("DELETE FROM embeddings WHERE thought_id = ?1")?.execute([&thought.id])?;
+            }
+            None => {}
+        }
+
+        let mut stmt = tx.prepare_cached(
+            r#"INSERT INTO thoughts
+               (id, content, role, category, importance, position_x, position_y, position_z, created_at, last_referenced, pinned, favorite, archived)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+               ON CONFLICT(id) DO UPDATE SET
+                   content = excluded.content,
+                   role = excluded.role,
+                   category = excluded.category,
+                   importance = excluded.importance,
+                   position_x = excluded.position_x,
+                   position_y = excluded.position_y,
+                   position_z = excluded.position_z,
+                   last_referenced = excluded.last_referenced,
+                   pinned = excluded.pinned,
+                   favorite = excluded.favorite,
+                   archived = excluded.archived
+               WHERE NOT thoughts.locked"#,
+        )?;
+        stmt.execute(
             params![
-                conn.id,
-                conn.from_thought,
-                conn.to_thought,
-                conn.strength,
-                conn.reason,
-                conn.created_at,
+                thought.id,
+                thought.content,
+                thought.role,
+                thought.category,
+                thought.importance,
+                thought.position_x,
+                thought.position_y,
+                thought.position_z,
+                thought.created_at,
+                thought.last_referenced,
+                thought.pinned,
+                thought.favorite,
+                thought.archived,
             ],
         )?;
+        drop(stmt);
+        Self::adjust_keyword_stats(tx, &thought.id, &thought.content, 1)?;
+        Self::replace_links(tx, &thought.id, &thought.content)?;
+        Self::set_code_metadata(tx, &thought.id, &thought.content)?;
+
+        // New categories become first-class as soon as a thought uses them
+        tx.prepare_cached("INSERT OR IGNORE INTO categories (name, created_at) VALUES (?1, ?2)")?
+            .execute(params![thought.category, thought.created_at])?;
+        Ok(true)
+    }
+
+    fn thought_content(conn: &Connection, id: &str) -> Result<Option<String>> {
+        let mut stmt = conn.prepare_cached("SELECT content FROM thoughts WHERE id = ?1")?;
+        let mut rows = stmt.query_map([id], |row| row.get(0))?;
+        rows.next().transpose()
+    }
+
+    /// Add `delta` to the document count of every keyword and phrase in `content`, dropping rows
+    /// that reach zero, and index them under the thought (or drop its index for a negative delta)
+    fn adjust_keyword_stats(conn: &Connection, thought_id: &str, content: &str, delta: i64) -> Result<()> {
+        let mut upsert = conn.prepare_cached(
+            "INSERT INTO keyword_stats (keyword, doc_count, is_phrase) VALUES (?1, MAX(?2, 0), ?3)
+             ON CONFLICT(keyword) DO UPDATE SET doc_count = doc_count + ?2",
+        )?;
+        let mut index = conn.prepare_cached("INSERT OR IGNORE INTO thought_keywords (thought_id, keyword) VALUES (?1, ?2)")?;
+        let terms = extract_terms(content);
+        let keys = terms.keywords.into_iter().map(|k| (k, false))
+            .chain(terms.phrases.into_iter().map(|p| (p.key, true)));
+        for (key, is_phrase) in keys {
+            upsert.execute(params![key, delta, is_phrase])?;
+            if delta > 0 {
+                index.execute(params![thought_id, key])?;
+            }
+        }
+        if delta < 0 {
+            conn.prepare_cached("DELETE FROM keyword_stats WHERE doc_count <= 0")?.execute([])?;
+            conn.prepare_cached("DELETE FROM thought_keywords WHERE thought_id = ?1")?.execute([thought_id])?;
+        }
+        Ok(())
+    }
+
+    /// Store the URLs in `content` as the thought's links, replacing any it had
+    fn replace_links(conn: &Connection, thought_id: &str, content: &str) -> Result<()> {
+        conn.prepare_cached("DELETE FROM thought_links WHERE thought_id = ?1")?.execute([thought_id])?;
+        let mut insert = conn.prepare_cached("INSERT OR IGNORE INTO thought_links (thought_id, url) VALUES (?1, ?2)")?;
+        for url in crate::utils::extract_urls(content) {
+            insert.execute(params![thought_id, url])?;
+        }
+        Ok(())
+    }
+
+    /// Record in the thought's metadata whether its content has code (has_code) and in
+    /// which language (code_language, null when unknown); see utils::detect_code
+    fn set_code_metadata(conn: &Connection, thought_id: &str, content: &str) -> Result<()> {
+        let code = crate::utils::detect_code(content);
+        conn.prepare_cached(
+            "UPDATE thoughts SET metadata = json_set(
+                 CASE WHEN json_valid(metadata) THEN metadata ELSE '{}' END,
+                 '$.has_code', json(?2), '$.code_language', ?3)
+             WHERE id = ?1",
+        )?.execute(params![
+            thought_id,
+            if code.is_some() { "true" } else { "false" },
+            code.and_then(|c| c.language),
+        ])?;
+        Ok(())
+    }
+
+    fn backfill_code_metadata(&self) -> Result<()> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare("SELECT id, content FROM thoughts")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, content) = row?;
+                Self::set_code_metadata(&tx, &id, &content)?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Extract links from every thought that might have one
+    fn backfill_links(&self) -> Result<()> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare("SELECT id, content FROM thoughts WHERE content LIKE '%http%'")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, content) = row?;
+                Self::replace_links(&tx, &id, &content)?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Recount keyword_stats and reindex thought_keywords from every thought
+    fn rebuild_keyword_stats(&self) -> Result<()> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM keyword_stats", [])?;
+        tx.execute("DELETE FROM thought_keywords", [])?;
+        {
+            let mut stmt = tx.prepare("SELECT id, content FROM thoughts")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, content) = row?;
+                Self::adjust_keyword_stats(&tx, &id, &content, 1)?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Number of thoughts, and how many of them contain each of `keywords` (absent = 0).
+    /// Phrase keys can be looked up the same way.
+    pub fn keyword_document_frequencies(&self, keywords: &[String]) -> Result<(i64, HashMap<String, i64>)> {
+        let conn = self.reader()?;
+        let total: i64 = conn.query_row("SELECT COUNT(*) FROM thoughts", [], |row| row.get(0))?;
+        let mut stmt = conn.prepare_cached("SELECT doc_count FROM keyword_stats WHERE keyword = ?1")?;
+        let mut counts = HashMap::with_capacity(keywords.len());
+        for keyword in keywords {
+            let count: Option<i64> = stmt.query_map([keyword], |row| row.get(0))?.next().transpose()?;
+            counts.insert(keyword.clone(), count.unwrap_or(0));
+        }
+        Ok((total, counts))
+    }
+
+    /// Store a thought's embedding, replacing any earlier one
+    pub fn set_embedding(&self, thought_id: &str, model: &str, vector: &[f32]) -> Result<()> {
+        self.writer().prepare_cached(
+            "INSERT OR REPLACE INTO embeddings (thought_id, model, vector) VALUES (?1, ?2, ?3)",
+        )?.execute(params![thought_id, model, crate::embeddings::vector_to_blob(vector)])?;
         Ok(())
     }
+
+    /// Thoughts with no embedding from `model`, oldest first
+    pub fn thoughts_without_embedding(&self, model: &str, limit: i64) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts
+             WHERE id NOT IN (SELECT thought_id FROM embeddings WHERE model = ?1)
+             ORDER BY created_at LIMIT ?2",
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map(params![model, limit], Self::row_to_thought)?;
+        thoughts.collect()
+    }
+
+    /// Brute-force cosine similarity of `vector` against every stored embedding from `model`
+    /// (archived thoughts left out).
+    /// Returns the closest `limit` thoughts with their similarity, best first.
+    #[tracing::instrument(level = "debug", skip(self, vector))]
+    pub fn semantic_search(&self, vector: &[f32], model: &str, limit: i64) -> Result<Vec<(Thought, f32)>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {}, vector FROM thoughts JOIN embeddings ON thought_id = id WHERE model = ?1 AND archived = 0",
+            THOUGHT_COLUMNS
+        ))?;
+        let rows = stmt.query_map([model], |row| {
+            let blob: Vec<u8> = row.get(13)?;
+            Ok((Self::row_to_thought(row)?, crate::embeddings::blob_to_vector(&blob)))
+        })?;
+
+        let mut scored = Vec::new();
+        for row in rows {
+            let (thought, stored) = row?;
+            scored.push((thought, crate::embeddings::cosine_similarity(vector, &stored)));
+        }
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit.max(0) as usize);
+        Ok(scored)
+    }
+
+    /// Thoughts most like `id` that aren't directly connected to it, best first. Scored by cosine
+    /// similarity of stored embeddings when embeddings are on and the thought has one, otherwise
+    /// by IDF-weighted keyword overlap (as auto-connect scores it) over thought_keywords. Scores
+    /// under the floor (embeddings_min_similarity, or SIMILAR_MIN_KEYWORD_SCORE) are left out
+    /// rather than padding to `limit`.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_similar_thoughts(&self, id: &str, limit: usize) -> Result<Vec<crate::SimilarThought>> {
+        let mut excluded: HashSet<String> = self.get_connections_of_thought(id)?
+            .into_iter()
+            .map(|c| if c.from_thought == id { c.to_thought } else { c.from_thought })
+            .collect();
+        excluded.insert(id.to_string());
+
+        if self.setting_or(crate::embeddings::SETTING_ENABLED, false) {
+            if let Some((model, vector)) = self.thought_embedding(id)? {
+                let floor = self.setting_or(crate::embeddings::SETTING_MIN_SIMILARITY, crate::embeddings::DEFAULT_MIN_SIMILARITY);
+                let hits = self.semantic_search(&vector, &model, (limit + excluded.len()) as i64)?;
+                return Ok(hits.into_iter()
+                    .filter(|(t, similarity)| !excluded.contains(&t.id) && *similarity >= floor)
+                    .take(limit)
+                    .map(|(thought, similarity)| crate::SimilarThought {
+                        thought,
+                        score: f64::from(similarity),
+                        basis: "embedding".to_string(),
+                    })
+                    .collect());
+            }
+        }
+
+        // Every term of this thought and of each thought sharing at least one term with it
+        let (total, rows) = {
+            let conn = self.reader()?;
+            let total: i64 = conn.query_row("SELECT COUNT(*) FROM thoughts", [], |row| row.get(0))?;
+            let mut stmt = conn.prepare_cached(
+                "SELECT k.thought_id, k.keyword, s.doc_count
+                 FROM thought_keywords k JOIN keyword_stats s ON s.keyword = k.keyword
+                 WHERE k.thought_id IN (
+                     SELECT other.thought_id FROM thought_keywords mine
+                     JOIN thought_keywords other ON other.keyword = mine.keyword
+                     WHERE mine.thought_id = ?1)",
+            )?;
+            let rows = stmt.query_map([id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?)))?;
+            (total, rows.collect::<Result<Vec<_>>>()?)
+        };
+
+        let mut weights = HashMap::new();
+        let mut terms: HashMap<String, HashSet<String>> = HashMap::new();
+        for (thought_id, keyword, doc_count) in rows {
+            weights.insert(keyword.clone(), crate::auto_connect::idf(doc_count, total));
+            terms.entry(thought_id).or_default().insert(keyword);
+        }
+        let Some(own) = terms.remove(id) else {
+            return Ok(Vec::new());
+        };
+
+        let mut scored: Vec<(String, f64)> = terms.into_iter()
+            .filter(|(other, _)| !excluded.contains(other))
+            .map(|(other, other_terms)| {
+                let score = crate::auto_connect::weighted_overlap(&own, &other_terms, &weights);
+                (other, score)
+            })
+            .filter(|(_, score)| *score >= SIMILAR_MIN_KEYWORD_SCORE)
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut similar = Vec::with_capacity(limit.min(scored.len()));
+        for (other, score) in scored.into_iter().take(limit) {
+            if let Some(thought) = self.get_thought_by_id(&other)? {
+                similar.push(crate::SimilarThought { thought, score, basis: "keywords".to_string() });
+            }
+        }
+        Ok(similar)
+    }
+
+    /// A thought's stored embedding and the model that made it
+    fn thought_embedding(&self, id: &str) -> Result<Option<(String, Vec<f32>)>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached("SELECT model, vector FROM embeddings WHERE thought_id = ?1")?;
+        let mut rows = stmt.query_map([id], |row| {
+            let blob: Vec<u8> = row.get(1)?;
+            Ok((row.get(0)?, crate::embeddings::blob_to_vector(&blob)))
+        })?;
+        rows.next().transpose()
+    }
+
+    /// The most widespread keywords and phrases, with how many thoughts contain each and
+    /// whether it's a phrase, plus the thought count
+    pub fn get_keyword_stats(&self, limit: i64) -> Result<(i64, Vec<KeywordCount>)> {
+        let conn = self.reader()?;
+        let total: i64 = conn.query_row("SELECT COUNT(*) FROM thoughts", [], |row| row.get(0))?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT keyword, doc_count, is_phrase FROM keyword_stats ORDER BY doc_count DESC, keyword LIMIT ?1",
+        )?;
+        let stats = stmt.query_map([limit.clamp(1, MAX_PAGE_SIZE)], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        Ok((total, stats.collect::<Result<_>>()?))
+    }
     
-    pub fn get_all_thoughts(&self) -> Result<Vec<Thought>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, content, role, category, importance, position_x, position_y, position_z, created_at, last_referenced FROM thoughts"
+    /// The keywords (not phrases) in the most thoughts, optionally only thoughts in `category`
+    /// and created since `since` (RFC3339), with their summed importance and how many matching
+    /// thoughts came in the last 7 days and the 7 before. Stems under 3 characters and
+    /// `stopwords` are skipped; each keyword is returned as it's most often written.
+    pub fn get_top_keywords(
+        &self,
+        limit: i64,
+        category: Option<&str>,
+        since: Option<&str>,
+        stopwords: &HashSet<String>,
+    ) -> Result<Vec<crate::TopKeyword>> {
+        let conn = self.reader()?;
+        let stopwords = serde_json::to_string(stopwords).unwrap_or_default();
+        // The trend windows ignore `since`, so a recent cutoff doesn't make every keyword look new
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT k.keyword,
+                      SUM(?2 IS NULL OR julianday(t.created_at) >= julianday(?2)) AS docs,
+                      SUM(CASE WHEN ?2 IS NULL OR julianday(t.created_at) >= julianday(?2) THEN t.importance ELSE 0 END),
+                      SUM(julianday(t.created_at) >= julianday('now', '-7 days')),
+                      SUM(julianday(t.created_at) >= julianday('now', '-14 days')
+                          AND julianday(t.created_at) < julianday('now', '-7 days'))
+               FROM thought_keywords k
+               JOIN keyword_stats s ON s.keyword = k.keyword AND s.is_phrase = 0
+               JOIN thoughts t ON t.id = k.thought_id
+               WHERE (?1 IS NULL OR t.category = ?1)
+                 AND length(k.keyword) >= 3
+                 AND k.keyword NOT IN (SELECT value FROM json_each(?3))
+               GROUP BY k.keyword
+               HAVING docs > 0
+               ORDER BY docs DESC, 3 DESC, k.keyword
+               LIMIT ?4"#,
         )?;
-        
-        let thoughts = stmt.query_map([], |row| {
-            Ok(Thought {
-                id: row.get(0)?,
-                content: row.get(1)?,
-                role: row.get(2)?,
-                category: row.get(3)?,
-                importance: row.get(4)?,
-                position_x: row.get(5)?,
-                position_y: row.get(6)?,
-                position_z: row.get(7)?,
-                created_at: row.get(8)?,
-                last_referenced: row.get(9)?,
+        let rows = stmt.query_map(params![category, since, stopwords, limit.clamp(1, MAX_PAGE_SIZE)], |row| {
+            Ok(crate::TopKeyword {
+                stem: row.get(0)?,
+                keyword: String::new(),
+                doc_count: row.get(1)?,
+                importance_sum: row.get(2)?,
+                last_7_days: row.get(3)?,
+                previous_7_days: row.get(4)?,
+            })
+        })?;
+        let mut keywords = rows.collect::<Result<Vec<_>>>()?;
+
+        // Display forms from a sample of the thoughts behind each keyword
+        let stems = serde_json::to_string(&keywords.iter().map(|k| &k.stem).collect::<Vec<_>>()).unwrap_or_default();
+        let mut stmt = conn.prepare_cached(
+            "SELECT content FROM (
+                 SELECT t.content, ROW_NUMBER() OVER (PARTITION BY k.keyword ORDER BY t.created_at DESC) AS n
+                 FROM thought_keywords k JOIN thoughts t ON t.id = k.thought_id
+                 WHERE k.keyword IN (SELECT value FROM json_each(?1))
+             ) WHERE n <= 20",
+        )?;
+        let contents = stmt.query_map([stems], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>>>()?;
+        let written = written_forms(contents.iter().map(String::as_str));
+        for keyword in &mut keywords {
+            keyword.keyword = display_form(&written, keyword.stem.clone());
+        }
+        Ok(keywords)
+    }
+
+    pub fn insert_connection(&self, conn: &ThoughtConnection) -> Result<()> {
+        let writer = self.writer();
+        Self::insert_connection_with(&writer, conn)
+    }
+
+    /// Insert many connections in a single transaction with one prepared statement, skipping
+    /// any whose id is already taken
+    #[tracing::instrument(level = "debug", skip_all, fields(count = conns.len()))]
+    pub fn insert_connections_batch(&self, conns: &[ThoughtConnection]) -> Result<crate::BatchInsert> {
+        let mut writer = self.writer();
+        let tx = writer.transaction()?;
+        let mut result = crate::BatchInsert::default();
+        for conn in conns {
+            let taken: i64 = tx.prepare_cached("SELECT COUNT(*) FROM connections WHERE id = ?1")?
+                .query_row([&conn.id], |row| row.get(0))?;
+            if taken > 0 {
+                result.skipped += 1;
+                continue;
+            }
+            Self::insert_connection_with(&tx, conn)?;
+            result.inserted += 1;
+        }
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// Insert automatic connections, strongest first, keeping every thought at no more than
+    /// `cap` connections. A connection that would go over on an endpoint replaces that
+    /// endpoint's weakest automatic connection if it's stronger, and is skipped otherwise (also
+    /// when the endpoint only has manual connections). One transaction for the whole batch.
+    /// Returns the connections inserted and how many existing ones were evicted.
+    pub fn insert_connections_capped(&self, conns: &[ThoughtConnection], cap: usize) -> Result<(Vec<ThoughtConnection>, usize)> {
+        let mut writer = self.writer();
+        let tx = writer.transaction()?;
+        let mut inserted = Vec::with_capacity(conns.len());
+        let mut evicted = 0;
+        for conn in conns {
+            // Decide for both endpoints before touching either
+            let mut evictions = Vec::new();
+            let mut fits = true;
+            for endpoint in [&conn.from_thought, &conn.to_thought] {
+                if Self::connection_degree(&tx, endpoint)? < cap as i64 {
+                    continue;
+                }
+                match Self::weakest_auto_connection(&tx, endpoint)? {
+                    Some((id, strength)) if strength < conn.strength => evictions.push(id),
+                    _ => {
+                        fits = false;
+                        break;
+                    }
+                }
+            }
+            if !fits {
+                continue;
+            }
+            for id in &evictions {
+                evicted += tx.prepare_cached("DELETE FROM connections WHERE id = ?1")?.execute([id])?;
+            }
+            Self::insert_connection_with(&tx, conn)?;
+            inserted.push(conn.clone());
+        }
+        tx.commit()?;
+        Ok((inserted, evicted))
+    }
+
+    fn connection_degree(conn: &Connection, thought_id: &str) -> Result<i64> {
+        conn.prepare_cached("SELECT COUNT(*) FROM connections WHERE from_thought = ?1 OR to_thought = ?1")?
+            .query_row([thought_id], |row| row.get(0))
+    }
+
+    /// Id and strength of the thought's weakest automatically created connection
+    fn weakest_auto_connection(conn: &Connection, thought_id: &str) -> Result<Option<(String, f64)>> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, strength FROM connections
+             WHERE (from_thought = ?1 OR to_thought = ?1) AND kind IN (?2, ?3)
+             ORDER BY strength ASC, created_at ASC
+             LIMIT 1",
+        )?;
+        let mut rows = stmt.query_map(params![thought_id, AUTO_CREATED_KINDS[0], AUTO_CREATED_KINDS[1]], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.next().transpose()
+    }
+
+    /// Thoughts with more than `threshold` connections, most connected first, with their total
+    /// and automatic connection counts
+    pub fn get_overconnected_thoughts(&self, threshold: i64) -> Result<Vec<crate::OverconnectedThought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {}, d.total, d.auto
+             FROM thoughts
+             JOIN (SELECT thought_id, COUNT(*) AS total, SUM(kind IN (?2, ?3)) AS auto
+                   FROM (SELECT from_thought AS thought_id, kind FROM connections
+                         UNION ALL
+                         SELECT to_thought, kind FROM connections)
+                   GROUP BY thought_id) d ON d.thought_id = id
+             WHERE d.total > ?1
+             ORDER BY d.total DESC, id",
+            THOUGHT_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![threshold, AUTO_CREATED_KINDS[0], AUTO_CREATED_KINDS[1]], |row| {
+            Ok(crate::OverconnectedThought {
+                thought: Self::row_to_thought(row)?,
+                connections: row.get(13)?,
+                auto_connections: row.get(14)?,
             })
         })?;
+        rows.collect()
+    }
+
+    fn insert_connection_with(db: &Connection, conn: &ThoughtConnection) -> Result<()> {
+        let mut stmt = db.prepare_cached(
+            r#"INSERT OR REPLACE INTO connections 
+               (id, from_thought, to_thought, strength, reason, created_at, kind, last_reinforced)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?6)"#,
+        )?;
+        stmt.execute(params![
+            conn.id,
+            conn.from_thought,
+            conn.to_thought,
+            conn.strength,
+            conn.reason,
+            conn.created_at,
+            conn.kind,
+        ])?;
+        Ok(())
+    }
+    
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn get_all_thoughts(&self) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            &format!("SELECT {} FROM thoughts", THOUGHT_COLUMNS)
+        )?;
+        
+        let thoughts = stmt.query_map([], Self::row_to_thought)?;
         
         thoughts.collect()
     }
     
+    /// One page of unarchived thoughts in a stable order: the id tiebreak means consecutive
+    /// pages never skip or repeat rows that share a sort value.
+    pub fn get_thoughts_page(&self, offset: i64, limit: i64, sort: ThoughtSort, ascending: bool) -> Result<Vec<Thought>> {
+        let direction = if ascending { "ASC" } else { "DESC" };
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts WHERE archived = 0 ORDER BY {} {dir}, id {dir} LIMIT ?1 OFFSET ?2",
+            THOUGHT_COLUMNS,
+            sort.column(),
+            dir = direction
+        ))?;
+        let thoughts = stmt.query_map(params![limit.clamp(0, MAX_PAGE_SIZE), offset.max(0)], Self::row_to_thought)?;
+        thoughts.collect()
+    }
+
+    /// Up to `max` thoughts fetched page by page, oldest first. The flag is true
+    /// when the cap cut the result short.
+    pub fn get_thoughts_capped(&self, max: usize) -> Result<(Vec<Thought>, bool)> {
+        let mut thoughts = Vec::new();
+        loop {
+            let page = self.get_thoughts_page(thoughts.len() as i64, MAX_PAGE_SIZE, ThoughtSort::CreatedAt, true)?;
+            let exhausted = (page.len() as i64) < MAX_PAGE_SIZE;
+            thoughts.extend(page);
+
+            if thoughts.len() > max {
+                thoughts.truncate(max);
+                return Ok((thoughts, true));
+            }
+            if exhausted {
+                return Ok((thoughts, false));
+            }
+        }
+    }
+
+    /// One page of connections ordered by creation time (ties broken by id)
+    pub fn get_connections_page(&self, offset: i64, limit: i64, ascending: bool) -> Result<Vec<ThoughtConnection>> {
+        let direction = if ascending { "ASC" } else { "DESC" };
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM connections ORDER BY created_at {dir}, id {dir} LIMIT ?1 OFFSET ?2",
+            CONNECTION_COLUMNS,
+            dir = direction
+        ))?;
+        let connections = stmt.query_map(params![limit.clamp(0, MAX_PAGE_SIZE), offset.max(0)], Self::row_to_connection)?;
+        connections.collect()
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
     pub fn get_all_connections(&self) -> Result<Vec<ThoughtConnection>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, from_thought, to_thought, strength, reason, created_at FROM connections"
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            &format!("SELECT {} FROM connections", CONNECTION_COLUMNS)
         )?;
         
-        let connections = stmt.query_map([], |row| {
-            Ok(ThoughtConnection {
-                id: row.get(0)?,
-                from_thought: row.get(1)?,
-                to_thought: row.get(2)?,
-                strength: row.get(3)?,
-                reason: row.get(4)?,
-                created_at: row.get(5)?,
-            })
-        })?;
+        let connections = stmt.query_map([], Self::row_to_connection)?;
         
         connections.collect()
     }
     
     pub fn search_thoughts(&self, query: &str) -> Result<Vec<Thought>> {
-        let search_pattern = format!("%{}%", query);
-        let mut stmt = self.conn.prepare(
-            "SELECT id, content, role, category, importance, position_x, position_y, position_z, created_at, last_referenced 
+        self.search_thoughts_by(query, false, None)
+    }
+
+    /// Like search_thoughts, optionally ranking by computed importance (see recompute_importance)
+    /// and keeping only thoughts with the given role. Archived thoughts never match.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn search_thoughts_by(&self, query: &str, by_computed: bool, role: Option<&str>) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let search_pattern = like_pattern(query);
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} 
              FROM thoughts 
-             WHERE content LIKE ?1
-             ORDER BY importance DESC, last_referenced DESC
-             LIMIT 20"
-        )?;
+             WHERE content LIKE ?1 ESCAPE '\\' AND (?2 IS NULL OR role = ?2) AND archived = 0
+             ORDER BY {} DESC, favorite DESC, last_referenced DESC
+             LIMIT 20",
+            THOUGHT_COLUMNS,
+            importance_expr(by_computed)
+        ))?;
         
-        let thoughts = stmt.query_map([search_pattern], |row| {
-            Ok(Thought {
-                id: row.get(0)?,
-                content: row.get(1)?,
-                role: row.get(2)?,
-                category: row.get(3)?,
-                importance: row.get(4)?,
-                position_x: row.get(5)?,
-                position_y: row.get(6)?,
-                position_z: row.get(7)?,
-                created_at: row.get(8)?,
-                last_referenced: row.get(9)?,
-            })
-        })?;
+        let thoughts = stmt.query_map(params![search_pattern, role], Self::row_to_thought)?;
         
         thoughts.collect()
     }
-    
-    pub fn insert_session(&self, id: &str, title: &str, summary: &str, started_at: &str, ended_at: &str) -> Result<()> {
-        self.conn.execute(
-            r#"INSERT OR REPLACE INTO sessions
-               (id, title, summary, started_at, ended_at)
-               VALUES (?1, ?2, ?3, ?4, ?5)"#,
-            params![id, title, summary, started_at, ended_at],
+
+    /// The first `max_chars` characters of up to `limit` thoughts, most recently referenced first
+    pub fn recent_thought_contents(&self, limit: i64, max_chars: i64) -> Result<Vec<String>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT substr(content, 1, ?2) FROM thoughts ORDER BY last_referenced DESC LIMIT ?1",
         )?;
-        Ok(())
+        let contents = stmt.query_map(params![limit, max_chars], |row| row.get(0))?;
+        contents.collect()
     }
 
-    pub fn get_all_sessions(&self) -> Result<Vec<crate::Session>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, summary, started_at, ended_at FROM sessions ORDER BY started_at DESC"
-        )?;
+    /// Thoughts from one role, newest first
+    pub fn get_thoughts_by_role(&self, role: &str, limit: usize) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts WHERE role = ?1 ORDER BY created_at DESC LIMIT ?2",
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map(params![role, limit as i64], Self::row_to_thought)?;
+        thoughts.collect()
+    }
+    
+    pub fn get_thought_by_id(&self, id: &str) -> Result<Option<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM thoughts WHERE id = ?1", THOUGHT_COLUMNS))?;
+        let mut rows = stmt.query_map([id], Self::row_to_thought)?;
+        rows.next().transpose()
+    }
 
-        let sessions = stmt.query_map([], |row| {
-            Ok(crate::Session {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                summary: row.get(2)?,
-                started_at: row.get(3)?,
-                ended_at: row.get(4)?,
-            })
+    /// Unarchived thoughts most due for revisiting: importance × days since last referenced,
+    /// highest first. Returns (thought, days since last referenced, score).
+    pub fn get_review_queue(&self, limit: i64, by_computed: bool) -> Result<Vec<(Thought, f64, f64)>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {}, days, {} * days AS score
+             FROM (SELECT *, MAX(julianday('now') - julianday(last_referenced), 0) AS days FROM thoughts WHERE archived = 0)
+             ORDER BY score DESC, id
+             LIMIT ?1",
+            THOUGHT_COLUMNS,
+            importance_expr(by_computed)
+        ))?;
+        let queue = stmt.query_map([limit], |row| {
+            Ok((Self::row_to_thought(row)?, row.get(13)?, row.get(14)?))
         })?;
-
-        sessions.collect()
+        queue.collect()
     }
 
-    pub fn get_max_thought_rowid(&self) -> Result<i64> {
-        self.conn.query_row(
-            "SELECT COALESCE(MAX(rowid), 0) FROM thoughts",
-            [],
-            |row| row.get(0),
-        )
+    /// Touch last_referenced and bump the reference counter. False if the thought doesn't exist.
+    pub fn mark_reviewed(&self, id: &str) -> Result<bool> {
+        let changed = self.writer().execute(
+            "UPDATE thoughts SET last_referenced = ?2, reference_count = COALESCE(reference_count, 0) + 1 WHERE id = ?1",
+            params![id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(changed > 0)
     }
 
-    pub fn get_max_connection_rowid(&self) -> Result<i64> {
-        self.conn.query_row(
-            "SELECT COALESCE(MAX(rowid), 0) FROM connections",
-            [],
-            |row| row.get(0),
-        )
+    /// Lock or unlock a thought. Locked thoughts are left alone by MCP tools, forge re-imports,
+    /// bulk operations and importance recomputation. False if the thought doesn't exist.
+    pub fn set_thought_locked(&self, id: &str, locked: bool) -> Result<bool> {
+        let changed = self.writer().execute("UPDATE thoughts SET locked = ?2 WHERE id = ?1", params![id, locked])?;
+        Ok(changed > 0)
     }
 
-    pub fn get_thought_count(&self) -> Result<i64> {
-        self.conn.query_row(
-            "SELECT COUNT(*) FROM thoughts",
-            [],
+    /// False also when the thought doesn't exist
+    pub fn is_thought_locked(&self, id: &str) -> Result<bool> {
+        self.reader()?.query_row(
+            "SELECT EXISTS(SELECT 1 FROM thoughts WHERE id = ?1 AND locked)",
+            [id],
             |row| row.get(0),
         )
     }
 
-    /// Get thoughts within a radius of a 3D point, sorted by distance.
-    /// Uses Euclidean distance calculated in SQL for efficiency.
-    pub fn get_thoughts_near(&self, x: f64, y: f64, z: f64, radius: f64, limit: i64) -> Result<Vec<Thought>> {
-        let mut stmt = self.conn.prepare(
-            r#"SELECT id, content, role, category, importance, position_x, position_y, position_z, created_at, last_referenced,
-                      ((position_x - ?1) * (position_x - ?1) +
-                       (position_y - ?2) * (position_y - ?2) +
-                       (position_z - ?3) * (position_z - ?3)) AS dist_sq
-               FROM thoughts
-               WHERE dist_sq <= (?4 * ?4)
-               ORDER BY dist_sq ASC
-               LIMIT ?5"#
-        )?;
-
-        let thoughts = stmt.query_map(params![x, y, z, radius, limit], |row| {
-            Ok(Thought {
-                id: row.get(0)?,
-                content: row.get(1)?,
-                role: row.get(2)?,
-                category: row.get(3)?,
-                importance: row.get(4)?,
-                position_x: row.get(5)?,
-                position_y: row.get(6)?,
-                position_z: row.get(7)?,
-                created_at: row.get(8)?,
-                last_referenced: row.get(9)?,
-            })
-        })?;
+    /// Which of `ids` are locked
+    pub fn locked_among(&self, ids: &[String]) -> Result<HashSet<String>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id FROM thoughts WHERE locked AND id IN (SELECT value FROM json_each(?1))",
+        )?;
+        let ids = serde_json::to_string(ids).unwrap_or_default();
+        let locked = stmt.query_map([ids], |row| row.get(0))?;
+        locked.collect()
+    }
 
-        thoughts.collect()
+    /// How many locked thoughts match `filter` (and so are left out of bulk operations)
+    pub fn count_locked_matching(&self, filter: &crate::ThoughtFilter) -> Result<usize> {
+        let (clause, values) = filter_clause(filter);
+        let count: i64 = self.reader()?.query_row(
+            &format!("SELECT COUNT(*) FROM thoughts WHERE ({}) AND locked", clause),
+            rusqlite::params_from_iter(&values),
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
     }
 
-    /// Get connections where both endpoints are in the given thought ID set
-    pub fn get_connections_for_thoughts(&self, ids: &[String]) -> Result<Vec<ThoughtConnection>> {
-        if ids.is_empty() {
-            return Ok(Vec::new());
-        }
+    /// False if the thought doesn't exist
+    pub fn set_thought_pinned(&self, id: &str, pinned: bool) -> Result<bool> {
+        let changed = self.writer().execute("UPDATE thoughts SET pinned = ?2 WHERE id = ?1", params![id, pinned])?;
+        Ok(changed > 0)
+    }
 
-        // Build placeholders: (?1, ?2, ?3, ...)
-        let placeholders: Vec<String> = (1..=ids.len()).map(|i| format!("?{}", i)).collect();
-        let ph = placeholders.join(", ");
+    /// False if the thought doesn't exist
+    pub fn set_thought_favorite(&self, id: &str, favorite: bool) -> Result<bool> {
+        let changed = self.writer().execute("UPDATE thoughts SET favorite = ?2 WHERE id = ?1", params![id, favorite])?;
+        Ok(changed > 0)
+    }
 
-        let sql = format!(
-            "SELECT id, from_thought, to_thought, strength, reason, created_at FROM connections WHERE from_thought IN ({ph}) AND to_thought IN ({ph})"
-        );
+    /// Favorite thoughts, most important first
+    pub fn get_favorite_thoughts(&self) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts WHERE favorite ORDER BY importance DESC, last_referenced DESC",
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map([], Self::row_to_thought)?;
+        thoughts.collect()
+    }
 
-        let mut stmt = self.conn.prepare(&sql)?;
+    /// Thought whose content equals `content`, ignoring case and surrounding whitespace
+    pub fn find_thought_by_content(&self, content: &str) -> Result<Option<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts WHERE lower(trim(content)) = lower(trim(?1))
+             ORDER BY importance DESC LIMIT 1",
+            THOUGHT_COLUMNS
+        ))?;
+        let mut rows = stmt.query_map([content], Self::row_to_thought)?;
+        rows.next().transpose()
+    }
 
-        // Bind IDs twice (once for from_thought IN, once for to_thought IN)
-        let mut param_values: Vec<&dyn rusqlite::types::ToSql> = Vec::new();
-        for id in ids.iter() {
-            param_values.push(id);
-        }
-        for id in ids.iter() {
-            param_values.push(id);
-        }
+    /// Thoughts created at or after `since` (RFC3339), newest first
+    pub fn get_thoughts_since(&self, since: &str) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts WHERE created_at >= ?1 ORDER BY created_at DESC",
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map([since], Self::row_to_thought)?;
+        thoughts.collect()
+    }
 
-        let connections = stmt.query_map(rusqlite::params_from_iter(param_values), |row| {
-            Ok(ThoughtConnection {
-                id: row.get(0)?,
-                from_thought: row.get(1)?,
-                to_thought: row.get(2)?,
-                strength: row.get(3)?,
-                reason: row.get(4)?,
-                created_at: row.get(5)?,
-            })
-        })?;
+    /// created_at of the newest thought other than `excluding_id`
+    pub fn latest_thought_created_at(&self, excluding_id: &str) -> Result<Option<String>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT created_at FROM thoughts WHERE id != ?1 ORDER BY julianday(created_at) DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query_map([excluding_id], |row| row.get(0))?;
+        rows.next().transpose()
+    }
 
-        connections.collect()
+    /// Thought previously imported from `source` (e.g. a session-forge file) for the entry at `timestamp`
+    pub fn find_thought_by_source(&self, source: &str, timestamp: &str) -> Result<Option<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts
+             WHERE json_valid(metadata)
+               AND json_extract(metadata, '$.source') = ?1
+               AND json_extract(metadata, '$.source_timestamp') = ?2
+             LIMIT 1",
+            THOUGHT_COLUMNS
+        ))?;
+        let mut rows = stmt.query_map([source, timestamp], Self::row_to_thought)?;
+        rows.next().transpose()
     }
 
-    /// Recompute clusters by grouping thoughts by category and averaging positions.
-    /// Replaces all existing clusters.
-    pub fn compute_clusters(&self) -> Result<Vec<crate::Cluster>> {
-        // Delete old clusters
-        self.conn.execute("DELETE FROM clusters", [])?;
-
-        // Group thoughts by category and compute centroids
-        let mut stmt = self.conn.prepare(
-            r#"SELECT category,
-                      AVG(position_x), AVG(position_y), AVG(position_z),
-                      COUNT(*)
-               FROM thoughts
-               GROUP BY category
-               HAVING COUNT(*) >= 2"#
-        )?;
+    /// Every thought whose metadata names `source`
+    pub fn get_thoughts_by_source(&self, source: &str) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts WHERE json_valid(metadata) AND json_extract(metadata, '$.source') = ?1",
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map([source], Self::row_to_thought)?;
+        thoughts.collect()
+    }
 
-        let now = Utc::now().to_rfc3339();
-        let mut clusters = Vec::new();
-
-        let rows = stmt.query_map([], |row| {
-            let category: String = row.get(0)?;
-            let cx: f64 = row.get(1)?;
-            let cy: f64 = row.get(2)?;
-            let cz: f64 = row.get(3)?;
-            let count: i64 = row.get(4)?;
-            Ok((category, cx, cy, cz, count))
+    /// Thoughts that mention at least one URL, each with all of its URLs, newest first.
+    /// With `url_substring`, only thoughts with a URL containing it.
+    pub fn get_thoughts_with_links(&self, url_substring: Option<&str>) -> Result<Vec<crate::ThoughtLinks>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {}, l.url FROM thoughts JOIN thought_links l ON l.thought_id = id
+               WHERE ?1 IS NULL OR id IN (SELECT thought_id FROM thought_links WHERE url LIKE ?1 ESCAPE '\')
+               ORDER BY created_at DESC, id, l.url"#,
+            THOUGHT_COLUMNS
+        ))?;
+        let rows = stmt.query_map([url_substring.map(like_pattern)], |row| {
+            Ok((Self::row_to_thought(row)?, row.get::<_, String>(13)?))
         })?;
 
+        // Rows come grouped by thought, one per link
+        let mut result: Vec<crate::ThoughtLinks> = Vec::new();
         for row in rows {
-            let (category, cx, cy, cz, count) = row?;
-            let id = Uuid::new_v4().to_string();
-            let name = format!("{} cluster", category);
-
-            self.conn.execute(
-                r#"INSERT INTO clusters (id, name, category, center_x, center_y, center_z, thought_count, created_at)
-                   VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)"#,
-                params![id, name, category, cx, cy, cz, count, now],
-            )?;
-
-            clusters.push(crate::Cluster {
-                id,
-                name,
-                category,
-                center_x: cx,
-                center_y: cy,
-                center_z: cz,
-                thought_count: count,
-                created_at: now.clone(),
-            });
+            let (thought, url) = row?;
+            match result.last_mut() {
+                Some(last) if last.thought.id == thought.id => last.links.push(url),
+                _ => result.push(crate::ThoughtLinks { thought, links: vec![url] }),
+            }
         }
-
-        Ok(clusters)
+        Ok(result)
     }
 
-    pub fn get_all_clusters(&self) -> Result<Vec<crate::Cluster>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, name, category, center_x, center_y, center_z, thought_count, created_at FROM clusters"
-        )?;
-
-        let clusters = stmt.query_map([], |row| {
-            Ok(crate::Cluster {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                category: row.get(2)?,
-                center_x: row.get(3)?,
-                center_y: row.get(4)?,
-                center_z: row.get(5)?,
-                thought_count: row.get(6)?,
-                created_at: row.get(7)?,
-            })
-        })?;
+    /// Merge a JSON object into a thought's metadata (provenance for imported thoughts);
+    /// keys it doesn't mention, like has_code, are kept
+    pub fn set_thought_metadata(&self, id: &str, metadata: &str) -> Result<()> {
+        Self::merge_metadata_with(&self.writer(), id, metadata)
+    }
 
+    fn merge_metadata_with(conn: &Connection, id: &str, metadata: &str) -> Result<()> {
+        conn.prepare_cached(
+            "UPDATE thoughts SET metadata = json_patch(CASE WHEN json_valid(metadata) THEN metadata ELSE '{}' END, ?2)
+             WHERE id = ?1",
+        )?.execute(params![id, metadata])?;
+        Ok(())
+    }
+
+    /// Thoughts whose content has code, optionally only in `language`, newest first
+    pub fn get_thoughts_with_code(&self, language: Option<&str>) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts
+             WHERE json_valid(metadata) AND json_extract(metadata, '$.has_code') = 1
+               AND (?1 IS NULL OR json_extract(metadata, '$.code_language') = lower(?1))
+             ORDER BY created_at DESC",
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map([language], Self::row_to_thought)?;
+        thoughts.collect()
+    }
+
+    /// Replace a thought's content and category, keeping its position and history
+    pub fn update_thought_content(&self, id: &str, content: &str, category: &str) -> Result<()> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        let Some(previous) = Self::thought_content(&tx, id)? else {
+            return Ok(());
+        };
+
+        tx.execute(
+            "UPDATE thoughts SET content = ?2, category = ?3 WHERE id = ?1",
+            params![id, content, category],
+        )?;
+        Self::adjust_keyword_stats(&tx, id, &previous, -1)?;
+        Self::adjust_keyword_stats(&tx, id, content, 1)?;
+        if previous != content {
+            tx.prepare_cached("DELETE FROM embeddings WHERE thought_id = ?1")?.execute([id])?;
+            Self::replace_links(&tx, id, content)?;
+            Self::set_code_metadata(&tx, id, content)?;
+        }
+        tx.prepare_cached("INSERT OR IGNORE INTO categories (name, created_at) VALUES (?1, ?2)")?
+            .execute(params![category, Utc::now().to_rfc3339()])?;
+        tx.commit()
+    }
+
+    /// Whether any connection links the two thoughts, in either direction
+    pub fn connection_exists(&self, a: &str, b: &str) -> Result<bool> {
+        self.reader()?.query_row(
+            "SELECT EXISTS(SELECT 1 FROM connections
+                           WHERE (from_thought = ?1 AND to_thought = ?2) OR (from_thought = ?2 AND to_thought = ?1))",
+            [a, b],
+            |row| row.get(0),
+        )
+    }
+
+    /// Record that a thought came up again: set its importance and touch last_referenced
+    pub fn reinforce_thought(&self, id: &str, importance: f64) -> Result<()> {
+        self.writer().execute(
+            "UPDATE thoughts SET importance = ?2, last_referenced = ?3 WHERE id = ?1",
+            params![id, importance, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Search connection reasons, returning each match with both endpoints' content
+    pub fn search_connections(&self, query: &str, limit: i64) -> Result<Vec<crate::ConnectionMatch>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT c.id, c.from_thought, c.to_thought, c.strength, c.reason, c.created_at, c.kind,
+                      f.content, t.content
+               FROM connections c
+               JOIN thoughts f ON f.id = c.from_thought
+               JOIN thoughts t ON t.id = c.to_thought
+               WHERE c.reason LIKE ?1 ESCAPE '\'
+               ORDER BY c.strength DESC, c.created_at DESC
+               LIMIT ?2"#
+        )?;
+
+        let matches = stmt.query_map(params![like_pattern(query), limit], |row| {
+            Ok(crate::ConnectionMatch {
+                connection: Self::row_to_connection(row)?,
+                from_content: row.get(7)?,
+                to_content: row.get(8)?,
+            })
+        })?;
+
+        matches.collect()
+    }
+    
+    /// Store proposed connections as pending suggestions in one transaction. A pair that was
+    /// already suggested (whatever became of it) is skipped. Returns how many were stored.
+    pub fn insert_suggestions(&self, proposed: &[ThoughtConnection]) -> Result<usize> {
+        let mut writer = self.writer();
+        let tx = writer.transaction()?;
+        let mut stored = 0;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT OR IGNORE INTO connection_suggestions
+                 (id, from_thought, to_thought, strength, reason, created_at, kind, status)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'pending')",
+            )?;
+            for c in proposed {
+                stored += stmt.execute(params![c.id, c.from_thought, c.to_thought, c.strength, c.reason, c.created_at, c.kind])?;
+            }
+        }
+        tx.commit()?;
+        Ok(stored)
+    }
+
+    /// Whether the two thoughts were ever suggested as a pair, in either direction
+    pub fn suggestion_exists(&self, a: &str, b: &str) -> Result<bool> {
+        self.reader()?.query_row(
+            "SELECT EXISTS(SELECT 1 FROM connection_suggestions
+                           WHERE min(from_thought, to_thought) = min(?1, ?2) AND max(from_thought, to_thought) = max(?1, ?2))",
+            [a, b],
+            |row| row.get(0),
+        )
+    }
+
+    /// Pending suggestions, strongest first, with both thoughts' text
+    pub fn get_pending_suggestions(&self, limit: i64) -> Result<Vec<crate::ConnectionSuggestion>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT s.id, s.from_thought, s.to_thought, s.strength, s.reason, s.created_at, s.kind,
+                      f.content, t.content
+               FROM connection_suggestions s
+               JOIN thoughts f ON f.id = s.from_thought
+               JOIN thoughts t ON t.id = s.to_thought
+               WHERE s.status = 'pending'
+               ORDER BY s.strength DESC, s.created_at DESC
+               LIMIT ?1"#
+        )?;
+        let suggestions = stmt.query_map([limit], |row| {
+            Ok(crate::ConnectionSuggestion {
+                connection: Self::row_to_connection(row)?,
+                from_content: row.get(7)?,
+                to_content: row.get(8)?,
+            })
+        })?;
+        suggestions.collect()
+    }
+
+    pub fn count_pending_suggestions(&self) -> Result<i64> {
+        self.reader()?.query_row(
+            "SELECT COUNT(*) FROM connection_suggestions WHERE status = 'pending'",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Turn a pending suggestion into a real connection (with a fresh id and timestamp) and mark
+    /// it accepted. None if there's no pending suggestion with that id.
+    pub fn accept_suggestion(&self, id: &str) -> Result<Option<ThoughtConnection>> {
+        let mut writer = self.writer();
+        let tx = writer.transaction()?;
+        let suggestion = {
+            let mut stmt = tx.prepare_cached(&format!(
+                "SELECT {} FROM connection_suggestions WHERE id = ?1 AND status = 'pending'",
+                CONNECTION_COLUMNS
+            ))?;
+            let mut rows = stmt.query_map([id], Self::row_to_connection)?;
+            rows.next().transpose()?
+        };
+        let Some(mut connection) = suggestion else {
+            return Ok(None);
+        };
+        connection.id = Uuid::new_v4().to_string();
+        connection.created_at = Utc::now().to_rfc3339();
+        Self::insert_connection_with(&tx, &connection)?;
+        tx.execute("UPDATE connection_suggestions SET status = 'accepted' WHERE id = ?1", [id])?;
+        tx.commit()?;
+        Ok(Some(connection))
+    }
+
+    /// Mark a pending suggestion rejected so the pair is never suggested again. False if there's
+    /// no pending suggestion with that id.
+    pub fn reject_suggestion(&self, id: &str) -> Result<bool> {
+        let changed = self.writer().execute(
+            "UPDATE connection_suggestions SET status = 'rejected' WHERE id = ?1 AND status = 'pending'",
+            [id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Sessions whose title or summary contains `query` (case-insensitive), title matches
+    /// first, then newest first
+    pub fn search_sessions(&self, query: &str, limit: i64) -> Result<Vec<crate::Session>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {} FROM sessions
+               WHERE title LIKE ?1 ESCAPE '\' OR summary LIKE ?1 ESCAPE '\'
+               ORDER BY title LIKE ?1 ESCAPE '\' DESC, started_at DESC
+               LIMIT ?2"#,
+            SESSION_COLUMNS
+        ))?;
+        let sessions = stmt.query_map(params![like_pattern(query), limit], Self::row_to_session)?;
+        sessions.collect()
+    }
+
+    pub fn insert_session(&self, id: &str, title: &str, summary: &str, started_at: &str, ended_at: &str) -> Result<()> {
+        self.writer().execute(
+            r#"INSERT OR REPLACE INTO sessions
+               (id, title, summary, started_at, ended_at)
+               VALUES (?1, ?2, ?3, ?4, ?5)"#,
+            params![id, title, summary, started_at, ended_at],
+        )?;
+        Ok(())
+    }
+
+    /// Start an open-ended session (ended_at stays NULL until end_session)
+    pub fn open_session(&self, id: &str, title: &str, started_at: &str) -> Result<()> {
+        self.writer().execute(
+            "INSERT INTO sessions (id, title, started_at) VALUES (?1, ?2, ?3)",
+            params![id, title, started_at],
+        )?;
+        Ok(())
+    }
+
+    /// Position for the next thought appended to a session
+    pub fn next_session_position(&self, session_id: &str) -> Result<i64> {
+        self.reader()?.query_row(
+            "SELECT COALESCE(MAX(position) + 1, 0) FROM session_thoughts WHERE session_id = ?1",
+            [session_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Attach a thought to a session at the given position
+    pub fn link_session_thought(&self, session_id: &str, thought_id: &str, position: i64) -> Result<()> {
+        self.writer().prepare_cached(
+            "INSERT OR REPLACE INTO session_thoughts (session_id, thought_id, position) VALUES (?1, ?2, ?3)",
+        )?.execute(params![session_id, thought_id, position])?;
+        Ok(())
+    }
+
+    pub fn end_session(&self, id: &str, ended_at: &str) -> Result<()> {
+        self.writer().execute("UPDATE sessions SET ended_at = ?2 WHERE id = ?1", params![id, ended_at])?;
+        Ok(())
+    }
+
+    /// Partially update a session; None fields are left alone. False if the session doesn't exist.
+    pub fn update_session(&self, id: &str, title: Option<&str>, summary: Option<&str>) -> Result<bool> {
+        let changed = self.writer().execute(
+            "UPDATE sessions SET title = COALESCE(?2, title), summary = COALESCE(?3, summary) WHERE id = ?1",
+            params![id, title, summary],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Delete thoughts along with their connections, session links, embeddings, attachments, and
+    /// keyword counts. Returns how many thoughts existed and were deleted. Copied attachment
+    /// files are left for remove_unreferenced_attachment_files once the transaction commits.
+    fn delete_thoughts(conn: &Connection, ids: &[String]) -> Result<usize> {
+        // Foreign keys are enforced, so clear everything referencing a thought before the thought itself
+        let mut delete_connections = conn.prepare_cached("DELETE FROM connections WHERE from_thought = ?1 OR to_thought = ?1")?;
+        let mut delete_links = conn.prepare_cached("DELETE FROM session_thoughts WHERE thought_id = ?1")?;
+        let mut delete_embedding = conn.prepare_cached("DELETE FROM embeddings WHERE thought_id = ?1")?;
+        let mut delete_urls = conn.prepare_cached("DELETE FROM thought_links WHERE thought_id = ?1")?;
+        let mut delete_suggestions = conn.prepare_cached("DELETE FROM connection_suggestions WHERE from_thought = ?1 OR to_thought = ?1")?;
+        let mut delete_attachments = conn.prepare_cached("DELETE FROM attachments WHERE thought_id = ?1")?;
+        let mut delete_thought = conn.prepare_cached("DELETE FROM thoughts WHERE id = ?1")?;
+        let mut deleted = 0;
+        for thought_id in ids {
+            delete_connections.execute([thought_id])?;
+            delete_links.execute([thought_id])?;
+            delete_embedding.execute([thought_id])?;
+            delete_urls.execute([thought_id])?;
+            delete_suggestions.execute([thought_id])?;
+            delete_attachments.execute([thought_id])?;
+            if let Some(content) = Self::thought_content(conn, thought_id)? {
+                Self::adjust_keyword_stats(conn, thought_id, &content, -1)?;
+                deleted += delete_thought.execute([thought_id])?;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// How many unlocked thoughts match `filter`, with the contents of up to `samples` of them
+    /// (newest first)
+    pub fn preview_thought_filter(&self, filter: &crate::ThoughtFilter, samples: usize) -> Result<(usize, Vec<String>)> {
+        let conn = self.reader()?;
+        let (clause, values) = filter_clause(filter);
+        let clause = format!("({}) AND NOT locked", clause);
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM thoughts WHERE {}", clause),
+            rusqlite::params_from_iter(&values),
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT content FROM thoughts WHERE {} ORDER BY created_at DESC LIMIT {}",
+            clause, samples
+        ))?;
+        let contents = stmt.query_map(rusqlite::params_from_iter(&values), |row| row.get(0))?;
+        Ok((count as usize, contents.collect::<Result<_>>()?))
+    }
+
+    /// Apply `changes` to every unlocked thought matching `filter` in one transaction. Returns how
+    /// many changed.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn bulk_update_thoughts(&self, filter: &crate::ThoughtFilter, changes: &crate::ThoughtChanges) -> Result<usize> {
+        let (clause, mut values) = filter_clause(filter);
+        let first = values.len();
+        values.push(changes.category.clone().into());
+        values.push(changes.importance_delta.unwrap_or(0.0).into());
+        values.push(changes.archived.into());
+
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        let updated = tx.execute(
+            &format!(
+                "UPDATE thoughts SET
+                     category = COALESCE(?{}, category),
+                     importance = MIN(MAX(importance + ?{}, 0.0), 1.0),
+                     archived = COALESCE(?{}, archived)
+                 WHERE ({}) AND NOT locked",
+                first + 1, first + 2, first + 3, clause
+            ),
+            rusqlite::params_from_iter(&values),
+        )?;
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    /// Delete every unlocked thought matching `filter` (see delete_thoughts) in one transaction.
+    /// Returns how many matched; when that's more than `max` nothing is deleted.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn bulk_delete_thoughts(&self, filter: &crate::ThoughtFilter, max: usize) -> Result<usize> {
+        let (clause, values) = filter_clause(filter);
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+
+        let ids: Vec<String> = {
+            let mut stmt = tx.prepare(&format!("SELECT id FROM thoughts WHERE ({}) AND NOT locked", clause))?;
+            let ids = stmt.query_map(rusqlite::params_from_iter(&values), |row| row.get(0))?;
+            ids.collect::<Result<_>>()?
+        };
+        if ids.len() > max {
+            return Ok(ids.len());
+        }
+
+        let deleted = Self::delete_thoughts(&tx, &ids)?;
+        tx.commit()?;
+        drop(conn);
+        self.remove_unreferenced_attachment_files();
+        Ok(deleted)
+    }
+
+    /// Delete a session and its session_thoughts rows. With `delete_linked_thoughts`, the
+    /// linked thoughts and every connection touching them go too. All-or-nothing.
+    /// Returns the number of thoughts deleted, or None if the session doesn't exist.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn delete_session(&self, id: &str, delete_linked_thoughts: bool) -> Result<Option<usize>> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+
+        let exists: bool = tx.query_row("SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ?1)", [id], |row| row.get(0))?;
+        if !exists {
+            return Ok(None);
+        }
+
+        let mut thoughts_deleted = 0;
+        if delete_linked_thoughts {
+            // Locked thoughts outlive the session
+            let linked: Vec<String> = {
+                let mut stmt = tx.prepare_cached(
+                    "SELECT st.thought_id FROM session_thoughts st JOIN thoughts t ON t.id = st.thought_id
+                     WHERE st.session_id = ?1 AND NOT t.locked",
+                )?;
+                let ids = stmt.query_map([id], |row| row.get(0))?;
+                ids.collect::<Result<_>>()?
+            };
+
+            // The thoughts may also be linked from other sessions; delete_thoughts unlinks them everywhere
+            thoughts_deleted = Self::delete_thoughts(&tx, &linked)?;
+        }
+
+        tx.execute("DELETE FROM session_thoughts WHERE session_id = ?1", [id])?;
+        tx.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+        tx.commit()?;
+        drop(conn);
+        if thoughts_deleted > 0 {
+            self.remove_unreferenced_attachment_files();
+        }
+
+        Ok(Some(thoughts_deleted))
+    }
+
+    pub fn session_exists(&self, id: &str) -> Result<bool> {
+        self.reader()?.query_row("SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ?1)", [id], |row| row.get(0))
+    }
+
+    pub fn get_all_sessions(&self) -> Result<Vec<crate::Session>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM sessions ORDER BY started_at DESC",
+            SESSION_COLUMNS
+        ))?;
+
+        let sessions = stmt.query_map([], Self::row_to_session)?;
+
+        sessions.collect()
+    }
+
+    /// Session by id, falling back to the most recent session whose title matches
+    /// (exactly, then as a substring; case-insensitive)
+    pub fn find_session(&self, id_or_title: &str) -> Result<Option<crate::Session>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {} FROM sessions
+               WHERE id = ?1 OR lower(title) = lower(?1) OR title LIKE ?2 ESCAPE '\'
+               ORDER BY id = ?1 DESC, lower(title) = lower(?1) DESC, started_at DESC
+               LIMIT 1"#,
+            SESSION_COLUMNS
+        ))?;
+        let mut rows = stmt.query_map(params![id_or_title.trim(), like_pattern(id_or_title.trim())], Self::row_to_session)?;
+        rows.next().transpose()
+    }
+
+    /// Thoughts linked to a session, in session order
+    pub fn get_session_thoughts(&self, session_id: &str) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let columns = THOUGHT_COLUMNS.split(", ").map(|c| format!("t.{}", c)).collect::<Vec<_>>().join(", ");
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM session_thoughts st JOIN thoughts t ON t.id = st.thought_id
+             WHERE st.session_id = ?1 ORDER BY st.position",
+            columns
+        ))?;
+        let thoughts = stmt.query_map([session_id], Self::row_to_thought)?;
+        thoughts.collect()
+    }
+
+    /// Thoughts created within [start, end], oldest first
+    pub fn get_thoughts_in_window(&self, start: &str, end: &str) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts WHERE julianday(created_at) BETWEEN julianday(?1) AND julianday(?2) ORDER BY created_at",
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map([start, end], Self::row_to_thought)?;
+        thoughts.collect()
+    }
+
+    /// Connections created within [start, end], oldest first
+    pub fn get_connections_in_window(&self, start: &str, end: &str) -> Result<Vec<ThoughtConnection>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM connections WHERE julianday(created_at) BETWEEN julianday(?1) AND julianday(?2) ORDER BY created_at",
+            CONNECTION_COLUMNS
+        ))?;
+        let connections = stmt.query_map([start, end], Self::row_to_connection)?;
+        connections.collect()
+    }
+
+    /// A session with its thoughts, the connections made while it ran, and summary stats.
+    /// Older sessions have no session_thoughts rows, so their thoughts come from the time window.
+    pub fn get_session_detail(&self, id_or_title: &str) -> Result<Option<crate::SessionDetail>> {
+        let Some(session) = self.find_session(id_or_title)? else {
+            return Ok(None);
+        };
+
+        let window_end = session.ended_at.clone()
+            .filter(|end| !end.is_empty())
+            .unwrap_or_else(|| Utc::now().to_rfc3339());
+
+        let mut thoughts = self.get_session_thoughts(&session.id)?;
+        if thoughts.is_empty() {
+            thoughts = self.get_thoughts_in_window(&session.started_at, &window_end)?;
+        }
+        let connections = self.get_connections_in_window(&session.started_at, &window_end)?;
+
+        let mut categories: Vec<String> = thoughts.iter().map(|t| t.category.clone()).collect();
+        categories.sort();
+        categories.dedup();
+
+        let duration_seconds = match (
+            chrono::DateTime::parse_from_rfc3339(&session.started_at),
+            session.ended_at.as_deref().map(chrono::DateTime::parse_from_rfc3339),
+        ) {
+            (Ok(start), Some(Ok(end))) => Some((end - start).num_seconds()),
+            _ => None,
+        };
+
+        Ok(Some(crate::SessionDetail {
+            stats: crate::SessionStats {
+                thought_count: thoughts.len(),
+                connection_count: connections.len(),
+                categories,
+                duration_seconds,
+            },
+            session,
+            thoughts,
+            connections,
+        }))
+    }
+
+    /// Change markers for polling, read in one statement: newest thought, connection and
+    /// session rowids plus the counters and the latest change_log seq
+    pub fn get_db_version(&self) -> Result<crate::DbVersion> {
+        self.reader()?.query_row(
+            r#"SELECT (SELECT COALESCE(MAX(rowid), 0) FROM thoughts),
+                      (SELECT COALESCE(MAX(rowid), 0) FROM connections),
+                      (SELECT COALESCE(MAX(rowid), 0) FROM sessions),
+                      (SELECT COALESCE(MAX(value), 0) FROM counters WHERE name = ?1),
+                      (SELECT COALESCE(MAX(value), 0) FROM counters WHERE name = ?2),
+                      (SELECT COALESCE(MAX(seq), 0) FROM change_log)"#,
+            [CLUSTER_GENERATION_COUNTER, MUTATIONS_COUNTER],
+            |row| Ok(crate::DbVersion {
+                thought_max_id: row.get(0)?,
+                connection_max_id: row.get(1)?,
+                session_max_id: row.get(2)?,
+                cluster_generation: row.get(3)?,
+                mutation_counter: row.get(4)?,
+                change_seq: row.get(5)?,
+            }),
+        )
+    }
+
+    /// The latest change_log seq, and the (entity type, id) of every thought and connection
+    /// inserted after `since`, oldest first
+    pub fn get_inserted_since(&self, since: i64) -> Result<(i64, Vec<(String, String)>)> {
+        let conn = self.reader()?;
+        let seq: i64 = conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM change_log", [], |row| row.get(0))?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT entity_type, entity_id FROM change_log
+             WHERE seq > ?1 AND seq <= ?2 AND op = 'insert' AND entity_type IN ('thought', 'connection')
+             ORDER BY seq",
+        )?;
+        let rows = stmt.query_map(params![since, seq], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok((seq, rows.collect::<Result<_>>()?))
+    }
+
+    /// Everything that changed after change_log seq `since`: current rows of entities inserted
+    /// or updated, and ids of those deleted. Several changes to one entity count once, by its
+    /// last change, and entities both created and deleted since are left out. `stale` is set
+    /// (and nothing else returned) when entries after `since` have been pruned, in which case
+    /// the caller has to reload everything. Thoughts archived since count as deleted.
+    pub fn get_graph_delta(&self, since: i64) -> Result<crate::GraphDelta> {
+        let conn = self.reader()?;
+        let (seq, pruned): (i64, i64) = conn.query_row(
+            r#"SELECT (SELECT COALESCE(MAX(seq), 0) FROM change_log),
+                      (SELECT COALESCE(MAX(value), 0) FROM counters WHERE name = ?1)"#,
+            [CHANGE_LOG_PRUNED_COUNTER],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let mut delta = crate::GraphDelta { seq, ..Default::default() };
+        if since < pruned {
+            delta.stale = true;
+            return Ok(delta);
+        }
+
+        // (type, id) -> (first op, last op), in first-change order
+        let mut changes: Vec<((String, String), (String, String))> = Vec::new();
+        let mut index: HashMap<(String, String), usize> = HashMap::new();
+        {
+            let mut stmt = conn.prepare_cached(
+                "SELECT entity_type, entity_id, op FROM change_log WHERE seq > ?1 AND seq <= ?2 ORDER BY seq",
+            )?;
+            let rows = stmt.query_map(params![since, seq], |row| {
+                Ok(((row.get::<_, String>(0)?, row.get::<_, String>(1)?), row.get::<_, String>(2)?))
+            })?;
+            for row in rows {
+                let (key, op) = row?;
+                match index.get(&key) {
+                    Some(&i) => changes[i].1.1 = op,
+                    None => {
+                        index.insert(key.clone(), changes.len());
+                        changes.push((key, (op.clone(), op)));
+                    }
+                }
+            }
+        }
+
+        let mut changed: HashMap<&str, Vec<&str>> = HashMap::new();
+        for ((entity, id), (first, last)) in &changes {
+            if last == "delete" {
+                if first != "insert" {
+                    let deleted = &mut delta.deleted;
+                    match entity.as_str() {
+                        "thought" => deleted.thoughts.push(id.clone()),
+                        "connection" => deleted.connections.push(id.clone()),
+                        "session" => deleted.sessions.push(id.clone()),
+                        "cluster" => deleted.clusters.push(id.clone()),
+                        _ => {}
+                    }
+                }
+            } else {
+                changed.entry(entity.as_str()).or_default().push(id.as_str());
+            }
+        }
+
+        let ids = |entity: &str| serde_json::to_string(changed.get(entity).map(Vec::as_slice).unwrap_or_default()).unwrap_or_default();
+        let by_ids = |table: &str, columns: &str| {
+            format!("SELECT {} FROM {} WHERE id IN (SELECT value FROM json_each(?1))", columns, table)
+        };
+        if changed.contains_key("thought") {
+            let mut stmt = conn.prepare_cached(&by_ids("thoughts", THOUGHT_COLUMNS))?;
+            let thoughts: Vec<Thought> = stmt.query_map([ids("thought")], Self::row_to_thought)?.collect::<Result<_>>()?;
+            // The graph doesn't show archived thoughts, so archiving one reads as removing it
+            let (archived, shown): (Vec<_>, Vec<_>) = thoughts.into_iter().partition(|t| t.archived);
+            delta.deleted.thoughts.extend(archived.into_iter().map(|t| t.id));
+            delta.thoughts = shown;
+        }
+        if changed.contains_key("connection") {
+            let mut stmt = conn.prepare_cached(&by_ids("connections", CONNECTION_COLUMNS))?;
+            delta.connections = stmt.query_map([ids("connection")], Self::row_to_connection)?.collect::<Result<_>>()?;
+        }
+        if changed.contains_key("session") {
+            let mut stmt = conn.prepare_cached(&by_ids("sessions", SESSION_COLUMNS))?;
+            delta.sessions = stmt.query_map([ids("session")], Self::row_to_session)?.collect::<Result<_>>()?;
+        }
+        if changed.contains_key("cluster") {
+            let mut stmt = conn.prepare_cached(&by_ids("clusters", CLUSTER_COLUMNS))?;
+            delta.clusters = stmt.query_map([ids("cluster")], Self::row_to_cluster)?.collect::<Result<_>>()?;
+        }
+        Ok(delta)
+    }
+
+    /// Drop change_log entries older than `max_age_days`, remembering the last seq dropped so
+    /// deltas from before it are reported stale. Returns how many were dropped.
+    pub fn prune_change_log(&self, max_age_days: i64) -> Result<usize> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        let cutoff = format!("-{} days", max_age_days.max(0));
+        let last: Option<i64> = tx.query_row(
+            "SELECT MAX(seq) FROM change_log WHERE changed_at < strftime('%Y-%m-%dT%H:%M:%fZ', 'now', ?1)",
+            [&cutoff],
+            |row| row.get(0),
+        )?;
+        let Some(last) = last else {
+            return Ok(0);
+        };
+        let removed = tx.execute("DELETE FROM change_log WHERE seq <= ?1", [last])?;
+        tx.execute(
+            "UPDATE counters SET value = MAX(value, ?2) WHERE name = ?1",
+            params![CHANGE_LOG_PRUNED_COUNTER, last],
+        )?;
+        tx.commit()?;
+        Ok(removed)
+    }
+
+    pub fn get_max_thought_rowid(&self) -> Result<i64> {
+        self.reader()?.query_row(
+            "SELECT COALESCE(MAX(rowid), 0) FROM thoughts",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Thoughts inserted after `after_rowid` (up to `until_rowid`) at or above `min_importance`, oldest first
+    pub fn get_thoughts_in_rowid_range(&self, after_rowid: i64, until_rowid: i64, min_importance: f64) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts WHERE rowid > ?1 AND rowid <= ?2 AND importance >= ?3 ORDER BY rowid",
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map(params![after_rowid, until_rowid, min_importance], Self::row_to_thought)?;
+        thoughts.collect()
+    }
+
+    /// Up to `limit` unarchived thoughts with rowid in (after_rowid, until_rowid], in rowid order,
+    /// each with its rowid
+    pub fn get_thought_batch(&self, after_rowid: i64, until_rowid: i64, limit: i64) -> Result<Vec<(i64, Thought)>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {}, rowid FROM thoughts WHERE rowid > ?1 AND rowid <= ?2 AND archived = 0 ORDER BY rowid LIMIT ?3",
+            THOUGHT_COLUMNS
+        ))?;
+        let batch = stmt.query_map(params![after_rowid, until_rowid, limit], |row| {
+            Ok((row.get(13)?, Self::row_to_thought(row)?))
+        })?;
+        batch.collect()
+    }
+
+    /// Up to `limit` connections with rowid in (after_rowid, until_rowid], in rowid order, each with its rowid
+    pub fn get_connection_batch(&self, after_rowid: i64, until_rowid: i64, limit: i64) -> Result<Vec<(i64, ThoughtConnection)>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {}, rowid FROM connections WHERE rowid > ?1 AND rowid <= ?2 ORDER BY rowid LIMIT ?3",
+            CONNECTION_COLUMNS
+        ))?;
+        let batch = stmt.query_map(params![after_rowid, until_rowid, limit], |row| {
+            Ok((row.get(7)?, Self::row_to_connection(row)?))
+        })?;
+        batch.collect()
+    }
+
+    pub fn get_max_connection_rowid(&self) -> Result<i64> {
+        self.reader()?.query_row(
+            "SELECT COALESCE(MAX(rowid), 0) FROM connections",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn get_thought_count(&self) -> Result<i64> {
+        self.reader()?.query_row(
+            "SELECT COUNT(*) FROM thoughts",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    /// Get thoughts within a radius of a 3D point, sorted by distance.
+    /// Uses Euclidean distance calculated in SQL for efficiency.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_thoughts_near(&self, x: f64, y: f64, z: f64, radius: f64, limit: i64) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            &format!(r#"SELECT {},
+                      ((position_x - ?1) * (position_x - ?1) +
+                       (position_y - ?2) * (position_y - ?2) +
+                       (position_z - ?3) * (position_z - ?3)) AS dist_sq
+               FROM thoughts
+               WHERE dist_sq <= (?4 * ?4)
+               ORDER BY dist_sq ASC
+               LIMIT ?5"#, THOUGHT_COLUMNS)
+        )?;
+
+        let thoughts = stmt.query_map(params![x, y, z, radius, limit], Self::row_to_thought)?;
+
+        thoughts.collect()
+    }
+
+    /// Get connections where both endpoints are in the given thought ID set. Large sets are
+    /// queried ID_QUERY_CHUNK source ids at a time, each batch matched against the whole
+    /// set as targets, so a connection between two batches is still found (and only once).
+    /// Repeated ids count once.
+    #[tracing::instrument(level = "debug", skip_all, fields(count = ids.len()))]
+    pub fn get_connections_for_thoughts(&self, ids: &[String]) -> Result<Vec<ThoughtConnection>> {
+        // A repeated id in two chunks would return its connections twice
+        let mut seen = HashSet::new();
+        let ids: Vec<&String> = ids.iter().filter(|id| seen.insert(id.as_str())).collect();
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {CONNECTION_COLUMNS} FROM connections
+             WHERE from_thought IN (SELECT value FROM json_each(?1))
+               AND to_thought IN (SELECT value FROM json_each(?2))"
+        ))?;
+        let all_ids = serde_json::to_string(&ids).unwrap_or_default();
+        let mut connections = Vec::new();
+        for chunk in ids.chunks(ID_QUERY_CHUNK) {
+            let chunk = serde_json::to_string(chunk).unwrap_or_default();
+            let rows = stmt.query_map(params![chunk, all_ids], Self::row_to_connection)?;
+            connections.extend(rows.collect::<Result<Vec<_>>>()?);
+        }
+        Ok(connections)
+    }
+
+    /// The most important thoughts matching the filters (at most `max_nodes`, clamped to
+    /// MAX_SNAPSHOT_NODES) and the connections between them. Ties in importance go to the
+    /// lower id, so the same data always yields the same cut; archived thoughts are left out.
+    /// `since` is RFC3339. Content over SNAPSHOT_CONTENT_CHARS is cut short and ends in "…".
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn get_graph_snapshot(&self, category: Option<&str>, since: Option<&str>, max_nodes: usize) -> Result<crate::GraphSnapshot> {
+        let max_nodes = max_nodes.min(MAX_SNAPSHOT_NODES);
+        let mut thoughts = {
+            let conn = self.reader()?;
+            let mut stmt = conn.prepare_cached(&format!(
+                "SELECT {} FROM thoughts
+                 WHERE (?1 IS NULL OR category = ?1) AND (?2 IS NULL OR julianday(created_at) >= julianday(?2))
+                   AND archived = 0
+                 ORDER BY importance DESC, id ASC LIMIT ?3",
+                THOUGHT_COLUMNS
+            ))?;
+            // One extra row tells us whether the cap cut anything off
+            let rows = stmt.query_map(params![category, since, max_nodes as i64 + 1], Self::row_to_thought)?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let truncated = thoughts.len() > max_nodes;
+        thoughts.truncate(max_nodes);
+
+        let ids: Vec<String> = thoughts.iter().map(|t| t.id.clone()).collect();
+        let connections = self.get_connections_for_thoughts(&ids)?;
+
+        for thought in &mut thoughts {
+            if thought.content.chars().count() > SNAPSHOT_CONTENT_CHARS {
+                thought.content = format!("{}…", crate::utils::snippet(&thought.content, SNAPSHOT_CONTENT_CHARS));
+            }
+        }
+        Ok(crate::GraphSnapshot { thoughts, connections, truncated })
+    }
+
+    /// The thoughts with these ids, in the order asked for (once each); ids with no thought are
+    /// left out
+    #[tracing::instrument(level = "debug", skip_all, fields(count = ids.len()))]
+    pub fn get_thoughts_by_ids(&self, ids: &[String]) -> Result<Vec<Thought>> {
+        self.rows_by_ids("thoughts", THOUGHT_COLUMNS, ids, Self::row_to_thought, |t| &t.id)
+    }
+
+    /// The connections with these ids, in the order asked for (once each); ids with no
+    /// connection are left out
+    #[tracing::instrument(level = "debug", skip_all, fields(count = ids.len()))]
+    pub fn get_connections_by_ids(&self, ids: &[String]) -> Result<Vec<ThoughtConnection>> {
+        self.rows_by_ids("connections", CONNECTION_COLUMNS, ids, Self::row_to_connection, |c| &c.id)
+    }
+
+    /// Rows of `table` by id, ID_QUERY_CHUNK ids per query, put back in the order of `ids`
+    fn rows_by_ids<T>(
+        &self,
+        table: &str,
+        columns: &str,
+        ids: &[String],
+        row_to: fn(&rusqlite::Row) -> Result<T>,
+        id_of: fn(&T) -> &String,
+    ) -> Result<Vec<T>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM {} WHERE id IN (SELECT value FROM json_each(?1))",
+            columns, table
+        ))?;
+        let mut found = HashMap::new();
+        for chunk in ids.chunks(ID_QUERY_CHUNK) {
+            let chunk = serde_json::to_string(chunk).unwrap_or_default();
+            for row in stmt.query_map([chunk], row_to)? {
+                let row = row?;
+                found.insert(id_of(&row).clone(), row);
+            }
+        }
+        Ok(ids.iter().filter_map(|id| found.remove(id)).collect())
+    }
+
+    pub fn get_connection_by_id(&self, id: &str) -> Result<Option<ThoughtConnection>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM connections WHERE id = ?1", CONNECTION_COLUMNS))?;
+        let mut rows = stmt.query_map([id], Self::row_to_connection)?;
+        rows.next().transpose()
+    }
+
+    /// Every connection with `thought_id` at either end, strongest first
+    pub fn get_connections_of_thought(&self, thought_id: &str) -> Result<Vec<ThoughtConnection>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM connections WHERE from_thought = ?1 OR to_thought = ?1 ORDER BY strength DESC",
+            CONNECTION_COLUMNS
+        ))?;
+        let connections = stmt.query_map([thought_id], Self::row_to_connection)?;
+        connections.collect()
+    }
+
+    pub fn get_connections_by_kind(&self, kind: &str) -> Result<Vec<ThoughtConnection>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            &format!("SELECT {} FROM connections WHERE kind = ?1", CONNECTION_COLUMNS)
+        )?;
+        let connections = stmt.query_map([kind], Self::row_to_connection)?;
+        connections.collect()
+    }
+
+    /// Delete connections weaker than `below_strength`, optionally only of one kind or only
+    /// automatically created ones (AUTO_CREATED_KINDS). Returns the number removed.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn prune_connections(&self, kind: Option<&str>, below_strength: f64, auto_only: bool) -> Result<usize> {
+        self.writer().execute(
+            "DELETE FROM connections
+             WHERE strength < ?1 AND (?2 IS NULL OR kind = ?2) AND (NOT ?3 OR kind IN (?4, ?5))",
+            params![below_strength, kind, auto_only, AUTO_CREATED_KINDS[0], AUTO_CREATED_KINDS[1]],
+        )
+    }
+
+    /// How many automatically created connections are weaker than `below_strength`, i.e. what
+    /// prune_connections with auto_only would remove
+    pub fn count_weak_auto_connections(&self, below_strength: f64) -> Result<i64> {
+        self.reader()?.query_row(
+            "SELECT COUNT(*) FROM connections WHERE strength < ?1 AND kind IN (?2, ?3)",
+            params![below_strength, AUTO_CREATED_KINDS[0], AUTO_CREATED_KINDS[1]],
+            |row| row.get(0),
+        )
+    }
+
+    /// Strengthen every connection between two of `ids` (thoughts recalled together) by
+    /// REINFORCE_STEP, up to 1.0, and mark it reinforced now. Does nothing read-only.
+    /// Returns how many connections were reinforced.
+    pub fn reinforce_connections(&self, ids: &[String]) -> Result<usize> {
+        if self.is_read_only() || ids.len() < 2 {
+            return Ok(0);
+        }
+        let ids = serde_json::to_string(ids).unwrap_or_default();
+        self.writer().execute(
+            "UPDATE connections SET strength = MIN(1.0, strength + ?2), last_reinforced = ?3
+             WHERE from_thought IN (SELECT value FROM json_each(?1))
+               AND to_thought IN (SELECT value FROM json_each(?1))",
+            params![ids, REINFORCE_STEP, Utc::now().to_rfc3339()],
+        )
+    }
+
+    /// Halve connection strength every `half_life_days` since the connection was last reinforced
+    /// (or created), never taking it below `floor`. Time already decayed by an earlier run isn't
+    /// counted again. Returns how many connections weakened.
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn decay_connection_strength(&self, half_life_days: f64, floor: f64) -> Result<usize> {
+        let now = Utc::now();
+        let parse = |s: &str| chrono::DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc));
+        let last_run = self.get_setting(CONNECTION_DECAY_RUN_KEY)?.as_deref().and_then(parse);
+
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        let rows = {
+            let mut stmt = tx.prepare_cached(
+                "SELECT id, strength, COALESCE(last_reinforced, created_at) FROM connections WHERE strength > ?1",
+            )?;
+            let rows = stmt.query_map([floor], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, String>(2)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        let mut decayed = 0;
+        {
+            let mut update = tx.prepare_cached("UPDATE connections SET strength = ?2 WHERE id = ?1")?;
+            for (id, strength, reinforced) in rows {
+                let Some(reinforced) = parse(&reinforced) else { continue };
+                let since = last_run.map_or(reinforced, |run| run.max(reinforced));
+                let days = (now - since).num_seconds() as f64 / 86_400.0;
+                if days <= 0.0 {
+                    continue;
+                }
+                let weakened = (strength * 0.5f64.powf(days / half_life_days)).max(floor);
+                if weakened < strength {
+                    decayed += update.execute(params![id, weakened])?;
+                }
+            }
+        }
+        tx.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![CONNECTION_DECAY_RUN_KEY, now.to_rfc3339()],
+        )?;
+        tx.commit()?;
+        Ok(decayed)
+    }
+
+    /// Blend stated importance, normalized connection degree, and normalized reference
+    /// count into computed_importance (the original importance is left alone). Pinned thoughts
+    /// never fall below their stated importance; locked thoughts keep their computed importance.
+    /// Returns every thought whose rank moved, biggest moves first.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn recompute_importance(&self, weights: &crate::ImportanceWeights) -> Result<Vec<crate::ImportanceChange>> {
+        let total_weight = (weights.original + weights.degree + weights.references).max(f64::EPSILON);
+
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+
+        // (thought, previous effective importance, degree, references, locked)
+        let rows = {
+            let mut stmt = tx.prepare_cached(&format!(
+                "SELECT {}, COALESCE(computed_importance, importance), COALESCE(reference_count, 0),
+                        (SELECT COUNT(*) FROM connections c WHERE c.from_thought = t.id OR c.to_thought = t.id), locked
+                 FROM thoughts t",
+                THOUGHT_COLUMNS
+            ))?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    Self::row_to_thought(row)?,
+                    row.get::<_, f64>(13)?,
+                    row.get::<_, i64>(14)?,
+                    row.get::<_, i64>(15)?,
+                    row.get::<_, bool>(16)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        let max_degree = rows.iter().map(|r| r.3).max().unwrap_or(0).max(1) as f64;
+        let max_references = rows.iter().map(|r| r.2).max().unwrap_or(0).max(1) as f64;
+
+        let computed: Vec<f64> = rows.iter()
+            .map(|(t, previous, references, degree, locked)| {
+                if *locked {
+                    return *previous;
+                }
+                let blended = (weights.original * t.importance
+                    + weights.degree * (*degree as f64 / max_degree)
+                    + weights.references * (*references as f64 / max_references))
+                    / total_weight;
+                if t.pinned { blended.max(t.importance) } else { blended }
+            })
+            .collect();
+
+        {
+            let mut update = tx.prepare_cached("UPDATE thoughts SET computed_importance = ?2 WHERE id = ?1")?;
+            for ((t, .., locked), value) in rows.iter().zip(&computed) {
+                if !locked {
+                    update.execute(params![t.id, value])?;
+                }
+            }
+        }
+        tx.commit()?;
+        let locked = rows.iter().filter(|r| r.4).count();
+        if locked > 0 {
+            tracing::info!("Importance recompute: {} thoughts skipped: locked", locked);
+        }
+
+        let old_ranks = rank_positions(rows.iter().map(|r| (r.0.id.as_str(), r.1)));
+        let new_ranks = rank_positions(rows.iter().zip(&computed).map(|(r, v)| (r.0.id.as_str(), *v)));
+
+        let mut changes: Vec<crate::ImportanceChange> = rows.into_iter()
+            .enumerate()
+            .filter(|(i, _)| old_ranks[*i] != new_ranks[*i])
+            .map(|(i, (thought, ..))| crate::ImportanceChange {
+                thought,
+                computed_importance: computed[i],
+                old_rank: old_ranks[i],
+                new_rank: new_ranks[i],
+            })
+            .collect();
+        changes.sort_by_key(|c| std::cmp::Reverse(c.old_rank.abs_diff(c.new_rank)));
+
+        Ok(changes)
+    }
+
+    /// Rebuild clusters: one per category, with any cluster over cluster_max_size split by
+    /// k-means on thought positions into sub-clusters, down to clustering::MAX_DEPTH levels.
+    /// Every thought is assigned to exactly one leaf cluster (thoughts.cluster_id). Clusters
+    /// made with create_cluster or holding locked thoughts (set_thought_cluster) are kept as
+    /// top-level clusters with their locked members; only their centroid, count and keywords
+    /// are refreshed. Returns the top-level clusters.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn compute_clusters(&self) -> Result<Vec<crate::Cluster>> {
+        let max_size = self.setting_or(clustering::SETTING_MAX_SIZE, clustering::DEFAULT_MAX_SIZE).max(1);
+        let mut conn = self.writer();
+        // Delete and re-insert in one transaction so readers never see an empty cluster set
+        let tx = conn.transaction()?;
+
+        // A lock only holds while its cluster exists
+        tx.execute(
+            "UPDATE thoughts SET cluster_locked = 0 WHERE cluster_locked = 1 AND cluster_id NOT IN (SELECT id FROM clusters)",
+            [],
+        )?;
+
+        // Names from the last run by category, so clusters whose keywords barely changed keep them
+        let mut previous: HashMap<String, Vec<String>> = HashMap::new();
+        {
+            let mut stmt = tx.prepare_cached(&format!(
+                "SELECT category, name FROM clusters WHERE NOT ({}) ORDER BY parent_id IS NOT NULL",
+                KEPT_CLUSTER
+            ))?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (category, name) = row?;
+                previous.entry(category).or_default().push(name);
+            }
+        }
+        tx.execute(&format!("DELETE FROM clusters WHERE NOT ({})", KEPT_CLUSTER), [])?;
+        tx.execute("UPDATE thoughts SET cluster_id = NULL WHERE cluster_id IS NOT NULL AND cluster_locked = 0", [])?;
+
+        let doc_counts: HashMap<String, i64> = {
+            let mut stmt = tx.prepare_cached("SELECT keyword, doc_count FROM keyword_stats")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+        let total_docs: i64 = tx.query_row("SELECT COUNT(*) FROM thoughts", [], |row| row.get(0))?;
+        let mut clusters = self.refresh_kept_clusters(&tx, &doc_counts, total_docs)?;
+        let colors: HashMap<String, Option<String>> = {
+            let mut stmt = tx.prepare_cached("SELECT name, color FROM categories")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+
+        // Every unlocked thought, grouped by category
+        let mut by_category: Vec<(String, Vec<ClusterMember>)> = Vec::new();
+        {
+            let mut stmt = tx.prepare_cached(
+                r#"SELECT category, id, content, position_x, position_y, position_z FROM thoughts
+                   WHERE cluster_locked = 0
+                   ORDER BY category, created_at"#,
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?, [row.get(3)?, row.get(4)?, row.get(5)?])))
+            })?;
+            for row in rows {
+                let (category, member) = row?;
+                match by_category.last_mut() {
+                    Some((last, members)) if *last == category => members.push(member),
+                    _ => by_category.push((category, vec![member])),
+                }
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+
+        {
+            let mut insert = tx.prepare_cached(
+                r#"INSERT INTO clusters (id, name, category, center_x, center_y, center_z, thought_count, created_at, keywords, parent_id)
+                   VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"#,
+            )?;
+            let mut assign = tx.prepare_cached("UPDATE thoughts SET cluster_id = ?1 WHERE id = ?2")?;
+
+            for (category, members) in &by_category {
+                let names = previous.entry(category.clone()).or_default();
+                // Breadth first, so parents claim their previous names before their children
+                let mut pending: VecDeque<(Vec<usize>, Option<String>, usize)> = VecDeque::new();
+                pending.push_back(((0..members.len()).collect(), None, 1));
+
+                while let Some((indexes, parent_id, depth)) = pending.pop_front() {
+                    let id = Uuid::new_v4().to_string();
+                    let points: Vec<[f64; 3]> = indexes.iter().map(|&i| members[i].2).collect();
+                    let contents: Vec<String> = indexes.iter().map(|&i| members[i].1.clone()).collect();
+                    let keywords = cluster_keywords(&contents, |k| doc_counts.get(k).copied().unwrap_or(0), total_docs);
+                    let name = cluster_name(category, &keywords, names);
+                    let [cx, cy, cz] = clustering::centroid(&points);
+
+                    let children = if indexes.len() > max_size && depth < clustering::MAX_DEPTH {
+                        clustering::split(&points, max_size)
+                    } else {
+                        Vec::new()
+                    };
+
+                    insert.execute(params![
+                        id, name, category, cx, cy, cz, indexes.len() as i64, now,
+                        serde_json::to_string(&keywords).unwrap_or_default(), parent_id,
+                    ])?;
+
+                    if children.is_empty() {
+                        for &i in &indexes {
+                            assign.execute(params![id, members[i].0])?;
+                        }
+                    }
+                    for child in &children {
+                        let child_indexes = child.iter().map(|&i| indexes[i]).collect();
+                        pending.push_back((child_indexes, Some(id.clone()), depth + 1));
+                    }
+
+                    if parent_id.is_none() {
+                        clusters.push(crate::Cluster {
+                            id,
+                            name,
+                            category: category.clone(),
+                            center_x: cx,
+                            center_y: cy,
+                            center_z: cz,
+                            thought_count: indexes.len() as i64,
+                            created_at: now.clone(),
+                            keywords,
+                            parent_id,
+                            child_count: children.len() as i64,
+                            manual: false,
+                            color: colors.get(category).cloned().flatten(),
+                        });
+                    }
+                }
+            }
+        }
+
+        tx.execute("UPDATE counters SET value = value + 1 WHERE name = ?1", [CLUSTER_GENERATION_COUNTER])?;
+        tx.commit()?;
+        Ok(clusters)
+    }
+
+    /// Recompute the centroid, count and keywords of clusters kept through compute_clusters
+    /// from their locked members. Clusters from create_cluster keep their name; the rest are
+    /// renamed only once their keywords move on.
+    fn refresh_kept_clusters(
+        &self,
+        tx: &rusqlite::Transaction,
+        doc_counts: &HashMap<String, i64>,
+        total_docs: i64,
+    ) -> Result<Vec<crate::Cluster>> {
+        let kept = {
+            let mut stmt = tx.prepare_cached(&format!("SELECT {} FROM clusters", CLUSTER_COLUMNS))?;
+            let rows = stmt.query_map([], Self::row_to_cluster)?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        let mut members = tx.prepare_cached(
+            "SELECT id, content, position_x, position_y, position_z FROM thoughts WHERE cluster_id = ?1",
+        )?;
+        let mut update = tx.prepare_cached(
+            r#"UPDATE clusters SET name = ?2, center_x = ?3, center_y = ?4, center_z = ?5, thought_count = ?6, keywords = ?7,
+                      parent_id = NULL
+               WHERE id = ?1"#,
+        )?;
+
+        let mut clusters = Vec::with_capacity(kept.len());
+        for mut cluster in kept {
+            let rows = members.query_map([&cluster.id], |row| {
+                Ok((row.get(0)?, row.get(1)?, [row.get(2)?, row.get(3)?, row.get(4)?]))
+            })?;
+            let rows: Vec<ClusterMember> = rows.collect::<Result<_>>()?;
+
+            if !rows.is_empty() {
+                let points: Vec<[f64; 3]> = rows.iter().map(|m| m.2).collect();
+                [cluster.center_x, cluster.center_y, cluster.center_z] = clustering::centroid(&points);
+                let contents: Vec<String> = rows.into_iter().map(|m| m.1).collect();
+                cluster.keywords = cluster_keywords(&contents, |k| doc_counts.get(k).copied().unwrap_or(0), total_docs);
+                if !cluster.manual {
+                    cluster.name = cluster_name(&cluster.category, &cluster.keywords, &mut vec![cluster.name.clone()]);
+                }
+                cluster.thought_count = contents.len() as i64;
+            } else {
+                cluster.keywords = Vec::new();
+                cluster.thought_count = 0;
+            }
+            cluster.parent_id = None;
+            cluster.child_count = 0;
+
+            update.execute(params![
+                cluster.id, cluster.name, cluster.center_x, cluster.center_y, cluster.center_z, cluster.thought_count,
+                serde_json::to_string(&cluster.keywords).unwrap_or_default(),
+            ])?;
+            clusters.push(cluster);
+        }
+        Ok(clusters)
+    }
+
+    /// An empty cluster to move thoughts into with set_thought_cluster; it lasts through
+    /// compute_clusters
+    pub fn create_cluster(&self, name: &str, category: &str) -> Result<crate::Cluster> {
+        let color: Option<String> = {
+            let conn = self.reader()?;
+            let mut stmt = conn.prepare_cached("SELECT color FROM categories WHERE name = ?1")?;
+            let mut rows = stmt.query_map([category], |row| row.get(0))?;
+            rows.next().transpose()?.flatten()
+        };
+        let cluster = crate::Cluster {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            category: category.to_string(),
+            center_x: 0.0,
+            center_y: 0.0,
+            center_z: 0.0,
+            thought_count: 0,
+            created_at: Utc::now().to_rfc3339(),
+            keywords: Vec::new(),
+            parent_id: None,
+            child_count: 0,
+            manual: true,
+            color,
+        };
+        self.writer().execute(
+            r#"INSERT INTO clusters (id, name, category, center_x, center_y, center_z, thought_count, created_at, manual)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, ?7, 1)"#,
+            params![cluster.id, cluster.name, cluster.category, cluster.center_x, cluster.center_y, cluster.center_z, cluster.created_at],
+        )?;
+        Ok(cluster)
+    }
+
+    /// Move a thought into a cluster and lock it there, so compute_clusters leaves it in place.
+    /// Thought counts along both clusters' parent chains are adjusted right away. False if the
+    /// thought doesn't exist.
+    pub fn set_thought_cluster(&self, thought_id: &str, cluster_id: &str) -> Result<bool> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        let previous: Option<Option<String>> = {
+            let mut stmt = tx.prepare_cached("SELECT cluster_id FROM thoughts WHERE id = ?1")?;
+            let mut rows = stmt.query_map([thought_id], |row| row.get(0))?;
+            rows.next().transpose()?
+        };
+        let Some(previous) = previous else {
+            return Ok(false);
+        };
+
+        tx.execute(
+            "UPDATE thoughts SET cluster_id = ?2, cluster_locked = 1 WHERE id = ?1",
+            params![thought_id, cluster_id],
+        )?;
+        if previous.as_deref() != Some(cluster_id) {
+            if let Some(previous) = previous {
+                tx.execute(ADJUST_CLUSTER_CHAIN_COUNTS, params![previous, -1])?;
+            }
+            tx.execute(ADJUST_CLUSTER_CHAIN_COUNTS, params![cluster_id, 1])?;
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Put a thought that has no cluster yet into the leaf cluster nearest its position,
+    /// walking down from the nearest top-level cluster, and count it along that chain.
+    /// Centroids are left for the next compute_clusters. None if the thought doesn't exist,
+    /// already has a cluster, or there are no clusters.
+    pub fn join_nearest_cluster(&self, thought_id: &str) -> Result<Option<crate::Cluster>> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        let position: Option<(f64, f64, f64)> = tx
+            .prepare_cached("SELECT position_x, position_y, position_z FROM thoughts WHERE id = ?1 AND cluster_id IS NULL")?
+            .query_row([thought_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .optional()?;
+        let Some((x, y, z)) = position else {
+            return Ok(None);
+        };
+
+        let mut leaf: Option<crate::Cluster> = None;
+        while let Some(nearest) = Self::nearest_cluster_in(&tx, leaf.as_ref().map(|c| c.id.as_str()), x, y, z)? {
+            leaf = Some(nearest.cluster);
+        }
+        let Some(mut leaf) = leaf else {
+            return Ok(None);
+        };
+        tx.execute("UPDATE thoughts SET cluster_id = ?2 WHERE id = ?1", params![thought_id, leaf.id])?;
+        tx.execute(ADJUST_CLUSTER_CHAIN_COUNTS, params![leaf.id, 1])?;
+        tx.commit()?;
+        leaf.thought_count += 1;
+        Ok(Some(leaf))
+    }
+
+    /// Let compute_clusters place the thought again; it stays in its cluster until then.
+    /// False if the thought doesn't exist.
+    pub fn clear_thought_cluster_lock(&self, thought_id: &str) -> Result<bool> {
+        let changed = self.writer().execute("UPDATE thoughts SET cluster_locked = 0 WHERE id = ?1", [thought_id])?;
+        Ok(changed > 0)
+    }
+
+    /// The top-level cluster whose centroid is closest to a point, ignoring empty clusters;
+    /// None when there are no clusters
+    pub fn get_nearest_cluster(&self, x: f64, y: f64, z: f64) -> Result<Option<crate::ClusterMatch>> {
+        Self::nearest_cluster_in(&*self.reader()?, None, x, y, z)
+    }
+
+    /// The non-empty child of `parent_id` (or top-level cluster for None) closest to a point
+    fn nearest_cluster_in(conn: &Connection, parent_id: Option<&str>, x: f64, y: f64, z: f64) -> Result<Option<crate::ClusterMatch>> {
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {},
+                      (center_x - ?1) * (center_x - ?1) + (center_y - ?2) * (center_y - ?2) + (center_z - ?3) * (center_z - ?3) AS distance_sq
+               FROM clusters
+               WHERE parent_id IS ?4 AND thought_count > 0
+               ORDER BY distance_sq, id
+               LIMIT 1"#,
+            CLUSTER_COLUMNS
+        ))?;
+        let mut rows = stmt.query_map(params![x, y, z, parent_id], |row| {
+            let distance_sq: f64 = row.get("distance_sq")?;
+            Ok(crate::ClusterMatch { cluster: Self::row_to_cluster(row)?, distance: distance_sq.sqrt(), member: false })
+        })?;
+        rows.next().transpose()
+    }
+
+    /// The leaf cluster a thought was assigned to by compute_clusters or set_thought_cluster,
+    /// or, for a thought added since, the nearest cluster to its position. None when the
+    /// thought doesn't exist or there are no clusters.
+    pub fn get_cluster_for_thought(&self, thought_id: &str) -> Result<Option<crate::ClusterMatch>> {
+        let conn = self.reader()?;
+        let thought: Option<(f64, f64, f64, Option<String>)> = {
+            let mut stmt = conn.prepare_cached("SELECT position_x, position_y, position_z, cluster_id FROM thoughts WHERE id = ?1")?;
+            let mut rows = stmt.query_map([thought_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+            rows.next().transpose()?
+        };
+        let Some((x, y, z, cluster_id)) = thought else {
+            return Ok(None);
+        };
+
+        if let Some(cluster_id) = cluster_id {
+            let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM clusters WHERE id = ?1", CLUSTER_COLUMNS))?;
+            let mut rows = stmt.query_map([cluster_id], Self::row_to_cluster)?;
+            if let Some(cluster) = rows.next().transpose()? {
+                let distance = ((x - cluster.center_x).powi(2) + (y - cluster.center_y).powi(2) + (z - cluster.center_z).powi(2)).sqrt();
+                return Ok(Some(crate::ClusterMatch { cluster, distance, member: true }));
+            }
+        }
+        drop(conn);
+        self.get_nearest_cluster(x, y, z)
+    }
+
+    /// Top-level clusters; their sub-clusters come from get_cluster_children
+    pub fn get_all_clusters(&self) -> Result<Vec<crate::Cluster>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM clusters WHERE parent_id IS NULL", CLUSTER_COLUMNS))?;
+        let clusters = stmt.query_map([], Self::row_to_cluster)?;
+        clusters.collect()
+    }
+
+    /// The sub-clusters a cluster was split into, largest first; empty for a leaf cluster
+    pub fn get_cluster_children(&self, id: &str) -> Result<Vec<crate::Cluster>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM clusters WHERE parent_id = ?1 ORDER BY thought_count DESC, name",
+            CLUSTER_COLUMNS
+        ))?;
+        let clusters = stmt.query_map([id], Self::row_to_cluster)?;
         clusters.collect()
     }
 
-    // Helper to generate random position for new thoughts
-    pub fn generate_position() -> (f64, f64, f64) {
+    pub fn cluster_exists(&self, id: &str) -> Result<bool> {
+        let conn = self.reader()?;
+        conn.query_row("SELECT EXISTS(SELECT 1 FROM clusters WHERE id = ?1)", [id], |row| row.get(0))
+    }
+
+    /// A cluster with its member thoughts (those in it or any of its sub-clusters), most
+    /// important first
+    pub fn get_cluster_detail(&self, id: &str) -> Result<Option<crate::ClusterDetail>> {
+        let conn = self.reader()?;
+        let cluster = {
+            let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM clusters WHERE id = ?1", CLUSTER_COLUMNS))?;
+            let mut rows = stmt.query_map([id], Self::row_to_cluster)?;
+            rows.next().transpose()?
+        };
+        let Some(cluster) = cluster else {
+            return Ok(None);
+        };
+
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"WITH RECURSIVE tree(id) AS (
+                   SELECT ?1
+                   UNION ALL
+                   SELECT c.id FROM clusters c JOIN tree ON c.parent_id = tree.id
+               )
+               SELECT {} FROM thoughts
+               WHERE cluster_id IN (SELECT id FROM tree)
+               ORDER BY importance DESC, created_at DESC"#,
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map([&cluster.id], Self::row_to_thought)?.collect::<Result<Vec<_>>>()?;
+        Ok(Some(crate::ClusterDetail { cluster, thoughts }))
+    }
+
+    fn row_to_cluster(row: &rusqlite::Row) -> Result<crate::Cluster> {
+        let keywords: String = row.get(8)?;
+        Ok(crate::Cluster {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            category: row.get(2)?,
+            center_x: row.get(3)?,
+            center_y: row.get(4)?,
+            center_z: row.get(5)?,
+            thought_count: row.get(6)?,
+            created_at: row.get(7)?,
+            keywords: serde_json::from_str(&keywords).unwrap_or_default(),
+            parent_id: row.get(9)?,
+            child_count: row.get(10)?,
+            manual: row.get(11)?,
+            color: row.get(12)?,
+        })
+    }
+
+    /// Count thoughts, connections, and sessions created on each of the last `days` days.
+    /// Dates are shifted by `tz_offset_minutes` so grouping follows the user's local calendar,
+    /// and days without activity are included with zero counts.
+    pub fn get_activity(&self, days: i64, tz_offset_minutes: i32) -> Result<Vec<crate::DayActivity>> {
+        let days = days.clamp(1, 3660);
+        let offset = format!("{:+} minutes", tz_offset_minutes);
+        let today = (Utc::now() + chrono::Duration::minutes(tz_offset_minutes as i64)).date_naive();
+        let start = today - chrono::Duration::days(days - 1);
+
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT day,
+                      SUM(kind = 't'), SUM(kind = 'c'), SUM(kind = 's')
+               FROM (
+                   SELECT date(created_at, ?1) AS day, 't' AS kind FROM thoughts
+                   UNION ALL
+                   SELECT date(created_at, ?1), 'c' FROM connections
+                   UNION ALL
+                   SELECT date(started_at, ?1), 's' FROM sessions
+               )
+               WHERE day >= ?2
+               GROUP BY day"#
+        )?;
+
+        let mut counts = std::collections::HashMap::new();
+        let rows = stmt.query_map(params![offset, start.to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?, row.get(3)?)))
+        })?;
+        for row in rows {
+            let (day, day_counts) = row?;
+            counts.insert(day, day_counts);
+        }
+
+        Ok(start.iter_days()
+            .take(days as usize)
+            .map(|day| {
+                let date = day.to_string();
+                let (thoughts, connections, sessions) = counts.get(&date).copied().unwrap_or((0, 0, 0));
+                crate::DayActivity { date, thoughts, connections, sessions }
+            })
+            .collect())
+    }
+
+    /// Thoughts created on a local calendar day (YYYY-MM-DD), oldest first
+    pub fn get_thoughts_on_day(&self, date: &str, tz_offset_minutes: i32) -> Result<Vec<Thought>> {
+        self.get_thoughts_between(date, date, tz_offset_minutes)
+    }
+
+    /// Thoughts created on the local calendar days `first` through `last` (YYYY-MM-DD), oldest first
+    pub fn get_thoughts_between(&self, first: &str, last: &str, tz_offset_minutes: i32) -> Result<Vec<Thought>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM thoughts WHERE date(created_at, ?1) BETWEEN ?2 AND ?3 ORDER BY created_at",
+            THOUGHT_COLUMNS
+        ))?;
+        let thoughts = stmt.query_map(
+            params![format!("{:+} minutes", tz_offset_minutes), first, last],
+            Self::row_to_thought,
+        )?;
+        thoughts.collect()
+    }
+
+    /// Connections created on a local calendar day, with both endpoints' text
+    pub fn get_connections_on_day(&self, date: &str, tz_offset_minutes: i32) -> Result<Vec<crate::ConnectionMatch>> {
+        self.get_connections_between(date, date, tz_offset_minutes)
+    }
+
+    /// Connections created on the local calendar days `first` through `last`, oldest first,
+    /// with both endpoints' text
+    pub fn get_connections_between(&self, first: &str, last: &str, tz_offset_minutes: i32) -> Result<Vec<crate::ConnectionMatch>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT c.id, c.from_thought, c.to_thought, c.strength, c.reason, c.created_at, c.kind,
+                      f.content, t.content
+               FROM connections c
+               JOIN thoughts f ON f.id = c.from_thought
+               JOIN thoughts t ON t.id = c.to_thought
+               WHERE date(c.created_at, ?1) BETWEEN ?2 AND ?3
+               ORDER BY c.created_at"#
+        )?;
+        let matches = stmt.query_map(params![format!("{:+} minutes", tz_offset_minutes), first, last], |row| {
+            Ok(crate::ConnectionMatch {
+                connection: Self::row_to_connection(row)?,
+                from_content: row.get(7)?,
+                to_content: row.get(8)?,
+            })
+        })?;
+        matches.collect()
+    }
+
+    /// Connections with at least one endpoint in `ids`, oldest first, with both endpoints' text
+    pub fn get_connections_touching(&self, ids: &[String]) -> Result<Vec<crate::ConnectionMatch>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT c.id, c.from_thought, c.to_thought, c.strength, c.reason, c.created_at, c.kind,
+                      f.content, t.content
+               FROM connections c
+               JOIN thoughts f ON f.id = c.from_thought
+               JOIN thoughts t ON t.id = c.to_thought
+               WHERE c.from_thought IN (SELECT value FROM json_each(?1))
+                  OR c.to_thought IN (SELECT value FROM json_each(?1))
+               ORDER BY c.created_at"#
+        )?;
+        let ids = serde_json::to_string(ids).unwrap_or_default();
+        let matches = stmt.query_map([ids], |row| {
+            Ok(crate::ConnectionMatch {
+                connection: Self::row_to_connection(row)?,
+                from_content: row.get(7)?,
+                to_content: row.get(8)?,
+            })
+        })?;
+        matches.collect()
+    }
+
+    /// Sessions started on a local calendar day, oldest first
+    pub fn get_sessions_on_day(&self, date: &str, tz_offset_minutes: i32) -> Result<Vec<crate::Session>> {
+        self.get_sessions_between(date, date, tz_offset_minutes)
+    }
+
+    /// Sessions started on the local calendar days `first` through `last`, oldest first
+    pub fn get_sessions_between(&self, first: &str, last: &str, tz_offset_minutes: i32) -> Result<Vec<crate::Session>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM sessions WHERE date(started_at, ?1) BETWEEN ?2 AND ?3 ORDER BY started_at",
+            SESSION_COLUMNS
+        ))?;
+        let sessions = stmt.query_map(
+            params![format!("{:+} minutes", tz_offset_minutes), first, last],
+            Self::row_to_session,
+        )?;
+        sessions.collect()
+    }
+
+    /// Row totals, get_category_stats and the STATS_TOP_KEYWORDS top keywords (skipping
+    /// `stopwords`)
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn get_stats(&self, stopwords: &HashSet<String>) -> Result<crate::MindStats> {
+        let (thoughts, connections, sessions, clusters) = self.reader()?.query_row(
+            r#"SELECT (SELECT COUNT(*) FROM thoughts), (SELECT COUNT(*) FROM connections),
+                      (SELECT COUNT(*) FROM sessions), (SELECT COUNT(*) FROM clusters)"#,
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        Ok(crate::MindStats {
+            thoughts,
+            connections,
+            sessions,
+            clusters,
+            categories: self.get_category_stats()?,
+            top_keywords: self.get_top_keywords(STATS_TOP_KEYWORDS, None, None, stopwords)?,
+        })
+    }
+
+    /// Per-category counts, average importance, touching connections, newest thought and color
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn get_category_stats(&self) -> Result<Vec<crate::CategoryStats>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            r#"WITH connection_categories AS (
+                   SELECT c.id, t.category FROM connections c JOIN thoughts t ON t.id = c.from_thought
+                   UNION
+                   SELECT c.id, t.category FROM connections c JOIN thoughts t ON t.id = c.to_thought
+               ),
+               connection_counts AS (
+                   SELECT category, COUNT(*) AS n FROM connection_categories GROUP BY category
+               )
+               SELECT t.category, COUNT(*), AVG(t.importance), MAX(t.created_at),
+                      COALESCE(cc.n, 0), (SELECT color FROM categories WHERE name = t.category)
+               FROM thoughts t
+               LEFT JOIN connection_counts cc ON cc.category = t.category
+               GROUP BY t.category
+               ORDER BY COUNT(*) DESC"#
+        )?;
+
+        let stats = stmt.query_map([], |row| {
+            Ok(crate::CategoryStats {
+                category: row.get(0)?,
+                thought_count: row.get(1)?,
+                avg_importance: row.get(2)?,
+                newest_thought: row.get(3)?,
+                connection_count: row.get(4)?,
+                color: row.get(5)?,
+            })
+        })?;
+
+        stats.collect()
+    }
+
+    /// Thought counts per category for the last `buckets` buckets (UTC calendar), oldest first.
+    /// Every bucket in range is present even when empty.
+    pub fn get_category_trend(&self, bucket: TimeBucket, buckets: i64) -> Result<Vec<crate::CategoryTrendBucket>> {
+        let buckets = buckets.clamp(1, 520);
+        let mut first = bucket.start_of(Utc::now().date_naive());
+        for _ in 1..buckets {
+            first = bucket.prev(first);
+        }
+
+        let sql = format!(
+            r#"SELECT {start} AS bucket, category, COUNT(*)
+               FROM thoughts
+               WHERE bucket >= ?2
+               GROUP BY bucket, category"#,
+            start = bucket.sql_start("created_at")
+        );
+
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let mut counts: std::collections::HashMap<String, std::collections::BTreeMap<String, i64>> =
+            std::collections::HashMap::new();
+        let rows = stmt.query_map(params!["+0 minutes", first.to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+        })?;
+        for row in rows {
+            let (start, category, count) = row?;
+            counts.entry(start).or_default().insert(category, count);
+        }
+
+        let mut result = Vec::new();
+        let mut start = first;
+        for _ in 0..buckets {
+            let key = start.to_string();
+            result.push(crate::CategoryTrendBucket {
+                counts: counts.remove(&key).unwrap_or_default(),
+                bucket_start: key,
+            });
+            start = bucket.next(start);
+        }
+        Ok(result)
+    }
+
+    /// Thoughts created between the local dates `from` and `to` (inclusive), grouped into
+    /// day/week/month buckets shifted by `tz_offset_minutes`: each bucket's thought ids in
+    /// creation order, counts per category and its most important thought. Every bucket
+    /// touching the range is present even when empty, oldest first.
+    pub fn get_timeline(&self, bucket: TimeBucket, from: NaiveDate, to: NaiveDate, tz_offset_minutes: i32) -> Result<Vec<crate::TimelineBucket>> {
+        let first = bucket.start_of(from);
+        let last = bucket.start_of(to);
+        let offset = format!("{:+} minutes", tz_offset_minutes);
+        let start = bucket.sql_start("created_at");
+        // A bucket can reach outside the range (a week starting before `from`), so the range
+        // bounds the local date and the bucket only groups
+        let in_range = "date(created_at, ?1) BETWEEN ?2 AND ?3";
+        let range = params![offset, from.to_string(), to.to_string()];
+        let conn = self.reader()?;
+
+        let mut ids: HashMap<String, Vec<String>> = HashMap::new();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT bucket, json_group_array(id) FROM (
+                 SELECT {start} AS bucket, id FROM thoughts WHERE {in_range} ORDER BY julianday(created_at), id
+             ) GROUP BY bucket"
+        ))?;
+        let rows = stmt.query_map(range, |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (key, list) = row?;
+            ids.insert(key, serde_json::from_str(&list).unwrap_or_default());
+        }
+
+        let mut counts: HashMap<String, std::collections::BTreeMap<String, i64>> = HashMap::new();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {start} AS bucket, category, COUNT(*) FROM thoughts WHERE {in_range} GROUP BY bucket, category"
+        ))?;
+        let rows = stmt.query_map(range, |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?)))?;
+        for row in rows {
+            let (key, category, count) = row?;
+            counts.entry(key).or_default().insert(category, count);
+        }
+
+        // Ties on importance go to the earliest thought
+        let mut representatives: HashMap<String, Thought> = HashMap::new();
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {THOUGHT_COLUMNS}, bucket FROM (
+                 SELECT *, {start} AS bucket,
+                        ROW_NUMBER() OVER (PARTITION BY {start} ORDER BY importance DESC, julianday(created_at), id) AS rank
+                 FROM thoughts WHERE {in_range}
+             ) WHERE rank = 1"
+        ))?;
+        let rows = stmt.query_map(range, |row| Ok((row.get::<_, String>(13)?, Self::row_to_thought(row)?)))?;
+        for row in rows {
+            let (key, thought) = row?;
+            representatives.insert(key, thought);
+        }
+
+        let mut result = Vec::new();
+        let mut start = first;
+        while start <= last && result.len() < MAX_TIMELINE_BUCKETS {
+            let key = start.to_string();
+            let thought_ids = ids.remove(&key).unwrap_or_default();
+            result.push(crate::TimelineBucket {
+                count: thought_ids.len(),
+                thought_ids,
+                counts: counts.remove(&key).unwrap_or_default(),
+                representative: representatives.remove(&key),
+                bucket_start: key,
+            });
+            start = bucket.next(start);
+        }
+        Ok(result)
+    }
+
+    pub fn list_categories(&self) -> Result<Vec<crate::Category>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT c.name, c.color, c.icon, c.created_at, COUNT(t.id)
+               FROM categories c
+               LEFT JOIN thoughts t ON t.category = c.name
+               GROUP BY c.name
+               ORDER BY c.name"#
+        )?;
+
+        let categories = stmt.query_map([], |row| {
+            Ok(crate::Category {
+                name: row.get(0)?,
+                color: row.get(1)?,
+                icon: row.get(2)?,
+                created_at: row.get(3)?,
+                thought_count: row.get(4)?,
+            })
+        })?;
+
+        categories.collect()
+    }
+
+    pub fn category_exists(&self, name: &str) -> Result<bool> {
+        self.reader()?.query_row(
+            "SELECT EXISTS(SELECT 1 FROM categories WHERE name = ?1)",
+            [name],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn create_category(&self, name: &str, color: Option<&str>, icon: Option<&str>) -> Result<crate::Category> {
+        let now = Utc::now().to_rfc3339();
+        self.writer().execute(
+            "INSERT INTO categories (name, color, icon, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![name, color, icon, now],
+        )?;
+        Ok(crate::Category {
+            name: name.to_string(),
+            color: color.map(|c| c.to_string()),
+            icon: icon.map(|i| i.to_string()),
+            created_at: now,
+            thought_count: 0,
+        })
+    }
+
+    /// Set a category's color and icon (None clears one). False if there's no such category.
+    pub fn set_category_appearance(&self, name: &str, color: Option<&str>, icon: Option<&str>) -> Result<bool> {
+        let updated = self.writer().execute(
+            "UPDATE categories SET color = ?2, icon = ?3 WHERE name = ?1",
+            params![name, color, icon],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Move every thought and cluster from `old` to `new` and drop `old`, in one transaction.
+    /// If `new` already exists the two categories are merged. Returns the number of thoughts moved.
+    pub fn rename_category(&self, old: &str, new: &str) -> Result<usize> {
+        let mut conn = self.writer();
+        let tx = conn.transaction()?;
+        tx.execute(
+            r#"INSERT OR IGNORE INTO categories (name, color, icon, created_at)
+               SELECT ?2, color, icon, created_at FROM categories WHERE name = ?1"#,
+            params![old, new],
+        )?;
+        let moved = tx.execute("UPDATE thoughts SET category = ?2 WHERE category = ?1", params![old, new])?;
+        tx.execute(
+            // Keyword names stay; only the "<category> cluster" fallback follows the category
+            r#"UPDATE clusters
+               SET category = ?2, name = CASE WHEN manual = 0 AND name = ?1 || ' cluster' THEN ?2 || ' cluster' ELSE name END
+               WHERE category = ?1"#,
+            params![old, new],
+        )?;
+        if old != new {
+            tx.execute("DELETE FROM categories WHERE name = ?1", [old])?;
+        }
+        tx.commit()?;
+        Ok(moved)
+    }
+
+    fn row_to_view(row: &rusqlite::Row) -> Result<crate::CameraView> {
+        Ok(crate::CameraView {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            position_x: row.get(2)?,
+            position_y: row.get(3)?,
+            position_z: row.get(4)?,
+            target_x: row.get(5)?,
+            target_y: row.get(6)?,
+            target_z: row.get(7)?,
+            zoom: row.get(8)?,
+            created_at: row.get(9)?,
+        })
+    }
+
+    pub fn list_views(&self) -> Result<Vec<crate::CameraView>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT id, name, position_x, position_y, position_z, target_x, target_y, target_z, zoom, created_at
+               FROM views ORDER BY name COLLATE NOCASE"#
+        )?;
+        let views = stmt.query_map([], Self::row_to_view)?;
+        views.collect()
+    }
+
+    /// Names are unique ignoring case
+    pub fn find_view_by_name(&self, name: &str) -> Result<Option<crate::CameraView>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT id, name, position_x, position_y, position_z, target_x, target_y, target_z, zoom, created_at
+               FROM views WHERE name = ?1"#
+        )?;
+        let view = stmt.query_map([name], Self::row_to_view)?.next().transpose();
+        view
+    }
+
+    /// Insert a view, or replace the camera of the one with the same name (which keeps its id
+    /// and created_at).
+    /// Returns the stored view.
+    pub fn save_view(&self, view: &crate::CameraView) -> Result<crate::CameraView> {
+        self.writer().execute(
+            r#"INSERT INTO views (id, name, position_x, position_y, position_z, target_x, target_y, target_z, zoom, created_at)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+               ON CONFLICT(name) DO UPDATE SET
+                   name = excluded.name,
+                   position_x = excluded.position_x, position_y = excluded.position_y, position_z = excluded.position_z,
+                   target_x = excluded.target_x, target_y = excluded.target_y, target_z = excluded.target_z,
+                   zoom = excluded.zoom"#,
+            params![
+                view.id, view.name,
+                view.position_x, view.position_y, view.position_z,
+                view.target_x, view.target_y, view.target_z,
+                view.zoom, view.created_at,
+            ],
+        )?;
+        self.find_view_by_name(&view.name)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    /// Returns false when no view has this id
+    pub fn rename_view(&self, id: &str, name: &str) -> Result<bool> {
+        let changed = self.writer().execute("UPDATE views SET name = ?2 WHERE id = ?1", params![id, name])?;
+        Ok(changed > 0)
+    }
+
+    /// Returns false when no view has this id
+    pub fn delete_view(&self, id: &str) -> Result<bool> {
+        let changed = self.writer().execute("DELETE FROM views WHERE id = ?1", [id])?;
+        Ok(changed > 0)
+    }
+
+    /// Thoughts matching a query definition, most important first, at most `limit` of them.
+    /// The definition is expected to have been checked with validation::query.
+    pub fn run_query(&self, query: &crate::QueryDefinition, limit: usize) -> Result<Vec<crate::Thought>> {
+        let (clause, mut values) = query_clause(query);
+        values.push((limit as i64).into());
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM thoughts WHERE {} ORDER BY importance DESC, created_at DESC LIMIT ?{}",
+            THOUGHT_COLUMNS,
+            clause,
+            values.len()
+        ))?;
+        let thoughts = stmt.query_map(rusqlite::params_from_iter(&values), Self::row_to_thought)?;
+        thoughts.collect()
+    }
+
+    fn row_to_saved_query(row: &rusqlite::Row) -> Result<crate::SavedQuery> {
+        let definition: String = row.get(2)?;
+        Ok(crate::SavedQuery {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            definition: serde_json::from_str(&definition)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?,
+            created_at: row.get(3)?,
+        })
+    }
+
+    pub fn list_saved_queries(&self) -> Result<Vec<crate::SavedQuery>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, name, definition, created_at FROM saved_queries ORDER BY name COLLATE NOCASE"
+        )?;
+        let queries = stmt.query_map([], Self::row_to_saved_query)?;
+        queries.collect()
+    }
+
+    pub fn get_saved_query(&self, id: &str) -> Result<Option<crate::SavedQuery>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached("SELECT id, name, definition, created_at FROM saved_queries WHERE id = ?1")?;
+        let query = stmt.query_map([id], Self::row_to_saved_query)?.next().transpose();
+        query
+    }
+
+    /// Names are unique ignoring case
+    pub fn find_saved_query_by_name(&self, name: &str) -> Result<Option<crate::SavedQuery>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached("SELECT id, name, definition, created_at FROM saved_queries WHERE name = ?1")?;
+        let query = stmt.query_map([name], Self::row_to_saved_query)?.next().transpose();
+        query
+    }
+
+    /// Insert a query, or replace the definition of the one with the same name (which keeps its
+    /// id and created_at).
+    /// Returns the stored query.
+    pub fn save_query(&self, query: &crate::SavedQuery) -> Result<crate::SavedQuery> {
+        let definition = serde_json::to_string(&query.definition)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        self.writer().execute(
+            "INSERT INTO saved_queries (id, name, definition, created_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(name) DO UPDATE SET name = excluded.name, definition = excluded.definition",
+            params![query.id, query.name, definition, query.created_at],
+        )?;
+        self.find_saved_query_by_name(&query.name)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    /// Returns false when no saved query has this id
+    pub fn delete_saved_query(&self, id: &str) -> Result<bool> {
+        let changed = self.writer().execute("DELETE FROM saved_queries WHERE id = ?1", [id])?;
+        Ok(changed > 0)
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Where copied attachment files are kept: an attachments folder beside the database
+    pub fn attachments_dir(&self) -> PathBuf {
+        self.path.parent().unwrap_or(std::path::Path::new(".")).join("attachments")
+    }
+
+    pub fn add_attachment(&self, attachment: &crate::Attachment) -> Result<()> {
+        self.writer().execute(
+            "INSERT INTO attachments (id, thought_id, kind, path_or_url, label, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                attachment.id, attachment.thought_id, attachment.kind,
+                attachment.path_or_url, attachment.label, attachment.created_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn row_to_attachment(row: &rusqlite::Row) -> Result<crate::Attachment> {
+        Ok(crate::Attachment {
+            id: row.get(0)?,
+            thought_id: row.get(1)?,
+            kind: row.get(2)?,
+            path_or_url: row.get(3)?,
+            label: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    }
+
+    /// A thought's attachments, oldest first
+    pub fn get_attachments(&self, thought_id: &str) -> Result<Vec<crate::Attachment>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, thought_id, kind, path_or_url, label, created_at FROM attachments
+             WHERE thought_id = ?1 ORDER BY created_at, id"
+        )?;
+        let attachments = stmt.query_map([thought_id], Self::row_to_attachment)?;
+        attachments.collect()
+    }
+
+    pub fn count_attachments(&self, thought_id: &str) -> Result<i64> {
+        self.reader()?.query_row("SELECT COUNT(*) FROM attachments WHERE thought_id = ?1", [thought_id], |row| row.get(0))
+    }
+
+    /// Remove an attachment, and its file if it was copied into attachments_dir. Returns
+    /// false when no attachment has this id.
+    pub fn remove_attachment(&self, id: &str) -> Result<bool> {
+        let changed = self.writer().execute("DELETE FROM attachments WHERE id = ?1", [id])?;
+        if changed > 0 {
+            self.remove_unreferenced_attachment_files();
+        }
+        Ok(changed > 0)
+    }
+
+    /// Delete files in attachments_dir that no attachment refers to any more. Best effort:
+    /// failures are logged and the files left for the next run. Returns how many were deleted.
+    pub fn remove_unreferenced_attachment_files(&self) -> usize {
+        let Ok(entries) = std::fs::read_dir(self.attachments_dir()) else {
+            return 0;
+        };
+        let referenced: HashSet<PathBuf> = match self.copied_attachment_paths() {
+            Ok(paths) => paths,
+            Err(e) => {
+                tracing::warn!("Skipping attachment cleanup: {}", e);
+                return 0;
+            }
+        };
+        let mut removed = 0;
+        for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            if !path.is_file() || referenced.contains(&path) {
+                continue;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => removed += 1,
+                Err(e) => tracing::warn!("Failed to remove attachment file {}: {}", path.display(), e),
+            }
+        }
+        removed
+    }
+
+    fn copied_attachment_paths(&self) -> Result<HashSet<PathBuf>> {
+        let dir = self.attachments_dir();
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached("SELECT path_or_url FROM attachments WHERE kind != 'url'")?;
+        let paths = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        Ok(paths
+            .filter_map(|path| path.ok())
+            .map(PathBuf::from)
+            .filter(|path| path.starts_with(&dir))
+            .collect())
+    }
+
+    /// Row count of every table
+    pub fn table_counts(&self) -> Result<std::collections::BTreeMap<String, i64>> {
+        let conn = self.reader()?;
+        Self::count_rows(&conn)
+    }
+
+    fn count_rows(conn: &Connection) -> Result<std::collections::BTreeMap<String, i64>> {
+        let tables: Vec<String> = {
+            let mut stmt = conn.prepare_cached(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+            )?;
+            let names = stmt.query_map([], |row| row.get(0))?;
+            names.collect::<Result<_>>()?
+        };
+        tables.into_iter()
+            .map(|table| {
+                let count = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| row.get(0))?;
+                Ok((table, count))
+            })
+            .collect()
+    }
+
+    pub fn journal_mode(&self) -> Result<String> {
+        self.reader()?.query_row("PRAGMA journal_mode", [], |row| row.get(0))
+    }
+
+    /// Run a passive checkpoint (never waits on readers); returns the WAL frames it couldn't copy
+    pub fn wal_checkpoint(&self) -> Result<i64> {
+        self.writer().query_row("PRAGMA wal_checkpoint(PASSIVE)", [], |row| {
+            let (log, checkpointed): (i64, i64) = (row.get(1)?, row.get(2)?);
+            Ok(log - checkpointed)
+        })
+    }
+
+    /// Checkpoint the whole WAL and truncate it to zero bytes, waiting on readers and writers
+    /// in other processes (up to the busy timeout). Returns the frames it couldn't copy.
+    pub fn wal_checkpoint_truncate(&self) -> Result<i64> {
+        self.writer().query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+            let (busy, log, checkpointed): (i64, i64, i64) = (row.get(0)?, row.get(1)?, row.get(2)?);
+            Ok(if busy != 0 { log.max(0) } else { log - checkpointed })
+        })
+    }
+
+    /// Refresh the query planner's statistics: every index with `full`, otherwise only those
+    /// SQLite thinks are out of date (PRAGMA optimize)
+    pub fn analyze(&self, full: bool) -> Result<()> {
+        self.writer().execute_batch(if full { "ANALYZE" } else { "PRAGMA optimize" })
+    }
+
+    /// Share of the file's pages that are free, i.e. what VACUUM would give back
+    pub fn free_page_ratio(&self) -> Result<f64> {
+        self.reader()?.query_row(
+            "SELECT CAST(f.freelist_count AS REAL) / MAX(p.page_count, 1) FROM pragma_freelist_count f, pragma_page_count p",
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn vacuum(&self) -> Result<()> {
+        self.writer().execute_batch("VACUUM")
+    }
+
+    /// Rewrite the whole file encrypted with `key`, or unencrypted for None, and switch every
+    /// connection over to it. The copy is made beside the database with sqlcipher_export and
+    /// only replaces it once complete, so a failure leaves the original untouched.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn reencrypt(&self, key: Option<String>) -> Result<()> {
+        let mut copy = self.path.as_os_str().to_owned();
+        copy.push("-rekey");
+        let copy = PathBuf::from(copy);
+        let _ = std::fs::remove_file(&copy);
+
+        let mut writer = self.writer();
+        let exported = (|| {
+            writer.execute(
+                "ATTACH DATABASE ?1 AS target KEY ?2",
+                params![copy.to_string_lossy(), key.as_deref().map(encryption::raw_key).unwrap_or_default()],
+            )?;
+            let result = writer.query_row("SELECT sqlcipher_export('target')", [], |_| Ok(()))
+                .and_then(|_| writer.pragma_update(Some(DatabaseName::Attached("target")), "user_version", Self::schema_version(&writer)?));
+            writer.execute_batch("DETACH DATABASE target")?;
+            result
+        })();
+        if let Err(e) = exported {
+            let _ = std::fs::remove_file(&copy);
+            return Err(e);
+        }
+
+        // Close every connection to the old file; readers still checked out are dropped when returned
+        self.epoch.fetch_add(1, Ordering::SeqCst);
+        lock_recovering(&self.readers).clear();
+        let old = std::mem::replace(&mut *writer, Connection::open_in_memory()?);
+        old.close().map_err(|(_, e)| e)?;
+
+        // The last connection to close removes the WAL; if it's still there another process
+        // (e.g. the MCP server) has the file open and would keep writing to the old copy
+        let mut wal = self.path.as_os_str().to_owned();
+        wal.push("-wal");
+        let replaced = if std::path::Path::new(&wal).exists() {
+            Err("the database is open in another process".to_string())
+        } else {
+            std::fs::rename(&copy, &self.path).map_err(|e| e.to_string())
+        };
+        if let Err(reason) = replaced {
+            let old_key = lock_recovering(&self.key).clone();
+            *writer = Self::connect(&self.path, false, old_key.as_deref())?;
+            let _ = std::fs::remove_file(&copy);
+            return Err(Self::failure(
+                rusqlite::ffi::SQLITE_BUSY,
+                format!("couldn't replace the database file ({}); close the MCP server and anything else using it and retry", reason),
+            ));
+        }
+        *writer = Self::connect(&self.path, false, key.as_deref())?;
+        *lock_recovering(&self.key) = key;
+        Ok(())
+    }
+
+    /// Write a consistent copy of the whole database to `path` (which must not exist yet)
+    #[tracing::instrument(level = "debug", skip(self))]
+    pub fn backup_to(&self, path: &std::path::Path) -> Result<()> {
+        self.writer().execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+        Ok(())
+    }
+
+    /// Delete every row of every table in one transaction, then re-seed the default
+    /// categories and VACUUM. Settings are kept unless `include_settings` is set. Returns the
+    /// row counts that were deleted.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub fn wipe_all(&self, include_settings: bool) -> Result<std::collections::BTreeMap<String, i64>> {
+        let deleted = {
+            let mut conn = self.writer();
+            let tx = conn.transaction()?;
+            // Tables are cleared in name order; foreign keys are checked once everything is gone
+            tx.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+            let mut deleted = Self::count_rows(&tx)?;
+            // Counters keep counting, so pollers see the wipe as a change, and the change
+            // log records the deletions for get_graph_delta
+            deleted.remove("counters");
+            deleted.remove("change_log");
+            if !include_settings {
+                deleted.remove("settings");
+            }
+            for table in deleted.keys() {
+                tx.execute(&format!("DELETE FROM \"{}\"", table), [])?;
+            }
+            tx.commit()?;
+            deleted
+        };
+
+        self.seed_categories()?;
+        self.writer().execute_batch("VACUUM")?;
+        Ok(deleted)
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare_cached("SELECT value FROM settings WHERE key = ?1")?;
+        let mut rows = stmt.query([key])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        self.writer().execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// Read a setting and parse it, falling back to `default` when missing or malformed
+    pub fn setting_or<T: std::str::FromStr>(&self, key: &str, default: T) -> T {
+        self.get_setting(key)
+            .ok()
+            .flatten()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// The randomness to place a thought with `content` and `category` by (see PositionRandom)
+    pub fn position_random(&self, content: &str, category: &str) -> PositionRandom {
+        if self.setting_or(SETTING_DETERMINISTIC_POSITIONS, false) {
+            PositionRandom::Seeded(crate::utils::SeededRandom::for_thought(content, category))
+        } else {
+            PositionRandom::Clock
+        }
+    }
+
+    /// Where a new thought in `category` goes: within the category's sector of angles around the
+    /// vertical axis (fixed by its name), at about the distance from the center its thoughts
+    /// already sit. The first thought of a category is placed anywhere (generate_position).
+    pub fn generate_position_for_category(&self, category: &str, random: &mut PositionRandom) -> (f64, f64, f64) {
+        use std::f64::consts::PI;
+        let radius = match self.category_radius(category) {
+            Ok(Some(radius)) => radius,
+            Ok(None) => return Self::generate_position(random),
+            Err(e) => {
+                tracing::warn!("Placing thought at random, category radius lookup failed: {}", e);
+                return Self::generate_position(random);
+            }
+        };
+        let radius = (radius + (random.next() * 2.0 - 1.0) * CATEGORY_RADIUS_JITTER)
+            .clamp(MIN_PLACEMENT_RADIUS, MAX_PLACEMENT_RADIUS);
+        let theta = category_sector_center(category) + (random.next() - 0.5) * CATEGORY_SECTOR_WIDTH;
+        let phi = random.next() * PI;
+
+        (radius * phi.sin() * theta.cos(), radius * phi.sin() * theta.sin(), radius * phi.cos())
+    }
+
+    /// Root-mean-square distance of the category's thoughts from the center; None when it has none
+    fn category_radius(&self, category: &str) -> Result<Option<f64>> {
+        self.reader()?.query_row(
+            "SELECT AVG(position_x * position_x + position_y * position_y + position_z * position_z) FROM thoughts WHERE category = ?1",
+            [category],
+            |row| Ok(row.get::<_, Option<f64>>(0)?.map(f64::sqrt)),
+        )
+    }
+
+    /// `candidate`, or if another thought is within placement_min_distance of it, the first
+    /// point clear of them going outward from the center one minimum distance at a time. After
+    /// PLACEMENT_RETRIES nudges the last one is used even if it's still crowded.
+    pub fn clear_position(&self, candidate: (f64, f64, f64)) -> (f64, f64, f64) {
+        let min_distance = self.setting_or(SETTING_MIN_PLACEMENT_DISTANCE, DEFAULT_MIN_PLACEMENT_DISTANCE);
+        if !min_distance.is_finite() || min_distance <= 0.0 {
+            return candidate;
+        }
+        let (mut x, mut y, mut z) = candidate;
+        let length = (x * x + y * y + z * z).sqrt();
+        // Straight up for a thought at the very center, which has no outward direction
+        let direction = if length > f64::EPSILON { (x / length, y / length, z / length) } else { (0.0, 0.0, 1.0) };
+        for _ in 0..PLACEMENT_RETRIES {
+            match self.get_thoughts_near(x, y, z, min_distance, 1) {
+                Ok(neighbors) if neighbors.is_empty() => break,
+                Ok(_) => {
+                    x += direction.0 * min_distance;
+                    y += direction.1 * min_distance;
+                    z += direction.2 * min_distance;
+                }
+                Err(e) => {
+                    tracing::warn!("Skipping overlap check for a new thought: {}", e);
+                    break;
+                }
+            }
+        }
+        (x, y, z)
+    }
+
+    /// Random position anywhere in the shell new thoughts are placed in, ignoring category
+    pub fn generate_position(random: &mut PositionRandom) -> (f64, f64, f64) {
         use std::f64::consts::PI;
-        let radius = 10.0 + rand::random::<f64>() * 30.0;
-        let theta = rand::random::<f64>() * 2.0 * PI;
-        let phi = rand::random::<f64>() * PI;
+        let radius = MIN_PLACEMENT_RADIUS + random.next() * (MAX_PLACEMENT_RADIUS - MIN_PLACEMENT_RADIUS);
+        let theta = random.next() * 2.0 * PI;
+        let phi = random.next() * PI;
         
         let x = radius * phi.sin() * theta.cos();
         let y = radius * phi.sin() * theta.sin();
@@ -419,27 +4233,939 @@ impl Database {
         
         (x, y, z)
     }
+
+    /// Random position within `spread` units of an anchor point
+    pub fn generate_position_near(x: f64, y: f64, z: f64, spread: f64, random: &mut PositionRandom) -> (f64, f64, f64) {
+        let mut jitter = || (random.next() * 2.0 - 1.0) * spread;
+        (x + jitter(), y + jitter(), z + jitter())
+    }
 }
 
 // For random position generation
 mod rand {
+    use std::sync::atomic::{AtomicU64, Ordering};
     use std::time::{SystemTime, UNIX_EPOCH};
-    
-    static mut SEED: u64 = 0;
-    
+
+    // Shared by every thread placing thoughts; seeded from the clock on first use
+    static SEED: AtomicU64 = AtomicU64::new(0);
+
     pub fn random<T: From<f64>>() -> T {
-        unsafe {
-            if SEED == 0 {
-                SEED = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_nanos() as u64;
-            }
-            
+        let mut next = 0;
+        // The closure always returns Some, so this can't fail
+        let _ = SEED.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |seed| {
+            let seed = if seed == 0 {
+                SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |d| d.as_nanos() as u64)
+            } else {
+                seed
+            };
             // Simple LCG
-            SEED = SEED.wrapping_mul(6364136223846793005).wrapping_add(1);
-            let val = (SEED >> 33) as f64 / (1u64 << 31) as f64;
-            T::from(val)
+            next = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            Some(next)
+        });
+        T::from((next >> 33) as f64 / (1u64 << 31) as f64)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::ops::Deref;
+    use std::path::PathBuf;
+    use super::Database;
+    use crate::{Thought, Connection as ThoughtConnection};
+
+    /// A database in its own temp directory, removed again on drop
+    pub(crate) struct TempDb {
+        db: Option<Database>,
+        dir: PathBuf,
+    }
+
+    impl TempDb {
+        pub(crate) fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("the-mind-test-{}", uuid::Uuid::new_v4()));
+            let db = Database::open_at(dir.join("mind.db"), false).expect("open temp database");
+            TempDb { db: Some(db), dir }
+        }
+
+        /// A database file first written by `sql` on a bare connection, then opened as usual
+        pub(crate) fn from_sql(sql: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("the-mind-test-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).expect("create temp directory");
+            rusqlite::Connection::open(dir.join("mind.db"))
+                .and_then(|conn| conn.execute_batch(sql))
+                .expect("write fixture database");
+            let db = Database::open_at(dir.join("mind.db"), false).expect("open fixture database");
+            TempDb { db: Some(db), dir }
+        }
+
+        pub(crate) fn path(&self) -> PathBuf {
+            self.dir.join("mind.db")
+        }
+    }
+
+    impl Deref for TempDb {
+        type Target = Database;
+
+        fn deref(&self) -> &Database {
+            self.db.as_ref().expect("database is open until drop")
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            self.db.take();
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    pub(crate) fn connection(id: &str, from: &str, to: &str, created_at: &str) -> ThoughtConnection {
+        ThoughtConnection {
+            id: id.to_string(),
+            from_thought: from.to_string(),
+            to_thought: to.to_string(),
+            strength: 0.5,
+            reason: "test".to_string(),
+            created_at: created_at.to_string(),
+            kind: "related".to_string(),
+        }
+    }
+
+    /// A session's session_thoughts positions, in order
+    pub(crate) fn session_positions(db: &Database, session_id: &str) -> Vec<i64> {
+        let conn = db.reader().unwrap();
+        let mut stmt = conn.prepare("SELECT position FROM session_thoughts WHERE session_id = ?1 ORDER BY position").unwrap();
+        let positions = stmt.query_map([session_id], |row| row.get(0)).unwrap();
+        positions.collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    /// Panic while holding the writer lock, leaving it poisoned
+    pub(crate) fn poison_writer(db: &Database) {
+        let poisoned = std::panic::catch_unwind(|| {
+            let _writer = db.writer.lock().unwrap();
+            panic!("deliberate panic holding the writer");
+        });
+        assert!(poisoned.is_err());
+        assert!(db.writer.is_poisoned());
+    }
+
+    pub(crate) fn thought(id: &str, content: &str, category: &str, importance: f64, created_at: &str) -> Thought {
+        Thought {
+            id: id.to_string(),
+            content: content.to_string(),
+            role: None,
+            category: category.to_string(),
+            importance,
+            position_x: 0.0,
+            position_y: 0.0,
+            position_z: 0.0,
+            created_at: created_at.to_string(),
+            last_referenced: created_at.to_string(),
+            pinned: false,
+            favorite: false,
+            archived: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use super::testing::{connection, thought, TempDb};
+    use super::{Database, ThoughtSort, TimeBucket, DEFAULT_CATEGORIES, DEFAULT_MIN_PLACEMENT_DISTANCE, SCHEMA_VERSION, SETTING_DETERMINISTIC_POSITIONS};
+
+    const T0: &str = "2026-03-01T12:00:00+00:00";
+
+    fn column<T: rusqlite::types::FromSql>(db: &Database, id: &str, column: &str) -> T {
+        db.reader().unwrap()
+            .query_row(&format!("SELECT {} FROM thoughts WHERE id = ?1", column), [id], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn reinserting_a_locked_thought_leaves_it_alone() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("a", "original wording", "idea", 0.5, T0)).unwrap();
+        assert!(db.set_thought_locked("a", true).unwrap());
+
+        db.insert_thought(&thought("a", "overwritten wording", "task", 0.9, T0)).unwrap();
+
+        assert!(db.is_thought_locked("a").unwrap());
+        let stored = db.get_thought_by_id("a").unwrap().unwrap();
+        assert_eq!(stored.content, "original wording");
+        assert_eq!(stored.category, "idea");
+    }
+
+    #[test]
+    fn reinserting_keeps_columns_outside_the_thought() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("a", "first", "idea", 0.5, T0)).unwrap();
+        db.set_thought_metadata("a", r#"{"source":"test"}"#).unwrap();
+        assert!(db.mark_reviewed("a").unwrap());
+
+        db.insert_thought(&thought("a", "second", "idea", 0.7, "2026-03-02T12:00:00+00:00")).unwrap();
+
+        let stored = db.get_thought_by_id("a").unwrap().unwrap();
+        assert_eq!(stored.content, "second");
+        assert_eq!(stored.created_at, T0);
+        assert_eq!(column::<i64>(&db, "a", "reference_count"), 1);
+        let metadata: String = column(&db, "a", "metadata");
+        assert!(metadata.contains(r#""source":"test""#), "{}", metadata);
+        assert!(!column::<bool>(&db, "a", "locked"));
+    }
+
+    #[test]
+    fn review_queue_leaves_out_archived_thoughts() {
+        let db = TempDb::new();
+        let mut archived = thought("archived", "stale but important", "idea", 1.0, "2020-01-01T00:00:00+00:00");
+        archived.archived = true;
+        db.insert_thought(&archived).unwrap();
+        db.insert_thought(&thought("recent", "fresh", "idea", 0.2, "2026-01-01T00:00:00+00:00")).unwrap();
+
+        let queue = db.get_review_queue(10, false).unwrap();
+
+        let ids: Vec<&str> = queue.iter().map(|(t, _, _)| t.id.as_str()).collect();
+        assert_eq!(ids, ["recent"]);
+    }
+
+    fn archive(db: &TempDb, content: &str) {
+        let filter = crate::ThoughtFilter { content: Some(content.to_string()), ..Default::default() };
+        let changes = crate::ThoughtChanges { archived: Some(true), ..Default::default() };
+        assert_eq!(db.bulk_update_thoughts(&filter, &changes).unwrap(), 1);
+    }
+
+    #[test]
+    fn archived_thoughts_are_left_out_of_reads() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("kept", "sqlite tuning notes", "idea", 0.5, T0)).unwrap();
+        db.insert_thought(&thought("gone", "sqlite vacuum notes", "idea", 0.9, T0)).unwrap();
+        let since = db.get_graph_delta(0).unwrap().seq;
+        archive(&db, "vacuum");
+
+        let ids = |thoughts: Vec<crate::Thought>| thoughts.into_iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(ids(db.search_thoughts("sqlite").unwrap()), ["kept"]);
+        assert_eq!(ids(db.get_thoughts_capped(10).unwrap().0), ["kept"]);
+        assert_eq!(ids(db.get_graph_snapshot(None, None, 10).unwrap().thoughts), ["kept"]);
+        assert_eq!(ids(db.get_thought_batch(0, i64::MAX, 10).unwrap().into_iter().map(|(_, t)| t).collect()), ["kept"]);
+
+        let mut query = crate::QueryDefinition { text: Some("sqlite".to_string()), ..Default::default() };
+        assert_eq!(ids(db.run_query(&query, 10).unwrap()), ["kept"]);
+        query.include_archived = true;
+        assert_eq!(ids(db.run_query(&query, 10).unwrap()), ["gone", "kept"]);
+
+        let delta = db.get_graph_delta(since).unwrap();
+        assert!(delta.thoughts.is_empty());
+        assert_eq!(delta.deleted.thoughts, ["gone"]);
+    }
+
+    #[test]
+    fn nearest_cluster_is_measured_from_its_centroid() {
+        let db = TempDb::new();
+        for i in 0..3 {
+            let mut t = thought(&format!("a{}", i), "alpha", "alpha", 0.5, T0);
+            t.position_x = 100.0;
+            db.insert_thought(&t).unwrap();
+        }
+        db.compute_clusters().unwrap();
+
+        let nearest = db.get_nearest_cluster(103.0, 4.0, 0.0).unwrap().expect("a cluster");
+
+        assert_eq!(nearest.cluster.category, "alpha");
+        assert!((nearest.distance - 5.0).abs() < 1e-9, "{}", nearest.distance);
+    }
+
+    fn placed(id: &str, category: &str, x: f64) -> crate::Thought {
+        let mut t = thought(id, &format!("{} thought {}", category, id), category, 0.5, T0);
+        t.position_x = x;
+        t
+    }
+
+    #[test]
+    fn nearest_cluster_picks_the_closest_of_known_centroids() {
+        let db = TempDb::new();
+        assert!(db.get_nearest_cluster(0.0, 0.0, 0.0).unwrap().is_none());
+        // Centroids at x = 1 and x = 101
+        for (id, category, x) in [("a0", "alpha", 0.0), ("a1", "alpha", 2.0), ("b0", "beta", 100.0), ("b1", "beta", 102.0)] {
+            db.insert_thought(&placed(id, category, x)).unwrap();
+        }
+        db.compute_clusters().unwrap();
+
+        let near_alpha = db.get_nearest_cluster(40.0, 0.0, 0.0).unwrap().unwrap();
+        let near_beta = db.get_nearest_cluster(60.0, 0.0, 0.0).unwrap().unwrap();
+
+        assert_eq!((near_alpha.cluster.category.as_str(), near_alpha.distance), ("alpha", 39.0));
+        assert_eq!((near_beta.cluster.category.as_str(), near_beta.distance), ("beta", 41.0));
+        assert!(!near_beta.member);
+        // A clustered thought reports its own cluster, measured from the centroid
+        let own = db.get_cluster_for_thought("a0").unwrap().unwrap();
+        assert_eq!((own.cluster.category.as_str(), own.distance, own.member), ("alpha", 1.0, true));
+    }
+
+    #[test]
+    fn renaming_a_category_keeps_keyword_cluster_names() {
+        let db = TempDb::new();
+        for (i, content) in ["Wallpaper window behind desktop icons", "Wallpaper window on every monitor", "Wallpaper window after explorer restarts"].iter().enumerate() {
+            db.insert_thought(&thought(&format!("t{}", i), content, "alpha", 0.5, T0)).unwrap();
+        }
+        db.compute_clusters().unwrap();
+        let before = db.get_all_clusters().unwrap();
+        assert_eq!(before.len(), 1);
+        assert!(before[0].name.contains("wallpaper"), "{}", before[0].name);
+
+        db.rename_category("alpha", "beta").unwrap();
+
+        let after = db.get_all_clusters().unwrap();
+        assert_eq!((after[0].name.as_str(), after[0].category.as_str()), (before[0].name.as_str(), "beta"));
+        // Recomputing finds the same keywords and keeps the name
+        db.compute_clusters().unwrap();
+        assert_eq!(db.get_all_clusters().unwrap()[0].name, before[0].name);
+    }
+
+    #[test]
+    fn a_new_thought_joins_the_nearest_cluster() {
+        let db = TempDb::new();
+        assert!(db.join_nearest_cluster("missing").unwrap().is_none());
+        for i in 0..3 {
+            db.insert_thought(&placed(&format!("a{}", i), "alpha", i as f64)).unwrap();
+            db.insert_thought(&placed(&format!("b{}", i), "beta", 100.0 + i as f64)).unwrap();
+        }
+        db.compute_clusters().unwrap();
+        db.insert_thought(&placed("new", "gamma", 98.0)).unwrap();
+
+        let joined = db.join_nearest_cluster("new").unwrap().expect("joined a cluster");
+
+        assert_eq!(joined.category, "beta");
+        assert_eq!(joined.thought_count, 4);
+        let found = db.get_cluster_for_thought("new").unwrap().unwrap();
+        assert!(found.member);
+        assert_eq!(found.cluster.id, joined.id);
+        assert_eq!(found.cluster.thought_count, 4);
+        // Already placed
+        assert!(db.join_nearest_cluster("new").unwrap().is_none());
+    }
+
+    #[test]
+    fn wipe_keeps_settings_unless_asked() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("a", "wiped", "idea", 0.5, T0)).unwrap();
+        db.set_setting("theme", "dark").unwrap();
+
+        let deleted = db.wipe_all(false).unwrap();
+
+        assert_eq!(deleted.get("thoughts"), Some(&1));
+        assert!(!deleted.contains_key("settings"));
+        assert_eq!(db.get_thought_count().unwrap(), 0);
+        assert_eq!(db.get_setting("theme").unwrap().as_deref(), Some("dark"));
+
+        let deleted = db.wipe_all(true).unwrap();
+
+        assert!(deleted.get("settings").is_some_and(|&n| n >= 1));
+        assert_eq!(db.get_setting("theme").unwrap(), None);
+    }
+
+    #[test]
+    fn connections_for_thoughts_across_chunks() {
+        let db = TempDb::new();
+        let ids: Vec<String> = (0..2000).map(|i| format!("t{:04}", i)).collect();
+        let thoughts: Vec<_> = ids.iter().map(|id| thought(id, id, "idea", 0.5, T0)).collect();
+        db.insert_thoughts_batch(&thoughts).unwrap();
+        // A chain through every thought, plus one between the first and last chunk
+        let mut connections: Vec<_> = ids.windows(2)
+            .map(|pair| connection(&format!("{}-{}", pair[0], pair[1]), &pair[0], &pair[1], T0))
+            .collect();
+        connections.push(connection("ends", &ids[0], &ids[1999], T0));
+        db.insert_connections_batch(&connections).unwrap();
+
+        let count = |ids: &[String]| {
+            let found = db.get_connections_for_thoughts(ids).unwrap();
+            let unique: HashSet<String> = found.iter().map(|c| c.id.clone()).collect();
+            assert_eq!(unique.len(), found.len(), "a connection came back twice");
+            found.len()
+        };
+        assert_eq!(count(&[]), 0);
+        assert_eq!(count(&ids[..1]), 0);
+        assert_eq!(count(&ids[..2]), 1);
+        assert_eq!(count(&ids[..500]), 499);
+        assert_eq!(count(&ids), 2000);
+        // Every id twice, in separate chunks
+        assert_eq!(count(&[ids.clone(), ids.clone()].concat()), 2000);
+        // The first 1,000 and the last: "ends" joins the first chunk to the third
+        let ends: Vec<String> = ids[..1000].iter().chain(&ids[1999..]).cloned().collect();
+        assert_eq!(count(&ends), 1000);
+    }
+
+    #[test]
+    fn stats_total_everything_and_list_the_top_keywords() {
+        let db = TempDb::new();
+        for i in 0..12 {
+            // Keywords shared by more thoughts the lower their number
+            let words: Vec<String> = (0..=i).map(|k| format!("keyword{}", k)).collect();
+            db.insert_thought(&thought(&format!("t{}", i), &words.join(" "), "idea", 0.5, T0)).unwrap();
+        }
+        db.insert_connection(&connection("c", "t0", "t1", T0)).unwrap();
+
+        let stats = db.get_stats(&HashSet::new()).unwrap();
+
+        assert_eq!((stats.thoughts, stats.connections, stats.sessions), (12, 1, 0));
+        assert_eq!(stats.categories.len(), 1);
+        assert_eq!(stats.categories[0].thought_count, 12);
+        assert_eq!(stats.top_keywords.len(), super::STATS_TOP_KEYWORDS as usize);
+        assert_eq!(stats.top_keywords[0].doc_count, 12);
+    }
+
+    #[test]
+    fn stats_carry_category_colors() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("a", "built in", "technical", 0.5, T0)).unwrap();
+        db.insert_thought(&thought("b", "made up", "brand-new", 0.5, T0)).unwrap();
+
+        let stats = db.get_stats(&HashSet::new()).unwrap();
+
+        let color = |name: &str| stats.categories.iter().find(|c| c.category == name).unwrap().color.clone();
+        assert!(color("technical").is_some_and(|c| c.starts_with('#')));
+        assert_eq!(color("brand-new"), None);
+    }
+
+    /// Time `one_by_one` against `batched`, print both and check the batch is faster.
+    /// Returns the batched time.
+    fn time_batch(what: &str, one_by_one: impl FnOnce(), batched: impl FnOnce()) -> std::time::Duration {
+        let started = std::time::Instant::now();
+        one_by_one();
+        let one_by_one = started.elapsed();
+
+        let started = std::time::Instant::now();
+        batched();
+        let batched = started.elapsed();
+
+        println!("{}: {:?} one by one, {:?} batched", what, one_by_one, batched);
+        assert!(batched < one_by_one, "batch {:?} vs one by one {:?}", batched, one_by_one);
+        batched
+    }
+
+    #[test]
+    #[ignore = "timing benchmark; run with --release --ignored"]
+    fn batch_inserts_connections_in_one_transaction() {
+        let db = TempDb::new();
+        let ids: Vec<String> = (0..100).map(|i| format!("t{}", i)).collect();
+        let thoughts: Vec<_> = ids.iter().map(|id| thought(id, id, "idea", 0.5, T0)).collect();
+        db.insert_thoughts_batch(&thoughts).unwrap();
+        let connections = |prefix: &str| -> Vec<_> {
+            (0..5000)
+                .map(|i| connection(&format!("{}{}", prefix, i), &ids[i % 100], &ids[(i / 100 + i + 1) % 100], T0))
+                .collect()
+        };
+
+        let mut inserted = 0;
+        time_batch(
+            "5,000 connections",
+            || for c in connections("one-") {
+                db.insert_connection(&c).unwrap();
+            },
+            || inserted = db.insert_connections_batch(&connections("batch-")).unwrap().inserted,
+        );
+
+        assert_eq!(inserted, 5000);
+    }
+
+    #[test]
+    fn batch_insert_skips_taken_ids() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("t1", "already here", "idea", 0.5, T0)).unwrap();
+        let batch: Vec<_> = ["t0", "t1", "t2"].iter().map(|id| thought(id, &format!("batch {}", id), "idea", 0.5, T0)).collect();
+
+        let result = db.insert_thoughts_batch(&batch).unwrap();
+
+        assert_eq!((result.inserted, result.skipped), (2, 1));
+        assert_eq!(db.get_thought_count().unwrap(), 3);
+        assert_eq!(db.get_thought_by_id("t1").unwrap().unwrap().content, "already here");
+    }
+
+    #[test]
+    #[ignore = "timing benchmark; run with --release --ignored"]
+    fn batch_inserts_thoughts_in_one_transaction() {
+        let db = TempDb::new();
+        let thoughts = |prefix: &str| -> Vec<_> {
+            (0..10_000)
+                .map(|i| thought(&format!("{}{}", prefix, i), &format!("benchmark thought {} about topic {}", i, i % 50), "idea", 0.5, T0))
+                .collect()
+        };
+
+        let mut inserted = 0;
+        let batched = time_batch(
+            "10,000 thoughts",
+            || for t in thoughts("one-") {
+                db.insert_thought(&t).unwrap();
+            },
+            || inserted = db.insert_thoughts_batch(&thoughts("batch-")).unwrap().inserted,
+        );
+
+        assert_eq!(inserted, 10_000);
+        assert_eq!(db.get_thought_count().unwrap(), 20_000);
+        assert!(batched < std::time::Duration::from_secs(1), "batch took {:?}", batched);
+    }
+
+    fn seq(db: &TempDb) -> i64 {
+        db.get_graph_delta(0).unwrap().seq
+    }
+
+    fn delete_thought(db: &TempDb, id: &str) {
+        Database::delete_thoughts(&db.writer(), &[id.to_string()]).unwrap();
+    }
+
+    #[test]
+    fn delta_reports_every_change_since_the_given_seq() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("kept", "kept", "technical", 0.5, T0)).unwrap();
+        db.insert_thought(&thought("gone", "gone", "technical", 0.5, T0)).unwrap();
+        let since = seq(&db);
+        db.insert_thought(&thought("new", "new", "technical", 0.5, T0)).unwrap();
+        db.insert_connection(&connection("c1", "kept", "new", T0)).unwrap();
+        db.update_thought_content("kept", "kept, edited", "technical").unwrap();
+        delete_thought(&db, "gone");
+
+        let delta = db.get_graph_delta(since).unwrap();
+
+        let ids: HashSet<&str> = delta.thoughts.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, HashSet::from(["kept", "new"]));
+        assert_eq!(delta.connections.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), ["c1"]);
+        assert_eq!(delta.deleted.thoughts, ["gone"]);
+        assert!(!delta.stale);
+        assert_eq!(delta.seq, seq(&db));
+        // Reading the delta changes nothing, so asking again gives the same answer
+        let again = db.get_graph_delta(since).unwrap();
+        assert_eq!((again.seq, again.thoughts.len(), again.deleted.thoughts.len()), (delta.seq, 2, 1));
+        assert!(db.get_graph_delta(delta.seq).unwrap().thoughts.is_empty());
+    }
+
+    #[test]
+    fn delta_leaves_out_a_thought_inserted_and_deleted_since() {
+        let db = TempDb::new();
+        let since = seq(&db);
+        db.insert_thought(&thought("brief", "brief", "technical", 0.5, T0)).unwrap();
+        delete_thought(&db, "brief");
+
+        let delta = db.get_graph_delta(since).unwrap();
+
+        assert!(delta.thoughts.is_empty());
+        assert!(delta.deleted.thoughts.is_empty());
+        assert!(delta.seq > since);
+    }
+
+    #[test]
+    fn delta_coalesces_repeated_updates_into_the_latest_row() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("t1", "first", "technical", 0.5, T0)).unwrap();
+        let since = seq(&db);
+        for content in ["second", "third", "fourth"] {
+            db.update_thought_content("t1", content, "technical").unwrap();
+        }
+
+        let delta = db.get_graph_delta(since).unwrap();
+
+        assert_eq!(delta.thoughts.len(), 1);
+        assert_eq!(delta.thoughts[0].content, "fourth");
+    }
+
+    #[test]
+    fn pruning_marks_older_deltas_stale_and_is_safe_to_repeat() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("old", "old", "technical", 0.5, T0)).unwrap();
+        let pruned_to = seq(&db);
+        db.writer().execute("UPDATE change_log SET changed_at = '2000-01-01T00:00:00.000Z'", []).unwrap();
+        db.insert_thought(&thought("recent", "recent", "technical", 0.5, T0)).unwrap();
+
+        assert!(db.prune_change_log(30).unwrap() > 0);
+        assert_eq!(db.prune_change_log(30).unwrap(), 0);
+
+        assert!(db.get_graph_delta(0).unwrap().stale);
+        let delta = db.get_graph_delta(pruned_to).unwrap();
+        assert!(!delta.stale);
+        assert_eq!(delta.thoughts.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), ["recent"]);
+    }
+
+    fn has_column(db: &Database, table: &str, column: &str) -> bool {
+        db.reader().unwrap().query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1)", table),
+            [column],
+            |row| row.get(0),
+        ).unwrap()
+    }
+
+    #[test]
+    fn a_new_database_is_at_the_current_schema_version() {
+        let db = TempDb::new();
+        assert_eq!(db.stored_schema_version().unwrap(), SCHEMA_VERSION);
+    }
+
+    /// The schema of the first release, before versioned migrations and every column since
+    const V0_SCHEMA: &str = r#"
+        CREATE TABLE thoughts (
+            id TEXT PRIMARY KEY,
+            content TEXT NOT NULL,
+            role TEXT,
+            category TEXT DEFAULT 'other',
+            importance REAL DEFAULT 0.5,
+            position_x REAL DEFAULT 0.0,
+            position_y REAL DEFAULT 0.0,
+            position_z REAL DEFAULT 0.0,
+            created_at TEXT NOT NULL,
+            last_referenced TEXT NOT NULL,
+            metadata TEXT
+        );
+        CREATE TABLE connections (
+            id TEXT PRIMARY KEY,
+            from_thought TEXT NOT NULL,
+            to_thought TEXT NOT NULL,
+            strength REAL DEFAULT 0.5,
+            reason TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (from_thought) REFERENCES thoughts(id),
+            FOREIGN KEY (to_thought) REFERENCES thoughts(id)
+        );
+        CREATE TABLE sessions (
+            id TEXT PRIMARY KEY,
+            title TEXT,
+            started_at TEXT NOT NULL,
+            ended_at TEXT,
+            summary TEXT,
+            metadata TEXT
+        );
+        CREATE TABLE session_thoughts (
+            session_id TEXT,
+            thought_id TEXT,
+            position INTEGER,
+            PRIMARY KEY (session_id, thought_id),
+            FOREIGN KEY (session_id) REFERENCES sessions(id),
+            FOREIGN KEY (thought_id) REFERENCES thoughts(id)
+        );
+        CREATE TABLE clusters (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            category TEXT NOT NULL,
+            center_x REAL DEFAULT 0.0,
+            center_y REAL DEFAULT 0.0,
+            center_z REAL DEFAULT 0.0,
+            thought_count INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX idx_thoughts_category ON thoughts(category);
+        CREATE INDEX idx_thoughts_content ON thoughts(content);
+        CREATE INDEX idx_connections_from ON connections(from_thought);
+        CREATE INDEX idx_connections_to ON connections(to_thought);
+
+        INSERT INTO thoughts (id, content, role, category, importance, created_at, last_referenced)
+        VALUES ('t1', 'kept through the upgrade', 'assistant', 'technical', 0.5, '2026-03-01T12:00:00+00:00', '2026-03-01T12:00:00+00:00'),
+               ('t2', 'so was this one', 'assistant', 'idea', 0.7, '2026-03-01T12:05:00+00:00', '2026-03-01T12:05:00+00:00');
+        INSERT INTO connections (id, from_thought, to_thought, strength, reason, created_at)
+        VALUES ('c1', 't1', 't2', 0.5, 'same release', '2026-03-01T12:10:00+00:00');
+    "#;
+
+    /// Schema version, every table's columns and the thought count
+    fn schema_snapshot(db: &Database) -> (i64, Vec<(String, Vec<String>)>, i64) {
+        let conn = db.reader().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p
+             WHERE m.type = 'table' ORDER BY m.name, p.cid",
+        ).unwrap();
+        let mut tables: Vec<(String, Vec<String>)> = Vec::new();
+        for row in stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))).unwrap() {
+            let (table, column) = row.unwrap();
+            match tables.last_mut() {
+                Some((last, columns)) if *last == table => columns.push(column),
+                _ => tables.push((table, vec![column])),
+            }
+        }
+        let thoughts = conn.query_row("SELECT COUNT(*) FROM thoughts", [], |row| row.get(0)).unwrap();
+        (db.stored_schema_version().unwrap(), tables, thoughts)
+    }
+
+    #[test]
+    fn an_unversioned_database_is_migrated_to_the_current_version() {
+        let db = TempDb::from_sql(V0_SCHEMA);
+
+        assert_eq!(db.stored_schema_version().unwrap(), SCHEMA_VERSION);
+        for (table, column) in [("thoughts", "pinned"), ("thoughts", "archived"), ("thoughts", "locked"), ("categories", "icon")] {
+            assert!(has_column(&db, table, column), "{}.{}", table, column);
+        }
+        assert!(!column::<bool>(&db, "t1", "locked"));
+        assert_eq!(db.get_thought_by_id("t1").unwrap().unwrap().content, "kept through the upgrade");
+        assert_eq!(db.get_all_connections().unwrap().len(), 1);
+        // Built-in categories get their icon; "idea" only comes from the old thoughts
+        let icons: Vec<(String, Option<String>)> = db.list_categories().unwrap().into_iter().map(|c| (c.name, c.icon)).collect();
+        for (name, icon) in &icons {
+            assert_eq!(icon.is_some(), DEFAULT_CATEGORIES.iter().any(|(n, _, _)| n == name), "{:?}", icons);
+        }
+
+        // Opening the upgraded file again changes nothing
+        let first = schema_snapshot(&db);
+        let reopened = Database::open_at(db.path(), false).unwrap();
+        assert_eq!(schema_snapshot(&reopened), first);
+        assert_eq!(first.2, 2);
+    }
+
+    #[test]
+    fn a_database_from_a_newer_build_is_refused() {
+        let db = TempDb::new();
+        db.writer().pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+
+        for read_only in [false, true] {
+            let error = Database::open_at(db.path(), read_only).err().expect("refused").to_string();
+            assert!(
+                error.contains(&format!("database schema version {} is newer than this version of The Mind supports ({})", SCHEMA_VERSION + 1, SCHEMA_VERSION)),
+                "{}",
+                error
+            );
+        }
+    }
+
+    fn day(date: &str) -> chrono::NaiveDate {
+        date.parse().unwrap()
+    }
+
+    /// (bucket start, thought ids) of every bucket in the timeline
+    fn timeline(db: &TempDb, bucket: TimeBucket, from: &str, to: &str, tz_offset_minutes: i32) -> Vec<(String, Vec<String>)> {
+        db.get_timeline(bucket, day(from), day(to), tz_offset_minutes).unwrap()
+            .into_iter()
+            .map(|b| (b.bucket_start, b.thought_ids))
+            .collect()
+    }
+
+    #[test]
+    fn category_trend_counts_each_bucket_and_keeps_empty_ones() {
+        let db = TempDb::new();
+        let this_week = TimeBucket::Week.start_of(chrono::Utc::now().date_naive());
+        let weeks_ago = |n: i64| this_week - chrono::Duration::days(7 * n);
+        let seeded = [
+            ("a", "alpha", 0.2, weeks_ago(2)),
+            ("b", "alpha", 0.4, weeks_ago(0)),
+            ("c", "alpha", 0.9, weeks_ago(0)),
+            ("d", "beta", 0.3, weeks_ago(3)),
+            ("e", "beta", 0.5, weeks_ago(0)),
+        ];
+        for (id, category, importance, day) in seeded {
+            let created_at = format!("{}T12:00:00+00:00", day);
+            db.insert_thought(&thought(id, id, category, importance, &created_at)).unwrap();
+        }
+
+        let weeks: Vec<(String, Vec<(String, i64)>)> = db.get_category_trend(TimeBucket::Week, 4).unwrap()
+            .into_iter()
+            .map(|b| (b.bucket_start, b.counts.into_iter().collect()))
+            .collect();
+        let counts = |pairs: &[(&str, i64)]| pairs.iter().map(|(c, n)| (c.to_string(), *n)).collect::<Vec<_>>();
+        assert_eq!(weeks, vec![
+            (weeks_ago(3).to_string(), counts(&[("beta", 1)])),
+            (weeks_ago(2).to_string(), counts(&[("alpha", 1)])),
+            (weeks_ago(1).to_string(), counts(&[])),
+            (weeks_ago(0).to_string(), counts(&[("alpha", 2), ("beta", 1)])),
+        ]);
+
+        // Three weeks back never reaches two months back, so the oldest month is empty
+        let months = db.get_category_trend(TimeBucket::Month, 3).unwrap();
+        assert_eq!(months.len(), 3);
+        assert!(months[0].counts.is_empty());
+        let mut expected: std::collections::BTreeMap<String, std::collections::BTreeMap<String, i64>> = Default::default();
+        for (_, category, _, day) in seeded {
+            *expected.entry(TimeBucket::Month.start_of(day).to_string()).or_default()
+                .entry(category.to_string()).or_default() += 1;
+        }
+        for month in &months[1..] {
+            assert_eq!(month.counts, expected.remove(&month.bucket_start).unwrap_or_default(), "{}", month.bucket_start);
+        }
+        assert!(expected.is_empty(), "{:?}", expected);
+    }
+
+    #[test]
+    fn category_stats_average_importance_and_count_connections() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("a1", "a1", "alpha", 0.2, "2026-03-01T12:00:00+00:00")).unwrap();
+        db.insert_thought(&thought("a2", "a2", "alpha", 0.4, "2026-03-09T12:00:00+00:00")).unwrap();
+        db.insert_thought(&thought("a3", "a3", "alpha", 0.9, "2026-03-20T12:00:00+00:00")).unwrap();
+        db.insert_thought(&thought("b1", "b1", "beta", 0.3, "2026-03-02T12:00:00+00:00")).unwrap();
+        db.insert_thought(&thought("b2", "b2", "beta", 0.5, "2026-03-16T12:00:00+00:00")).unwrap();
+        db.insert_connection(&connection("c1", "a1", "a2", T0)).unwrap();
+        db.insert_connection(&connection("c2", "a3", "b1", T0)).unwrap();
+
+        let stats = db.get_category_stats().unwrap();
+
+        let summary: Vec<(&str, i64, i64, Option<&str>)> = stats.iter()
+            .map(|s| (s.category.as_str(), s.thought_count, s.connection_count, s.newest_thought.as_deref()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("alpha", 3, 2, Some("2026-03-20T12:00:00+00:00")),
+            ("beta", 2, 1, Some("2026-03-16T12:00:00+00:00")),
+        ]);
+        assert!((stats[0].avg_importance - 0.5).abs() < 1e-9, "{}", stats[0].avg_importance);
+        assert!((stats[1].avg_importance - 0.4).abs() < 1e-9, "{}", stats[1].avg_importance);
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        // 2026-03-29 is a Sunday
+        assert_eq!(TimeBucket::Week.start_of(day("2026-03-29")), day("2026-03-23"));
+        assert_eq!(TimeBucket::Week.start_of(day("2026-03-30")), day("2026-03-30"));
+
+        let db = TempDb::new();
+        db.insert_thought(&thought("sunday", "sunday", "technical", 0.5, "2026-03-29T23:00:00+00:00")).unwrap();
+        db.insert_thought(&thought("monday", "monday", "technical", 0.5, "2026-03-30T01:00:00+00:00")).unwrap();
+
+        assert_eq!(
+            timeline(&db, TimeBucket::Week, "2026-03-25", "2026-04-01", 0),
+            [("2026-03-23".to_string(), vec!["sunday".to_string()]), ("2026-03-30".to_string(), vec!["monday".to_string()])]
+        );
+    }
+
+    #[test]
+    fn month_buckets_step_cleanly_from_the_end_of_a_month() {
+        assert_eq!(TimeBucket::Month.start_of(day("2026-03-31")), day("2026-03-01"));
+        assert_eq!(TimeBucket::Month.prev(TimeBucket::Month.start_of(day("2026-03-31"))), day("2026-02-01"));
+
+        let db = TempDb::new();
+        db.insert_thought(&thought("feb", "feb", "technical", 0.5, "2026-02-28T12:00:00+00:00")).unwrap();
+        db.insert_thought(&thought("mar", "mar", "technical", 0.5, "2026-03-31T12:00:00+00:00")).unwrap();
+
+        let buckets = timeline(&db, TimeBucket::Month, "2026-01-31", "2026-03-31", 0);
+
+        let starts: Vec<&str> = buckets.iter().map(|(start, _)| start.as_str()).collect();
+        assert_eq!(starts, ["2026-01-01", "2026-02-01", "2026-03-01"]);
+        assert_eq!(buckets[1].1, ["feb"]);
+        assert_eq!(buckets[2].1, ["mar"]);
+    }
+
+    #[test]
+    fn days_follow_the_offset_across_a_dst_change() {
+        let db = TempDb::new();
+        // Central Europe moves from +01:00 to +02:00 at 01:00 UTC on 2026-03-29
+        db.insert_thought(&thought("before", "before", "technical", 0.5, "2026-03-29T01:30:00+01:00")).unwrap();
+        db.insert_thought(&thought("after", "after", "technical", 0.5, "2026-03-29T03:30:00+02:00")).unwrap();
+        db.insert_thought(&thought("late", "late", "technical", 0.5, "2026-03-29T22:30:00+00:00")).unwrap();
+
+        // The caller's offset after the change puts the late thought on the next local day
+        let summer = timeline(&db, TimeBucket::Day, "2026-03-28", "2026-03-30", 120);
+        assert_eq!(summer[1], ("2026-03-29".to_string(), vec!["before".to_string(), "after".to_string()]));
+        assert_eq!(summer[2], ("2026-03-30".to_string(), vec!["late".to_string()]));
+
+        // In UTC the early thoughts are still on the 29th, stored offsets notwithstanding
+        let utc = timeline(&db, TimeBucket::Day, "2026-03-28", "2026-03-30", 0);
+        assert_eq!(utc[1].1, ["before", "after", "late"]);
+    }
+
+    #[test]
+    fn deterministic_positions_repeat_across_databases() {
+        let place = |db: &TempDb, content: &str, category: &str| {
+            let mut random = db.position_random(content, category);
+            db.generate_position_for_category(category, &mut random)
+        };
+        let (first, second) = (TempDb::new(), TempDb::new());
+        for db in [&first, &second] {
+            db.set_setting(SETTING_DETERMINISTIC_POSITIONS, "true").unwrap();
+        }
+
+        assert_eq!(place(&first, "Use WAL mode", "technical"), place(&second, "Use WAL mode", "technical"));
+        assert_ne!(place(&first, "Use WAL mode", "technical"), place(&first, "Use WAL mode", "insight"));
+        assert_ne!(place(&first, "Use WAL mode", "technical"), place(&first, "Use journal mode", "technical"));
+    }
+
+    #[test]
+    fn clock_random_numbers_can_be_drawn_from_many_threads() {
+        let draws: Vec<f64> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| (0..1000).map(|_| super::rand::random::<f64>()).collect::<Vec<_>>()))
+                .collect();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        });
+
+        assert!(draws.iter().all(|n| (0.0..1.0).contains(n)));
+        // The threads advance one shared sequence rather than each repeating the same draws
+        let distinct: HashSet<u64> = draws.iter().map(|n| n.to_bits()).collect();
+        assert!(distinct.len() > draws.len() * 99 / 100, "{} distinct of {}", distinct.len(), draws.len());
+    }
+
+    #[test]
+    fn two_hundred_placed_thoughts_keep_the_minimum_distance() {
+        let db = TempDb::new();
+        db.set_setting(SETTING_DETERMINISTIC_POSITIONS, "true").unwrap();
+        let mut placed: Vec<(f64, f64, f64)> = Vec::new();
+        for i in 0..200 {
+            let category = ["technical", "insight", "question", "idea"][i % 4];
+            let content = format!("placement thought {}", i);
+            // The same path mind_log and quick_capture place new thoughts by
+            let mut random = db.position_random(&content, category);
+            let (x, y, z) = db.clear_position(db.generate_position_for_category(category, &mut random));
+            let mut t = thought(&format!("t{}", i), &content, category, 0.5, T0);
+            (t.position_x, t.position_y, t.position_z) = (x, y, z);
+            db.insert_thought(&t).unwrap();
+            placed.push((x, y, z));
+        }
+
+        for (i, a) in placed.iter().enumerate() {
+            for b in &placed[i + 1..] {
+                let distance = ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt();
+                assert!(distance >= DEFAULT_MIN_PLACEMENT_DISTANCE, "{:?} and {:?} are {} apart", a, b, distance);
+            }
+        }
+    }
+
+    #[test]
+    fn readers_are_not_blocked_by_a_long_write() {
+        let db = TempDb::new();
+        db.insert_thought(&thought("a", "committed", "idea", 0.5, T0)).unwrap();
+        let (started, wait) = std::sync::mpsc::channel();
+
+        std::thread::scope(|scope| {
+            let writer = scope.spawn(|| {
+                let mut conn = db.writer();
+                let tx = conn.transaction().unwrap();
+                tx.execute("UPDATE thoughts SET content = 'uncommitted' WHERE id = 'a'", []).unwrap();
+                started.send(()).unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(500));
+                tx.commit().unwrap();
+            });
+            wait.recv().unwrap();
+
+            let reading = std::time::Instant::now();
+            let seen = db.get_thought_by_id("a").unwrap().unwrap();
+            let read_in = reading.elapsed();
+
+            assert!(!writer.is_finished(), "the write ended before the read could overlap it");
+            assert_eq!(seen.content, "committed");
+            assert!(read_in < std::time::Duration::from_millis(250), "read took {:?}", read_in);
+        });
+        assert_eq!(db.get_thought_by_id("a").unwrap().unwrap().content, "uncommitted");
+    }
+
+    #[test]
+    fn pages_with_tied_sort_keys_never_skip_or_repeat() {
+        let db = TempDb::new();
+        // Inserted out of id order, with every sort key shared by several thoughts
+        let ids: Vec<String> = (0..25).map(|i| format!("t{:02}", (i * 7) % 25)).collect();
+        for (i, id) in ids.iter().enumerate() {
+            db.insert_thought(&thought(id, &format!("same {}", i % 2), "idea", (i % 3) as f64 / 2.0, T0)).unwrap();
+        }
+        for (i, pair) in ids.windows(2).enumerate() {
+            db.insert_connection(&connection(&format!("c{:02}", (i * 11) % 24), &pair[0], &pair[1], T0)).unwrap();
+        }
+
+        for sort in [ThoughtSort::CreatedAt, ThoughtSort::LastReferenced, ThoughtSort::Importance, ThoughtSort::Content] {
+            for ascending in [true, false] {
+                let mut paged = Vec::new();
+                for offset in (0..30).step_by(4) {
+                    paged.extend(db.get_thoughts_page(offset, 4, sort, ascending).unwrap().into_iter().map(|t| t.id));
+                }
+                let unique: HashSet<&String> = paged.iter().collect();
+                assert_eq!((paged.len(), unique.len()), (25, 25), "{:?} ascending={}", sort, ascending);
+                let whole: Vec<String> = db.get_thoughts_page(0, 100, sort, ascending).unwrap().into_iter().map(|t| t.id).collect();
+                assert_eq!(paged, whole);
+            }
+        }
+
+        for ascending in [true, false] {
+            let mut paged = Vec::new();
+            for offset in (0..30).step_by(4) {
+                paged.extend(db.get_connections_page(offset, 4, ascending).unwrap().into_iter().map(|c| c.id));
+            }
+            let mut expected: Vec<String> = (0..24).map(|i| format!("c{:02}", i)).collect();
+            if !ascending {
+                expected.reverse();
+            }
+            assert_eq!(paged, expected);
         }
     }
 }
@@ -0,0 +1,125 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::sync::Mutex;
+use serde::Serialize;
+use crate::database::Database;
+use crate::session_forge::{self, ForgeStatus};
+use crate::utils::lock_recovering;
+use crate::wallpaper::{self, MonitorInfo};
+
+// Errors kept for get_diagnostics; older ones are dropped
+const MAX_RECENT_ERRORS: usize = 50;
+// A WAL this large means checkpoints aren't keeping up (usually a reader that never finishes)
+const WAL_WARN_BYTES: u64 = 64 * 1024 * 1024;
+
+static RECENT_ERRORS: Mutex<VecDeque<RecentError>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecentError {
+    pub at: String,
+    /// Command or subsystem that failed
+    pub source: String,
+    pub message: String,
+}
+
+/// Log an error and remember it for the diagnostics report
+pub fn record_error(source: &str, message: &str) {
+    tracing::warn!(source, "{}", message);
+    let mut errors = lock_recovering(&RECENT_ERRORS);
+    if errors.len() == MAX_RECENT_ERRORS {
+        errors.pop_front();
+    }
+    errors.push_back(RecentError {
+        at: chrono::Utc::now().to_rfc3339(),
+        source: source.to_string(),
+        message: message.to_string(),
+    });
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalStatus {
+    pub size_bytes: u64,
+    /// Frames still waiting after a passive checkpoint; None when read-only (no checkpoint is run)
+    pub pending_frames: Option<i64>,
+    pub healthy: bool,
+}
+
+/// Result of get_diagnostics and `--diagnose`
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostics {
+    pub app_version: String,
+    pub database_path: String,
+    pub database_size_bytes: u64,
+    pub read_only: bool,
+    pub table_counts: BTreeMap<String, i64>,
+    /// Connection suggestions still waiting for accept_suggestion or reject_suggestion
+    pub pending_suggestions: i64,
+    pub journal_mode: String,
+    /// None unless the journal mode is WAL
+    pub wal: Option<WalStatus>,
+    pub forge: ForgeStatus,
+    pub monitors: Vec<MonitorInfo>,
+    pub wallpaper_active: bool,
+    /// Modification time of the newest file in the backups folder
+    pub last_backup: Option<String>,
+    /// Oldest first
+    pub recent_errors: Vec<RecentError>,
+}
+
+/// Gather the report. A part that can't be read is left empty and its error shows up in
+/// recent_errors, so one broken piece doesn't hide the rest.
+pub fn collect(db: &Database, wallpaper_active: bool) -> Diagnostics {
+    let path = db.path();
+    let file_size = |p: &Path| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+
+    let table_counts = db.table_counts().unwrap_or_else(|e| {
+        record_error("diagnostics", &format!("Counting rows failed: {}", e));
+        BTreeMap::new()
+    });
+    let pending_suggestions = db.count_pending_suggestions().unwrap_or_else(|e| {
+        record_error("diagnostics", &format!("Counting suggestions failed: {}", e));
+        0
+    });
+    let journal_mode = db.journal_mode().unwrap_or_else(|e| {
+        record_error("diagnostics", &format!("Reading journal mode failed: {}", e));
+        "unknown".to_string()
+    });
+
+    let wal = journal_mode.eq_ignore_ascii_case("wal").then(|| {
+        let mut wal_path = path.as_os_str().to_owned();
+        wal_path.push("-wal");
+        let pending_frames = (!db.is_read_only()).then(|| db.wal_checkpoint())
+            .transpose()
+            .unwrap_or_else(|e| {
+                record_error("diagnostics", &format!("WAL checkpoint failed: {}", e));
+                None
+            });
+        // Size after the checkpoint, which is when it matters
+        let size_bytes = file_size(Path::new(&wal_path));
+        WalStatus { size_bytes, pending_frames, healthy: size_bytes < WAL_WARN_BYTES }
+    });
+
+    Diagnostics {
+        app_version: crate::app_info::VERSION.to_string(),
+        database_path: path.display().to_string(),
+        database_size_bytes: file_size(path),
+        read_only: db.is_read_only(),
+        table_counts,
+        pending_suggestions,
+        journal_mode,
+        wal,
+        forge: session_forge::status(),
+        monitors: wallpaper::get_monitors(),
+        wallpaper_active,
+        last_backup: last_backup(&crate::reset::backup_dir(path)),
+        recent_errors: lock_recovering(&RECENT_ERRORS).iter().cloned().collect(),
+    }
+}
+
+fn last_backup(dir: &Path) -> Option<String> {
+    std::fs::read_dir(dir).ok()?
+        .flatten()
+        .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+        .max()
+        .map(|modified| chrono::DateTime::<chrono::Utc>::from(modified).to_rfc3339())
+}
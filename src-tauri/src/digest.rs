@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+use chrono::{NaiveDate, Utc};
+use crate::database::Database;
+use crate::utils::snippet;
+
+/// Markdown summary of everything logged on one local day.
+/// `date` is YYYY-MM-DD and defaults to today at `tz_offset_minutes` from UTC.
+pub fn daily_digest(db: &Database, date: Option<&str>, tz_offset_minutes: i32) -> Result<String, String> {
+    let day = match date.map(str::trim).filter(|d| !d.is_empty()) {
+        Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date: {} (expected YYYY-MM-DD)", d))?,
+        None => (Utc::now() + chrono::Duration::minutes(tz_offset_minutes as i64)).date_naive(),
+    };
+    let date = day.to_string();
+
+    let thoughts = db.get_thoughts_on_day(&date, tz_offset_minutes).map_err(|e| e.to_string())?;
+    let connections = db.get_connections_on_day(&date, tz_offset_minutes).map_err(|e| e.to_string())?;
+    let sessions = db.get_sessions_on_day(&date, tz_offset_minutes).map_err(|e| e.to_string())?;
+
+    let mut out = format!("# Daily digest — {}\n", day.format("%A, %B %-d, %Y"));
+
+    if thoughts.is_empty() && connections.is_empty() && sessions.is_empty() {
+        out.push_str("\nNothing logged to The Mind this day.\n");
+        return Ok(out);
+    }
+
+    out.push_str(&format!(
+        "\n{} thought(s), {} connection(s), {} session(s).\n",
+        thoughts.len(),
+        connections.len(),
+        sessions.len()
+    ));
+
+    if !thoughts.is_empty() {
+        let mut by_category: BTreeMap<&str, Vec<&crate::Thought>> = BTreeMap::new();
+        for t in &thoughts {
+            by_category.entry(t.category.as_str()).or_default().push(t);
+        }
+
+        out.push_str("\n## Thoughts\n");
+        for (category, items) in by_category {
+            out.push_str(&format!("\n### {} ({})\n", category, items.len()));
+            for t in items {
+                out.push_str(&format!("- {} _(importance {:.0}%)_\n", t.content, t.importance * 100.0));
+            }
+        }
+
+        // Degree counts every connection, not just today's, so it reflects how central the idea already is
+        let mut most_connected: Option<(&crate::Thought, usize)> = None;
+        for t in &thoughts {
+            let degree = db.get_connections_of_thought(&t.id).map_err(|e| e.to_string())?.len();
+            if degree > 0 && most_connected.is_none_or(|(_, best)| degree > best) {
+                most_connected = Some((t, degree));
+            }
+        }
+        if let Some((t, degree)) = most_connected {
+            out.push_str(&format!(
+                "\n**Most connected new thought:** {} ({} connection(s))\n",
+                snippet(&t.content, 120),
+                degree
+            ));
+        }
+    }
+
+    if !connections.is_empty() {
+        out.push_str("\n## Connections\n\n");
+        for m in &connections {
+            out.push_str(&format!(
+                "- \"{}\" → \"{}\" ({}): {}\n",
+                snippet(&m.from_content, 60),
+                snippet(&m.to_content, 60),
+                m.connection.kind,
+                m.connection.reason
+            ));
+        }
+    }
+
+    if !sessions.is_empty() {
+        out.push_str("\n## Sessions\n\n");
+        for s in &sessions {
+            match s.summary.as_deref().filter(|summary| !summary.is_empty()) {
+                Some(summary) => out.push_str(&format!("- **{}** — {}\n", s.title, summary)),
+                None => out.push_str(&format!("- **{}**\n", s.title)),
+            }
+        }
+    }
+
+    Ok(out)
+}
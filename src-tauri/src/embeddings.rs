@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use serde_json::{json, Value};
+use crate::database::Database;
+use crate::Thought;
+
+// Settings keys (also readable/writable through get_setting/set_setting)
+pub const SETTING_ENABLED: &str = "embeddings_enabled";
+pub const SETTING_ENDPOINT: &str = "embeddings_endpoint";
+pub const SETTING_MODEL: &str = "embeddings_model";
+pub const SETTING_API_KEY: &str = "embeddings_api_key";
+pub const SETTING_MIN_SIMILARITY: &str = "embeddings_min_similarity";
+
+// Ollama's OpenAI-compatible endpoint
+const DEFAULT_ENDPOINT: &str = "http://localhost:11434/v1/embeddings";
+const DEFAULT_MODEL: &str = "nomic-embed-text";
+
+// Thoughts are embedded inline as they're logged, so a stuck endpoint must not hold that up for long
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const BACKFILL_BATCH: usize = 32;
+// Semantic search first embeds up to this many thoughts that were saved without a vector
+const SEARCH_BACKFILL_LIMIT: usize = 256;
+// Below this cosine similarity a semantic hit is noise (unrelated texts still score 0.2-0.4 on most models)
+pub const DEFAULT_MIN_SIMILARITY: f32 = 0.5;
+
+// Reciprocal rank fusion constant: damps how much the very top ranks dominate the merge
+const RRF_K: f64 = 60.0;
+
+/// Turns text into vectors whose cosine similarity reflects meaning
+pub trait EmbeddingProvider {
+    /// Names the vector space; vectors from different models are never compared
+    fn model(&self) -> &str;
+
+    /// One vector per text, in order
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// An OpenAI-compatible /v1/embeddings endpoint (Ollama, LM Studio, llama.cpp, OpenAI)
+pub struct HttpProvider {
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl HttpProvider {
+    pub fn new(endpoint: String, model: String, api_key: Option<String>) -> Self {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into();
+        HttpProvider { endpoint, model, api_key, agent }
+    }
+
+    /// The provider configured in settings, or None while embeddings are turned off
+    pub fn from_settings(db: &Database) -> Option<Self> {
+        if !db.setting_or(SETTING_ENABLED, false) {
+            return None;
+        }
+        let setting = |key: &str| db.get_setting(key).ok().flatten()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        Some(HttpProvider::new(
+            setting(SETTING_ENDPOINT).unwrap_or_else(|| DEFAULT_ENDPOINT.to_string()),
+            setting(SETTING_MODEL).unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            setting(SETTING_API_KEY),
+        ))
+    }
+}
+
+impl EmbeddingProvider for HttpProvider {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let body = json!({ "model": self.model, "input": texts }).to_string();
+        let mut request = self.agent.post(&self.endpoint).header("Content-Type", "application/json");
+        if let Some(key) = &self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+        let mut response = request.send(body)
+            .map_err(|e| format!("Embedding request to {} failed: {}", self.endpoint, e))?;
+        let text = response.body_mut().read_to_string()
+            .map_err(|e| format!("Embedding response from {} unreadable: {}", self.endpoint, e))?;
+        parse_vectors(&text, texts.len())
+    }
+}
+
+/// Vectors from an OpenAI-style {"data": [{"index", "embedding"}]} response,
+/// or Ollama's native {"embeddings": [[...]]}
+fn parse_vectors(body: &str, expected: usize) -> Result<Vec<Vec<f32>>, String> {
+    let value: Value = serde_json::from_str(body).map_err(|e| format!("Invalid embedding response: {}", e))?;
+    let to_vector = |v: &Value| -> Option<Vec<f32>> {
+        v.as_array()?.iter().map(|x| x.as_f64().map(|x| x as f32)).collect()
+    };
+
+    let vectors: Option<Vec<Vec<f32>>> = if let Some(data) = value.get("data").and_then(Value::as_array) {
+        let mut items: Vec<(u64, &Value)> = data.iter()
+            .enumerate()
+            .map(|(i, item)| (item.get("index").and_then(Value::as_u64).unwrap_or(i as u64), item))
+            .collect();
+        items.sort_by_key(|(index, _)| *index);
+        items.into_iter().map(|(_, item)| item.get("embedding").and_then(to_vector)).collect()
+    } else if let Some(embeddings) = value.get("embeddings").and_then(Value::as_array) {
+        embeddings.iter().map(to_vector).collect()
+    } else {
+        None
+    };
+
+    match vectors {
+        Some(vectors) if vectors.len() == expected && vectors.iter().all(|v| !v.is_empty()) => Ok(vectors),
+        Some(vectors) => Err(format!("Expected {} embedding(s), got {}", expected, vectors.len())),
+        None => {
+            let message = value.pointer("/error/message").or_else(|| value.get("error"))
+                .map(|e| e.as_str().map(str::to_string).unwrap_or_else(|| e.to_string()))
+                .unwrap_or_else(|| "no embeddings in response".to_string());
+            Err(format!("Invalid embedding response: {}", message))
+        }
+    }
+}
+
+/// Embed a newly saved thought when embeddings are on. Failures are logged, not returned:
+/// the thought is saved either way and a later backfill picks it up.
+pub fn embed_new_thought(db: &Database, thought: &Thought) {
+    let Some(provider) = HttpProvider::from_settings(db) else {
+        return;
+    };
+    let result = provider.embed(std::slice::from_ref(&thought.content))
+        .and_then(|mut vectors| {
+            db.set_embedding(&thought.id, provider.model(), &vectors.remove(0)).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        tracing::warn!("Embedding thought {} failed: {}", thought.id, e);
+    }
+}
+
+/// Embed up to `limit` thoughts that have no vector from the provider's model yet
+/// (new ones, or ones embedded by a model since replaced). Returns how many were embedded.
+pub fn backfill(db: &Database, provider: &dyn EmbeddingProvider, limit: usize) -> Result<usize, String> {
+    let mut embedded = 0;
+    while embedded < limit {
+        let batch = db.thoughts_without_embedding(provider.model(), BACKFILL_BATCH.min(limit - embedded) as i64)
+            .map_err(|e| e.to_string())?;
+        if batch.is_empty() {
+            break;
+        }
+        let texts: Vec<String> = batch.iter().map(|t| t.content.clone()).collect();
+        let vectors = provider.embed(&texts)?;
+        for (thought, vector) in batch.iter().zip(&vectors) {
+            db.set_embedding(&thought.id, provider.model(), vector).map_err(|e| e.to_string())?;
+        }
+        embedded += batch.len();
+    }
+    Ok(embedded)
+}
+
+/// Thoughts closest in meaning to `query`, with cosine similarity, best first. Thoughts still
+/// missing a vector (logged while the endpoint was down, or imported) are embedded first.
+pub fn search(db: &Database, provider: &dyn EmbeddingProvider, query: &str, limit: usize) -> Result<Vec<(Thought, f32)>, String> {
+    // Read-only searches whatever vectors are already stored
+    if !db.is_read_only() {
+        if let Err(e) = backfill(db, provider, SEARCH_BACKFILL_LIMIT) {
+            tracing::warn!("Embedding backfill failed: {}", e);
+        }
+    }
+    let vector = provider.embed(&[query.to_string()])?.remove(0);
+    let min_similarity = db.setting_or(SETTING_MIN_SIMILARITY, DEFAULT_MIN_SIMILARITY);
+    let mut hits = db.semantic_search(&vector, provider.model(), limit as i64).map_err(|e| e.to_string())?;
+    hits.retain(|(_, similarity)| *similarity >= min_similarity);
+    Ok(hits)
+}
+
+/// Merge keyword and semantic results by reciprocal rank fusion, so a thought both searches
+/// rank well comes first; on a tie favorites come first, then the keyword match. Semantic hits
+/// keep their similarity.
+pub fn merge_results(keyword: Vec<Thought>, semantic: Vec<(Thought, f32)>, limit: usize) -> Vec<(Thought, Option<f32>)> {
+    struct Merged {
+        score: f64,
+        keyword_rank: Option<usize>,
+        thought: Thought,
+        similarity: Option<f32>,
+    }
+
+    let mut merged: HashMap<String, Merged> = HashMap::new();
+    for (rank, thought) in keyword.into_iter().enumerate() {
+        merged.entry(thought.id.clone()).or_insert(Merged {
+            score: 1.0 / (RRF_K + rank as f64),
+            keyword_rank: Some(rank),
+            thought,
+            similarity: None,
+        });
+    }
+    for (rank, (thought, similarity)) in semantic.into_iter().enumerate() {
+        let entry = merged.entry(thought.id.clone())
+            .or_insert(Merged { score: 0.0, keyword_rank: None, thought, similarity: None });
+        entry.score += 1.0 / (RRF_K + rank as f64);
+        entry.similarity = Some(similarity);
+    }
+
+    let mut results: Vec<Merged> = merged.into_values().collect();
+    results.sort_by(|a, b| {
+        b.score.total_cmp(&a.score)
+            .then_with(|| b.thought.favorite.cmp(&a.thought.favorite))
+            .then_with(|| a.keyword_rank.unwrap_or(usize::MAX).cmp(&b.keyword_rank.unwrap_or(usize::MAX)))
+            .then_with(|| b.similarity.unwrap_or(0.0).total_cmp(&a.similarity.unwrap_or(0.0)))
+    });
+    results.into_iter().take(limit).map(|m| (m.thought, m.similarity)).collect()
+}
+
+/// Cosine similarity; 0 for mismatched lengths or zero vectors
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
+
+/// Vectors are stored as little-endian f32s
+pub fn vector_to_blob(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub fn blob_to_vector(blob: &[u8]) -> Vec<f32> {
+    blob.chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
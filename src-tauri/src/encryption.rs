@@ -0,0 +1,107 @@
+use std::io::Read;
+use std::path::Path;
+
+// Keychain entry holding the database key (Windows Credential Manager, macOS Keychain,
+// Secret Service on Linux)
+#[cfg(feature = "encryption")]
+const KEYCHAIN_SERVICE: &str = "the-mind";
+#[cfg(feature = "encryption")]
+const KEYCHAIN_ACCOUNT: &str = "database-key";
+
+// First bytes of every unencrypted SQLite file; SQLCipher files start with random salt
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Whether this build can encrypt databases (the `encryption` cargo feature)
+pub fn supported() -> bool {
+    cfg!(feature = "encryption")
+}
+
+/// Whether `path` holds an unencrypted database. A missing or empty file is neither.
+pub fn is_plaintext(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| &header == PLAINTEXT_HEADER)
+}
+
+fn is_new(path: &Path) -> bool {
+    std::fs::metadata(path).map_or(true, |m| m.len() == 0)
+}
+
+/// The key to open `path` with, or None to open it unencrypted: existing plaintext files
+/// stay readable (encrypt_existing_database converts them), new databases are encrypted
+/// when the build supports it, with a key created in the keychain on first use unless
+/// `read_only`.
+pub fn key_for(path: &Path, read_only: bool) -> Result<Option<String>, String> {
+    if !supported() || is_plaintext(path) {
+        return Ok(None);
+    }
+    keychain_key(!read_only && is_new(path)).map(Some)
+}
+
+/// The keychain's database key, created there first when `create` is set and none exists.
+/// Errors start with "KeychainUnavailable" when the keychain can't be reached and
+/// "MissingKey" when it has no key for an existing encrypted database.
+#[cfg(feature = "encryption")]
+pub fn keychain_key(create: bool) -> Result<String, String> {
+    let unavailable = |e: keyring::Error| {
+        format!(
+            "KeychainUnavailable: couldn't reach the OS keychain for the database key ({}); unlock or enable it and restart",
+            e
+        )
+    };
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).map_err(unavailable)?;
+    match entry.get_password() {
+        Ok(key) => Ok(key),
+        Err(keyring::Error::NoEntry) if create => {
+            let key = generate_key();
+            entry.set_password(&key).map_err(unavailable)?;
+            tracing::info!("Created a database key in the OS keychain");
+            Ok(key)
+        }
+        Err(keyring::Error::NoEntry) => Err(format!(
+            "MissingKey: the database is encrypted but the keychain has no '{}' entry for '{}'; restore it from the machine that created the database",
+            KEYCHAIN_ACCOUNT, KEYCHAIN_SERVICE
+        )),
+        Err(e) => Err(unavailable(e)),
+    }
+}
+
+#[cfg(not(feature = "encryption"))]
+pub fn keychain_key(_create: bool) -> Result<String, String> {
+    Err("Unsupported: this build of The Mind was made without database encryption".to_string())
+}
+
+/// 256 random bits as hex, used as a raw SQLCipher key (skipping its passphrase derivation)
+#[cfg(feature = "encryption")]
+fn generate_key() -> String {
+    [uuid::Uuid::new_v4(), uuid::Uuid::new_v4()]
+        .iter()
+        .flat_map(|id| id.as_bytes().to_vec())
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// A hex key in SQLCipher's raw key form, as given to PRAGMA key and ATTACH ... KEY
+pub fn raw_key(key: &str) -> String {
+    format!("x'{}'", key)
+}
+
+/// Unlock a freshly opened connection. A key that doesn't fit the file shows up on the first
+/// read, so one is done here to report it as "WrongKey" rather than a corrupt database.
+pub fn apply_key(conn: &rusqlite::Connection, key: Option<&str>, path: &Path) -> rusqlite::Result<()> {
+    let Some(key) = key else {
+        return Ok(());
+    };
+    conn.pragma_update(None, "key", raw_key(key))?;
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)) {
+        Ok(_) => Ok(()),
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::NotADatabase => {
+            Err(rusqlite::Error::SqliteFailure(e, Some(format!(
+                "WrongKey: the keychain's database key doesn't open {}; restore the key it was encrypted with",
+                path.display()
+            ))))
+        }
+        Err(e) => Err(e),
+    }
+}
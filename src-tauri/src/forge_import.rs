@@ -0,0 +1,313 @@
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use uuid::Uuid;
+use crate::database::Database;
+use crate::progress::{Progress, BATCH_SIZE};
+use crate::session_forge::{self, DecisionEntry, DEAD_ENDS_FILE, DECISIONS_FILE, JOURNAL_FILE};
+use crate::utils::{count_shared_keywords, extract_keywords_with, KeywordConfig};
+use crate::{ForgeImport, ForgeImportCounts, Thought};
+
+// Marks thoughts created from session-forge entries (stored in thought metadata)
+const SOURCE_PREFIX: &str = "session-forge/";
+
+// Decisions and dead ends sharing this many keywords get linked
+const MIN_SHARED_KEYWORDS: usize = 2;
+
+pub const FORGE_KINDS: [&str; 3] = ["journal", "decisions", "dead-ends"];
+
+/// A forge entry flattened into what a thought needs
+struct ForgeItem {
+    timestamp: String,
+    content: String,
+    category: String,
+    importance: f64,
+}
+
+/// Import session-forge entries as thoughts. Each entry is keyed by its file and timestamp,
+/// so re-importing updates changed entries and skips unchanged ones instead of duplicating.
+/// An empty `kinds` imports everything. Entries that fail to parse are listed in `warnings`.
+/// Progress is reported every BATCH_SIZE entries; a cancelled import stops there.
+pub fn import_forge(db: &Database, kinds: &[String], progress: Progress) -> Result<ForgeImport, String> {
+    let kinds: Vec<String> = if kinds.is_empty() {
+        FORGE_KINDS.iter().map(|k| k.to_string()).collect()
+    } else {
+        kinds.iter().map(|k| k.trim().to_lowercase().replace('_', "-")).collect()
+    };
+    if let Some(unknown) = kinds.iter().find(|k| !FORGE_KINDS.contains(&k.as_str())) {
+        return Err(format!("Unknown forge kind '{}' (expected journal, decisions, or dead-ends)", unknown));
+    }
+
+    // Keep maintenance (VACUUM in particular) from running underneath the import
+    let _exclusive = crate::maintenance::exclusive();
+    let categories: Vec<String> = db.list_categories()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+
+    let mut result = ForgeImport { kinds: Vec::new(), connections_created: 0, warnings: Vec::new(), cancelled: false };
+    for kind in &kinds {
+        let (file, items, warnings) = match kind.as_str() {
+            "journal" => {
+                let (entries, warnings) = session_forge::journal_entries()?;
+                let items = entries.into_iter()
+                    .map(|j| ForgeItem {
+                        content: format!("Journal: {}", j.session_summary),
+                        timestamp: j.timestamp,
+                        category: "personal".to_string(),
+                        importance: 0.5,
+                    })
+                    .collect::<Vec<_>>();
+                (JOURNAL_FILE, items, warnings)
+            }
+            "decisions" => {
+                let (entries, warnings) = session_forge::decision_entries()?;
+                let items = entries.into_iter()
+                    .map(|d| ForgeItem {
+                        content: decision_content(&d),
+                        category: tagged_category(&d.tags, &categories).unwrap_or_else(|| "technical".to_string()),
+                        timestamp: d.timestamp,
+                        importance: 0.7,
+                    })
+                    .collect();
+                (DECISIONS_FILE, items, warnings)
+            }
+            _ => {
+                let (entries, warnings) = session_forge::dead_end_entries()?;
+                let items = entries.into_iter()
+                    .map(|d| ForgeItem {
+                        content: format!("Dead end: {} — {} Lesson: {}", d.attempted, d.why_failed, d.lesson),
+                        category: tagged_category(&d.tags, &categories).unwrap_or_else(|| "technical".to_string()),
+                        timestamp: d.timestamp,
+                        importance: 0.6,
+                    })
+                    .collect();
+                (DEAD_ENDS_FILE, items, warnings)
+            }
+        };
+
+        // Malformed entries are skipped and reported rather than failing the whole file
+        result.warnings.extend(warnings);
+        let (counts, finished) = import_items(db, kind, file, items, progress).map_err(|e| e.to_string())?;
+        result.kinds.push(counts);
+        if !finished {
+            result.cancelled = true;
+            return Ok(result);
+        }
+    }
+
+    result.connections_created = connect_decisions_to_dead_ends(db).map_err(|e| e.to_string())?;
+    Ok(result)
+}
+
+fn decision_content(d: &DecisionEntry) -> String {
+    format!("Decision: {} — {}", d.choice, d.reasoning)
+}
+
+/// Record a decision in both session-forge (decisions.json) and The Mind. The thought carries
+/// the same provenance an import would give it, so a later import_forge won't duplicate it.
+/// The forge file is written first: if the thought insert fails, the next import recovers it.
+pub fn log_decision(db: &Database, entry: DecisionEntry, importance: f64) -> Result<Thought, String> {
+    session_forge::append_decision(&entry)?;
+
+    let categories: Vec<String> = db.list_categories()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    let item = ForgeItem {
+        content: decision_content(&entry),
+        category: tagged_category(&entry.tags, &categories).unwrap_or_else(|| "technical".to_string()),
+        timestamp: entry.timestamp,
+        importance,
+    };
+    insert_item(db, &format!("{}{}", SOURCE_PREFIX, DECISIONS_FILE), item).map_err(|e| e.to_string())
+}
+
+/// The first entry tag naming an existing category, if any
+fn tagged_category(tags: &[String], categories: &[String]) -> Option<String> {
+    tags.iter()
+        .map(|t| t.trim().to_lowercase())
+        .find(|t| categories.contains(t))
+}
+
+/// Import one file's entries; the flag is false if the import was cancelled part way.
+/// New entries are inserted BATCH_SIZE at a time, each batch in one transaction.
+fn import_items(db: &Database, kind: &str, file: &str, items: Vec<ForgeItem>, progress: Progress) -> rusqlite::Result<(ForgeImportCounts, bool)> {
+    let source = format!("{}{}", SOURCE_PREFIX, file);
+    let mut counts = ForgeImportCounts { kind: kind.to_string(), imported: 0, updated: 0, skipped: 0, locked: 0 };
+    let total = items.len();
+    // New thoughts and their entries' timestamps, not yet inserted
+    let mut pending: Vec<(Thought, String)> = Vec::new();
+
+    for (done, item) in items.into_iter().enumerate() {
+        if done % BATCH_SIZE == 0 {
+            counts.imported += insert_pending(db, &source, &mut pending)?;
+            if progress.is_cancelled() {
+                return Ok((counts, false));
+            }
+            progress.report(done, Some(total), &format!("Importing {}", kind));
+        }
+
+        // A repeated timestamp must see the entry it repeats
+        if pending.iter().any(|(_, timestamp)| *timestamp == item.timestamp) {
+            counts.imported += insert_pending(db, &source, &mut pending)?;
+        }
+        if let Some(existing) = db.find_thought_by_source(&source, &item.timestamp)? {
+            if existing.content == item.content && existing.category == item.category {
+                counts.skipped += 1;
+            } else if db.is_thought_locked(&existing.id)? {
+                counts.locked += 1;
+            } else {
+                db.update_thought_content(&existing.id, &item.content, &item.category)?;
+                counts.updated += 1;
+            }
+            continue;
+        }
+
+        let timestamp = item.timestamp.clone();
+        pending.push((thought_for_item(db, item), timestamp));
+    }
+    counts.imported += insert_pending(db, &source, &mut pending)?;
+
+    progress.report(total, Some(total), &format!("Imported {}", kind));
+    Ok((counts, true))
+}
+
+/// Metadata tagging a thought with the forge file and entry timestamp it came from
+fn source_metadata(source: &str, timestamp: &str) -> String {
+    json!({ "source": source, "source_timestamp": timestamp }).to_string()
+}
+
+/// Insert and clear the pending thoughts, returning how many were inserted
+fn insert_pending(db: &Database, source: &str, pending: &mut Vec<(Thought, String)>) -> rusqlite::Result<usize> {
+    if pending.is_empty() {
+        return Ok(0);
+    }
+    let tagged: Vec<(&Thought, String)> = pending.iter()
+        .map(|(thought, timestamp)| (thought, source_metadata(source, timestamp)))
+        .collect();
+    let result = db.insert_tagged_thoughts_batch(tagged.iter().map(|(thought, metadata)| (*thought, Some(metadata.as_str()))))?;
+    pending.clear();
+    Ok(result.inserted)
+}
+
+/// Create a thought for a forge entry, tagged with its source file and timestamp
+fn insert_item(db: &Database, source: &str, item: ForgeItem) -> rusqlite::Result<Thought> {
+    let metadata = source_metadata(source, &item.timestamp);
+    let thought = thought_for_item(db, item);
+    db.insert_tagged_thoughts_batch([(&thought, Some(metadata.as_str()))])?;
+    Ok(thought)
+}
+
+/// The thought for a forge entry, placed with its category
+fn thought_for_item(db: &Database, item: ForgeItem) -> Thought {
+    // Keep the entry's own time so imported history lands in the right place on the timeline
+    let created_at = DateTime::parse_from_rfc3339(&item.timestamp)
+        .map(|t| t.with_timezone(&Utc).to_rfc3339())
+        .unwrap_or_else(|_| Utc::now().to_rfc3339());
+    let mut random = db.position_random(&item.content, &item.category);
+    let (x, y, z) = db.clear_position(db.generate_position_for_category(&item.category, &mut random));
+    Thought {
+        id: Uuid::new_v4().to_string(),
+        content: item.content,
+        role: Some("system".to_string()),
+        category: item.category,
+        importance: item.importance,
+        position_x: x,
+        position_y: y,
+        position_z: z,
+        created_at: created_at.clone(),
+        last_referenced: created_at,
+        pinned: false,
+        favorite: false,
+        archived: false,
+    }
+}
+
+/// Link every imported decision to imported dead ends that share enough keywords
+fn connect_decisions_to_dead_ends(db: &Database) -> rusqlite::Result<usize> {
+    let decisions = db.get_thoughts_by_source(&format!("{}{}", SOURCE_PREFIX, DECISIONS_FILE))?;
+    let dead_ends = db.get_thoughts_by_source(&format!("{}{}", SOURCE_PREFIX, DEAD_ENDS_FILE))?;
+    if decisions.is_empty() || dead_ends.is_empty() {
+        return Ok(0);
+    }
+
+    let config = KeywordConfig::load(db);
+    let dead_end_keywords: Vec<(&Thought, Vec<String>)> = dead_ends.iter()
+        .map(|t| (t, extract_keywords_with(&t.content, &config)))
+        .collect();
+
+    let now = Utc::now().to_rfc3339();
+    let mut new_connections = Vec::new();
+    for decision in &decisions {
+        let keywords = extract_keywords_with(&decision.content, &config);
+        for (dead_end, dead_end_kw) in &dead_end_keywords {
+            let shared = count_shared_keywords(&keywords, dead_end_kw);
+            if shared < MIN_SHARED_KEYWORDS || db.connection_exists(&decision.id, &dead_end.id)? {
+                continue;
+            }
+            new_connections.push(crate::Connection {
+                id: Uuid::new_v4().to_string(),
+                from_thought: decision.id.clone(),
+                to_thought: dead_end.id.clone(),
+                strength: (shared as f64 * 0.15).min(1.0),
+                reason: format!("Forge: decision and dead end share {} keywords", shared),
+                created_at: now.clone(),
+                kind: "related".to_string(),
+            });
+        }
+    }
+
+    db.insert_connections_batch(&new_connections).map(|result| result.inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use serde_json::json;
+    use super::{import_forge, SOURCE_PREFIX};
+    use crate::database::testing::TempDb;
+    use crate::progress::{Progress, BATCH_SIZE};
+    use crate::session_forge::{self, DECISIONS_FILE};
+
+    #[test]
+    fn a_cancelled_import_is_finished_by_the_next_one_without_duplicates() {
+        let db = TempDb::new();
+        let total = BATCH_SIZE * 2 + 5;
+        // Dated long ago so no other test's date window picks these up from the shared forge dir
+        let decisions: Vec<_> = (0..total)
+            .map(|i| json!({
+                "timestamp": format!("2020-01-01T00:{:02}:00Z", i),
+                "choice": format!("Fixture choice {}", i),
+                "reasoning": "Forge import cancellation fixture",
+            }))
+            .collect();
+        let dir = std::env::temp_dir().join(format!("the-mind-forge-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(DECISIONS_FILE), json!({ "decisions": decisions }).to_string()).unwrap();
+        session_forge::set_forge_dir(&db, &dir.to_string_lossy()).unwrap();
+        let kinds = ["decisions".to_string()];
+
+        let cancel = AtomicBool::new(false);
+        // The first report comes before any entry is imported, so this stops after one batch
+        let report = |_: usize, _: Option<usize>, _: &str| cancel.store(true, Ordering::Relaxed);
+        let first = import_forge(&db, &kinds, Progress::new(Some(&report), Some(&cancel))).unwrap();
+
+        assert!(first.cancelled);
+        assert_eq!(first.kinds[0].imported, BATCH_SIZE);
+        assert_eq!(db.get_thought_count().unwrap(), BATCH_SIZE as i64);
+
+        let second = import_forge(&db, &kinds, Progress::none()).unwrap();
+
+        assert!(!second.cancelled);
+        assert_eq!((second.kinds[0].imported, second.kinds[0].skipped), (total - BATCH_SIZE, BATCH_SIZE));
+        let imported = db.get_thoughts_by_source(&format!("{}{}", SOURCE_PREFIX, DECISIONS_FILE)).unwrap();
+        let contents: HashSet<&str> = imported.iter().map(|t| t.content.as_str()).collect();
+        assert_eq!((imported.len(), contents.len()), (total, total));
+
+        session_forge::set_forge_dir(&db, "").unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
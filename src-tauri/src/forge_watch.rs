@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+use crate::session_forge::{self, DEAD_ENDS_FILE, DECISIONS_FILE, JOURNAL_FILE};
+
+pub const CHANGED_EVENT: &str = "mind://forge-changed";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// A file must stop changing for this long before it's reported, so a burst of writes is one event
+const DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Modified time and size; None when the file doesn't exist
+type Signature = Option<(SystemTime, u64)>;
+
+fn signature(path: &PathBuf) -> Signature {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Background poller for the session-forge files. Stops when dropped.
+pub struct ForgeWatcher {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ForgeWatcher {
+    /// Stop polling and wait for the thread to exit
+    pub fn stop(&mut self) {
+        // Dropping the sender wakes the thread immediately
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ForgeWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Poll the forge files and call `on_change(file)` once a changed file has settled.
+/// The directory is re-resolved every poll, so a forge installed (or re-pointed with
+/// set_forge_dir) while the app runs is picked up; switching directories resets the
+/// baseline instead of reporting every file as changed.
+pub fn spawn(on_change: impl Fn(&str) + Send + 'static) -> ForgeWatcher {
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let thread = std::thread::spawn(move || {
+        let mut dir: Option<String> = None;
+        let mut seen: HashMap<&'static str, Signature> = HashMap::new();
+        let mut pending: HashMap<&'static str, Instant> = HashMap::new();
+
+        loop {
+            match stopped.recv_timeout(POLL_INTERVAL) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+
+            let current = session_forge::forge_dir();
+            let Some(root) = current.as_deref().map(PathBuf::from) else {
+                continue;
+            };
+            let baseline = current != dir;
+            dir = current;
+            if baseline {
+                pending.clear();
+            }
+
+            for file in [JOURNAL_FILE, DECISIONS_FILE, DEAD_ENDS_FILE] {
+                let sig = signature(&root.join(file));
+                let previous = seen.insert(file, sig);
+                if !baseline && previous != Some(sig) {
+                    pending.insert(file, Instant::now());
+                }
+            }
+
+            let settled: Vec<&'static str> = pending.iter()
+                .filter(|(_, changed)| changed.elapsed() >= DEBOUNCE)
+                .map(|(file, _)| *file)
+                .collect();
+            for file in settled {
+                pending.remove(file);
+                session_forge::invalidate_cache(file);
+                on_change(file);
+            }
+        }
+    });
+
+    ForgeWatcher { stop: Some(stop), thread: Some(thread) }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+use crate::database::{Database, AUTO_CREATED_KINDS};
+use crate::utils::lock_recovering;
+use crate::{AppState, Connection};
+
+pub const THOUGHTS_ADDED_EVENT: &str = "mind://thoughts-added";
+pub const CONNECTIONS_ADDED_EVENT: &str = "mind://connections-added";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+// A recorded local write not seen by then (it failed, or hit an existing id) is dropped
+const LOCAL_WRITE_TTL: Duration = Duration::from_secs(60);
+
+/// One entry of the mind://connections-added payload
+#[derive(Debug, Clone, Serialize)]
+pub struct AddedConnection {
+    #[serde(flatten)]
+    pub connection: Connection,
+    /// Made by auto-connect or link matching rather than by hand
+    pub auto_created: bool,
+}
+
+/// Ids of thoughts and connections the frontend is writing itself (add_thought, add_thoughts,
+/// add_connection), so the watcher doesn't announce them back to it. Recorded before the
+/// write, so the watcher can't see the insert first; dropped once seen or after LOCAL_WRITE_TTL.
+#[derive(Default)]
+pub struct LocalWrites(Mutex<HashMap<String, Instant>>);
+
+impl LocalWrites {
+    pub fn record<'a>(&self, ids: impl IntoIterator<Item = &'a String>) {
+        let now = Instant::now();
+        lock_recovering(&self.0).extend(ids.into_iter().map(|id| (id.clone(), now)));
+    }
+
+    /// Leave out (and stop tracking) the ids the frontend wrote
+    fn filter(&self, ids: Vec<String>) -> Vec<String> {
+        let mut local = lock_recovering(&self.0);
+        local.retain(|_, at| at.elapsed() < LOCAL_WRITE_TTL);
+        ids.into_iter().filter(|id| local.remove(id).is_none()).collect()
+    }
+}
+
+/// Watch change_log for thoughts and connections inserted by anyone but the frontend (the MCP
+/// server, imports, auto-connect) and announce them: mind://thoughts-added with the thoughts,
+/// then mind://connections-added with the connections, each in insert order, so the graph can
+/// show a thought appearing before its edges.
+pub fn spawn_watcher(app: tauri::AppHandle, db: Arc<Database>) {
+    std::thread::spawn(move || {
+        // Only what's inserted while we run is news, so nothing is announced until the end of
+        // the change log is known; starting from 0 would replay the whole history
+        let mut last_seq: Option<i64> = None;
+        loop {
+            last_seq = match last_seq {
+                None => match db.get_inserted_since(i64::MAX) {
+                    Ok((seq, _)) => Some(seq),
+                    Err(e) => {
+                        tracing::warn!("Change watcher failed to read the change log: {}", e);
+                        None
+                    }
+                },
+                Some(since) => match announce(&app, &db, since) {
+                    Ok(seq) => Some(seq),
+                    Err(e) => {
+                        tracing::warn!("Change watcher failed: {}", e);
+                        Some(since)
+                    }
+                },
+            };
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// Emit what was inserted after `since`; returns the change_log seq covered
+fn announce(app: &tauri::AppHandle, db: &Database, since: i64) -> Result<i64, Box<dyn std::error::Error>> {
+    let (seq, inserted) = db.get_inserted_since(since)?;
+    if inserted.is_empty() {
+        return Ok(seq);
+    }
+
+    let (mut thought_ids, mut connection_ids) = (Vec::new(), Vec::new());
+    for (entity, id) in inserted {
+        match entity.as_str() {
+            "thought" => thought_ids.push(id),
+            "connection" => connection_ids.push(id),
+            _ => {}
+        }
+    }
+    let local = &app.state::<AppState>().local_writes;
+    let thought_ids = local.filter(thought_ids);
+    let connection_ids = local.filter(connection_ids);
+
+    // Rows deleted again since are left out
+    let thoughts = db.get_thoughts_by_ids(&thought_ids)?;
+    if !thoughts.is_empty() {
+        app.emit(THOUGHTS_ADDED_EVENT, &thoughts)?;
+    }
+    let connections: Vec<AddedConnection> = db.get_connections_by_ids(&connection_ids)?
+        .into_iter()
+        .map(|connection| AddedConnection {
+            auto_created: AUTO_CREATED_KINDS.contains(&connection.kind.as_str()),
+            connection,
+        })
+        .collect();
+    if !connections.is_empty() {
+        app.emit(CONNECTIONS_ADDED_EVENT, &connections)?;
+    }
+    Ok(seq)
+}
@@ -0,0 +1,140 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+use crate::database::{Database, MAX_PAGE_SIZE};
+use crate::utils::lock_recovering;
+use crate::{AppState, Connection, DbVersion, Thought};
+
+pub const BATCH_EVENT: &str = "mind://graph-batch";
+pub const COMPLETE_EVENT: &str = "mind://graph-complete";
+
+/// Payload of mind://graph-batch: thoughts first, then connections, each in rowid order
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum GraphBatch {
+    Thoughts { stream_id: u64, items: Vec<Thought> },
+    Connections { stream_id: u64, items: Vec<Connection> },
+}
+
+/// Payload of mind://graph-complete. `version` is the snapshot the stream covered;
+/// anything beyond it was written mid-stream and should be picked up by normal sync.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphComplete {
+    pub stream_id: u64,
+    pub thought_count: usize,
+    pub connection_count: usize,
+    pub version: DbVersion,
+    pub cancelled: bool,
+    pub error: Option<String>,
+}
+
+/// Tracks the running stream so it can be cancelled (starting a new one cancels the old)
+#[derive(Default)]
+pub struct GraphStreams {
+    next_id: AtomicU64,
+    current: Mutex<Option<(u64, Arc<AtomicBool>)>>,
+}
+
+impl GraphStreams {
+    fn begin(&self) -> (u64, Arc<AtomicBool>) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let mut current = lock_recovering(&self.current);
+        if let Some((_, previous)) = current.replace((id, Arc::clone(&cancelled))) {
+            previous.store(true, Ordering::Relaxed);
+        }
+        (id, cancelled)
+    }
+
+    /// Cancel the running stream; false if none was running
+    pub fn cancel(&self) -> bool {
+        match lock_recovering(&self.current).take() {
+            Some((_, cancelled)) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn finish(&self, id: u64) {
+        let mut current = lock_recovering(&self.current);
+        if current.as_ref().is_some_and(|(running, _)| *running == id) {
+            *current = None;
+        }
+    }
+}
+
+/// Snapshot the version, then stream the graph up to it on the blocking pool.
+/// Each batch checks a reader out of the pool and returns it, so writers and other
+/// commands are never held up for the whole load. Returns the stream id.
+pub fn start(app: tauri::AppHandle, db: Arc<Database>, batch_size: usize) -> Result<u64, String> {
+    let version = db.get_db_version().map_err(|e| e.to_string())?;
+    let batch_size = batch_size.clamp(1, MAX_PAGE_SIZE as usize);
+    let (stream_id, cancelled) = app.state::<AppState>().graph_streams.begin();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut complete = GraphComplete {
+            stream_id,
+            thought_count: 0,
+            connection_count: 0,
+            version,
+            cancelled: false,
+            error: None,
+        };
+
+        if let Err(e) = stream(&app, &db, batch_size, &cancelled, &mut complete) {
+            tracing::warn!("Graph stream {} failed: {}", stream_id, e);
+            complete.error = Some(e.to_string());
+        }
+        complete.cancelled = cancelled.load(Ordering::Relaxed);
+
+        app.state::<AppState>().graph_streams.finish(stream_id);
+        if let Err(e) = app.emit(COMPLETE_EVENT, &complete) {
+            tracing::warn!("Failed to emit {}: {}", COMPLETE_EVENT, e);
+        }
+    });
+
+    Ok(stream_id)
+}
+
+fn stream(
+    app: &tauri::AppHandle,
+    db: &Database,
+    batch_size: usize,
+    cancelled: &AtomicBool,
+    complete: &mut GraphComplete,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let limit = batch_size as i64;
+    let stream_id = complete.stream_id;
+
+    let mut after = 0;
+    while !cancelled.load(Ordering::Relaxed) {
+        let batch = db.get_thought_batch(after, complete.version.thought_max_id, limit)?;
+        let Some(&(last_rowid, _)) = batch.last() else {
+            break;
+        };
+        after = last_rowid;
+        complete.thought_count += batch.len();
+
+        let items = batch.into_iter().map(|(_, t)| t).collect();
+        app.emit(BATCH_EVENT, GraphBatch::Thoughts { stream_id, items })?;
+    }
+
+    let mut after = 0;
+    while !cancelled.load(Ordering::Relaxed) {
+        let batch = db.get_connection_batch(after, complete.version.connection_max_id, limit)?;
+        let Some(&(last_rowid, _)) = batch.last() else {
+            break;
+        };
+        after = last_rowid;
+        complete.connection_count += batch.len();
+
+        let items = batch.into_iter().map(|(_, c)| c).collect();
+        app.emit(BATCH_EVENT, GraphBatch::Connections { stream_id, items })?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+/// Overrides the starting log level, e.g. THE_MIND_LOG=debug or THE_MIND_LOG=the_mind::database=trace
+pub const LOG_ENV: &str = "THE_MIND_LOG";
+const DEFAULT_LEVEL: &str = "info";
+
+const LOG_FILE: &str = "mind.log";
+// mind.log rolls over to mind.log.1 at this size; mind.log.KEEP_ROTATED is the oldest kept
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+const KEEP_ROTATED: usize = 3;
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// The logs folder next to the database
+pub fn log_dir() -> PathBuf {
+    crate::database::Database::default_path()
+        .parent()
+        .map(|p| p.join("logs"))
+        .unwrap_or_else(|| PathBuf::from("logs"))
+}
+
+/// Send tracing output to the rotating log file, and to stderr as well when `stderr` is set.
+/// Never writes to stdout, which the MCP server uses for the protocol.
+pub fn init(stderr: bool) {
+    let directives = std::env::var(LOG_ENV).unwrap_or_else(|_| DEFAULT_LEVEL.to_string());
+    let filter = EnvFilter::try_new(&directives).unwrap_or_else(|_| EnvFilter::new(DEFAULT_LEVEL));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let file = RotatingFile::open(log_dir().join(LOG_FILE));
+    let file_error = file.as_ref().err().map(|e| e.to_string());
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(file.ok().map(|file| {
+            fmt::layer()
+                .with_ansi(false)
+                // Span close lines carry the time spent inside instrumented calls
+                .with_span_events(FmtSpan::CLOSE)
+                .with_writer(Mutex::new(file))
+        }))
+        .with(stderr.then(|| fmt::layer().with_writer(io::stderr)));
+    if registry.try_init().is_err() {
+        return;
+    }
+    let _ = FILTER.set(handle);
+    if let Some(e) = file_error {
+        tracing::warn!("Log file unavailable in {} ({}); logging to stderr only", log_dir().display(), e);
+    }
+}
+
+/// Replace the active filter, e.g. "debug" or "info,the_mind::database=trace"
+pub fn set_level(directives: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(directives.trim())
+        .map_err(|e| format!("Invalid: log level '{}': {}", directives, e))?;
+    let handle = FILTER.get().ok_or("Logging is not initialized")?;
+    handle.reload(filter).map_err(|e| e.to_string())?;
+    tracing::info!("Log level set to {}", directives.trim());
+    Ok(())
+}
+
+/// An append-only log file that rolls over to numbered copies once it reaches MAX_LOG_BYTES
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(RotatingFile { path, file, written })
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..KEEP_ROTATED).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated(n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated(1))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > MAX_LOG_BYTES {
+            // Keep writing to the current file if the rename fails (e.g. another process has it open)
+            let _ = self.rotate();
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
@@ -1,19 +1,97 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod app_info;
+mod auto_connect;
+mod autostart;
+mod clustering;
+mod auto_session;
 mod database;
+mod diagnostics;
+mod digest;
+mod embeddings;
+mod encryption;
+mod forge_import;
+mod forge_watch;
+mod graph_events;
+mod graph_stream;
+mod logging;
+mod maintenance;
+mod mcp_log;
 mod mcp_server;
+mod monitor_identify;
+mod navigation;
+mod notifier;
+mod power;
+mod progress;
+mod reflection;
+mod report;
+mod reset;
+mod search;
+mod suggestions;
+mod transcript;
+mod validation;
 pub mod session_forge;
 pub mod utils;
 mod wallpaper;
 
-use std::sync::Mutex;
+use std::sync::Arc;
 use database::Database;
 use serde::{Deserialize, Serialize};
 
 // Shared state
 pub struct AppState {
-    pub db: Mutex<Database>,
+    // Arc so blocking worker tasks can hold the database independently of the command.
+    // Database synchronizes internally (one writer, pooled readers), so there is no outer lock.
+    pub db: Arc<Database>,
+    // Deep-link targets waiting for the frontend to report it is listening
+    pub navigation: navigation::NavigationQueue,
+    pub graph_streams: graph_stream::GraphStreams,
+    // What the frontend wrote itself, left out of mind://thoughts-added and connections-added
+    pub local_writes: graph_events::LocalWrites,
+    pub wallpaper: wallpaper::WallpaperState,
+    // Outstanding reset_mind confirmation
+    pub reset_tokens: reset::ResetTokens,
+    pub mode: AppMode,
+}
+
+impl AppState {
+    pub fn db(&self) -> &Database {
+        &self.db
+    }
+
+    /// Run database work on the blocking thread pool so the IPC thread stays responsive
+    /// while slow queries (full-table fetches, LIKE scans, cluster recomputes) run.
+    pub async fn run_db<T, F>(&self, label: &'static str, work: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let db = Arc::clone(&self.db);
+        let started = std::time::Instant::now();
+
+        let result = tauri::async_runtime::spawn_blocking(move || work(&db))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        tracing::debug!("{} finished off the IPC thread in {:?}", label, started.elapsed());
+
+        result.map_err(|e| {
+            let message = e.to_string();
+            diagnostics::record_error(label, &message);
+            message
+        })
+    }
+}
+
+/// Record panic messages before the default hook prints them
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        utils::record_panic(info.to_string());
+        diagnostics::record_error("panic", &info.to_string());
+        default_hook(info);
+    }));
 }
 
 // Thought structure for Tauri commands
@@ -29,6 +107,15 @@ pub struct Thought {
     pub position_z: f64,
     pub created_at: String,
     pub last_referenced: String,
+    /// An anchor: layout never moves it, importance never decays, filters never hide it
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub favorite: bool,
+    /// Kept but out of the way: left out of search, the graph, the review queue and recall
+    /// (set through bulk_update_thoughts)
+    #[serde(default)]
+    pub archived: bool,
 }
 
 // Connection structure
@@ -40,6 +127,80 @@ pub struct Connection {
     pub strength: f64,
     pub reason: String,
     pub created_at: String,
+    #[serde(default = "default_connection_kind")]
+    pub kind: String,
+}
+
+pub fn default_connection_kind() -> String {
+    "related".to_string()
+}
+
+// A connection matched by search, with both endpoints' text for display
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionMatch {
+    pub connection: Connection,
+    pub from_content: String,
+    pub to_content: String,
+}
+
+// A proposed connection waiting for review, with both endpoints' text (suggest_connections,
+// and auto-connection when auto_connect_mode is "suggest")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionSuggestion {
+    /// The connection accepting it would create; strength is the similarity score
+    pub connection: Connection,
+    pub from_content: String,
+    pub to_content: String,
+}
+
+// A thought over the connection threshold (get_overconnected_thoughts)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverconnectedThought {
+    pub thought: Thought,
+    pub connections: i64,
+    /// How many of them were made automatically, and so can be pruned
+    pub auto_connections: i64,
+}
+
+// A single thought with its direct connections (either direction)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThoughtDetail {
+    pub thought: Thought,
+    pub connections: Vec<Connection>,
+    pub attachment_count: i64,
+    /// Locked thoughts are left alone by bulk operations, importance recomputes and imports
+    pub locked: bool,
+}
+
+// A thought that is due for revisiting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewItem {
+    pub thought: Thought,
+    pub days_since_referenced: f64,
+    pub score: f64,
+}
+
+// Weights for recompute_importance (normalized by their sum)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportanceWeights {
+    pub original: f64,
+    pub degree: f64,
+    pub references: f64,
+}
+
+impl Default for ImportanceWeights {
+    fn default() -> Self {
+        ImportanceWeights { original: 0.5, degree: 0.35, references: 0.15 }
+    }
+}
+
+// A thought whose importance rank moved after recompute_importance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportanceChange {
+    pub thought: Thought,
+    pub computed_importance: f64,
+    pub old_rank: usize,
+    pub new_rank: usize,
 }
 
 // Session structure
@@ -52,6 +213,151 @@ pub struct Session {
     pub ended_at: Option<String>,
 }
 
+// Session drill-down: the session, its thoughts in order, connections made during it, and stats
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDetail {
+    pub session: Session,
+    pub thoughts: Vec<Thought>,
+    pub connections: Vec<Connection>,
+    pub stats: SessionStats,
+}
+
+// A thought and the URLs found in its content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThoughtLinks {
+    pub thought: Thought,
+    pub links: Vec<String>,
+}
+
+// A thought like another one (get_similar_thoughts and the mind_similar MCP tool)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarThought {
+    pub thought: Thought,
+    /// 0..1; higher is more alike
+    pub score: f64,
+    /// What the score measures: "embedding" (cosine similarity) or "keywords" (weighted overlap)
+    pub basis: String,
+}
+
+// The most important thoughts matching a filter plus the connections among them
+// (get_graph_snapshot and the mind_export_graph MCP tool)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    pub thoughts: Vec<Thought>,
+    pub connections: Vec<Connection>,
+    /// More thoughts matched than max_nodes allowed
+    pub truncated: bool,
+}
+
+// Result of get_thoughts_by_ids: the thoughts found, in the order asked for, and the ids
+// with no thought
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThoughtsByIds {
+    pub thoughts: Vec<Thought>,
+    pub missing: Vec<String>,
+}
+
+// Result of get_connections_by_ids, like ThoughtsByIds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionsByIds {
+    pub connections: Vec<Connection>,
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionStats {
+    pub thought_count: usize,
+    pub connection_count: usize,
+    pub categories: Vec<String>,
+    pub duration_seconds: Option<i64>,
+}
+
+// Result of a batch insert: rows added, and rows skipped because their id was already taken
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchInsert {
+    pub inserted: usize,
+    pub skipped: usize,
+}
+
+// Result of importing a pasted conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptImport {
+    pub session_id: String,
+    pub turns_found: usize,
+    pub thoughts_created: usize,
+    pub turns_skipped: usize,
+    pub connections_created: usize,
+    // Stopped early; the thoughts created so far are kept
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+// Result of importing session-forge entries as thoughts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForgeImport {
+    pub kinds: Vec<ForgeImportCounts>,
+    pub connections_created: usize,
+    pub warnings: Vec<session_forge::ForgeWarning>,
+    // Stopped early; entries imported so far are kept and a re-import picks up the rest
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForgeImportCounts {
+    pub kind: String,
+    pub imported: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    // Changed entries whose thought is locked and so kept as it was
+    #[serde(default)]
+    pub locked: usize,
+}
+
+// Payload of mind://forge-changed
+#[derive(Debug, Clone, Serialize)]
+pub struct ForgeChanged {
+    pub file: String,
+}
+
+// Payload of mind://wallpaper-auto-started; fallback_from is the saved configuration
+// when its monitors were missing and the primary monitor was used instead
+#[derive(Debug, Clone, Serialize)]
+pub struct WallpaperAutoStarted {
+    pub config: wallpaper::WallpaperConfig,
+    pub fallback_from: Option<wallpaper::WallpaperConfig>,
+}
+
+// Payload of mind://presentation-mode; active while a fullscreen app, a presentation
+// or quiet hours means rendering should back off
+#[derive(Debug, Clone, Serialize)]
+pub struct PresentationMode {
+    pub active: bool,
+}
+
+// Result of get_app_mode; read-only comes from the --read-only flag or the read_only setting
+#[derive(Debug, Clone, Serialize)]
+pub struct AppMode {
+    pub read_only: bool,
+    // "flag" or "setting" while read-only
+    pub read_only_reason: Option<String>,
+}
+
+impl AppMode {
+    /// --read-write overrides the setting for one launch, which is the only way to turn the
+    /// setting back off since a read-only database can't change its own settings
+    fn from_args(args: &[String]) -> Self {
+        let reason = if args.iter().any(|a| a == READ_ONLY_FLAG) {
+            Some("flag")
+        } else if !args.iter().any(|a| a == READ_WRITE_FLAG) && Database::read_only_setting() {
+            Some("setting")
+        } else {
+            None
+        };
+        AppMode { read_only: reason.is_some(), read_only_reason: reason.map(str::to_string) }
+    }
+}
+
 // Cluster structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cluster {
@@ -63,6 +369,38 @@ pub struct Cluster {
     pub center_z: f64,
     pub thought_count: i64,
     pub created_at: String,
+    /// Words that set the cluster's thoughts apart, most telling first; the name is made of the first few
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// The cluster this one was split out of; None for a top-level (category) cluster
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// Sub-clusters, listed by get_cluster_children; 0 for a leaf
+    #[serde(default)]
+    pub child_count: i64,
+    /// Made with create_cluster rather than by compute_clusters
+    #[serde(default)]
+    pub manual: bool,
+    /// Its category's color, so every view renders it the same
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+// get_nearest_cluster / get_cluster_for_thought result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterMatch {
+    pub cluster: Cluster,
+    /// From the point or thought to the cluster's centroid
+    pub distance: f64,
+    /// The thought belongs to the cluster, rather than just being nearest to it
+    pub member: bool,
+}
+
+// A cluster with its member thoughts, most important first (get_cluster_detail)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterDetail {
+    pub cluster: Cluster,
+    pub thoughts: Vec<Thought>,
 }
 
 // DB version for smart polling
@@ -70,222 +408,2235 @@ pub struct Cluster {
 pub struct DbVersion {
     pub thought_max_id: i64,
     pub connection_max_id: i64,
+    pub session_max_id: i64,
+    /// Bumped by every compute_clusters
+    pub cluster_generation: i64,
+    /// Bumped by every insert, delete or edit of thoughts, connections, sessions, clusters
+    /// and categories, including those made by the MCP server
+    pub mutation_counter: i64,
+    /// Latest change_log entry; pass to get_graph_delta to get what changes after this
+    pub change_seq: i64,
+}
+
+// Ids removed since a get_graph_delta seq
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeletedEntities {
+    pub thoughts: Vec<String>,
+    pub connections: Vec<String>,
+    pub sessions: Vec<String>,
+    pub clusters: Vec<String>,
+}
+
+// get_graph_delta result: current rows of everything added or changed, and what was deleted
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphDelta {
+    /// Pass as since_seq next time
+    pub seq: i64,
+    /// The change log no longer reaches back to since_seq; reload everything instead
+    pub stale: bool,
+    pub thoughts: Vec<Thought>,
+    pub connections: Vec<Connection>,
+    pub sessions: Vec<Session>,
+    pub clusters: Vec<Cluster>,
+    pub deleted: DeletedEntities,
+}
+
+// Category structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Category {
+    pub name: String,
+    /// "#RRGGBB"
+    pub color: Option<String>,
+    /// Emoji or icon name shown next to the category
+    #[serde(default)]
+    pub icon: Option<String>,
+    pub created_at: String,
+    pub thought_count: i64,
+}
+
+// Which thoughts a bulk operation touches; every set condition must hold. Dates are RFC 3339.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ThoughtFilter {
+    pub category: Option<String>,
+    pub created_before: Option<String>,
+    pub created_after: Option<String>,
+    pub min_importance: Option<f64>,
+    pub max_importance: Option<f64>,
+    /// Case-insensitive substring of the content
+    pub content: Option<String>,
+    pub role: Option<String>,
+}
+
+// What bulk_update_thoughts changes; unset fields are left alone
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ThoughtChanges {
+    pub category: Option<String>,
+    /// Added to importance, which stays within 0-1
+    pub importance_delta: Option<f64>,
+    pub archived: Option<bool>,
+}
+
+// Outcome of bulk_update_thoughts
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkUpdateResult {
+    pub updated: usize,
+    /// Matching thoughts left unchanged because they're locked
+    pub skipped_locked: usize,
+}
+
+// Outcome of bulk_delete_thoughts; samples (a few matching contents) are only filled on a dry run
+#[derive(Debug, Clone, Serialize)]
+pub struct BulkDeleteResult {
+    pub affected: usize,
+    pub samples: Vec<String>,
+    pub dry_run: bool,
+    /// Matching thoughts kept because they're locked
+    pub skipped_locked: usize,
+}
+
+// First step of a data reset: what would be destroyed, and the token that confirms it
+#[derive(Debug, Clone, Serialize)]
+pub struct ResetPreview {
+    pub token: String,
+    pub expires_in_secs: u64,
+    /// Rows per table
+    pub counts: std::collections::BTreeMap<String, i64>,
+}
+
+// Outcome of reset_mind_confirm
+#[derive(Debug, Clone, Serialize)]
+pub struct ResetResult {
+    /// Rows deleted per table
+    pub deleted: std::collections::BTreeMap<String, i64>,
+    pub backup_path: Option<String>,
+}
+
+// A saved camera viewpoint: where the camera is, what it looks at, and how far zoomed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CameraView {
+    pub id: String,
+    pub name: String,
+    pub position_x: f64,
+    pub position_y: f64,
+    pub position_z: f64,
+    pub target_x: f64,
+    pub target_y: f64,
+    pub target_z: f64,
+    pub zoom: f64,
+    pub created_at: String,
+}
+
+// A constrained thought query for run_query, run_adhoc_query and the mind_query tool. Every
+// condition is optional and all of them must hold; checked by validation::query.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueryDefinition {
+    /// Any of these categories
+    pub categories: Vec<String>,
+    /// Every one of these tags (from the tags array in a thought's metadata)
+    pub tags: Vec<String>,
+    /// RFC 3339 timestamps or YYYY-MM-DD dates
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    /// Created within this many days of when the query runs
+    pub within_days: Option<f64>,
+    pub min_importance: Option<f64>,
+    pub max_importance: Option<f64>,
+    /// Only thoughts with (true) or without (false) any connection
+    pub has_connections: Option<bool>,
+    pub role: Option<String>,
+    /// Case-insensitive substring of the content
+    pub text: Option<String>,
+    /// Archived thoughts are left out unless this is set
+    pub include_archived: bool,
+    /// Most thoughts returned (default validation::DEFAULT_QUERY_LIMIT)
+    pub limit: Option<usize>,
+}
+
+// A named query definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub id: String,
+    pub name: String,
+    pub definition: QueryDefinition,
+    pub created_at: String,
+}
+
+// A file, image or link attached to a thought. path_or_url is an absolute path for files and
+// images (inside the attachments folder when copied there) and the URL for links.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: String,
+    pub thought_id: String,
+    pub kind: String,
+    pub path_or_url: String,
+    pub label: Option<String>,
+    pub created_at: String,
+}
+
+// Payload of mind://view-deleted (mind://view-saved carries the CameraView itself)
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewDeleted {
+    pub id: String,
+}
+
+// One calendar day of activity for the heatmap
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayActivity {
+    pub date: String,
+    pub thoughts: i64,
+    pub connections: i64,
+    pub sessions: i64,
+}
+
+// Aggregate figures for one category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryStats {
+    pub category: String,
+    pub thought_count: i64,
+    pub avg_importance: f64,
+    pub connection_count: i64,
+    pub newest_thought: Option<String>,
+    /// The category's #RRGGBB color, if it has one
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+// How many thoughts contain a keyword (or two-keyword phrase), and the IDF weight that gives it in auto-connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeywordStat {
+    pub keyword: String,
+    pub doc_count: i64,
+    pub idf: f64,
+    pub phrase: bool,
+}
+
+// A prominent keyword for the word cloud (get_top_keywords). `keyword` is how it's most often
+// written; `stem` is the form keywords are matched by.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopKeyword {
+    pub keyword: String,
+    pub stem: String,
+    pub doc_count: i64,
+    pub importance_sum: f64,
+    /// Matching thoughts created in the last 7 days and in the 7 days before, to spot rising topics
+    pub last_7_days: i64,
+    pub previous_7_days: i64,
+}
+
+// Overview of the whole mind (get_stats)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MindStats {
+    pub thoughts: i64,
+    pub connections: i64,
+    pub sessions: i64,
+    pub clusters: i64,
+    pub categories: Vec<CategoryStats>,
+    /// The STATS_TOP_KEYWORDS most prominent keywords, as get_top_keywords returns them
+    pub top_keywords: Vec<TopKeyword>,
+}
+
+// Thought counts per category within one week/month bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryTrendBucket {
+    pub bucket_start: String,
+    pub counts: std::collections::BTreeMap<String, i64>,
+}
+
+// One day/week/month of the timeline strip; bucket_start is a local YYYY-MM-DD date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineBucket {
+    pub bucket_start: String,
+    pub count: usize,
+    /// Oldest first
+    pub thought_ids: Vec<String>,
+    pub counts: std::collections::BTreeMap<String, i64>,
+    /// The bucket's most important thought; None for an empty bucket
+    pub representative: Option<Thought>,
 }
 
 // Tauri commands
+
+// Upper bound for get_all_thoughts (overridable via the max_all_thoughts setting)
+const DEFAULT_MAX_ALL_THOUGHTS: usize = 20_000;
+
 #[tauri::command]
-fn get_all_thoughts(state: tauri::State<AppState>) -> Result<Vec<Thought>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_all_thoughts().map_err(|e| e.to_string())
+async fn get_all_thoughts(state: tauri::State<'_, AppState>) -> Result<Vec<Thought>, String> {
+    state.run_db("get_all_thoughts", |db| {
+        let max = db.setting_or("max_all_thoughts", DEFAULT_MAX_ALL_THOUGHTS);
+        let (thoughts, truncated) = db.get_thoughts_capped(max)?;
+        if truncated {
+            tracing::info!("get_all_thoughts capped at {} thoughts; use get_thoughts_page for the rest", max);
+        }
+        Ok(thoughts)
+    }).await
 }
 
+/// One page of thoughts. sort_by: created_at, last_referenced, importance, or content.
+/// Limit is capped at database::MAX_PAGE_SIZE.
 #[tauri::command]
-fn get_all_connections(state: tauri::State<AppState>) -> Result<Vec<Connection>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_all_connections().map_err(|e| e.to_string())
+async fn get_thoughts_page(
+    state: tauri::State<'_, AppState>,
+    offset: i64,
+    limit: i64,
+    sort_by: Option<String>,
+    ascending: Option<bool>,
+) -> Result<Vec<Thought>, String> {
+    let sort = match sort_by.as_deref() {
+        Some(name) => database::ThoughtSort::parse(name).ok_or_else(|| {
+            format!("Invalid sort_by: {} (expected created_at, last_referenced, importance, or content)", name)
+        })?,
+        None => database::ThoughtSort::CreatedAt,
+    };
+    let ascending = ascending.unwrap_or(true);
+    state.run_db("get_thoughts_page", move |db| db.get_thoughts_page(offset, limit, sort, ascending)).await
 }
 
 #[tauri::command]
-fn add_thought(state: tauri::State<AppState>, thought: Thought) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.insert_thought(&thought).map_err(|e| e.to_string())
+async fn get_connections_page(state: tauri::State<'_, AppState>, offset: i64, limit: i64, ascending: Option<bool>) -> Result<Vec<Connection>, String> {
+    let ascending = ascending.unwrap_or(true);
+    state.run_db("get_connections_page", move |db| db.get_connections_page(offset, limit, ascending)).await
 }
 
 #[tauri::command]
-fn add_connection(state: tauri::State<AppState>, connection: Connection) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.insert_connection(&connection).map_err(|e| e.to_string())
+async fn get_all_connections(state: tauri::State<'_, AppState>) -> Result<Vec<Connection>, String> {
+    state.run_db("get_all_connections", |db| db.get_all_connections()).await
 }
 
+/// The most important thoughts (optionally one category, created since a YYYY-MM-DD date or
+/// RFC3339 time) and the connections between them; `truncated` is set when max_nodes cut it short
 #[tauri::command]
-fn search_thoughts(state: tauri::State<AppState>, query: String) -> Result<Vec<Thought>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.search_thoughts(&query).map_err(|e| e.to_string())
+async fn get_graph_snapshot(
+    state: tauri::State<'_, AppState>,
+    category: Option<String>,
+    since: Option<String>,
+    max_nodes: Option<usize>,
+) -> Result<GraphSnapshot, String> {
+    let since = since.as_deref().map(utils::parse_since).transpose()?;
+    let max_nodes = max_nodes.unwrap_or(database::DEFAULT_SNAPSHOT_NODES);
+    state.run_db("get_graph_snapshot", move |db| {
+        db.get_graph_snapshot(category.as_deref(), since.as_deref(), max_nodes)
+    }).await
 }
 
+/// Ids in `requested` (once each, in order) that aren't in `found`
+fn missing_ids<'a>(requested: &[String], found: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut seen: std::collections::HashSet<&String> = found.collect();
+    requested.iter().filter(|id| seen.insert(*id)).cloned().collect()
+}
+
+/// Thoughts by id for hydrating what get_graph_delta and friends point at
 #[tauri::command]
-fn get_all_sessions(state: tauri::State<AppState>) -> Result<Vec<Session>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_all_sessions().map_err(|e| e.to_string())
+async fn get_thoughts_by_ids(state: tauri::State<'_, AppState>, ids: Vec<String>) -> Result<ThoughtsByIds, String> {
+    state.run_db("get_thoughts_by_ids", move |db| {
+        let thoughts = db.get_thoughts_by_ids(&ids)?;
+        let missing = missing_ids(&ids, thoughts.iter().map(|t| &t.id));
+        Ok(ThoughtsByIds { thoughts, missing })
+    }).await
 }
 
 #[tauri::command]
-fn get_db_version(state: tauri::State<AppState>) -> Result<DbVersion, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let thought_max_id = db.get_max_thought_rowid().map_err(|e| e.to_string())?;
-    let connection_max_id = db.get_max_connection_rowid().map_err(|e| e.to_string())?;
-    Ok(DbVersion { thought_max_id, connection_max_id })
+async fn get_connections_by_ids(state: tauri::State<'_, AppState>, ids: Vec<String>) -> Result<ConnectionsByIds, String> {
+    state.run_db("get_connections_by_ids", move |db| {
+        let connections = db.get_connections_by_ids(&ids)?;
+        let missing = missing_ids(&ids, connections.iter().map(|c| &c.id));
+        Ok(ConnectionsByIds { connections, missing })
+    }).await
 }
 
+/// Fetch one thought plus its direct connections
 #[tauri::command]
-fn get_thought_count(state: tauri::State<AppState>) -> Result<i64, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_thought_count().map_err(|e| e.to_string())
+async fn get_thought_by_id(state: tauri::State<'_, AppState>, id: String) -> Result<ThoughtDetail, String> {
+    let lookup_id = id.clone();
+    let detail = state.run_db("get_thought_by_id", move |db| {
+        let Some(thought) = db.get_thought_by_id(&lookup_id)? else {
+            return Ok(None);
+        };
+        let connections = db.get_connections_of_thought(&lookup_id)?;
+        let attachment_count = db.count_attachments(&lookup_id)?;
+        let locked = db.is_thought_locked(&lookup_id)?;
+        Ok(Some(ThoughtDetail { thought, connections, attachment_count, locked }))
+    }).await?;
+
+    detail.ok_or_else(|| format!("NotFound: thought {}", id))
 }
 
+/// Attach a file, image or URL to a thought. Local files must exist; with `copy` (default: the
+/// attachments_copy_files setting) they're copied into the attachments folder beside the
+/// database so the mind stays self-contained, and deleted with the attachment.
 #[tauri::command]
-fn get_thoughts_near(state: tauri::State<AppState>, x: f64, y: f64, z: f64, radius: f64, limit: i64) -> Result<Vec<Thought>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_thoughts_near(x, y, z, radius, limit).map_err(|e| e.to_string())
+fn add_attachment(
+    state: tauri::State<AppState>,
+    thought_id: String,
+    kind: String,
+    path: String,
+    label: Option<String>,
+    copy: Option<bool>,
+) -> Result<Attachment, String> {
+    let db = state.db();
+    let kind = kind.trim().to_lowercase();
+    if !database::ATTACHMENT_KINDS.contains(&kind.as_str()) {
+        return Err(format!("Invalid: kind must be one of {}", database::ATTACHMENT_KINDS.join(", ")));
+    }
+    if db.get_thought_by_id(&thought_id).map_err(|e| e.to_string())?.is_none() {
+        return Err(format!("NotFound: thought {}", thought_id));
+    }
+    let label = label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let path_or_url = if kind == "url" {
+        let url = path.trim();
+        if !utils::extract_urls(url).iter().any(|found| found == url) {
+            return Err(format!("Invalid: '{}' is not an http(s) URL", url));
+        }
+        url.to_string()
+    } else {
+        let source = std::path::Path::new(path.trim());
+        if !source.is_file() {
+            return Err(format!("Invalid: no file at {}", source.display()));
+        }
+        if copy.unwrap_or_else(|| db.setting_or(database::SETTING_COPY_ATTACHMENTS, false)) {
+            let dir = db.attachments_dir();
+            std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            // Named by attachment id so two files called the same never collide
+            let mut target = dir.join(&id);
+            if let Some(extension) = source.extension() {
+                target.set_extension(extension);
+            }
+            std::fs::copy(source, &target).map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+            target.to_string_lossy().into_owned()
+        } else {
+            std::path::absolute(source).unwrap_or_else(|_| source.to_path_buf()).to_string_lossy().into_owned()
+        }
+    };
+
+    let attachment = Attachment {
+        id,
+        thought_id,
+        kind,
+        path_or_url,
+        label,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    if let Err(e) = db.add_attachment(&attachment) {
+        db.remove_unreferenced_attachment_files();
+        return Err(e.to_string());
+    }
+    Ok(attachment)
 }
 
+/// Remove an attachment (and its copy in the attachments folder, if any)
 #[tauri::command]
-fn get_connections_for_thoughts(state: tauri::State<AppState>, ids: Vec<String>) -> Result<Vec<Connection>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_connections_for_thoughts(&ids).map_err(|e| e.to_string())
+fn remove_attachment(state: tauri::State<AppState>, id: String) -> Result<(), String> {
+    if !state.db().remove_attachment(&id).map_err(|e| e.to_string())? {
+        return Err(format!("NotFound: attachment {}", id));
+    }
+    Ok(())
 }
 
 #[tauri::command]
-fn get_all_clusters(state: tauri::State<AppState>) -> Result<Vec<Cluster>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_all_clusters().map_err(|e| e.to_string())
+fn get_attachments(state: tauri::State<AppState>, thought_id: String) -> Result<Vec<Attachment>, String> {
+    state.db().get_attachments(&thought_id).map_err(|e| e.to_string())
 }
 
+/// Important thoughts that haven't been referenced in a while, most overdue first
 #[tauri::command]
-fn recompute_clusters(state: tauri::State<AppState>) -> Result<Vec<Cluster>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.compute_clusters().map_err(|e| e.to_string())
+async fn get_review_queue(state: tauri::State<'_, AppState>, limit: Option<i64>, by_computed_importance: Option<bool>) -> Result<Vec<ReviewItem>, String> {
+    let limit = limit.unwrap_or(10);
+    let by_computed = by_computed_importance.unwrap_or(false);
+    state.run_db("get_review_queue", move |db| {
+        let queue = db.get_review_queue(limit, by_computed)?;
+        Ok(queue.into_iter()
+            .map(|(thought, days_since_referenced, score)| ReviewItem { thought, days_since_referenced, score })
+            .collect())
+    }).await
 }
 
-// Session-forge integration
+/// Mark a thought as revisited so it drops out of the review queue
 #[tauri::command]
-fn get_forge_available() -> bool {
-    session_forge::is_available()
+fn mark_reviewed(state: tauri::State<AppState>, id: String) -> Result<(), String> {
+    if state.db().mark_reviewed(&id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("NotFound: thought {}", id))
+    }
 }
 
+/// Pin a thought in place: layout leaves it where it is and its importance doesn't decay
 #[tauri::command]
-fn get_forge_context(query: String) -> Result<session_forge::ForgeContext, String> {
-    session_forge::search_forge_context(&query)
+fn set_thought_pinned(state: tauri::State<AppState>, id: String, pinned: bool) -> Result<(), String> {
+    if state.db().set_thought_pinned(&id, pinned).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("NotFound: thought {}", id))
+    }
 }
 
-// Get available monitors
+/// Lock a thought against bulk updates and deletes, importance recomputes, forge re-imports
+/// and duplicate reinforcement
 #[tauri::command]
-fn get_monitors() -> Vec<wallpaper::MonitorInfo> {
-    wallpaper::get_monitors()
+fn set_thought_locked(state: tauri::State<AppState>, id: String, locked: bool) -> Result<(), String> {
+    if state.db().set_thought_locked(&id, locked).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("NotFound: thought {}", id))
+    }
 }
 
-// Enter wallpaper mode - embed window behind desktop icons (all monitors)
 #[tauri::command]
-fn enter_wallpaper_mode(window: tauri::Window) -> Result<(), String> {
-    #[cfg(windows)]
-    {
-        use tauri::Manager;
+fn set_thought_favorite(state: tauri::State<AppState>, id: String, favorite: bool) -> Result<(), String> {
+    if state.db().set_thought_favorite(&id, favorite).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("NotFound: thought {}", id))
+    }
+}
 
-        // Get the native window handle
-        let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+// Non-dry-run bulk deletes refuse to touch more thoughts than this (overridable via the bulk_delete_max setting)
+const DEFAULT_BULK_DELETE_MAX: usize = 100;
+// Contents shown in a dry run's confirmation
+const BULK_SAMPLE_SIZE: usize = 10;
 
-        wallpaper::set_as_wallpaper(hwnd.0 as isize)
+fn check_filter(filter: &ThoughtFilter) -> Result<(), String> {
+    let f = filter;
+    let empty = f.category.is_none() && f.created_before.is_none() && f.created_after.is_none()
+        && f.min_importance.is_none() && f.max_importance.is_none()
+        && f.content.as_deref().is_none_or(|c| c.trim().is_empty()) && f.role.is_none();
+    if empty {
+        return Err("Invalid: filter must set at least one condition".to_string());
     }
+    Ok(())
+}
 
-    #[cfg(not(windows))]
-    {
-        let _ = window;
-        Err("Wallpaper mode is only supported on Windows".to_string())
+/// Recategorize, shift the importance of, or archive every unlocked thought matching `filter`.
+/// Returns how many thoughts changed and how many were skipped for being locked.
+#[tauri::command]
+async fn bulk_update_thoughts(state: tauri::State<'_, AppState>, filter: ThoughtFilter, mut changes: ThoughtChanges) -> Result<BulkUpdateResult, String> {
+    check_filter(&filter)?;
+    if let Some(category) = &changes.category {
+        let category = database::normalize_category(category).ok_or("Category name cannot be empty")?;
+        if !state.db().category_exists(&category).map_err(|e| e.to_string())? {
+            return Err(format!("Category not found: {}", category));
+        }
+        changes.category = Some(category);
     }
+    state.run_db("bulk_update_thoughts", move |db| {
+        let skipped_locked = db.count_locked_matching(&filter)?;
+        let updated = db.bulk_update_thoughts(&filter, &changes)?;
+        Ok(BulkUpdateResult { updated, skipped_locked })
+    }).await
 }
 
-// Enter wallpaper mode on a specific monitor
+/// Delete every unlocked thought matching `filter`, with its connections and session links. A
+/// dry run only counts them and returns sample contents for a confirmation dialog. A real delete
+/// matching more than the bulk_delete_max setting (default 100) fails without deleting anything.
 #[tauri::command]
-fn enter_wallpaper_mode_on_monitor(window: tauri::Window, monitor_id: Option<u32>) -> Result<(), String> {
-    #[cfg(windows)]
-    {
-        use tauri::Manager;
-
-        let hwnd = window.hwnd().map_err(|e| e.to_string())?;
-
-        let mode = match monitor_id {
-            Some(id) => wallpaper::WallpaperDisplayMode::SingleMonitor(id),
-            None => wallpaper::WallpaperDisplayMode::AllMonitors,
-        };
-
-        wallpaper::set_as_wallpaper_on_monitors(hwnd.0 as isize, mode)
+async fn bulk_delete_thoughts(state: tauri::State<'_, AppState>, filter: ThoughtFilter, dry_run: bool) -> Result<BulkDeleteResult, String> {
+    check_filter(&filter)?;
+    let skipped_locked = state.db().count_locked_matching(&filter).map_err(|e| e.to_string())?;
+    if dry_run {
+        let (affected, samples) = state.run_db("bulk_delete_thoughts", move |db| {
+            db.preview_thought_filter(&filter, BULK_SAMPLE_SIZE)
+        }).await?;
+        return Ok(BulkDeleteResult { affected, samples, dry_run, skipped_locked });
     }
 
-    #[cfg(not(windows))]
-    {
-        let _ = (window, monitor_id);
-        Err("Wallpaper mode is only supported on Windows".to_string())
+    let max = state.db().setting_or("bulk_delete_max", DEFAULT_BULK_DELETE_MAX);
+    let affected = state.run_db("bulk_delete_thoughts", move |db| db.bulk_delete_thoughts(&filter, max)).await?;
+    if affected > max {
+        return Err(format!(
+            "Invalid: {} thoughts match, more than the bulk delete limit of {} (bulk_delete_max setting)",
+            affected, max
+        ));
     }
+    Ok(BulkDeleteResult { affected, samples: Vec::new(), dry_run, skipped_locked })
 }
 
-// Enter wallpaper mode with specific bounds (most reliable - no re-enumeration)
+/// Favorite thoughts, most important first
 #[tauri::command]
-fn enter_wallpaper_mode_with_bounds(window: tauri::Window, x: i32, y: i32, width: i32, height: i32) -> Result<(), String> {
-    #[cfg(windows)]
-    {
-        use tauri::Manager;
+fn get_favorite_thoughts(state: tauri::State<AppState>) -> Result<Vec<Thought>, String> {
+    state.db().get_favorite_thoughts().map_err(|e| e.to_string())
+}
 
-        let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+#[tauri::command]
+fn get_connection_by_id(state: tauri::State<AppState>, id: String) -> Result<Connection, String> {
+    state.db().get_connection_by_id(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("NotFound: connection {}", id))
+}
 
-        wallpaper::set_as_wallpaper_with_bounds(hwnd.0 as isize, x, y, width, height)
+#[tauri::command]
+fn add_thought(state: tauri::State<AppState>, mut thought: Thought) -> Result<(), String> {
+    let db = state.db();
+    for note in validation::thought(db, &mut thought)? {
+        tracing::info!("add_thought {}: {}", thought.id, note);
     }
+    state.local_writes.record([&thought.id]);
+    db.insert_thought(&thought).map_err(|e| e.to_string())
+}
 
-    #[cfg(not(windows))]
-    {
-        let _ = (window, x, y, width, height);
-        Err("Wallpaper mode is only supported on Windows".to_string())
+/// Add many thoughts in one transaction; any whose id is already taken is skipped
+#[tauri::command]
+fn add_thoughts(state: tauri::State<AppState>, mut thoughts: Vec<Thought>) -> Result<BatchInsert, String> {
+    let db = state.db();
+    for thought in &mut thoughts {
+        let notes = validation::thought(db, thought).map_err(|e| format!("{} (thought {})", e, thought.id))?;
+        for note in notes {
+            tracing::info!("add_thoughts {}: {}", thought.id, note);
+        }
     }
+    state.local_writes.record(thoughts.iter().map(|t| &t.id));
+    db.insert_thoughts_batch(&thoughts).map_err(|e| e.to_string())
 }
 
-// Exit wallpaper mode - restore normal window
 #[tauri::command]
-fn exit_wallpaper_mode(window: tauri::Window) -> Result<(), String> {
-    #[cfg(windows)]
-    {
-        use tauri::Manager;
+fn add_connection(state: tauri::State<AppState>, mut connection: Connection) -> Result<(), String> {
+    if !database::CONNECTION_KINDS.contains(&connection.kind.as_str()) {
+        return Err(format!("Unknown connection kind: {}", connection.kind));
+    }
+    if let Some(note) = validation::connection(&mut connection)? {
+        tracing::info!("add_connection {}: {}", connection.id, note);
+    }
+    let db = state.db();
+    state.local_writes.record([&connection.id]);
+    db.insert_connection(&connection).map_err(|e| e.to_string())
+}
 
-        // Get the native window handle
-        let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+/// Every thought that mentions a URL, with its URLs, newest first
+#[tauri::command]
+async fn get_thoughts_with_links(state: tauri::State<'_, AppState>) -> Result<Vec<ThoughtLinks>, String> {
+    state.run_db("get_thoughts_with_links", |db| db.get_thoughts_with_links(None)).await
+}
 
-        wallpaper::restore_from_wallpaper(hwnd.0 as isize)
-    }
+/// Thoughts with a URL containing `url_substring` (e.g. a domain), with all their URLs
+#[tauri::command]
+async fn get_thoughts_for_url(state: tauri::State<'_, AppState>, url_substring: String) -> Result<Vec<ThoughtLinks>, String> {
+    state.run_db("get_thoughts_for_url", move |db| db.get_thoughts_with_links(Some(&url_substring))).await
+}
 
-    #[cfg(not(windows))]
-    {
-        Err("Wallpaper mode is only supported on Windows".to_string())
-    }
+// Default and largest number of results get_similar_thoughts returns
+const DEFAULT_SIMILAR_LIMIT: usize = 10;
+const MAX_SIMILAR_LIMIT: usize = 100;
+
+/// Other thoughts like this one that aren't already connected to it, most alike first.
+/// Weak matches are left out, so there may be fewer than `limit`.
+#[tauri::command]
+async fn get_similar_thoughts(state: tauri::State<'_, AppState>, id: String, limit: Option<usize>) -> Result<Vec<SimilarThought>, String> {
+    let limit = limit.unwrap_or(DEFAULT_SIMILAR_LIMIT).clamp(1, MAX_SIMILAR_LIMIT);
+    let lookup_id = id.clone();
+    let similar = state.run_db("get_similar_thoughts", move |db| {
+        if db.get_thought_by_id(&lookup_id)?.is_none() {
+            return Ok(None);
+        }
+        db.get_similar_thoughts(&lookup_id, limit).map(Some)
+    }).await?;
+
+    similar.ok_or_else(|| format!("NotFound: thought {}", id))
 }
 
-fn main() {
-    // Check if running as MCP server (via --mcp flag)
-    let args: Vec<String> = std::env::args().collect();
-    if args.contains(&"--mcp".to_string()) {
-        // Run as MCP server (stdio mode)
-        mcp_server::run_mcp_server();
-        return;
-    }
+/// Thoughts containing code (a fenced or indented block), optionally only in `language`
+/// (e.g. "rust"), newest first
+#[tauri::command]
+async fn get_thoughts_with_code(state: tauri::State<'_, AppState>, language: Option<String>) -> Result<Vec<Thought>, String> {
+    state.run_db("get_thoughts_with_code", move |db| db.get_thoughts_with_code(language.as_deref())).await
+}
 
-    // Initialize database
-    let db = Database::new().expect("Failed to initialize database");
-    
-    // Run as Tauri application
-    tauri::Builder::default()
-        .manage(AppState {
-            db: Mutex::new(db),
-        })
-        .plugin(tauri_plugin_shell::init())
-        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
-        .invoke_handler(tauri::generate_handler![
+#[tauri::command]
+async fn get_connections_by_kind(state: tauri::State<'_, AppState>, kind: String) -> Result<Vec<Connection>, String> {
+    state.run_db("get_connections_by_kind", move |db| db.get_connections_by_kind(&kind)).await
+}
+
+/// Remove connections weaker than `below_strength`, optionally only of one kind or only
+/// automatically created ones
+#[tauri::command]
+async fn prune_connections(state: tauri::State<'_, AppState>, kind: Option<String>, below_strength: f64, auto_only: Option<bool>) -> Result<usize, String> {
+    let auto_only = auto_only.unwrap_or(false);
+    state.run_db("prune_connections", move |db| db.prune_connections(kind.as_deref(), below_strength, auto_only)).await
+}
+
+/// Thoughts with more than `threshold` connections (default: the max_connections_per_thought
+/// setting), most connected first
+#[tauri::command]
+async fn get_overconnected_thoughts(state: tauri::State<'_, AppState>, threshold: Option<i64>) -> Result<Vec<OverconnectedThought>, String> {
+    let threshold = threshold.unwrap_or_else(|| auto_connect::AutoConnectConfig::load(state.db()).max_connections as i64);
+    state.run_db("get_overconnected_thoughts", move |db| db.get_overconnected_thoughts(threshold)).await
+}
+
+/// Weaken connections by time since they were last reinforced; parameters default to the
+/// connection_decay_* settings
+#[tauri::command]
+async fn decay_connection_strength(state: tauri::State<'_, AppState>, half_life_days: Option<f64>, floor: Option<f64>) -> Result<maintenance::ConnectionDecay, String> {
+    let (default_half_life, default_floor) = maintenance::decay_settings(state.db());
+    let half_life_days = half_life_days.unwrap_or(default_half_life);
+    let floor = floor.unwrap_or(default_floor);
+    maintenance::check_decay(half_life_days, floor)?;
+    state.run_db("decay_connection_strength", move |db| maintenance::decay_connections(db, half_life_days, floor)).await
+}
+
+// Default and largest number of suggestions suggest_connections returns
+const DEFAULT_SUGGESTION_LIMIT: usize = 20;
+const MAX_SUGGESTION_LIMIT: usize = 200;
+
+/// Find new pairs of similar, unconnected thoughts to suggest connecting, and return the
+/// pending suggestions, strongest first
+#[tauri::command]
+async fn suggest_connections(state: tauri::State<'_, AppState>, limit: Option<usize>) -> Result<Vec<ConnectionSuggestion>, String> {
+    let limit = limit.unwrap_or(DEFAULT_SUGGESTION_LIMIT).clamp(1, MAX_SUGGESTION_LIMIT);
+    state.run_db("suggest_connections", move |db| suggestions::suggest_connections(db, limit)).await
+}
+
+/// Create the suggested connection; returns it
+#[tauri::command]
+fn accept_suggestion(state: tauri::State<AppState>, id: String) -> Result<Connection, String> {
+    state.db().accept_suggestion(&id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("NotFound: pending suggestion {}", id))
+}
+
+/// Dismiss a suggestion; the pair won't be suggested again
+#[tauri::command]
+fn reject_suggestion(state: tauri::State<AppState>, id: String) -> Result<(), String> {
+    if state.db().reject_suggestion(&id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("NotFound: pending suggestion {}", id))
+    }
+}
+
+/// "Invalid" unless `role` is user, assistant, or system
+fn check_role(role: &str) -> Result<(), String> {
+    if database::THOUGHT_ROLES.contains(&role) {
+        Ok(())
+    } else {
+        Err(format!("Invalid: role '{}' (expected user, assistant, or system)", role))
+    }
+}
+
+#[tauri::command]
+async fn search_thoughts(state: tauri::State<'_, AppState>, query: String, by_computed_importance: Option<bool>, role: Option<String>) -> Result<Vec<Thought>, String> {
+    if let Some(role) = &role {
+        check_role(role)?;
+    }
+    let by_computed = by_computed_importance.unwrap_or(false);
+    state.run_db("search_thoughts", move |db| db.search_thoughts_by(&query, by_computed, role.as_deref())).await
+}
+
+/// search_thoughts with where the query matched in each thought: UTF-16 offsets for
+/// highlighting in the UI and a snippet with the matches marked
+#[tauri::command]
+async fn search_thoughts_detailed(state: tauri::State<'_, AppState>, query: String, by_computed_importance: Option<bool>, role: Option<String>) -> Result<Vec<search::SearchHit>, String> {
+    if let Some(role) = &role {
+        check_role(role)?;
+    }
+    let by_computed = by_computed_importance.unwrap_or(false);
+    state.run_db("search_thoughts_detailed", move |db| search::search_thoughts_detailed(db, &query, by_computed, role.as_deref())).await
+}
+
+// Most results search_everything returns
+const SEARCH_EVERYTHING_LIMIT: usize = 50;
+
+/// Thoughts, sessions (title or summary), and connection reasons matching `query`, best first
+#[tauri::command]
+async fn search_everything(state: tauri::State<'_, AppState>, query: String) -> Result<Vec<search::SearchResult>, String> {
+    state.run_db("search_everything", move |db| search::search_everything(db, &query, SEARCH_EVERYTHING_LIMIT)).await
+}
+
+/// Thoughts from one role (user, assistant, or system), newest first, capped like get_all_thoughts
+#[tauri::command]
+async fn get_thoughts_by_role(state: tauri::State<'_, AppState>, role: String) -> Result<Vec<Thought>, String> {
+    check_role(&role)?;
+    state.run_db("get_thoughts_by_role", move |db| {
+        let max = db.setting_or("max_all_thoughts", DEFAULT_MAX_ALL_THOUGHTS);
+        db.get_thoughts_by_role(&role, max)
+    }).await
+}
+
+/// Re-derive computed_importance from stated importance, connection degree, and references.
+/// Returns the `limit` thoughts whose rank moved the most.
+#[tauri::command]
+async fn recompute_importance(state: tauri::State<'_, AppState>, weights: Option<ImportanceWeights>, limit: Option<usize>) -> Result<Vec<ImportanceChange>, String> {
+    let weights = weights.unwrap_or_default();
+    if weights.original < 0.0 || weights.degree < 0.0 || weights.references < 0.0
+        || weights.original + weights.degree + weights.references <= 0.0
+    {
+        return Err("Importance weights must be non-negative and sum to more than 0".to_string());
+    }
+    let limit = limit.unwrap_or(20);
+    state.run_db("recompute_importance", move |db| {
+        let mut changes = db.recompute_importance(&weights)?;
+        changes.truncate(limit);
+        Ok(changes)
+    }).await
+}
+
+#[tauri::command]
+async fn search_connections(state: tauri::State<'_, AppState>, query: String, limit: Option<i64>) -> Result<Vec<ConnectionMatch>, String> {
+    let limit = limit.unwrap_or(20);
+    state.run_db("search_connections", move |db| db.search_connections(&query, limit)).await
+}
+
+#[tauri::command]
+fn get_all_sessions(state: tauri::State<AppState>) -> Result<Vec<Session>, String> {
+    let db = state.db();
+    db.get_all_sessions().map_err(|e| e.to_string())
+}
+
+/// Jot a thought down directly (no assistant involved); runs the normal auto-connection pass
+#[tauri::command]
+async fn quick_capture(state: tauri::State<'_, AppState>, content: String, category: Option<String>) -> Result<Thought, String> {
+    let content = content.trim().to_string();
+    if content.is_empty() {
+        return Err("Nothing to capture".to_string());
+    }
+    let category = category.as_deref().and_then(database::normalize_category).unwrap_or_else(|| "other".to_string());
+
+    state.run_db("quick_capture", move |db| {
+        let mut random = db.position_random(&content, &category);
+        let (x, y, z) = db.clear_position(db.generate_position_for_category(&category, &mut random));
+        let now = chrono::Utc::now().to_rfc3339();
+        let thought = Thought {
+            id: uuid::Uuid::new_v4().to_string(),
+            content,
+            role: Some("user".to_string()),
+            category,
+            importance: 0.5,
+            position_x: x,
+            position_y: y,
+            position_z: z,
+            created_at: now.clone(),
+            last_referenced: now,
+            pinned: false,
+            favorite: false,
+            archived: false,
+        };
+        db.insert_thought(&thought)?;
+        auto_connect::auto_connect(db, &thought, &auto_connect::AutoConnectConfig::load(db))?;
+        embeddings::embed_new_thought(db, &thought);
+        Ok(thought)
+    }).await
+}
+
+// Quick-capture window: a small always-on-top webview opened by the global shortcut
+const CAPTURE_WINDOW: &str = "capture";
+const DEFAULT_CAPTURE_SHORTCUT: &str = "CommandOrControl+Shift+Space";
+
+/// Show the quick-capture window, creating it if needed. It is a separate top-level
+/// window so it works even while the main window is embedded as the wallpaper.
+fn open_quick_capture(app: &tauri::AppHandle) -> Result<(), String> {
+    use tauri::Manager;
+
+    if let Some(window) = app.get_webview_window(CAPTURE_WINDOW) {
+        window.show().map_err(|e| e.to_string())?;
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    tauri::WebviewWindowBuilder::new(app, CAPTURE_WINDOW, tauri::WebviewUrl::App("index.html#capture".into()))
+        .title("Quick Capture")
+        .inner_size(520.0, 180.0)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .focused(true)
+        .build()
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn open_quick_capture_window(app: tauri::AppHandle) -> Result<(), String> {
+    open_quick_capture(&app)
+}
+
+/// Close the capture window (ESC or after submitting)
+#[tauri::command]
+fn close_quick_capture(app: tauri::AppHandle) -> Result<(), String> {
+    use tauri::Manager;
+
+    match app.get_webview_window(CAPTURE_WINDOW) {
+        Some(window) => window.destroy().map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+// Deep links (the-mind://...) are forwarded to the frontend as this event
+const NAVIGATE_EVENT: &str = "mind://navigate";
+
+/// Bring the main window forward (a second launch or a deep link should surface the running app)
+fn focus_main_window(app: &tauri::AppHandle) {
+    use tauri::Manager;
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Parse and validate a deep link, then emit it (or queue it until the frontend is ready)
+fn handle_deep_link(app: &tauri::AppHandle, url: &str) {
+    use tauri::{Emitter, Manager};
+
+    let state = app.state::<AppState>();
+    let target = match navigation::parse_deep_link(url).and_then(|t| navigation::validate_target(state.db(), t)) {
+        Ok(target) => target,
+        Err(e) => {
+            tracing::warn!("Ignoring deep link: {}", e);
+            return;
+        }
+    };
+
+    focus_main_window(app);
+    state.navigation.dispatch(target, |t| {
+        if let Err(e) = app.emit(NAVIGATE_EVENT, t) {
+            tracing::warn!("Failed to emit {}: {}", NAVIGATE_EVENT, e);
+        }
+    });
+}
+
+/// Called by the frontend once its mind://navigate listener is attached; flushes queued deep links
+/// and re-enters the last wallpaper mode if auto_wallpaper is on
+#[tauri::command]
+fn frontend_ready(app: tauri::AppHandle, state: tauri::State<AppState>) -> usize {
+    use tauri::Emitter;
+
+    let flushed = state.navigation.mark_ready(|t| {
+        if let Err(e) = app.emit(NAVIGATE_EVENT, t) {
+            tracing::warn!("Failed to emit {}: {}", NAVIGATE_EVENT, e);
+        }
+    });
+    auto_start_wallpaper(&app);
+    flushed
+}
+
+/// One session with its thoughts, connections, and stats
+#[tauri::command]
+async fn get_session_detail(state: tauri::State<'_, AppState>, id: String) -> Result<SessionDetail, String> {
+    let lookup_id = id.clone();
+    state.run_db("get_session_detail", move |db| db.get_session_detail(&lookup_id)).await?
+        .ok_or_else(|| format!("NotFound: session {}", id))
+}
+
+/// Rename a session or replace its summary (omitted fields are kept)
+#[tauri::command]
+fn update_session(state: tauri::State<AppState>, id: String, title: Option<String>, summary: Option<String>) -> Result<(), String> {
+    if title.as_deref().is_some_and(|t| t.trim().is_empty()) {
+        return Err("Session title cannot be empty".to_string());
+    }
+    if state.db().update_session(&id, title.as_deref().map(str::trim), summary.as_deref()).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("NotFound: session {}", id))
+    }
+}
+
+/// Delete a session; optionally also its linked thoughts and their connections.
+/// Returns the number of thoughts deleted.
+#[tauri::command]
+async fn delete_session(state: tauri::State<'_, AppState>, id: String, delete_linked_thoughts: bool) -> Result<usize, String> {
+    let lookup_id = id.clone();
+    state.run_db("delete_session", move |db| db.delete_session(&lookup_id, delete_linked_thoughts)).await?
+        .ok_or_else(|| format!("NotFound: session {}", id))
+}
+
+/// Turn a pasted Human:/Assistant: conversation into a session of thoughts
+#[tauri::command]
+async fn import_transcript(state: tauri::State<'_, AppState>, text: String, title: String) -> Result<TranscriptImport, String> {
+    state.run_db("import_transcript", move |db| transcript::import_transcript(db, &text, &title, progress::Progress::none())).await
+}
+
+#[tauri::command]
+fn get_app_mode(state: tauri::State<AppState>) -> AppMode {
+    state.mode.clone()
+}
+
+#[tauri::command]
+fn get_diagnostics(state: tauri::State<AppState>) -> diagnostics::Diagnostics {
+    diagnostics::collect(state.db(), state.wallpaper.is_active())
+}
+
+#[tauri::command]
+fn set_log_level(level: String) -> Result<(), String> {
+    logging::set_level(&level)
+}
+
+#[tauri::command]
+fn get_db_version(state: tauri::State<AppState>) -> Result<DbVersion, String> {
+    state.db().get_db_version().map_err(|e| e.to_string())
+}
+
+/// Changes since a DbVersion change_seq (or an earlier delta's seq), for applying in place
+/// of a full reload
+#[tauri::command]
+async fn get_graph_delta(state: tauri::State<'_, AppState>, since_seq: i64) -> Result<GraphDelta, String> {
+    state.run_db("get_graph_delta", move |db| db.get_graph_delta(since_seq)).await
+}
+
+/// Stream the whole graph as mind://graph-batch events (thoughts, then connections),
+/// ending with mind://graph-complete. Returns the stream id carried by every event.
+#[tauri::command]
+fn start_graph_stream(app: tauri::AppHandle, state: tauri::State<AppState>, batch_size: usize) -> Result<u64, String> {
+    graph_stream::start(app, Arc::clone(&state.db), batch_size)
+}
+
+/// Stop the running graph stream; false if none was running
+#[tauri::command]
+fn cancel_graph_stream(state: tauri::State<AppState>) -> bool {
+    state.graph_streams.cancel()
+}
+
+#[tauri::command]
+fn get_thought_count(state: tauri::State<AppState>) -> Result<i64, String> {
+    let db = state.db();
+    db.get_thought_count().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_thoughts_near(state: tauri::State<'_, AppState>, x: f64, y: f64, z: f64, radius: f64, limit: i64) -> Result<Vec<Thought>, String> {
+    state.run_db("get_thoughts_near", move |db| {
+        let thoughts = db.get_thoughts_near(x, y, z, radius, limit)?;
+        let ids: Vec<String> = thoughts.iter().map(|t| t.id.clone()).collect();
+        db.reinforce_connections(&ids)?;
+        Ok(thoughts)
+    }).await
+}
+
+#[tauri::command]
+async fn get_connections_for_thoughts(state: tauri::State<'_, AppState>, ids: Vec<String>) -> Result<Vec<Connection>, String> {
+    state.run_db("get_connections_for_thoughts", move |db| db.get_connections_for_thoughts(&ids)).await
+}
+
+#[tauri::command]
+fn get_all_clusters(state: tauri::State<AppState>) -> Result<Vec<Cluster>, String> {
+    let db = state.db();
+    db.get_all_clusters().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_cluster_detail(state: tauri::State<'_, AppState>, id: String) -> Result<ClusterDetail, String> {
+    let lookup_id = id.clone();
+    let detail = state.run_db("get_cluster_detail", move |db| db.get_cluster_detail(&lookup_id)).await?;
+    detail.ok_or_else(|| format!("NotFound: cluster {}", id))
+}
+
+#[tauri::command]
+async fn get_cluster_children(state: tauri::State<'_, AppState>, id: String) -> Result<Vec<Cluster>, String> {
+    let lookup_id = id.clone();
+    let (exists, children) = state.run_db("get_cluster_children", move |db| {
+        Ok((db.cluster_exists(&lookup_id)?, db.get_cluster_children(&lookup_id)?))
+    }).await?;
+    if !exists {
+        return Err(format!("NotFound: cluster {}", id));
+    }
+    Ok(children)
+}
+
+/// The top-level cluster nearest a point in the mind space, e.g. the camera position
+#[tauri::command]
+async fn get_nearest_cluster(state: tauri::State<'_, AppState>, x: f64, y: f64, z: f64) -> Result<ClusterMatch, String> {
+    let nearest = state.run_db("get_nearest_cluster", move |db| db.get_nearest_cluster(x, y, z)).await?;
+    nearest.ok_or_else(|| "NotFound: no clusters".to_string())
+}
+
+/// The cluster a thought belongs to, or the nearest one if it hasn't been placed yet
+#[tauri::command]
+async fn get_cluster_for_thought(state: tauri::State<'_, AppState>, thought_id: String) -> Result<ClusterMatch, String> {
+    let lookup_id = thought_id.clone();
+    let found = state.run_db("get_cluster_for_thought", move |db| {
+        if db.get_thought_by_id(&lookup_id)?.is_none() {
+            return Ok(None);
+        }
+        db.get_cluster_for_thought(&lookup_id).map(Some)
+    }).await?;
+    match found {
+        None => Err(format!("NotFound: thought {}", thought_id)),
+        Some(None) => Err("NotFound: no clusters".to_string()),
+        Some(Some(found)) => Ok(found),
+    }
+}
+
+/// Make an empty cluster to move thoughts into with set_thought_cluster
+#[tauri::command]
+fn create_cluster(state: tauri::State<AppState>, name: String, category: String) -> Result<Cluster, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Invalid: cluster name cannot be empty".to_string());
+    }
+    let db = state.db();
+    if !db.category_exists(&category).map_err(|e| e.to_string())? {
+        return Err(format!("NotFound: category {}", category));
+    }
+    db.create_cluster(name, &category).map_err(|e| e.to_string())
+}
+
+/// Put a thought in a cluster and keep it there through compute_clusters
+#[tauri::command]
+fn set_thought_cluster(state: tauri::State<AppState>, thought_id: String, cluster_id: String) -> Result<(), String> {
+    let db = state.db();
+    if !db.cluster_exists(&cluster_id).map_err(|e| e.to_string())? {
+        return Err(format!("NotFound: cluster {}", cluster_id));
+    }
+    if !db.get_cluster_children(&cluster_id).map_err(|e| e.to_string())?.is_empty() {
+        return Err(format!("Invalid: cluster {} has sub-clusters; choose one of them", cluster_id));
+    }
+    if db.set_thought_cluster(&thought_id, &cluster_id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("NotFound: thought {}", thought_id))
+    }
+}
+
+/// Let compute_clusters move a thought set with set_thought_cluster again
+#[tauri::command]
+fn clear_thought_cluster_lock(state: tauri::State<AppState>, thought_id: String) -> Result<(), String> {
+    if state.db().clear_thought_cluster_lock(&thought_id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("NotFound: thought {}", thought_id))
+    }
+}
+
+#[tauri::command]
+async fn recompute_clusters(state: tauri::State<'_, AppState>) -> Result<Vec<Cluster>, String> {
+    state.run_db("recompute_clusters", |db| db.compute_clusters()).await
+}
+
+/// Per-day activity for the last `days` days, grouped in the caller's timezone
+#[tauri::command]
+async fn get_activity_timeline(state: tauri::State<'_, AppState>, days: i64, tz_offset_minutes: i32) -> Result<Vec<DayActivity>, String> {
+    state.run_db("get_activity_timeline", move |db| db.get_activity(days, tz_offset_minutes)).await
+}
+
+/// Totals, the per-category figures of get_category_stats and the top keywords in one call
+#[tauri::command]
+async fn get_stats(state: tauri::State<'_, AppState>) -> Result<MindStats, String> {
+    state.run_db("get_stats", |db| {
+        let stopwords = utils::KeywordConfig::load(db).extra_stopwords;
+        db.get_stats(&stopwords)
+    }).await
+}
+
+#[tauri::command]
+async fn get_category_stats(state: tauri::State<'_, AppState>) -> Result<Vec<CategoryStats>, String> {
+    state.run_db("get_category_stats", |db| db.get_category_stats()).await
+}
+
+/// The keywords in the most thoughts, for a word cloud: optionally only thoughts in one category
+/// and created since a YYYY-MM-DD date or RFC3339 time. User stopwords are left out.
+#[tauri::command]
+async fn get_top_keywords(
+    state: tauri::State<'_, AppState>,
+    limit: Option<i64>,
+    category: Option<String>,
+    since: Option<String>,
+) -> Result<Vec<TopKeyword>, String> {
+    let since = since.as_deref().map(utils::parse_since).transpose()?;
+    let limit = limit.unwrap_or(50);
+    state.run_db("get_top_keywords", move |db| {
+        let stopwords = utils::KeywordConfig::load(db).extra_stopwords;
+        db.get_top_keywords(limit, category.as_deref(), since.as_deref(), &stopwords)
+    }).await
+}
+
+/// The keywords and phrases found in the most thoughts (i.e. carrying the least weight), most common first
+#[tauri::command]
+async fn get_keyword_stats(state: tauri::State<'_, AppState>, limit: Option<i64>) -> Result<Vec<KeywordStat>, String> {
+    let limit = limit.unwrap_or(50);
+    state.run_db("get_keyword_stats", move |db| {
+        let (total, stats) = db.get_keyword_stats(limit)?;
+        Ok(stats.into_iter()
+            .map(|(keyword, doc_count, phrase)| KeywordStat {
+                idf: auto_connect::idf(doc_count, total),
+                keyword,
+                doc_count,
+                phrase,
+            })
+            .collect())
+    }).await
+}
+
+/// Version, commit and build time of this build, plus the schema versions, for bug reports
+#[tauri::command]
+fn get_app_info(state: tauri::State<AppState>) -> app_info::AppInfo {
+    app_info::collect(state.db())
+}
+
+/// Markdown digest of one day's thoughts, connections, and sessions (date defaults to today)
+#[tauri::command]
+async fn get_daily_digest(state: tauri::State<'_, AppState>, date: Option<String>, tz_offset_minutes: Option<i32>) -> Result<String, String> {
+    let db = Arc::clone(&state.db);
+    tauri::async_runtime::spawn_blocking(move || {
+        digest::daily_digest(&db, date.as_deref(), tz_offset_minutes.unwrap_or(0))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Markdown report of one session (see report::session_report), also written to `path` when given
+#[tauri::command]
+async fn generate_session_report(state: tauri::State<'_, AppState>, session_id: String, path: Option<String>) -> Result<String, String> {
+    let db = Arc::clone(&state.db);
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = report::session_report(&db, &session_id)?;
+        if let Some(path) = path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            std::fs::write(path, &report).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Thought counts per category for the last `buckets` weeks or months
+#[tauri::command]
+async fn get_category_trend(state: tauri::State<'_, AppState>, bucket: String, buckets: i64) -> Result<Vec<CategoryTrendBucket>, String> {
+    let bucket = database::TimeBucket::parse(&bucket)
+        .ok_or_else(|| format!("Invalid bucket: {} (expected day, week, or month)", bucket))?;
+    state.run_db("get_category_trend", move |db| db.get_category_trend(bucket, buckets)).await
+}
+
+/// Thoughts between two local YYYY-MM-DD dates (inclusive) in day, week, or month buckets,
+/// for the timeline strip. Empty buckets are included.
+#[tauri::command]
+async fn get_timeline(
+    state: tauri::State<'_, AppState>,
+    bucket: String,
+    from: String,
+    to: String,
+    tz_offset_minutes: Option<i32>,
+) -> Result<Vec<TimelineBucket>, String> {
+    let bucket = database::TimeBucket::parse(&bucket)
+        .ok_or_else(|| format!("Invalid bucket: {} (expected day, week, or month)", bucket))?;
+    let date = |value: &str, name: &str| {
+        chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("Invalid: {} '{}' (expected YYYY-MM-DD)", name, value))
+    };
+    let (from, to) = (date(&from, "from")?, date(&to, "to")?);
+    if from > to {
+        return Err(format!("Invalid: from {} is after to {}", from, to));
+    }
+    let mut buckets = 0;
+    let mut start = bucket.start_of(from);
+    while start <= to {
+        buckets += 1;
+        if buckets > database::MAX_TIMELINE_BUCKETS {
+            return Err(format!("Invalid: range spans more than {} buckets", database::MAX_TIMELINE_BUCKETS));
+        }
+        start = bucket.next(start);
+    }
+    state.run_db("get_timeline", move |db| db.get_timeline(bucket, from, to, tz_offset_minutes.unwrap_or(0))).await
+}
+
+#[tauri::command]
+fn list_categories(state: tauri::State<AppState>) -> Result<Vec<Category>, String> {
+    state.db().list_categories().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn create_category(
+    state: tauri::State<AppState>,
+    name: String,
+    color: Option<String>,
+    icon: Option<String>,
+) -> Result<Category, String> {
+    let name = database::normalize_category(&name).ok_or("Category name cannot be empty")?;
+    let color = color.as_deref().map(validation::hex_color).transpose()?;
+    let icon = icon.as_deref().map(validation::icon).transpose()?;
+    let db = state.db();
+    if db.category_exists(&name).map_err(|e| e.to_string())? {
+        return Err(format!("Category already exists: {}", name));
+    }
+    db.create_category(&name, color.as_deref(), icon.as_deref()).map_err(|e| e.to_string())
+}
+
+/// Set the color ("#RRGGBB" or "#RGB") and icon a category is drawn with; None clears one
+#[tauri::command]
+fn set_category_appearance(
+    state: tauri::State<AppState>,
+    name: String,
+    color: Option<String>,
+    icon: Option<String>,
+) -> Result<Category, String> {
+    let color = color.as_deref().map(validation::hex_color).transpose()?;
+    let icon = icon.as_deref().map(validation::icon).transpose()?;
+    let db = state.db();
+    if !db.set_category_appearance(&name, color.as_deref(), icon.as_deref()).map_err(|e| e.to_string())? {
+        return Err(format!("NotFound: category {}", name));
+    }
+    db.list_categories()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| format!("NotFound: category {}", name))
+}
+
+/// Rename a category everywhere it is used; renaming onto an existing category merges them
+#[tauri::command]
+async fn rename_category(state: tauri::State<'_, AppState>, old: String, new: String) -> Result<usize, String> {
+    let new = database::normalize_category(&new).ok_or("Category name cannot be empty")?;
+    if !state.db().category_exists(&old).map_err(|e| e.to_string())? {
+        return Err(format!("Category not found: {}", old));
+    }
+    state.run_db("rename_category", move |db| db.rename_category(&old, &new)).await
+}
+
+/// Delete a category, moving its thoughts to `reassign_to`
+#[tauri::command]
+async fn delete_category(state: tauri::State<'_, AppState>, name: String, reassign_to: String) -> Result<usize, String> {
+    let reassign_to = database::normalize_category(&reassign_to).ok_or("Reassignment category cannot be empty")?;
+    if name == reassign_to {
+        return Err("Cannot reassign a category's thoughts to itself".to_string());
+    }
+    if !state.db().category_exists(&name).map_err(|e| e.to_string())? {
+        return Err(format!("Category not found: {}", name));
+    }
+    state.run_db("delete_category", move |db| db.rename_category(&name, &reassign_to)).await
+}
+
+const VIEW_SAVED_EVENT: &str = "mind://view-saved";
+const VIEW_DELETED_EVENT: &str = "mind://view-deleted";
+
+fn view_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Invalid: view name cannot be empty".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// Tell every window (including a wallpaper instance) the view list changed
+fn emit_view_event<S: Serialize + Clone>(app: &tauri::AppHandle, event: &str, payload: S) {
+    use tauri::Emitter;
+
+    if let Err(e) = app.emit(event, payload) {
+        tracing::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+/// Saved camera views, by name
+#[tauri::command]
+fn list_views(state: tauri::State<AppState>) -> Result<Vec<CameraView>, String> {
+    state.db().list_views().map_err(|e| e.to_string())
+}
+
+/// Save the camera under `name`. An existing view with that name is only replaced when
+/// `overwrite` is true; otherwise this fails with a Conflict so the UI can ask first.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+fn save_view(
+    app: tauri::AppHandle,
+    state: tauri::State<AppState>,
+    name: String,
+    position_x: f64,
+    position_y: f64,
+    position_z: f64,
+    target_x: f64,
+    target_y: f64,
+    target_z: f64,
+    zoom: Option<f64>,
+    overwrite: Option<bool>,
+) -> Result<CameraView, String> {
+    let name = view_name(&name)?;
+    let db = state.db();
+    if !overwrite.unwrap_or(false) && db.find_view_by_name(&name).map_err(|e| e.to_string())?.is_some() {
+        return Err(format!("Conflict: view '{}' already exists", name));
+    }
+
+    let view = CameraView {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        position_x,
+        position_y,
+        position_z,
+        target_x,
+        target_y,
+        target_z,
+        zoom: zoom.unwrap_or(1.0),
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let saved = db.save_view(&view).map_err(|e| e.to_string())?;
+    emit_view_event(&app, VIEW_SAVED_EVENT, saved.clone());
+    Ok(saved)
+}
+
+/// Rename a view; fails with a Conflict if another view already has the name
+#[tauri::command]
+fn rename_view(app: tauri::AppHandle, state: tauri::State<AppState>, id: String, name: String) -> Result<CameraView, String> {
+    let name = view_name(&name)?;
+    let db = state.db();
+    if let Some(existing) = db.find_view_by_name(&name).map_err(|e| e.to_string())? {
+        if existing.id != id {
+            return Err(format!("Conflict: view '{}' already exists", existing.name));
+        }
+    }
+    if !db.rename_view(&id, &name).map_err(|e| e.to_string())? {
+        return Err(format!("NotFound: view {}", id));
+    }
+    let renamed = db.find_view_by_name(&name).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("NotFound: view {}", id))?;
+    emit_view_event(&app, VIEW_SAVED_EVENT, renamed.clone());
+    Ok(renamed)
+}
+
+#[tauri::command]
+fn delete_view(app: tauri::AppHandle, state: tauri::State<AppState>, id: String) -> Result<(), String> {
+    if !state.db().delete_view(&id).map_err(|e| e.to_string())? {
+        return Err(format!("NotFound: view {}", id));
+    }
+    emit_view_event(&app, VIEW_DELETED_EVENT, ViewDeleted { id });
+    Ok(())
+}
+
+/// Saved queries, by name
+#[tauri::command]
+fn list_queries(state: tauri::State<AppState>) -> Result<Vec<SavedQuery>, String> {
+    state.db().list_saved_queries().map_err(|e| e.to_string())
+}
+
+/// Save a query definition under `name`. An existing query with that name is only replaced
+/// when `overwrite` is true; otherwise this fails with a Conflict.
+#[tauri::command]
+fn save_query(state: tauri::State<AppState>, name: String, definition: QueryDefinition, overwrite: Option<bool>) -> Result<SavedQuery, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Invalid: query name cannot be empty".to_string());
+    }
+    let definition = validation::query(definition)?;
+    let db = state.db();
+    if !overwrite.unwrap_or(false) && db.find_saved_query_by_name(&name).map_err(|e| e.to_string())?.is_some() {
+        return Err(format!("Conflict: query '{}' already exists", name));
+    }
+    let query = SavedQuery {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        definition,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    db.save_query(&query).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn delete_query(state: tauri::State<AppState>, id: String) -> Result<(), String> {
+    if state.db().delete_saved_query(&id).map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err(format!("NotFound: query {}", id))
+    }
+}
+
+/// Thoughts matching a saved query, most important first
+#[tauri::command]
+async fn run_query(state: tauri::State<'_, AppState>, id: String) -> Result<Vec<Thought>, String> {
+    let query = state.db().get_saved_query(&id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("NotFound: query {}", id))?;
+    let limit = query.definition.limit.unwrap_or(validation::DEFAULT_QUERY_LIMIT);
+    state.run_db("run_query", move |db| db.run_query(&query.definition, limit)).await
+}
+
+/// Thoughts matching a definition that isn't saved, most important first
+#[tauri::command]
+async fn run_adhoc_query(state: tauri::State<'_, AppState>, definition: QueryDefinition) -> Result<Vec<Thought>, String> {
+    let definition = validation::query(definition)?;
+    let limit = definition.limit.unwrap_or(validation::DEFAULT_QUERY_LIMIT);
+    state.run_db("run_adhoc_query", move |db| db.run_query(&definition, limit)).await
+}
+
+/// Start wiping all data: returns what would be deleted and a single-use token that
+/// reset_mind_confirm accepts for the next 60 seconds
+#[tauri::command]
+fn reset_mind(state: tauri::State<AppState>) -> Result<ResetPreview, String> {
+    let counts = state.db().table_counts().map_err(|e| e.to_string())?;
+    Ok(ResetPreview {
+        token: state.reset_tokens.issue(),
+        expires_in_secs: reset::TOKEN_TTL.as_secs(),
+        counts,
+    })
+}
+
+/// Delete every row of every table (default categories are re-seeded). Settings survive
+/// unless include_settings is true. With backup_first, a copy of the database is saved to a
+/// backups folder next to it and the reset is abandoned if that fails.
+#[tauri::command]
+async fn reset_mind_confirm(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    token: String,
+    backup_first: Option<bool>,
+    include_settings: Option<bool>,
+) -> Result<ResetResult, String> {
+    use tauri::Emitter;
+
+    state.reset_tokens.redeem(&token)?;
+
+    let backup = if backup_first.unwrap_or(false) {
+        let path = reset::backup_path(state.db().path());
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        Some(path)
+    } else {
+        None
+    };
+    let backup_path = backup.as_ref().map(|p| p.to_string_lossy().into_owned());
+    let deleted = state.run_db("reset_mind_confirm", move |db| {
+        let _exclusive = maintenance::exclusive();
+        if let Some(path) = &backup {
+            db.backup_to(path)?;
+        }
+        db.wipe_all(include_settings.unwrap_or(false))
+    }).await?;
+
+    if let Err(e) = app.emit(reset::RESET_EVENT, ()) {
+        tracing::warn!("Failed to emit {}: {}", reset::RESET_EVENT, e);
+    }
+    Ok(ResetResult { deleted, backup_path })
+}
+
+/// Checkpoint the WAL, refresh planner statistics, prune old change log entries and, with
+/// `full` (the default), VACUUM when enough of the file is free space
+#[tauri::command]
+async fn run_maintenance(state: tauri::State<'_, AppState>, full: Option<bool>) -> Result<maintenance::MaintenanceReport, String> {
+    let db = Arc::clone(&state.db);
+    let full = full.unwrap_or(true);
+    tauri::async_runtime::spawn_blocking(move || maintenance::run_maintenance(&db, full))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Rewrite the database encrypted with SQLCipher, using the key in the OS keychain (created
+/// there if missing). Every connection switches to the encrypted file; the MCP server has to
+/// be stopped first since it holds the file open.
+#[tauri::command]
+async fn encrypt_existing_database(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    if !encryption::supported() {
+        return Err("Unsupported: this build of The Mind was made without database encryption".to_string());
+    }
+    let db = Arc::clone(&state.db);
+    tauri::async_runtime::spawn_blocking(move || {
+        let _guard = maintenance::exclusive();
+        if db.is_encrypted() {
+            return Err("Invalid: the database is already encrypted".to_string());
+        }
+        let key = encryption::keychain_key(true)?;
+        db.reencrypt(Some(key)).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Rewrite an encrypted database as a plain SQLite file. The keychain entry is left in place.
+#[tauri::command]
+async fn decrypt_database(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    if !encryption::supported() {
+        return Err("Unsupported: this build of The Mind was made without database encryption".to_string());
+    }
+    let db = Arc::clone(&state.db);
+    tauri::async_runtime::spawn_blocking(move || {
+        let _guard = maintenance::exclusive();
+        if !db.is_encrypted() {
+            return Err("Invalid: the database isn't encrypted".to_string());
+        }
+        db.reencrypt(None).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn get_setting(state: tauri::State<AppState>, key: String) -> Result<Option<String>, String> {
+    state.db().get_setting(&key).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_setting(state: tauri::State<AppState>, key: String, value: String) -> Result<(), String> {
+    state.db().set_setting(&key, &value).map_err(|e| e.to_string())
+}
+
+// Session-forge integration
+/// Resolved forge directory and which of its files were found
+#[tauri::command]
+fn get_forge_available() -> session_forge::ForgeStatus {
+    session_forge::status()
+}
+
+#[tauri::command]
+fn get_forge_dir() -> Option<String> {
+    session_forge::forge_dir()
+}
+
+/// Point forge features at another directory; an empty path restores the default
+#[tauri::command]
+async fn set_forge_dir(state: tauri::State<'_, AppState>, path: String) -> Result<session_forge::ForgeStatus, String> {
+    let db = Arc::clone(&state.db);
+    tauri::async_runtime::spawn_blocking(move || session_forge::set_forge_dir(&db, &path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn get_forge_context(
+    state: tauri::State<'_, AppState>,
+    query: String,
+    limit: Option<usize>,
+    min_score: Option<f64>,
+    project: Option<String>,
+    after: Option<String>,
+    before: Option<String>,
+) -> Result<session_forge::ForgeContext, String> {
+    let keywords = state.run_db("get_forge_context", |db| Ok(utils::KeywordConfig::load(db))).await?;
+    let filter = session_forge::ForgeFilter { project, after, before };
+    session_forge::search_forge_context(
+        &query,
+        limit.unwrap_or(session_forge::DEFAULT_FORGE_LIMIT),
+        min_score,
+        &filter,
+        &keywords,
+    )
+}
+
+/// Forge entries related to a thought's content
+#[tauri::command]
+async fn get_forge_context_for_thought(
+    state: tauri::State<'_, AppState>,
+    thought_id: String,
+    limit: Option<usize>,
+) -> Result<session_forge::ForgeContext, String> {
+    let id = thought_id.clone();
+    let (thought, keywords) = state.run_db("get_forge_context_for_thought", move |db| {
+        Ok((db.get_thought_by_id(&id)?, utils::KeywordConfig::load(db)))
+    }).await?;
+    let thought = thought.ok_or_else(|| format!("NotFound: thought {}", thought_id))?;
+    session_forge::search_forge_context(
+        &thought.content,
+        limit.unwrap_or(session_forge::DEFAULT_FORGE_LIMIT),
+        None,
+        &session_forge::ForgeFilter::default(),
+        &keywords,
+    )
+}
+
+/// Turn session-forge entries (journal, decisions, dead-ends) into thoughts; empty kinds = all
+#[tauri::command]
+async fn import_forge(state: tauri::State<'_, AppState>, kinds: Vec<String>) -> Result<ForgeImport, String> {
+    let db = Arc::clone(&state.db);
+    tauri::async_runtime::spawn_blocking(move || forge_import::import_forge(&db, &kinds, progress::Progress::none()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Embed up to `limit` thoughts (default 500) that have no vector from the configured model yet.
+/// Returns how many were embedded; errors if embeddings are off or the endpoint fails.
+#[tauri::command]
+async fn backfill_embeddings(state: tauri::State<'_, AppState>, limit: Option<usize>) -> Result<usize, String> {
+    let db = Arc::clone(&state.db);
+    tauri::async_runtime::spawn_blocking(move || {
+        let provider = embeddings::HttpProvider::from_settings(&db)
+            .ok_or_else(|| format!("Embeddings are disabled (set {} to true)", embeddings::SETTING_ENABLED))?;
+        embeddings::backfill(&db, &provider, limit.unwrap_or(500))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+// Get available monitors
+#[tauri::command]
+fn get_monitors() -> Vec<wallpaper::MonitorInfo> {
+    wallpaper::get_monitors()
+}
+
+/// Flash each monitor's number on it for `duration_ms` (default 2s) so ids can be matched to screens.
+/// Async so the overlay windows aren't created on the main thread (that deadlocks on Windows).
+#[tauri::command]
+async fn identify_monitors(app: tauri::AppHandle, duration_ms: Option<u64>) -> Result<usize, String> {
+    monitor_identify::identify(&app, duration_ms.unwrap_or(monitor_identify::DEFAULT_DURATION_MS))
+}
+
+/// The native handle wallpaper.rs works with (0 off Windows, where every call is a stub)
+fn window_handle(window: &tauri::Window) -> Result<isize, String> {
+    #[cfg(windows)]
+    {
+        let hwnd = window.hwnd().map_err(|e| e.to_string())?;
+        Ok(hwnd.0 as isize)
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = window;
+        Ok(0)
+    }
+}
+
+/// Embed `window` as described by `config`, then record it as the active wallpaper along
+/// with where it was, so exiting (or quitting) can put it back. The saved interactivity
+/// preference is reapplied.
+fn embed_as_wallpaper(window: &tauri::Window, state: &AppState, config: &wallpaper::WallpaperConfig) -> Result<(), String> {
+    let hwnd = window_handle(window)?;
+    let original = wallpaper::window_placement(hwnd);
+    config.embed(hwnd)?;
+    state.wallpaper.activate(hwnd, original);
+
+    if !state.db().setting_or(wallpaper::SETTING_INTERACTIVE, true) {
+        if let Err(e) = wallpaper::set_click_through(hwnd, true) {
+            tracing::warn!("Failed to make wallpaper click-through: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Embed and remember the configuration for auto-start (not remembered read-only)
+fn enter_wallpaper(window: &tauri::Window, state: &AppState, config: wallpaper::WallpaperConfig) -> Result<(), String> {
+    embed_as_wallpaper(window, state, &config)?;
+    save_wallpaper(state.db(), &wallpaper::SavedWallpaper { active: true, config });
+    Ok(())
+}
+
+fn saved_wallpaper(db: &Database) -> Option<wallpaper::SavedWallpaper> {
+    let json = db.get_setting(wallpaper::SETTING_LAST).ok().flatten()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Failing to persist only costs auto-start, so it's logged rather than failing the command
+fn save_wallpaper(db: &Database, saved: &wallpaper::SavedWallpaper) {
+    // Wallpaper mode still works read-only; it just isn't remembered
+    if db.is_read_only() {
+        return;
+    }
+    let result = serde_json::to_string(saved)
+        .map_err(|e| e.to_string())
+        .and_then(|json| db.set_setting(wallpaper::SETTING_LAST, &json).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        tracing::warn!("Failed to save wallpaper configuration: {}", e);
+    }
+}
+
+// Enter wallpaper mode - embed window behind desktop icons (all monitors)
+#[tauri::command]
+fn enter_wallpaper_mode(window: tauri::Window, state: tauri::State<AppState>) -> Result<(), String> {
+    enter_wallpaper(&window, &state, wallpaper::WallpaperConfig::AllMonitors)
+}
+
+// Enter wallpaper mode on a specific monitor
+#[tauri::command]
+fn enter_wallpaper_mode_on_monitor(window: tauri::Window, state: tauri::State<AppState>, monitor_id: Option<u32>) -> Result<(), String> {
+    let config = match monitor_id {
+        Some(id) => wallpaper::WallpaperConfig::Monitor { id },
+        None => wallpaper::WallpaperConfig::AllMonitors,
+    };
+    enter_wallpaper(&window, &state, config)
+}
+
+// Enter wallpaper mode spanning a set of monitors (their bounding rectangle)
+#[tauri::command]
+fn enter_wallpaper_mode_on_monitor_set(window: tauri::Window, state: tauri::State<AppState>, monitor_ids: Vec<u32>) -> Result<(), String> {
+    // Checked here too so an empty or unknown set is reported the same way on every platform
+    wallpaper::monitor_set_bounds(&monitor_ids)?;
+    enter_wallpaper(&window, &state, wallpaper::WallpaperConfig::Monitors { ids: monitor_ids })
+}
+
+// Enter wallpaper mode with specific bounds (most reliable - no re-enumeration)
+#[tauri::command]
+fn enter_wallpaper_mode_with_bounds(window: tauri::Window, state: tauri::State<AppState>, x: i32, y: i32, width: i32, height: i32) -> Result<(), String> {
+    enter_wallpaper(&window, &state, wallpaper::WallpaperConfig::Bounds { x, y, width, height })
+}
+
+// Exit wallpaper mode - restore normal window
+#[tauri::command]
+fn exit_wallpaper_mode(window: tauri::Window, state: tauri::State<AppState>) -> Result<(), String> {
+    let original = state.wallpaper.take().and_then(|(_, original)| original);
+    // Keep the configuration for next time, but don't auto-start into a mode the user left
+    if let Some(saved) = saved_wallpaper(state.db()) {
+        save_wallpaper(state.db(), &wallpaper::SavedWallpaper { active: false, ..saved });
+    }
+    wallpaper::restore_from_wallpaper(window_handle(&window)?, original)
+}
+
+/// With auto_wallpaper on, re-enter the wallpaper mode that was active when the app last
+/// quit. If its monitors are gone, the primary monitor is used instead (without replacing
+/// the saved configuration, so it applies again once the monitors are back).
+fn auto_start_wallpaper(app: &tauri::AppHandle) {
+    use tauri::{Emitter, Manager};
+
+    let state = app.state::<AppState>();
+    if state.wallpaper.is_active() || !state.db().setting_or(wallpaper::SETTING_AUTO, false) {
+        return;
+    }
+    let Some(saved) = saved_wallpaper(state.db()).filter(|s| s.active) else {
+        return;
+    };
+    let Some(window) = app.get_webview_window("main") else {
+        return;
+    };
+
+    let monitors = wallpaper::get_monitors();
+    let (config, fallback_from) = if saved.config.fits(&monitors) {
+        (saved.config, None)
+    } else {
+        let Some(primary) = monitors.iter().find(|m| m.is_primary).or(monitors.first()) else {
+            tracing::info!("Wallpaper auto-start skipped: no monitors found");
+            return;
+        };
+        (wallpaper::WallpaperConfig::Monitor { id: primary.id }, Some(saved.config))
+    };
+
+    if let Err(e) = embed_as_wallpaper(&window.as_ref().window(), &state, &config) {
+        tracing::warn!("Wallpaper auto-start failed: {}", e);
+        return;
+    }
+    let event = WallpaperAutoStarted { config, fallback_from };
+    if let Err(e) = app.emit(wallpaper::AUTO_STARTED_EVENT, event) {
+        tracing::warn!("Failed to emit {}: {}", wallpaper::AUTO_STARTED_EVENT, e);
+    }
+}
+
+/// Let the embedded wallpaper take mouse input (orbit the camera) or pass clicks through to
+/// the desktop. Remembered for the next time wallpaper mode is entered.
+#[tauri::command]
+fn set_wallpaper_interactive(state: tauri::State<AppState>, enabled: bool) -> Result<(), String> {
+    let hwnd = state.wallpaper.active_window()
+        .ok_or_else(|| "Invalid: wallpaper mode is not active".to_string())?;
+    wallpaper::set_click_through(hwnd, !enabled)?;
+    state.db().set_setting(wallpaper::SETTING_INTERACTIVE, &enabled.to_string()).map_err(|e| e.to_string())
+}
+
+/// Whether the main window is currently embedded as the wallpaper
+#[tauri::command]
+fn is_wallpaper_active(state: tauri::State<AppState>) -> bool {
+    state.wallpaper.is_active()
+}
+
+/// Whether the app launches at login. An entry left pointing at another executable
+/// (the app moved or was reinstalled elsewhere) is rewritten and reported in repaired_from.
+#[tauri::command]
+fn get_autostart() -> Result<autostart::AutostartStatus, String> {
+    autostart::status()
+}
+
+/// Launch the app (hidden, with --minimized) at login, or stop doing so
+#[tauri::command]
+fn set_autostart(enabled: bool) -> Result<autostart::AutostartStatus, String> {
+    autostart::set_enabled(enabled)
+}
+
+/// Whether the machine runs on AC or battery, and the battery level, so the frontend can
+/// scale down rendering when unplugged. Reports "unknown" where this can't be read.
+#[tauri::command]
+fn get_power_status() -> power::PowerStatus {
+    power::power_status()
+}
+
+/// Un-embed the wallpaper window, if any, and repaint the desktop. Runs when the main
+/// window closes and again on exit; only the first call finds anything to do.
+fn release_wallpaper(app: &tauri::AppHandle) {
+    use tauri::Manager;
+
+    let Some((hwnd, original)) = app.state::<AppState>().wallpaper.take() else {
+        return;
+    };
+    if let Err(e) = wallpaper::restore_from_wallpaper(hwnd, original) {
+        tracing::warn!("Failed to restore window from wallpaper mode: {}", e);
+    }
+    wallpaper::refresh_desktop();
+}
+
+const READ_ONLY_FLAG: &str = "--read-only";
+const READ_WRITE_FLAG: &str = "--read-write";
+const DIAGNOSE_FLAG: &str = "--diagnose";
+const VERSION_FLAG: &str = "--version";
+const MCP_LOG_FLAG: &str = "--mcp-log";
+const MCP_REPLAY_FLAG: &str = "--mcp-replay";
+
+// Commands that write to the database; refused up front in read-only mode. Entering and
+// leaving wallpaper mode stay available: read-only, the configuration just isn't saved.
+const MUTATING_COMMANDS: &[&str] = &[
+    "mark_reviewed", "set_thought_pinned", "set_thought_favorite", "bulk_update_thoughts",
+    "bulk_delete_thoughts", "add_thought", "add_thoughts", "add_connection", "prune_connections", "reset_mind",
+    "reset_mind_confirm", "recompute_importance", "update_session", "delete_session",
+    "import_transcript", "quick_capture", "recompute_clusters", "create_category",
+    "rename_category", "delete_category", "set_setting", "set_forge_dir", "import_forge",
+    "backfill_embeddings", "set_wallpaper_interactive", "save_view", "rename_view", "delete_view",
+    "suggest_connections", "accept_suggestion", "reject_suggestion", "decay_connection_strength",
+    "create_cluster", "set_thought_cluster", "clear_thought_cluster_lock", "run_maintenance",
+    "encrypt_existing_database", "decrypt_database", "add_attachment", "remove_attachment",
+    "set_category_appearance", "set_thought_locked", "save_query", "delete_query",
+];
+
+/// Wrap the generated command handler so mutating commands fail with a ReadOnly error
+/// instead of reaching SQLite
+fn reject_writes_when_read_only<R: tauri::Runtime>(
+    read_only: bool,
+    handler: impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if read_only && MUTATING_COMMANDS.contains(&invoke.message.command()) {
+            let error = format!("ReadOnly: {} is unavailable while The Mind is open read-only", invoke.message.command());
+            diagnostics::record_error(invoke.message.command(), &error);
+            invoke.resolver.reject(error);
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+/// --diagnose: print the diagnostics report as JSON. The database is opened read-only, so
+/// this is safe to run next to a running instance (whose wallpaper state it can't see).
+fn print_diagnostics() {
+    let report = Database::open(true)
+        .map_err(|e| format!("Failed to open database at {}: {}", Database::default_path().display(), e))
+        .and_then(|db| {
+            serde_json::to_string_pretty(&diagnostics::collect(&db, false)).map_err(|e| e.to_string())
+        });
+    match report {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The argument following `flag`, as in `--mcp-log <path>`
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).map(String::as_str)
+}
+
+/// --mcp-replay: re-run a recorded MCP session against a scratch database and print where the
+/// responses differ. Exits non-zero on any divergence.
+fn replay_mcp_log(path: &str) {
+    let report = match mcp_log::replay_in_temp_db(std::path::Path::new(path)) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    for divergence in &report.divergences {
+        println!("line {}: {}", divergence.line, divergence.method);
+        println!("  expected: {}", divergence.expected.as_deref().unwrap_or("(no response)"));
+        println!("  actual:   {}", divergence.actual.as_deref().unwrap_or("(no response)"));
+    }
+    println!("Replayed {} request(s), {} divergence(s)", report.replayed, report.divergences.len());
+    if !report.divergences.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|a| a == VERSION_FLAG) {
+        println!("{}", app_info::version_line());
+        return;
+    }
+    // Check if running as MCP server (via --mcp flag)
+    let mcp = args.contains(&"--mcp".to_string());
+    // Release builds have no console, so stderr only helps during development (and never in MCP mode)
+    logging::init(cfg!(debug_assertions) && !mcp);
+    let mode = AppMode::from_args(&args);
+    if args.iter().any(|a| a == DIAGNOSE_FLAG) {
+        print_diagnostics();
+        return;
+    }
+    if let Some(path) = flag_value(&args, MCP_REPLAY_FLAG) {
+        replay_mcp_log(path);
+        return;
+    }
+    if mcp {
+        // Run as MCP server (stdio mode)
+        mcp_server::run_mcp_server(mode.read_only, flag_value(&args, MCP_LOG_FLAG).map(std::path::PathBuf::from));
+        return;
+    }
+
+    install_panic_hook();
+
+    // Launched at login (see autostart.rs): stay out of the way until opened
+    let start_minimized = args.iter().any(|a| a == autostart::MINIMIZED_FLAG);
+
+    // Initialize database
+    let db = Database::open(mode.read_only).expect("Failed to initialize database");
+    let read_only = mode.read_only;
+    session_forge::load_configured_dir(&db);
+
+    // Global quick-capture hotkey (overridable via the quick_capture_shortcut setting)
+    let capture_shortcut: String = db.setting_or("quick_capture_shortcut", DEFAULT_CAPTURE_SHORTCUT.to_string());
+    let shortcuts = tauri_plugin_global_shortcut::Builder::new()
+        .with_shortcut(capture_shortcut.as_str())
+        .or_else(|e| {
+            tracing::warn!("Invalid quick capture shortcut {:?} ({}); using default", capture_shortcut, e);
+            tauri_plugin_global_shortcut::Builder::new().with_shortcut(DEFAULT_CAPTURE_SHORTCUT)
+        })
+        .expect("default quick capture shortcut is valid")
+        .with_handler(|app, _shortcut, event| {
+            if event.state() == tauri_plugin_global_shortcut::ShortcutState::Pressed {
+                if let Err(e) = open_quick_capture(app) {
+                    tracing::warn!("Failed to open quick capture: {}", e);
+                }
+            }
+        });
+    
+    // Run as Tauri application
+    tauri::Builder::default()
+        .manage(AppState {
+            db: Arc::new(db),
+            navigation: navigation::NavigationQueue::default(),
+            graph_streams: graph_stream::GraphStreams::default(),
+            local_writes: graph_events::LocalWrites::default(),
+            wallpaper: wallpaper::WallpaperState::default(),
+            reset_tokens: reset::ResetTokens::default(),
+            mode,
+        })
+        // Must be registered first: a second launch (e.g. from a the-mind:// link) hands its
+        // arguments to this instance, and the deep-link feature forwards the URL to on_open_url
+        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| focus_main_window(app)))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(tauri_plugin_shell::init())
+        .plugin(shortcuts.build())
+        .plugin(tauri_plugin_notification::init())
+        .setup(move |app| {
+            use tauri::{Emitter, Manager};
+            use tauri_plugin_deep_link::DeepLinkExt;
+
+            // The webview still loads, so wallpaper auto-start works; a second launch shows the window
+            if start_minimized {
+                if let Some(window) = app.get_webview_window("main") {
+                    if let Err(e) = window.hide() {
+                        tracing::warn!("Failed to start minimized: {}", e);
+                    }
+                }
+            }
+
+            notifier::spawn_watcher(app.handle().clone(), Arc::clone(&app.state::<AppState>().db));
+            graph_events::spawn_watcher(app.handle().clone(), Arc::clone(&app.state::<AppState>().db));
+
+            let db = Arc::clone(&app.state::<AppState>().db);
+            tauri::async_runtime::spawn_blocking(move || maintenance::run_startup(&db));
+
+            // Runs even without a forge directory so one created later is noticed
+            let handle = app.handle().clone();
+            app.manage(forge_watch::spawn(move |file| {
+                if let Err(e) = handle.emit(forge_watch::CHANGED_EVENT, ForgeChanged { file: file.to_string() }) {
+                    tracing::warn!("Failed to emit {}: {}", forge_watch::CHANGED_EVENT, e);
+                }
+            }));
+
+            let power_handle = app.handle().clone();
+            let presentation_handle = app.handle().clone();
+            app.manage(power::spawn(
+                move |status| {
+                    if let Err(e) = power_handle.emit(power::POWER_CHANGED_EVENT, status) {
+                        tracing::warn!("Failed to emit {}: {}", power::POWER_CHANGED_EVENT, e);
+                    }
+                },
+                move |active| {
+                    if let Err(e) = presentation_handle.emit(power::PRESENTATION_EVENT, PresentationMode { active }) {
+                        tracing::warn!("Failed to emit {}: {}", power::PRESENTATION_EVENT, e);
+                    }
+                },
+            ));
+
+            // Installed builds register the scheme at bundle time; this covers dev runs and AppImages
+            #[cfg(any(windows, target_os = "linux"))]
+            if let Err(e) = app.deep_link().register_all() {
+                tracing::warn!("Failed to register {}:// links: {}", navigation::DEEP_LINK_SCHEME, e);
+            }
+
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                for url in event.urls() {
+                    handle_deep_link(&handle, url.as_str());
+                }
+            });
+
+            // Cold start from a link: queued until the frontend calls frontend_ready
+            if let Ok(Some(urls)) = app.deep_link().get_current() {
+                for url in urls {
+                    handle_deep_link(app.handle(), url.as_str());
+                }
+            }
+
+            Ok(())
+        })
+        .invoke_handler(reject_writes_when_read_only(read_only, tauri::generate_handler![
             get_all_thoughts,
             get_all_connections,
+            get_thoughts_by_ids,
+            get_connections_by_ids,
+            get_graph_snapshot,
+            get_thoughts_page,
+            get_connections_page,
+            get_thought_by_id,
+            add_attachment,
+            remove_attachment,
+            get_attachments,
+            get_connection_by_id,
+            get_review_queue,
+            mark_reviewed,
+            set_thought_pinned,
+            set_thought_locked,
+            set_thought_favorite,
+            get_favorite_thoughts,
+            bulk_update_thoughts,
+            bulk_delete_thoughts,
             add_thought,
+            add_thoughts,
             add_connection,
+            get_connections_by_kind,
+            get_thoughts_with_links,
+            get_thoughts_for_url,
+            get_thoughts_with_code,
+            get_similar_thoughts,
+            prune_connections,
+            decay_connection_strength,
+            get_overconnected_thoughts,
+            suggest_connections,
+            accept_suggestion,
+            reject_suggestion,
             search_thoughts,
+            search_thoughts_detailed,
+            search_everything,
+            get_thoughts_by_role,
+            reset_mind,
+            reset_mind_confirm,
+            recompute_importance,
+            search_connections,
             get_all_sessions,
+            get_session_detail,
+            update_session,
+            delete_session,
+            import_transcript,
+            quick_capture,
+            open_quick_capture_window,
+            close_quick_capture,
+            frontend_ready,
             get_db_version,
+            get_graph_delta,
+            run_maintenance,
+            encrypt_existing_database,
+            decrypt_database,
+            start_graph_stream,
+            cancel_graph_stream,
             get_thought_count,
             get_thoughts_near,
             get_connections_for_thoughts,
             get_all_clusters,
+            get_cluster_detail,
+            get_cluster_children,
+            get_nearest_cluster,
+            get_cluster_for_thought,
+            create_cluster,
+            set_thought_cluster,
+            clear_thought_cluster_lock,
             recompute_clusters,
+            get_activity_timeline,
+            get_stats,
+            get_category_stats,
+            get_keyword_stats,
+            get_top_keywords,
+            get_category_trend,
+            get_timeline,
+            get_daily_digest,
+            get_app_info,
+            generate_session_report,
+            list_categories,
+            create_category,
+            set_category_appearance,
+            rename_category,
+            delete_category,
+            get_setting,
+            set_setting,
             get_forge_available,
+            get_forge_dir,
+            set_forge_dir,
             get_forge_context,
+            get_forge_context_for_thought,
+            import_forge,
+            backfill_embeddings,
             get_monitors,
+            identify_monitors,
             enter_wallpaper_mode,
             enter_wallpaper_mode_on_monitor,
+            enter_wallpaper_mode_on_monitor_set,
             enter_wallpaper_mode_with_bounds,
             exit_wallpaper_mode,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+            is_wallpaper_active,
+            set_wallpaper_interactive,
+            get_power_status,
+            get_autostart,
+            set_autostart,
+            list_views,
+            save_view,
+            rename_view,
+            delete_view,
+            list_queries,
+            save_query,
+            delete_query,
+            run_query,
+            run_adhoc_query,
+            get_app_mode,
+            get_diagnostics,
+            set_log_level,
+        ]))
+        // Un-embed before the window goes away; destroyed while parented to WorkerW it can
+        // leave a black rectangle on the desktop
+        .on_window_event(|window, event| {
+            use tauri::Manager;
+
+            if window.label() == "main" && matches!(event, tauri::WindowEvent::CloseRequested { .. }) {
+                release_wallpaper(window.app_handle());
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            use tauri::Manager;
+
+            if matches!(event, tauri::RunEvent::Exit) {
+                release_wallpaper(app);
+                app.state::<power::PowerWatcher>().stop();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::testing::{poison_writer, thought, TempDb};
+
+    fn app_state(db: Database) -> AppState {
+        AppState {
+            db: Arc::new(db),
+            navigation: navigation::NavigationQueue::default(),
+            graph_streams: graph_stream::GraphStreams::default(),
+            local_writes: graph_events::LocalWrites::default(),
+            wallpaper: wallpaper::WallpaperState::default(),
+            reset_tokens: reset::ResetTokens::default(),
+            mode: AppMode { read_only: false, read_only_reason: None },
+        }
+    }
+
+    #[test]
+    fn the_database_still_works_after_a_panic_holding_its_lock() {
+        let db = TempDb::new();
+        let state = app_state(Database::open_at(db.path(), false).unwrap());
+
+        poison_writer(state.db());
+
+        state.db().insert_thought(&thought("a", "after the panic", "idea", 0.5, "2026-03-01T12:00:00+00:00")).unwrap();
+        assert_eq!(state.db().get_thought_by_id("a").unwrap().unwrap().content, "after the panic");
+        assert_eq!(state.db().get_thought_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn wallpaper_configuration_is_only_saved_read_write() {
+        let db = TempDb::new();
+        let read_only = Database::open_at(db.path(), true).unwrap();
+        let saved = wallpaper::SavedWallpaper { active: true, config: wallpaper::WallpaperConfig::AllMonitors };
+
+        save_wallpaper(&read_only, &saved);
+        assert!(saved_wallpaper(&db).is_none());
+
+        save_wallpaper(&db, &saved);
+        assert!(saved_wallpaper(&db).is_some_and(|s| s.active));
+    }
 }
@@ -0,0 +1,203 @@
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use crate::database::Database;
+use crate::utils::lock_recovering;
+
+// Settings keys (also readable/writable through get_setting/set_setting)
+pub const SETTING_DECAY_ON_STARTUP: &str = "connection_decay_on_startup";
+pub const SETTING_HALF_LIFE_DAYS: &str = "connection_decay_half_life_days";
+pub const SETTING_FLOOR: &str = "connection_decay_floor";
+pub const SETTING_PRUNE_THRESHOLD: &str = "connection_prune_threshold";
+pub const SETTING_VACUUM_FREE_RATIO: &str = "maintenance_vacuum_free_ratio";
+
+// When run_maintenance last ran; the startup run waits a day between runs
+const MAINTENANCE_RUN_KEY: &str = "maintenance_last_run";
+const STARTUP_MAINTENANCE_INTERVAL_HOURS: i64 = 24;
+// VACUUM once at least this share of the file is free pages
+const DEFAULT_VACUUM_FREE_RATIO: f64 = 0.25;
+
+const DEFAULT_HALF_LIFE_DAYS: f64 = 90.0;
+const DEFAULT_FLOOR: f64 = 0.05;
+const DEFAULT_PRUNE_THRESHOLD: f64 = 0.1;
+
+// How long get_graph_delta can reach back; older change_log entries are dropped at launch
+const CHANGE_LOG_RETENTION_DAYS: i64 = 7;
+
+// Held by maintenance, imports and backups so none of them runs underneath another
+static MAINTENANCE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Wait until maintenance isn't running and keep it from starting until the guard drops
+pub fn exclusive() -> MutexGuard<'static, ()> {
+    lock_recovering(&MAINTENANCE_LOCK)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceStep {
+    pub name: String,
+    pub duration_ms: u64,
+    pub detail: String,
+}
+
+/// Result of run_maintenance
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceReport {
+    pub steps: Vec<MaintenanceStep>,
+    pub size_before_bytes: u64,
+    pub size_after_bytes: u64,
+    pub wal_before_bytes: u64,
+    pub wal_after_bytes: u64,
+    pub vacuumed: bool,
+}
+
+/// Result of decay_connection_strength
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionDecay {
+    /// Connections that weakened
+    pub decayed: usize,
+    /// Automatically created connections now under connection_prune_threshold, which
+    /// prune_connections with auto_only would remove
+    pub prune_candidates: i64,
+    pub prune_threshold: f64,
+}
+
+/// Decay parameters from settings: (half-life in days, floor)
+pub fn decay_settings(db: &Database) -> (f64, f64) {
+    (
+        db.setting_or(SETTING_HALF_LIFE_DAYS, DEFAULT_HALF_LIFE_DAYS),
+        db.setting_or(SETTING_FLOOR, DEFAULT_FLOOR),
+    )
+}
+
+/// "Invalid" unless the half-life is positive and the floor is within 0..1
+pub fn check_decay(half_life_days: f64, floor: f64) -> Result<(), String> {
+    if !(half_life_days.is_finite() && half_life_days > 0.0) {
+        return Err(format!("Invalid: half_life_days must be positive (got {})", half_life_days));
+    }
+    if !(0.0..=1.0).contains(&floor) {
+        return Err(format!("Invalid: floor must be between 0 and 1 (got {})", floor));
+    }
+    Ok(())
+}
+
+/// Weaken connections that haven't been reinforced lately and count the automatic ones
+/// that have become weak enough to prune
+pub fn decay_connections(db: &Database, half_life_days: f64, floor: f64) -> rusqlite::Result<ConnectionDecay> {
+    let decayed = db.decay_connection_strength(half_life_days, floor)?;
+    let prune_threshold = db.setting_or(SETTING_PRUNE_THRESHOLD, DEFAULT_PRUNE_THRESHOLD);
+    Ok(ConnectionDecay {
+        decayed,
+        prune_candidates: db.count_weak_auto_connections(prune_threshold)?,
+        prune_threshold,
+    })
+}
+
+fn file_sizes(path: &Path) -> (u64, u64) {
+    let size = |p: &Path| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    (size(path), size(Path::new(&wal)))
+}
+
+/// Prune the change log, checkpoint and truncate the WAL, and refresh planner statistics.
+/// `full` runs a complete ANALYZE and VACUUMs when free pages exceed
+/// maintenance_vacuum_free_ratio; otherwise only the cheap steps run. "Busy" while an import,
+/// backup or another maintenance run holds the lock.
+pub fn run_maintenance(db: &Database, full: bool) -> Result<MaintenanceReport, String> {
+    let _guard = match MAINTENANCE_LOCK.try_lock() {
+        Ok(guard) => guard,
+        Err(std::sync::TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => {
+            return Err("Busy: an import, backup or maintenance run is in progress".to_string());
+        }
+    };
+
+    let (size_before_bytes, wal_before_bytes) = file_sizes(db.path());
+    let mut steps = Vec::new();
+    let mut step = |name: &str, work: &mut dyn FnMut() -> rusqlite::Result<String>| -> Result<(), String> {
+        let started = Instant::now();
+        let detail = work().map_err(|e| format!("{} failed: {}", name, e))?;
+        steps.push(MaintenanceStep {
+            name: name.to_string(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            detail,
+        });
+        Ok(())
+    };
+
+    step("prune_change_log", &mut || {
+        db.prune_change_log(CHANGE_LOG_RETENTION_DAYS).map(|removed| format!("{} entries removed", removed))
+    })?;
+    step("wal_checkpoint", &mut || {
+        db.wal_checkpoint_truncate().map(|pending| format!("{} frames still pending", pending))
+    })?;
+    step("analyze", &mut || {
+        db.analyze(full).map(|_| if full { "full".to_string() } else { "optimize".to_string() })
+    })?;
+
+    let mut vacuumed = false;
+    if full {
+        let threshold = db.setting_or(SETTING_VACUUM_FREE_RATIO, DEFAULT_VACUUM_FREE_RATIO);
+        step("vacuum", &mut || {
+            let free = db.free_page_ratio()?;
+            if free < threshold {
+                return Ok(format!("skipped: {:.0}% free, below {:.0}%", free * 100.0, threshold * 100.0));
+            }
+            db.vacuum()?;
+            // In WAL mode the rebuilt pages land in the WAL; the file only shrinks once they're copied back
+            db.wal_checkpoint_truncate()?;
+            vacuumed = true;
+            Ok(format!("{:.0}% of pages were free", free * 100.0))
+        })?;
+    }
+
+    if let Err(e) = db.set_setting(MAINTENANCE_RUN_KEY, &Utc::now().to_rfc3339()) {
+        tracing::warn!("Failed to record maintenance run: {}", e);
+    }
+    let (size_after_bytes, wal_after_bytes) = file_sizes(db.path());
+    Ok(MaintenanceReport { steps, size_before_bytes, size_after_bytes, wal_before_bytes, wal_after_bytes, vacuumed })
+}
+
+/// Whether a day has passed since maintenance last ran
+fn maintenance_due(db: &Database) -> bool {
+    let last = db.get_setting(MAINTENANCE_RUN_KEY).ok().flatten()
+        .and_then(|at| DateTime::parse_from_rfc3339(&at).ok());
+    last.is_none_or(|at| Utc::now() - at.with_timezone(&Utc) >= Duration::hours(STARTUP_MAINTENANCE_INTERVAL_HOURS))
+}
+
+/// Housekeeping run once at launch: lightweight maintenance at most once a day, and
+/// connection decay with the configured half-life and floor when turned on in settings (off
+/// by default)
+pub fn run_startup(db: &Database) {
+    if db.is_read_only() {
+        return;
+    }
+    if maintenance_due(db) {
+        match run_maintenance(db, false) {
+            Ok(report) => tracing::info!(
+                "Startup maintenance: WAL {} -> {} bytes in {} ms",
+                report.wal_before_bytes,
+                report.wal_after_bytes,
+                report.steps.iter().map(|s| s.duration_ms).sum::<u64>()
+            ),
+            Err(e) => tracing::warn!("Startup maintenance skipped: {}", e),
+        }
+    }
+    if !db.setting_or(SETTING_DECAY_ON_STARTUP, false) {
+        return;
+    }
+    let (half_life_days, floor) = decay_settings(db);
+    if let Err(e) = check_decay(half_life_days, floor) {
+        tracing::warn!("Skipping connection decay: {}", e);
+        return;
+    }
+    match decay_connections(db, half_life_days, floor) {
+        Ok(result) => tracing::info!(
+            "Connection decay: {} weakened, {} automatic connection(s) below {} to prune",
+            result.decayed, result.prune_candidates, result.prune_threshold
+        ),
+        Err(e) => tracing::warn!("Connection decay failed: {}", e),
+    }
+}
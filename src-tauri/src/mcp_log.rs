@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+use crate::database::Database;
+use crate::mcp_server;
+
+/// One request/response exchange; an --mcp-log file holds one per line
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub at: String,
+    /// The request line exactly as received
+    pub request: String,
+    /// The response line exactly as sent; None for notifications and unparseable requests
+    pub response: Option<String>,
+    pub latency_ms: f64,
+}
+
+/// Appends exchanges to an --mcp-log file
+pub struct McpLog {
+    file: File,
+}
+
+impl McpLog {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(McpLog { file })
+    }
+
+    /// Append one exchange. A failed write is logged rather than passed on to the client.
+    pub fn record(&mut self, request: &str, response: Option<&str>, latency: Duration) {
+        let entry = LogEntry {
+            at: chrono::Utc::now().to_rfc3339(),
+            request: request.to_string(),
+            response: response.map(str::to_string),
+            latency_ms: latency.as_secs_f64() * 1000.0,
+        };
+        let result = serde_json::to_string(&entry)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(self.file, "{}", line).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            tracing::warn!("Failed to write MCP log entry: {}", e);
+        }
+    }
+}
+
+/// A replayed request whose response didn't match the recorded one
+#[derive(Debug)]
+pub struct Divergence {
+    /// 1-based line in the log file
+    pub line: usize,
+    /// The request method, plus the tool name for tools/call
+    pub method: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+/// Replay the log at `log_path` against a fresh database in a temp directory, which is
+/// removed afterwards
+pub fn replay_in_temp_db(log_path: &Path) -> Result<ReplayReport, String> {
+    let dir = std::env::temp_dir().join(format!("the-mind-replay-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let result = Database::open_at(dir.join("mind.db"), false)
+        .map_err(|e| format!("Failed to create replay database: {}", e))
+        .and_then(|db| replay(&db, log_path));
+    if let Err(e) = std::fs::remove_dir_all(&dir) {
+        tracing::warn!("Failed to remove replay database {}: {}", dir.display(), e);
+    }
+    result
+}
+
+/// Re-send every recorded request to `db` and compare the responses. IDs and RFC 3339
+/// timestamps differ from run to run, so both are masked before comparing, and IDs from
+/// the recording are swapped for the ones this replay created before a request is sent.
+pub fn replay(db: &Database, log_path: &Path) -> Result<ReplayReport, String> {
+    let file = File::open(log_path).map_err(|e| format!("Failed to open {}: {}", log_path.display(), e))?;
+    let session = mcp_server::McpSession::default();
+    let mut ids: HashMap<String, String> = HashMap::new();
+    let mut report = ReplayReport { replayed: 0, divergences: Vec::new() };
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: LogEntry = serde_json::from_str(&line)
+            .map_err(|e| format!("Invalid log entry on line {}: {}", index + 1, e))?;
+
+        let request = replace_ids(&entry.request, &ids);
+        let started = Instant::now();
+        let actual = mcp_server::handle_line(db, &session, &request);
+        tracing::debug!(line = index + 1, elapsed = ?started.elapsed(), recorded_ms = entry.latency_ms, "Replayed MCP request");
+        report.replayed += 1;
+
+        if let (Some(expected), Some(actual)) = (&entry.response, &actual) {
+            let (recorded, replayed) = (find_ids(expected), find_ids(actual));
+            if recorded.len() == replayed.len() {
+                ids.extend(recorded.into_iter().zip(replayed));
+            }
+        }
+        let matches = entry.response.as_deref().map(mask) == actual.as_deref().map(mask);
+        if !matches {
+            report.divergences.push(Divergence {
+                line: index + 1,
+                method: describe(&entry.request),
+                expected: entry.response,
+                actual,
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// "tools/call mind_log" for tool calls, otherwise the method
+fn describe(request: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(request) else {
+        return "(unparseable request)".to_string();
+    };
+    let method = value["method"].as_str().unwrap_or("(no method)");
+    match value.pointer("/params/name").and_then(Value::as_str) {
+        Some(tool) if method == "tools/call" => format!("{} {}", method, tool),
+        _ => method.to_string(),
+    }
+}
+
+fn replace_ids(text: &str, ids: &HashMap<String, String>) -> String {
+    let mut text = text.to_string();
+    for id in find_ids(&text) {
+        if let Some(replayed) = ids.get(&id) {
+            text = text.replace(&id, replayed);
+        }
+    }
+    text
+}
+
+/// Every hyphenated UUID in `text`, in order of appearance, without repeats
+fn find_ids(text: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        match text.get(i..i + 36).filter(|candidate| Uuid::try_parse(candidate).is_ok()) {
+            Some(id) => {
+                if !found.iter().any(|f| f == id) {
+                    found.push(id.to_string());
+                }
+                i += 36;
+            }
+            None => i += 1,
+        }
+    }
+    found
+}
+
+/// `text` with UUIDs and RFC 3339 timestamps replaced by placeholders
+fn mask(text: &str) -> String {
+    let mut text = text.to_string();
+    for id in find_ids(&text) {
+        text = text.replace(&id, "<id>");
+    }
+
+    let mut masked = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while !rest.is_empty() {
+        match timestamp_len(rest) {
+            Some(len) => {
+                masked.push_str("<time>");
+                rest = &rest[len..];
+            }
+            None => {
+                let c = rest.chars().next().expect("rest is non-empty");
+                masked.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    masked
+}
+
+/// Length of the RFC 3339 timestamp `text` starts with, if it starts with one
+fn timestamp_len(text: &str) -> Option<usize> {
+    // YYYY-MM-DDTHH:MM:SS
+    const SHAPE: &[u8] = b"dddd-dd-ddTdd:dd:dd";
+    let bytes = text.as_bytes();
+    if bytes.len() < SHAPE.len() {
+        return None;
+    }
+    let fits = SHAPE.iter().zip(bytes).all(|(shape, b)| match shape {
+        b'd' => b.is_ascii_digit(),
+        _ => shape == b,
+    });
+    if !fits {
+        return None;
+    }
+
+    let mut len = SHAPE.len();
+    if bytes.get(len) == Some(&b'.') {
+        len += 1;
+        while bytes.get(len).is_some_and(u8::is_ascii_digit) {
+            len += 1;
+        }
+    }
+    match bytes.get(len) {
+        Some(b'Z') => len += 1,
+        // +HH:MM / -HH:MM
+        Some(b'+' | b'-') if bytes.len() >= len + 6 && bytes[len + 3] == b':' => len += 6,
+        _ => {}
+    }
+    Some(len)
+}
@@ -1,13 +1,24 @@
+use std::collections::{HashMap, HashSet};
 use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use uuid::Uuid;
 use chrono::Utc;
-use crate::database::Database;
+use crate::database::{Database, AUTO_CONNECTION_KIND, CONNECTION_KINDS, LINK_CONNECTION_KIND};
+use crate::mcp_log::McpLog;
+use crate::progress::Progress;
+use crate::utils::lock_recovering;
+use crate::validation;
 
 // MCP Protocol structures
 #[derive(Debug, Deserialize)]
 struct McpRequest {
+    #[allow(dead_code)] // Always "2.0"; required by the protocol but not inspected
     jsonrpc: String,
     id: Option<Value>,
     method: String,
@@ -28,6 +39,8 @@ struct McpResponse {
 struct McpError {
     code: i32,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
 }
 
 // Tool input types
@@ -36,6 +49,41 @@ struct MindLogInput {
     content: String,
     category: String,
     importance: f64,
+    /// Log a new node even if a near-identical thought already exists
+    #[serde(default)]
+    force: bool,
+    /// Anchor the thought: layout won't move it and its importance won't decay
+    #[serde(default)]
+    pin: bool,
+    #[serde(default = "default_role")]
+    role: String,
+    /// Attach the thought to this session instead of the auto-detected one
+    session_id: Option<String>,
+    near: Option<Placement>,
+    /// URLs to attach to the thought
+    #[serde(default)]
+    attachments: Vec<String>,
+}
+
+fn default_role() -> String { "assistant".to_string() }
+
+/// Where mind_log puts a new thought: next to an existing thought, or at exact coordinates
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Placement {
+    Thought(String),
+    Point { x: f64, y: f64, z: f64 },
+}
+
+// How far from a `near` thought mind_log places the new one, per axis
+const NEAR_SPREAD: f64 = 3.0;
+
+fn check_role(role: &str) -> Result<(), String> {
+    if crate::database::THOUGHT_ROLES.contains(&role) {
+        Ok(())
+    } else {
+        Err(format!("Unknown role: {} (expected user, assistant, or system)", role))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,6 +91,21 @@ struct MindConnectInput {
     from: String,
     to: String,
     reason: String,
+    #[serde(default = "crate::default_connection_kind")]
+    kind: String,
+    /// Create a thought for any endpoint that can't be found instead of failing
+    #[serde(default)]
+    create_missing: bool,
+    /// Only accept endpoints whose content matches exactly (or an id)
+    #[serde(default)]
+    exact: bool,
+}
+
+/// How a mind_connect endpoint resolved against the database
+enum EndpointMatch {
+    Found(crate::Thought),
+    Missing,
+    Ambiguous(Vec<crate::Thought>),
 }
 
 #[derive(Debug, Deserialize)]
@@ -50,67 +113,431 @@ struct MindRecallInput {
     query: String,
     #[serde(default = "default_limit")]
     limit: usize,
+    #[serde(default)]
+    semantic: bool,
+    /// Only thoughts from this role
+    role: Option<String>,
+    /// Only thoughts with (true) or without (false) code
+    has_code: Option<bool>,
+    /// For relative dates in the query ("yesterday", "last week")
+    #[serde(default)]
+    tz_offset_minutes: i32,
 }
 
 fn default_limit() -> usize { 10 }
 
+// Thoughts fetched from a date range before the has_code filter and the limit apply
+const RANGE_SEARCH_LIMIT: usize = 50;
+// Question words trimmed from either end of what's left of a dated recall query, so "what did
+// we discuss yesterday" searches everything from yesterday
+const RECALL_FILLER: &[&str] = &[
+    "what", "which", "anything", "everything", "did", "do", "does", "we", "i", "you", "us", "me",
+    "discuss", "discussed", "talk", "talked", "say", "said", "mention", "mentioned", "log", "logged",
+    "think", "thought", "work", "worked", "about", "on", "of", "was", "were", "is", "are", "the",
+    "happened", "there",
+];
+
+/// A dated recall query's remaining text with filler words and punctuation trimmed from its ends
+fn recall_terms(rest: &str) -> String {
+    let is_filler = |w: &&str| {
+        let bare = w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+        bare.is_empty() || RECALL_FILLER.contains(&bare.as_str())
+    };
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let start = words.iter().position(|w| !is_filler(w)).unwrap_or(words.len());
+    let end = words.iter().rposition(|w| !is_filler(w)).map_or(start, |i| i + 1);
+    words[start..end].join(" ").trim_matches(|c: char| !c.is_alphanumeric()).to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct MindSimilarInput {
+    /// Id or content of the thought to find others like
+    thought: String,
+    #[serde(default = "default_similar_limit")]
+    limit: usize,
+}
+
+fn default_similar_limit() -> usize { 5 }
+
+#[derive(Debug, Deserialize)]
+struct MindReviewInput {
+    #[serde(default = "default_review_limit")]
+    limit: i64,
+}
+
+fn default_review_limit() -> i64 { 5 }
+
+#[derive(Debug, Deserialize)]
+struct MindDigestInput {
+    date: Option<String>,
+    #[serde(default)]
+    tz_offset_minutes: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct MindWeeklyReflectionInput {
+    /// Last day of the week, YYYY-MM-DD
+    end_date: Option<String>,
+    #[serde(default)]
+    tz_offset_minutes: i32,
+    #[serde(default = "default_reflection_chars")]
+    max_chars: usize,
+}
+
+fn default_reflection_chars() -> usize { crate::reflection::DEFAULT_MAX_CHARS }
+
+#[derive(Debug, Deserialize)]
+struct MindExportGraphInput {
+    category: Option<String>,
+    since: Option<String>,
+    #[serde(default = "default_max_nodes")]
+    max_nodes: usize,
+}
+
+fn default_max_nodes() -> usize { crate::database::DEFAULT_SNAPSHOT_NODES }
+
+#[derive(Debug, Deserialize)]
+struct MindSessionRecallInput {
+    id_or_title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MindSessionUpdateInput {
+    id_or_title: String,
+    title: Option<String>,
+    summary: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MindForgeImportInput {
+    #[serde(default)]
+    kinds: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MindLogDecisionInput {
+    choice: String,
+    reasoning: String,
+    #[serde(default)]
+    alternatives: Vec<String>,
+    project: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default = "default_decision_importance")]
+    importance: f64,
+}
+
+fn default_decision_importance() -> f64 { 0.7 }
+
+#[derive(Debug, Deserialize)]
+struct MindImportTranscriptInput {
+    text: String,
+    title: String,
+}
+
 #[derive(Debug, Deserialize)]
 struct MindSummarizeInput {
     title: String,
     summary: String,
 }
 
-pub fn run_mcp_server() {
-    let db = Database::new().expect("Failed to initialize database");
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+// Newest first. initialize answers with the newest of these not after the client's version.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+// Tool annotations (readOnlyHint, destructiveHint) arrived in this version
+const ANNOTATIONS_SINCE: &str = "2025-03-26";
+// Tools that never write to the database
+const NON_WRITING_TOOLS: &[&str] = &["mind_recall", "mind_similar", "mind_review", "mind_digest", "mind_session_recall", "mind_export_graph", "mind_query", "mind_weekly_reflection"];
+// Tools that can overwrite existing data rather than only adding to it
+const OVERWRITING_TOOLS: &[&str] = &["mind_summarize_session", "mind_session_update", "mind_forge_import"];
+
+/// Per-connection state: the protocol version agreed at initialize
+pub struct McpSession {
+    protocol: Mutex<&'static str>,
+}
+
+impl Default for McpSession {
+    /// Until initialize says otherwise, assume the oldest supported version
+    fn default() -> Self {
+        McpSession { protocol: Mutex::new(PROTOCOL_VERSIONS[PROTOCOL_VERSIONS.len() - 1]) }
+    }
+}
+
+impl McpSession {
+    fn set_protocol(&self, version: &'static str) {
+        *lock_recovering(&self.protocol) = version;
+    }
+
+    /// Whether the agreed version is `since` or later (versions are dates, so they sort as strings)
+    fn supports(&self, since: &str) -> bool {
+        *lock_recovering(&self.protocol) >= since
+    }
+}
+
+/// The newest supported version not after `requested`; None if the client only speaks
+/// something older than every supported version, or sent no valid version at all
+fn negotiate(requested: &str) -> Option<&'static str> {
+    let is_date = chrono::NaiveDate::parse_from_str(requested, "%Y-%m-%d").is_ok();
+    if !is_date {
+        return None;
+    }
+    PROTOCOL_VERSIONS.iter().copied().find(|version| *version <= requested)
+}
+
+// tools/call requests handled at once; the rest queue behind them
+const WORKERS: usize = 4;
+
+// The only tools served when the database is opened read-only
+const READ_ONLY_TOOLS: &[&str] = &["mind_recall", "mind_similar", "mind_export_graph", "mind_query"];
+
+/// Read-only serves just the READ_ONLY_TOOLS. With `log` set, every exchange is appended
+/// to that JSONL file (see mcp_log.rs).
+pub fn run_mcp_server(read_only: bool, log: Option<PathBuf>) {
+    let db = Database::open(read_only).expect("Failed to initialize database");
+    crate::session_forge::load_configured_dir(&db);
+    let mut log = log.map(|path| McpLog::open(&path).expect("Failed to open MCP log"));
     
-    eprintln!("The Mind MCP Server started");
+    tracing::info!("The Mind MCP Server started{}", if read_only { " (read-only)" } else { "" });
     
-    for line in stdin.lock().lines() {
-        let line = match line {
-            Ok(l) => l,
-            Err(_) => continue,
-        };
-        
-        if line.is_empty() {
-            continue;
+    serve(&db, io::stdin().lock(), &mut io::stdout(), log.as_mut());
+}
+
+/// Answer each request line from `input` on `output` until input ends. Kept apart from stdio
+/// so replays and tests can drive the protocol against any database and capture the output.
+///
+/// Requests are parsed here, in order. tools/call runs on a pool of WORKERS threads so a slow
+/// call (auto-connecting over a big database) doesn't hold up a quick one sent after it;
+/// everything else is answered inline. Responses can therefore arrive out of order, which
+/// JSON-RPC allows since each echoes its request id.
+pub fn serve(db: &Database, input: impl BufRead, output: &mut (impl Write + Send), log: Option<&mut McpLog>) {
+    let session = McpSession::default();
+    let output = Mutex::new(output);
+    let log = Mutex::new(log);
+    // Set once the client can no longer be written to; nothing more is read or answered
+    let closed = AtomicBool::new(false);
+    // One whole line per lock, so concurrent responses and notifications never interleave
+    let send = |line: &str| {
+        let mut output = lock_recovering(&output);
+        if closed.load(Ordering::Relaxed) {
+            return;
         }
-        
-        let request: McpRequest = match serde_json::from_str(&line) {
-            Ok(r) => r,
-            Err(e) => {
-                eprintln!("Failed to parse request: {}", e);
+        if let Err(e) = writeln!(output, "{}", line).and_then(|_| output.flush()) {
+            tracing::error!("Writing to the MCP client failed, stopping: {}", e);
+            closed.store(true, Ordering::Relaxed);
+        }
+    };
+    let finish = |line: &str, response: Option<String>, started: Instant| {
+        if let Some(log) = lock_recovering(&log).as_deref_mut() {
+            log.record(line, response.as_deref(), started.elapsed());
+        }
+        if let Some(response) = response {
+            send(&response);
+        }
+    };
+    // Cancellation flags of the tools/call requests still running, by request id
+    let in_flight: Mutex<HashMap<String, Arc<AtomicBool>>> = Mutex::new(HashMap::new());
+
+    let (jobs, queue) = mpsc::channel::<(String, McpRequest, Arc<AtomicBool>, Instant)>();
+    let queue = Mutex::new(queue);
+
+    std::thread::scope(|scope| {
+        for _ in 0..WORKERS {
+            scope.spawn(|| loop {
+                // The guard is dropped before the request runs, so other workers can take the next
+                let job = lock_recovering(&queue).recv();
+                let Ok((line, request, cancelled, started)) = job else {
+                    return;
+                };
+                if closed.load(Ordering::Relaxed) {
+                    continue;
+                }
+
+                let token = request.params.as_ref()
+                    .and_then(|p| p.pointer("/_meta/progressToken"))
+                    .cloned();
+                let notify = |done: usize, total: Option<usize>, message: &str| {
+                    send(&progress_notification(token.as_ref(), done, total, message).to_string());
+                };
+                let progress = Progress::new(token.is_some().then_some(&notify), Some(&cancelled));
+
+                // A panicking tool answers with an error instead of taking the worker down with it
+                let response = panic::catch_unwind(AssertUnwindSafe(|| respond(db, &session, &request, progress)))
+                    .unwrap_or_else(|_| internal_error(&request));
+                if let Some(id) = &request.id {
+                    lock_recovering(&in_flight).remove(&id.to_string());
+                }
+                // The client has given up on a cancelled request, so it gets no response
+                let response = response.filter(|_| !cancelled.load(Ordering::Relaxed));
+                finish(&line, response, started);
+            });
+        }
+
+        for line in input.lines() {
+            if closed.load(Ordering::Relaxed) {
+                break;
+            }
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => continue,
+            };
+            
+            if line.is_empty() {
                 continue;
             }
-        };
-        
-        let response = handle_request(&db, &request);
-        
-        if let Some(resp) = response {
-            let response_str = serde_json::to_string(&resp).unwrap();
-            writeln!(stdout, "{}", response_str).unwrap();
-            stdout.flush().unwrap();
+            
+            let started = Instant::now();
+            let Some(request) = parse_request(&line) else {
+                finish(&line, None, started);
+                continue;
+            };
+            match request.method.as_str() {
+                "tools/call" => {
+                    let cancelled = Arc::new(AtomicBool::new(false));
+                    if let Some(id) = &request.id {
+                        lock_recovering(&in_flight).insert(id.to_string(), Arc::clone(&cancelled));
+                    }
+                    jobs.send((line, request, cancelled, started)).expect("workers outlive the reader");
+                }
+                "notifications/cancelled" => {
+                    let target = request.params.as_ref().and_then(|p| p.get("requestId"));
+                    if let Some(flag) = target.and_then(|id| lock_recovering(&in_flight).get(&id.to_string()).cloned()) {
+                        flag.store(true, Ordering::Relaxed);
+                        tracing::debug!(request_id = %target.expect("found above"), "MCP request cancelled");
+                    }
+                    finish(&line, None, started);
+                }
+                _ => {
+                    let response = respond(db, &session, &request, Progress::none());
+                    finish(&line, response, started);
+                }
+            }
+        }
+        // Dropping the sender lets the workers drain the queue and exit
+        drop(jobs);
+    });
+}
+
+/// A notifications/progress message; `total` is left out when it isn't known
+fn progress_notification(token: Option<&Value>, done: usize, total: Option<usize>, message: &str) -> Value {
+    let mut params = json!({ "progressToken": token, "progress": done, "message": message });
+    if let Some(total) = total {
+        params["total"] = json!(total);
+    }
+    json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/progress",
+        "params": params
+    })
+}
+
+/// Handle one raw request line; returns the response line, or None for notifications and
+/// lines that aren't valid requests
+pub fn handle_line(db: &Database, session: &McpSession, line: &str) -> Option<String> {
+    respond(db, session, &parse_request(line)?, Progress::none())
+}
+
+fn parse_request(line: &str) -> Option<McpRequest> {
+    serde_json::from_str(line)
+        .inspect_err(|e| tracing::warn!("Failed to parse request: {}", e))
+        .ok()
+}
+
+/// Handle a parsed request; returns the serialized response
+fn respond(db: &Database, session: &McpSession, request: &McpRequest, progress: Progress) -> Option<String> {
+    let started = Instant::now();
+    let response = {
+        let _span = tracing::debug_span!("mcp_request", method = %request.method).entered();
+        handle_request(db, session, request, progress)
+    };
+    tracing::debug!(method = %request.method, elapsed = ?started.elapsed(), "MCP request handled");
+    
+    response.and_then(|resp| match serde_json::to_string(&resp) {
+        Ok(line) => Some(line),
+        Err(e) => {
+            tracing::error!(method = %request.method, "Serializing the response failed: {}", e);
+            internal_error(request)
+        }
+    })
+}
+
+/// The JSON-RPC error sent when handling `request` panicked or its response couldn't be serialized
+fn internal_error(request: &McpRequest) -> Option<String> {
+    let response = json!({
+        "jsonrpc": "2.0",
+        "id": request.id.clone()?,
+        "error": {
+            "code": -32603,
+            "message": format!("Internal error handling {}", request.method)
+        }
+    });
+    Some(response.to_string())
+}
+
+/// The tools/list result, cut down to READ_ONLY_TOOLS when the database is read-only, with
+/// behavior hints on each tool for clients new enough to read them
+fn served_tools(db: &Database, session: &McpSession, mut list: Value) -> Value {
+    let Some(tools) = list.get_mut("tools").and_then(Value::as_array_mut) else {
+        return list;
+    };
+    if db.is_read_only() {
+        tools.retain(|tool| READ_ONLY_TOOLS.contains(&tool["name"].as_str().unwrap_or_default()));
+    }
+    if session.supports(ANNOTATIONS_SINCE) {
+        for tool in tools.iter_mut() {
+            let name = tool["name"].as_str().unwrap_or_default();
+            tool["annotations"] = json!({
+                "readOnlyHint": NON_WRITING_TOOLS.contains(&name),
+                "destructiveHint": OVERWRITING_TOOLS.contains(&name),
+            });
         }
     }
+    list
 }
 
-fn handle_request(db: &Database, request: &McpRequest) -> Option<McpResponse> {
+fn handle_request(db: &Database, session: &McpSession, request: &McpRequest, progress: Progress) -> Option<McpResponse> {
     let id = request.id.clone()?;
     
     match request.method.as_str() {
         "initialize" => {
+            let params = request.params.as_ref();
+            let requested = params.and_then(|p| p.get("protocolVersion")).and_then(Value::as_str).unwrap_or_default();
+            let client_info = |key: &str| params
+                .and_then(|p| p.pointer(&format!("/clientInfo/{}", key)))
+                .and_then(Value::as_str)
+                .unwrap_or("unknown");
+            let (client, client_version) = (client_info("name"), client_info("version"));
+            tracing::info!(client, client_version, requested, "MCP client connected");
+
+            let Some(version) = negotiate(requested) else {
+                return Some(McpResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                    result: None,
+                    error: Some(McpError {
+                        code: -32602,
+                        message: "Unsupported protocol version".to_string(),
+                        data: Some(json!({ "supported": PROTOCOL_VERSIONS, "requested": requested })),
+                    }),
+                });
+            };
+            session.set_protocol(version);
+
             Some(McpResponse {
                 jsonrpc: "2.0".to_string(),
                 id,
                 result: Some(json!({
-                    "protocolVersion": "2024-11-05",
+                    "protocolVersion": version,
+                    // Only tools: The Mind serves no resources or prompts on any version
                     "capabilities": {
                         "tools": {}
                     },
+                    // Build and schema versions make a server and database from different releases easy to spot
                     "serverInfo": {
                         "name": "the-mind",
-                        "version": "0.1.0"
+                        "version": crate::app_info::VERSION,
+                        "gitCommit": crate::app_info::GIT_COMMIT,
+                        "buildTimestamp": crate::app_info::build_timestamp(),
+                        "schemaVersion": crate::database::SCHEMA_VERSION,
+                        "databaseSchemaVersion": db.stored_schema_version().ok()
                     }
                 })),
                 error: None,
@@ -118,10 +545,15 @@ fn handle_request(db: &Database, request: &McpRequest) -> Option<McpResponse> {
         }
         
         "tools/list" => {
+            // Category enum comes from the categories table so new ones need no rebuild
+            let categories: Vec<String> = db.list_categories()
+                .map(|cats| cats.into_iter().map(|c| c.name).collect())
+                .unwrap_or_else(|_| vec!["work".into(), "personal".into(), "technical".into(), "creative".into(), "other".into()]);
+
             Some(McpResponse {
                 jsonrpc: "2.0".to_string(),
                 id,
-                result: Some(json!({
+                result: Some(served_tools(db, session, json!({
                     "tools": [
                         {
                             "name": "mind_log",
@@ -135,7 +567,7 @@ fn handle_request(db: &Database, request: &McpRequest) -> Option<McpResponse> {
                                     },
                                     "category": {
                                         "type": "string",
-                                        "enum": ["work", "personal", "technical", "creative", "other"],
+                                        "enum": categories,
                                         "description": "Category of the thought (affects color in visualization)"
                                     },
                                     "importance": {
@@ -143,6 +575,46 @@ fn handle_request(db: &Database, request: &McpRequest) -> Option<McpResponse> {
                                         "minimum": 0,
                                         "maximum": 1,
                                         "description": "How significant is this thought (0-1, affects node size)"
+                                    },
+                                    "force": {
+                                        "type": "boolean",
+                                        "default": false,
+                                        "description": "Create a new thought even if a near-duplicate was logged recently (otherwise the existing one is reinforced)"
+                                    },
+                                    "pin": {
+                                        "type": "boolean",
+                                        "default": false,
+                                        "description": "Pin the thought as an anchor: it keeps its place in the layout and its importance never decays"
+                                    },
+                                    "role": {
+                                        "type": "string",
+                                        "enum": ["user", "assistant", "system"],
+                                        "default": "assistant",
+                                        "description": "Who the thought came from: the user's own idea, the assistant's, or a system/tool note"
+                                    },
+                                    "session_id": {
+                                        "type": "string",
+                                        "description": "Add the thought to this session (appended after its other thoughts) instead of the auto-detected one"
+                                    },
+                                    "near": {
+                                        "description": "Where to place the node: a thought id to put it next to, or exact {x, y, z} coordinates (random by default)",
+                                        "oneOf": [
+                                            { "type": "string" },
+                                            {
+                                                "type": "object",
+                                                "properties": {
+                                                    "x": { "type": "number" },
+                                                    "y": { "type": "number" },
+                                                    "z": { "type": "number" }
+                                                },
+                                                "required": ["x", "y", "z"]
+                                            }
+                                        ]
+                                    },
+                                    "attachments": {
+                                        "type": "array",
+                                        "items": { "type": "string" },
+                                        "description": "http(s) URLs to attach to the thought (screenshots, docs, issues) so the app can preview them"
                                     }
                                 },
                                 "required": ["content", "category", "importance"]
@@ -156,15 +628,31 @@ fn handle_request(db: &Database, request: &McpRequest) -> Option<McpResponse> {
                                 "properties": {
                                     "from": {
                                         "type": "string",
-                                        "description": "First concept (use exact text of a logged thought)"
+                                        "description": "First concept (exact text or id of a logged thought)"
                                     },
                                     "to": {
                                         "type": "string",
-                                        "description": "Second concept (use exact text of a logged thought)"
+                                        "description": "Second concept (exact text or id of a logged thought)"
                                     },
                                     "reason": {
                                         "type": "string",
                                         "description": "Why these concepts connect"
+                                    },
+                                    "kind": {
+                                        "type": "string",
+                                        "enum": ["related", "supports", "contradicts", "caused-by", "leads-to", "example-of"],
+                                        "default": "related",
+                                        "description": "How the first concept relates to the second"
+                                    },
+                                    "create_missing": {
+                                        "type": "boolean",
+                                        "default": false,
+                                        "description": "If an endpoint can't be found, log it as a new thought (category 'other') and connect it"
+                                    },
+                                    "exact": {
+                                        "type": "boolean",
+                                        "default": false,
+                                        "description": "Only match thoughts whose content is exactly the given text (or an id); no fuzzy search"
                                     }
                                 },
                                 "required": ["from", "to", "reason"]
@@ -172,7 +660,7 @@ fn handle_request(db: &Database, request: &McpRequest) -> Option<McpResponse> {
                         },
                         {
                             "name": "mind_recall",
-                            "description": "Search The Mind for relevant past thoughts and connections. Use to find related ideas from previous conversations.",
+                            "description": "Search The Mind for relevant past thoughts and connections. Use to find related ideas from previous conversations. Misspelled queries fall back to fuzzy near-matches. Dates in the query (today, yesterday, last week, last month, N days ago, month names) limit results to that range.",
                             "inputSchema": {
                                 "type": "object",
                                 "properties": {
@@ -184,11 +672,192 @@ fn handle_request(db: &Database, request: &McpRequest) -> Option<McpResponse> {
                                         "type": "number",
                                         "default": 10,
                                         "description": "Maximum number of results to return"
+                                    },
+                                    "semantic": {
+                                        "type": "boolean",
+                                        "default": false,
+                                        "description": "Also match by meaning using embeddings (finds paraphrases); falls back to keyword search when embeddings are off or unreachable"
+                                    },
+                                    "role": {
+                                        "type": "string",
+                                        "enum": ["user", "assistant", "system"],
+                                        "description": "Only return thoughts from this role"
+                                    },
+                                    "has_code": {
+                                        "type": "boolean",
+                                        "description": "Only return thoughts that contain code (true) or that don't (false)"
+                                    },
+                                    "tz_offset_minutes": {
+                                        "type": "number",
+                                        "default": 0,
+                                        "description": "Local timezone offset from UTC in minutes, for dates in the query"
                                     }
                                 },
                                 "required": ["query"]
                             }
                         },
+                        {
+                            "name": "mind_query",
+                            "description": "Find thoughts by structured conditions rather than search text, e.g. important architecture thoughts from the last month with no connections. Every field is optional and all given conditions must hold; results are ordered by importance.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "categories": {
+                                        "type": "array",
+                                        "items": { "type": "string" },
+                                        "description": "Only thoughts in any of these categories"
+                                    },
+                                    "tags": {
+                                        "type": "array",
+                                        "items": { "type": "string" },
+                                        "description": "Only thoughts carrying every one of these tags in their metadata"
+                                    },
+                                    "created_after": {
+                                        "type": "string",
+                                        "description": "RFC 3339 timestamp or YYYY-MM-DD date"
+                                    },
+                                    "created_before": {
+                                        "type": "string",
+                                        "description": "RFC 3339 timestamp or YYYY-MM-DD date"
+                                    },
+                                    "within_days": {
+                                        "type": "number",
+                                        "description": "Only thoughts created in the last this many days"
+                                    },
+                                    "min_importance": {
+                                        "type": "number",
+                                        "minimum": 0,
+                                        "maximum": 1
+                                    },
+                                    "max_importance": {
+                                        "type": "number",
+                                        "minimum": 0,
+                                        "maximum": 1
+                                    },
+                                    "has_connections": {
+                                        "type": "boolean",
+                                        "description": "Only thoughts with (true) or without (false) any connection"
+                                    },
+                                    "role": {
+                                        "type": "string",
+                                        "enum": ["user", "assistant", "system"]
+                                    },
+                                    "text": {
+                                        "type": "string",
+                                        "description": "Case-insensitive text the content must contain"
+                                    },
+                                    "include_archived": {
+                                        "type": "boolean",
+                                        "default": false,
+                                        "description": "Also return archived thoughts"
+                                    },
+                                    "limit": {
+                                        "type": "number",
+                                        "default": 100,
+                                        "maximum": 1000,
+                                        "description": "Maximum number of thoughts to return"
+                                    }
+                                },
+                                "additionalProperties": false
+                            }
+                        },
+                        {
+                            "name": "mind_review",
+                            "description": "Resurface important thoughts that haven't been revisited in a while (spaced review). Use to bring old but significant ideas back into the conversation.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "limit": {
+                                        "type": "number",
+                                        "default": 5,
+                                        "description": "How many thoughts to resurface"
+                                    }
+                                }
+                            }
+                        },
+                        {
+                            "name": "mind_digest",
+                            "description": "Get a Markdown digest of one day in The Mind: thoughts by category, new connections, sessions, and the most-connected new thought. Use at the end of the day to recap what was captured.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "date": {
+                                        "type": "string",
+                                        "description": "Day to summarize as YYYY-MM-DD (defaults to today)"
+                                    },
+                                    "tz_offset_minutes": {
+                                        "type": "number",
+                                        "default": 0,
+                                        "description": "Local timezone offset from UTC in minutes (e.g. -300 for US Eastern)"
+                                    }
+                                }
+                            }
+                        },
+                        {
+                            "name": "mind_weekly_reflection",
+                            "description": "Get a Markdown reflection on the last 7 days: thoughts by category, new connections and their reasons, session summaries, session-forge journal entries and decisions from the same days, and thoughts that never got connected. Use at the end of a week to look back on what was explored and what was left open.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "end_date": {
+                                        "type": "string",
+                                        "description": "Last day of the week as YYYY-MM-DD (defaults to today)"
+                                    },
+                                    "tz_offset_minutes": {
+                                        "type": "number",
+                                        "default": 0,
+                                        "description": "Local timezone offset from UTC in minutes (e.g. -300 for US Eastern)"
+                                    },
+                                    "max_chars": {
+                                        "type": "number",
+                                        "default": 8000,
+                                        "minimum": 500,
+                                        "description": "Character budget; the lowest-importance items are left out first to stay within it"
+                                    }
+                                }
+                            }
+                        },
+                        {
+                            "name": "mind_similar",
+                            "description": "Find thoughts like a given one that aren't connected to it yet. Use to surface related ideas worth linking or revisiting.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "thought": {
+                                        "type": "string",
+                                        "description": "Id or content of the thought to compare against"
+                                    },
+                                    "limit": {
+                                        "type": "number",
+                                        "default": 5,
+                                        "description": "Maximum number of similar thoughts; weak matches are left out, so there may be fewer"
+                                    }
+                                },
+                                "required": ["thought"]
+                            }
+                        },
+                        {
+                            "name": "mind_export_graph",
+                            "description": "Export thoughts and the connections between them as JSON, most important first. Use when you need the structure of The Mind rather than search results.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "category": {
+                                        "type": "string",
+                                        "description": "Only include thoughts in this category"
+                                    },
+                                    "since": {
+                                        "type": "string",
+                                        "description": "Only include thoughts created on or after this date (YYYY-MM-DD or RFC 3339)"
+                                    },
+                                    "max_nodes": {
+                                        "type": "number",
+                                        "default": 500,
+                                        "description": "Maximum number of thoughts; beyond it the least important are dropped and truncated is set"
+                                    }
+                                }
+                            }
+                        },
                         {
                             "name": "mind_summarize_session",
                             "description": "Generate a summary of the current conversation for The Mind. Use at the end of conversations to create a record.",
@@ -206,29 +875,153 @@ fn handle_request(db: &Database, request: &McpRequest) -> Option<McpResponse> {
                                 },
                                 "required": ["title", "summary"]
                             }
-                        }
-                    ]
-                })),
-                error: None,
-            })
-        }
-        
-        "tools/call" => {
-            let params = request.params.as_ref()?;
-            let tool_name = params.get("name")?.as_str()?;
-            let arguments = params.get("arguments")?;
-            
-            let result = match tool_name {
-                "mind_log" => handle_mind_log(db, arguments),
-                "mind_connect" => handle_mind_connect(db, arguments),
-                "mind_recall" => handle_mind_recall(db, arguments),
-                "mind_summarize_session" => handle_mind_summarize(db, arguments),
-                _ => Err(format!("Unknown tool: {}", tool_name)),
-            };
-            
-            Some(McpResponse {
-                jsonrpc: "2.0".to_string(),
-                id,
+                        },
+                        {
+                            "name": "mind_session_recall",
+                            "description": "Recall a past session: its summary, the thoughts logged during it (in order), connections made, and stats.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "id_or_title": {
+                                        "type": "string",
+                                        "description": "Session id, or (part of) its title; the most recent match wins"
+                                    }
+                                },
+                                "required": ["id_or_title"]
+                            }
+                        },
+                        {
+                            "name": "mind_session_update",
+                            "description": "Fix a recorded session's title or summary. Only the fields you pass are changed.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "id_or_title": {
+                                        "type": "string",
+                                        "description": "Session id, or (part of) its current title; the most recent match wins"
+                                    },
+                                    "title": {
+                                        "type": "string",
+                                        "description": "New title"
+                                    },
+                                    "summary": {
+                                        "type": "string",
+                                        "description": "New summary"
+                                    }
+                                },
+                                "required": ["id_or_title"]
+                            }
+                        },
+                        {
+                            "name": "mind_forge_import",
+                            "description": "Import session-forge journal entries, decisions, and dead ends as thoughts in The Mind. Re-running updates changed entries instead of duplicating them; decisions are linked to dead ends they share keywords with.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "kinds": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "string",
+                                            "enum": ["journal", "decisions", "dead-ends"]
+                                        },
+                                        "description": "Which forge files to import (default: all)"
+                                    }
+                                }
+                            }
+                        },
+                        {
+                            "name": "mind_log_decision",
+                            "description": "Record a decision in both The Mind (as a thought) and session-forge's decisions log, keeping the two in sync. Use for meaningful choices made during work.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "choice": {
+                                        "type": "string",
+                                        "description": "What was decided"
+                                    },
+                                    "reasoning": {
+                                        "type": "string",
+                                        "description": "Why"
+                                    },
+                                    "alternatives": {
+                                        "type": "array",
+                                        "items": { "type": "string" },
+                                        "description": "Options that were considered and rejected"
+                                    },
+                                    "project": {
+                                        "type": "string",
+                                        "description": "Project the decision belongs to"
+                                    },
+                                    "tags": {
+                                        "type": "array",
+                                        "items": { "type": "string" },
+                                        "description": "Tags; one naming a category sets the thought's category (default technical)"
+                                    },
+                                    "importance": {
+                                        "type": "number",
+                                        "minimum": 0,
+                                        "maximum": 1,
+                                        "default": 0.7,
+                                        "description": "How significant the decision is (0-1)"
+                                    }
+                                },
+                                "required": ["choice", "reasoning"]
+                            }
+                        },
+                        {
+                            "name": "mind_import_transcript",
+                            "description": "Import a past conversation into The Mind. The text is split on 'Human:' / 'Assistant:' markers (or blank lines), each substantive turn becomes a thought linked to a new session, and turns are auto-connected.",
+                            "inputSchema": {
+                                "type": "object",
+                                "properties": {
+                                    "text": {
+                                        "type": "string",
+                                        "description": "The full conversation transcript"
+                                    },
+                                    "title": {
+                                        "type": "string",
+                                        "description": "Title for the session created from this transcript"
+                                    }
+                                },
+                                "required": ["text", "title"]
+                            }
+                        }
+                    ]
+                }))),
+                error: None,
+            })
+        }
+        
+        "tools/call" => {
+            let params = request.params.as_ref()?;
+            let tool_name = params.get("name")?.as_str()?;
+            let arguments = params.get("arguments")?;
+            
+            let result = match tool_name {
+                _ if db.is_read_only() && !READ_ONLY_TOOLS.contains(&tool_name) => {
+                    Err(format!("ReadOnly: {} is unavailable while The Mind is open read-only", tool_name))
+                }
+                "mind_log" => handle_mind_log(db, arguments),
+                "mind_connect" => handle_mind_connect(db, arguments),
+                "mind_recall" => handle_mind_recall(db, arguments),
+                "mind_similar" => handle_mind_similar(db, arguments),
+                "mind_query" => handle_mind_query(db, arguments),
+                "mind_review" => handle_mind_review(db, arguments),
+                "mind_digest" => handle_mind_digest(db, arguments),
+                "mind_weekly_reflection" => handle_mind_weekly_reflection(db, arguments),
+                "mind_export_graph" => handle_mind_export_graph(db, arguments),
+                "mind_summarize_session" => handle_mind_summarize(db, arguments),
+                "mind_session_recall" => handle_mind_session_recall(db, arguments),
+                "mind_session_update" => handle_mind_session_update(db, arguments),
+                "mind_forge_import" => handle_mind_forge_import(db, arguments, progress),
+                "mind_log_decision" => handle_mind_log_decision(db, arguments),
+                "mind_import_transcript" => handle_mind_import_transcript(db, arguments, progress),
+                _ => Err(format!("Unknown tool: {}", tool_name)),
+            };
+            
+            Some(McpResponse {
+                jsonrpc: "2.0".to_string(),
+                id,
                 result: Some(match result {
                     Ok(content) => json!({
                         "content": [{
@@ -258,26 +1051,116 @@ fn handle_request(db: &Database, request: &McpRequest) -> Option<McpResponse> {
                 error: Some(McpError {
                     code: -32601,
                     message: format!("Method not found: {}", request.method),
+                    data: None,
                 }),
             })
         }
     }
 }
 
-use crate::utils::{extract_keywords, count_shared_keywords};
+use crate::auto_connect::{auto_connect, connect_shared_links, AutoConnectConfig};
+use crate::utils::{extract_keywords_with, snippet, KeywordConfig};
+
+/// Attach each URL to the thought, skipping ones it already has
+fn add_url_attachments(db: &Database, thought_id: &str, urls: &[String]) -> rusqlite::Result<()> {
+    let attached: Vec<String> = db.get_attachments(thought_id)?.into_iter()
+        .filter(|a| a.kind == "url")
+        .map(|a| a.path_or_url)
+        .collect();
+    for url in urls {
+        if attached.contains(url) {
+            continue;
+        }
+        db.add_attachment(&crate::Attachment {
+            id: Uuid::new_v4().to_string(),
+            thought_id: thought_id.to_string(),
+            kind: "url".to_string(),
+            path_or_url: url.clone(),
+            label: None,
+            created_at: Utc::now().to_rfc3339(),
+        })?;
+    }
+    Ok(())
+}
 
 fn handle_mind_log(db: &Database, arguments: &Value) -> Result<String, String> {
-    let input: MindLogInput = serde_json::from_value(arguments.clone())
+    let mut input: MindLogInput = serde_json::from_value(arguments.clone())
         .map_err(|e| format!("Invalid arguments: {}", e))?;
+    check_role(&input.role)?;
+
+    // Normalized before the duplicate check so it compares what would be stored
+    input.content = validation::content(&input.content, validation::max_content_chars(db))?;
+    let (importance, importance_note) = validation::unit_interval("importance", input.importance)?;
+    input.importance = importance;
+    let known: Vec<String> = db.list_categories()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    let (category, category_note) = validation::category(&input.category, &known)?;
+    input.category = category;
+    if let Some(session_id) = &input.session_id {
+        if !db.session_exists(session_id).map_err(|e| e.to_string())? {
+            return Err(format!("No session with id {}", session_id));
+        }
+    }
+    let mut urls: Vec<String> = Vec::new();
+    for url in input.attachments.iter().map(|url| url.trim().to_string()) {
+        if !crate::utils::extract_urls(&url).contains(&url) {
+            return Err(format!("Invalid: attachment '{}' is not an http(s) URL", url));
+        }
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    input.attachments = urls;
+
+    // Reinforce a recent near-duplicate instead of adding a second node for the same idea
+    if !input.force {
+        if let Some(existing) = find_recent_duplicate(db, &input.content).map_err(|e| e.to_string())? {
+            if db.is_thought_locked(&existing.id).map_err(|e| e.to_string())? {
+                return Ok(format!(
+                    "🔒 A locked thought already says this, so it was left unchanged.\n\nID: {}\nContent: \"{}\"\n\n(Pass force: true to log it as a new thought anyway.)",
+                    existing.id, existing.content
+                ));
+            }
+            let importance = (existing.importance + DUPLICATE_IMPORTANCE_BOOST).min(1.0);
+            db.reinforce_thought(&existing.id, importance).map_err(|e| e.to_string())?;
+            if input.pin {
+                db.set_thought_pinned(&existing.id, true).map_err(|e| e.to_string())?;
+            }
+            if let Some(session_id) = &input.session_id {
+                attach_to_session(db, session_id, &existing.id).map_err(|e| e.to_string())?;
+            }
+            add_url_attachments(db, &existing.id, &input.attachments).map_err(|e| e.to_string())?;
+            return Ok(format!(
+                "♻️ Reinforced existing thought instead of logging a duplicate.\n\nID: {}\nImportance: {:.0}% → {:.0}%\nContent: \"{}\"\n\n(Pass force: true to log it as a new thought anyway.)",
+                existing.id, existing.importance * 100.0, importance * 100.0, existing.content
+            ));
+        }
+    }
     
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
-    let (x, y, z) = Database::generate_position();
+    let mut placement_note = None;
+    // Exact coordinates are kept as given; generated positions are moved off existing nodes
+    let mut random = db.position_random(&input.content, &input.category);
+    let (x, y, z) = match &input.near {
+        Some(Placement::Point { x, y, z }) => (*x, *y, *z),
+        Some(Placement::Thought(near_id)) => db.clear_position(match db.get_thought_by_id(near_id).map_err(|e| e.to_string())? {
+            Some(a) => Database::generate_position_near(a.position_x, a.position_y, a.position_z, NEAR_SPREAD, &mut random),
+            None => {
+                placement_note = Some(format!("No thought with id {} to place it near; placed with its category.", near_id));
+                db.generate_position_for_category(&input.category, &mut random)
+            }
+        }),
+        None => db.clear_position(db.generate_position_for_category(&input.category, &mut random)),
+    };
     
     let thought = crate::Thought {
         id: id.clone(),
         content: input.content.clone(),
-        role: Some("assistant".to_string()),
+        role: Some(input.role.clone()),
         category: input.category.clone(),
         importance: input.importance,
         position_x: x,
@@ -285,73 +1168,230 @@ fn handle_mind_log(db: &Database, arguments: &Value) -> Result<String, String> {
         position_z: z,
         created_at: now.clone(),
         last_referenced: now.clone(),
+        pinned: input.pin,
+        favorite: false,
+        archived: false,
     };
     
     db.insert_thought(&thought).map_err(|e| e.to_string())?;
+    add_url_attachments(db, &id, &input.attachments).map_err(|e| e.to_string())?;
+
+    // An explicit session wins; otherwise group into a time-gap session unless sessions are managed manually
+    if let Some(session_id) = &input.session_id {
+        attach_to_session(db, session_id, &id).map_err(|e| e.to_string())?;
+    } else if let Err(e) = crate::auto_session::track_thought(db, &thought) {
+        tracing::warn!("Auto session tracking failed: {}", e);
+    }
     
     // === AUTO-CONNECTION LOGIC ===
-    let new_keywords = extract_keywords(&input.content);
-    let mut auto_connections = Vec::new();
-    
-    // Get all existing thoughts (except the one we just created)
-    if let Ok(all_thoughts) = db.get_all_thoughts() {
-        for existing in all_thoughts.iter().filter(|t| t.id != id) {
-            let existing_keywords = extract_keywords(&existing.content);
-            let shared = count_shared_keywords(&new_keywords, &existing_keywords);
-            
-            // If 2+ shared keywords, create a connection
-            if shared >= 2 {
-                let conn_id = Uuid::new_v4().to_string();
-                let connection = crate::Connection {
-                    id: conn_id,
-                    from_thought: id.clone(),
-                    to_thought: existing.id.clone(),
-                    strength: (shared as f64 * 0.15).min(1.0), // Strength based on keyword overlap
-                    reason: format!("Auto-connected: {} shared keywords", shared),
-                    created_at: now.clone(),
-                };
-                
-                if db.insert_connection(&connection).is_ok() {
-                    auto_connections.push(existing.content[..existing.content.len().min(40)].to_string());
-                }
-            }
-        }
-    }
+    let config = AutoConnectConfig::load(db);
+    // Same-page links first, so a pair that qualifies for both gets the more specific kind
+    let shared_links = connect_shared_links(db, &thought, &config).unwrap_or_default();
+    let outcome = auto_connect(db, &thought, &config).unwrap_or_default();
+    crate::embeddings::embed_new_thought(db, &thought);
     
     // Build response
     let mut response = format!(
         "✨ Thought logged to The Mind!\n\nID: {}\nCategory: {}\nImportance: {:.0}%\nContent: \"{}\"",
         id, input.category, input.importance * 100.0, input.content
     );
+    if input.pin {
+        response.push_str("\n📌 Pinned");
+    }
+    if let Some(session_id) = &input.session_id {
+        response.push_str(&format!("\nSession: {}", session_id));
+    }
+    if !input.attachments.is_empty() {
+        response.push_str(&format!("\n📎 {} attachment(s)", input.attachments.len()));
+    }
+    for note in importance_note.iter().chain(&category_note) {
+        response.push_str(&format!("\n⚠️ Note: {}", note));
+    }
+    if let Some(note) = placement_note {
+        response.push_str(&format!("\n\n{}", note));
+    }
     
-    if !auto_connections.is_empty() {
+    let describe = |c: &crate::auto_connect::ConnectedThought| match c.phrases.as_slice() {
+        [] => format!("  • {}... (via: {})", c.snippet, c.shared.join(", ")),
+        phrases => format!(
+            "  • {}... (shared phrase: {})",
+            c.snippet,
+            phrases.iter().map(|p| format!("'{}'", p)).collect::<Vec<_>>().join(", ")
+        ),
+    };
+    if !outcome.connected.is_empty() {
         response.push_str(&format!(
             "\n\n🔗 Auto-connected to {} existing thought(s):\n{}",
-            auto_connections.len(),
-            auto_connections.iter().map(|c| format!("  • {}...", c)).collect::<Vec<_>>().join("\n")
+            outcome.connected.len(),
+            outcome.connected.iter().map(describe).collect::<Vec<_>>().join("\n")
+        ));
+    }
+    if !outcome.suggested.is_empty() {
+        response.push_str(&format!(
+            "\n\n💡 Suggested connections to {} existing thought(s), awaiting review:\n{}",
+            outcome.suggested.len(),
+            outcome.suggested.iter().map(describe).collect::<Vec<_>>().join("\n")
         ));
     }
 
-    // Recompute clusters after adding a thought
-    if let Ok(clusters) = db.compute_clusters() {
-        response.push_str(&format!("\n\n🌐 {} cluster(s) updated", clusters.len()));
+    if outcome.suppressed > 0 {
+        response.push_str(&format!(
+            "\n({} more candidate(s) skipped by the per-thought connection cap)",
+            outcome.suppressed
+        ));
+    }
+    if outcome.capped > 0 || outcome.evicted > 0 {
+        response.push_str(&format!(
+            "\n({} candidate(s) skipped and {} weaker auto-connection(s) replaced to stay under the per-thought connection limit)",
+            outcome.capped,
+            outcome.evicted
+        ));
+    }
+
+    let links = crate::utils::extract_urls(&thought.content);
+    if !links.is_empty() {
+        response.push_str(&format!(
+            "\n\n🌍 Captured {} link(s):\n{}",
+            links.len(),
+            links.iter().map(|url| format!("  • {}", url)).collect::<Vec<_>>().join("\n")
+        ));
+    }
+    if !shared_links.is_empty() {
+        response.push_str(&format!(
+            "\n🔗 Linked to {} thought(s) mentioning the same page:\n{}",
+            shared_links.len(),
+            shared_links.iter()
+                .map(|(snippet, page)| format!("  • {}... ({})", snippet, page))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+
+    if let Some(decision) = crate::session_forge::related_decision(&thought.content, RELATED_DECISION_MIN_KEYWORDS, &KeywordConfig::load(db)) {
+        response.push_str(&format!(
+            "\n\n⚖️ Related past decision: {} — {}",
+            decision.entry.choice,
+            crate::utils::snippet(&decision.entry.reasoning, 160)
+        ));
+    }
+
+    // The new thought joins its nearest cluster; reclustering every thought is left to
+    // recompute_clusters, apart from making the first clusters
+    match db.join_nearest_cluster(&thought.id) {
+        Ok(Some(cluster)) => response.push_str(&format!("\n\n🌐 Joined cluster: {}", cluster.name)),
+        Ok(None) if db.get_all_clusters().is_ok_and(|clusters| clusters.is_empty()) => {
+            if let Ok(clusters) = db.compute_clusters() {
+                response.push_str(&format!("\n\n🌐 {} cluster(s) created", clusters.len()));
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Joining the nearest cluster failed: {}", e),
     }
 
     Ok(response)
 }
 
+/// Add a thought to the end of a session's thought list
+fn attach_to_session(db: &Database, session_id: &str, thought_id: &str) -> rusqlite::Result<()> {
+    let position = db.next_session_position(session_id)?;
+    db.link_session_thought(session_id, thought_id, position)
+}
+
+// Duplicate detection: how far back to look, how similar counts as the same idea,
+// and how much a repeat mention bumps importance
+const DUPLICATE_WINDOW_HOURS: i64 = 24;
+const DUPLICATE_KEYWORD_OVERLAP: f64 = 0.8;
+const DUPLICATE_IMPORTANCE_BOOST: f64 = 0.05;
+
+// A forge decision sharing this many keywords with a new thought is mentioned in mind_log's reply
+const RELATED_DECISION_MIN_KEYWORDS: usize = 3;
+
+/// Find a thought from the last day that is an exact (case-insensitive) match,
+/// or shares at least 80% of its keywords with `content`.
+fn find_recent_duplicate(db: &Database, content: &str) -> rusqlite::Result<Option<crate::Thought>> {
+    let since = (Utc::now() - chrono::Duration::hours(DUPLICATE_WINDOW_HOURS)).to_rfc3339();
+    let recent = db.get_thoughts_since(&since)?;
+
+    let normalized = content.trim().to_lowercase();
+    if let Some(exact) = recent.iter().find(|t| t.content.trim().to_lowercase() == normalized) {
+        return Ok(Some(exact.clone()));
+    }
+
+    let config = KeywordConfig::load(db);
+    let keywords: HashSet<String> = extract_keywords_with(content, &config).into_iter().collect();
+    if keywords.is_empty() {
+        return Ok(None);
+    }
+
+    let best = recent.into_iter()
+        .filter_map(|t| {
+            let other: HashSet<String> = extract_keywords_with(&t.content, &config).into_iter().collect();
+            let shared = keywords.intersection(&other).count();
+            let overlap = shared as f64 / keywords.len().max(other.len()) as f64;
+            (overlap >= DUPLICATE_KEYWORD_OVERLAP).then_some((overlap, t))
+        })
+        .max_by(|a, b| a.0.total_cmp(&b.0));
+
+    Ok(best.map(|(_, t)| t))
+}
+
 fn handle_mind_connect(db: &Database, arguments: &Value) -> Result<String, String> {
     let input: MindConnectInput = serde_json::from_value(arguments.clone())
         .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    if !CONNECTION_KINDS.contains(&input.kind.as_str()) || [AUTO_CONNECTION_KIND, LINK_CONNECTION_KIND].contains(&input.kind.as_str()) {
+        return Err(format!("Unknown connection kind: {}", input.kind));
+    }
     
-    // Find thoughts by content
-    let from_thoughts = db.search_thoughts(&input.from).map_err(|e| e.to_string())?;
-    let to_thoughts = db.search_thoughts(&input.to).map_err(|e| e.to_string())?;
-    
-    let from_thought = from_thoughts.first()
-        .ok_or_else(|| format!("Could not find thought: {}", input.from))?;
-    let to_thought = to_thoughts.first()
-        .ok_or_else(|| format!("Could not find thought: {}", input.to))?;
+    // Find thoughts by id or content
+    let from_match = resolve_endpoint(db, &input.from, input.exact)?;
+    let to_match = resolve_endpoint(db, &input.to, input.exact)?;
+
+    // Don't guess between several plausible thoughts; let the caller pick by id
+    if matches!(from_match, EndpointMatch::Ambiguous(_)) || matches!(to_match, EndpointMatch::Ambiguous(_)) {
+        let mut response = "🤔 More than one thought matches, so no connection was created. Retry with the id of the intended thought.".to_string();
+        for (label, text, m) in [("from", &input.from, &from_match), ("to", &input.to, &to_match)] {
+            if let EndpointMatch::Ambiguous(candidates) = m {
+                response.push_str(&format!("\n\nCandidates for \"{}\" ({}):\n", text, label));
+                response.push_str(&candidates.iter()
+                    .map(|t| format!("  • {} [{}] {}", t.id, t.category, snippet(&t.content, 80)))
+                    .collect::<Vec<_>>()
+                    .join("\n"));
+            }
+        }
+        return Ok(response);
+    }
+
+    let from_found = match from_match { EndpointMatch::Found(t) => Some(t), _ => None };
+    let to_found = match to_match { EndpointMatch::Found(t) => Some(t), _ => None };
+
+    if !input.create_missing {
+        if from_found.is_none() {
+            return Err(format!("Could not find thought: {}", input.from));
+        }
+        if to_found.is_none() {
+            return Err(format!("Could not find thought: {}", input.to));
+        }
+    }
+
+    // Missing endpoints are created next to whichever endpoint does exist
+    let mut created = Vec::new();
+    let from_thought = match from_found {
+        Some(t) => t,
+        None => {
+            let t = create_endpoint_thought(db, &input.from, to_found.as_ref())?;
+            created.push(format!("from → {} (id: {})", snippet(&t.content, 50), t.id));
+            t
+        }
+    };
+    let to_thought = match to_found {
+        Some(t) => t,
+        None => {
+            let t = create_endpoint_thought(db, &input.to, Some(&from_thought))?;
+            created.push(format!("to → {} (id: {})", snippet(&t.content, 50), t.id));
+            t
+        }
+    };
     
     let id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
@@ -363,59 +1403,778 @@ fn handle_mind_connect(db: &Database, arguments: &Value) -> Result<String, Strin
         strength: 0.7,
         reason: input.reason.clone(),
         created_at: now,
+        kind: input.kind.clone(),
     };
     
     db.insert_connection(&connection).map_err(|e| e.to_string())?;
     
-    Ok(format!(
-        "🔗 Connection created in The Mind!\n\nFrom: \"{}\"\nTo: \"{}\"\nReason: {}",
-        &from_thought.content[..from_thought.content.len().min(50)],
-        &to_thought.content[..to_thought.content.len().min(50)],
+    let mut response = format!(
+        "🔗 Connection created in The Mind!\n\nFrom: \"{}\"\nTo: \"{}\"\nKind: {}\nReason: {}",
+        snippet(&from_thought.content, 50),
+        snippet(&to_thought.content, 50),
+        input.kind,
         input.reason
-    ))
+    );
+
+    if !created.is_empty() {
+        response.push_str(&format!(
+            "\n\n🆕 Created {} new thought(s) for missing endpoint(s):\n{}",
+            created.len(),
+            created.iter().map(|c| format!("  • {}", c)).collect::<Vec<_>>().join("\n")
+        ));
+    }
+
+    Ok(response)
+}
+
+/// Resolve a mind_connect endpoint: an id, an exact content match, or a unique search hit.
+/// With `exact`, only ids and exact content matches count.
+fn resolve_endpoint(db: &Database, text: &str, exact: bool) -> Result<EndpointMatch, String> {
+    if let Some(t) = db.get_thought_by_id(text.trim()).map_err(|e| e.to_string())? {
+        return Ok(EndpointMatch::Found(t));
+    }
+    if let Some(t) = db.find_thought_by_content(text).map_err(|e| e.to_string())? {
+        return Ok(EndpointMatch::Found(t));
+    }
+    if exact {
+        return Ok(EndpointMatch::Missing);
+    }
+
+    let mut candidates = db.search_thoughts(text).map_err(|e| e.to_string())?;
+    Ok(match candidates.len() {
+        0 => EndpointMatch::Missing,
+        1 => EndpointMatch::Found(candidates.remove(0)),
+        _ => {
+            candidates.truncate(5);
+            EndpointMatch::Ambiguous(candidates)
+        }
+    })
+}
+
+/// Log a placeholder thought for a mind_connect endpoint that didn't exist yet,
+/// placed near `anchor` when there is one.
+fn create_endpoint_thought(db: &Database, content: &str, anchor: Option<&crate::Thought>) -> Result<crate::Thought, String> {
+    let content = validation::content(content, validation::max_content_chars(db))?;
+    let mut random = db.position_random(&content, "other");
+    let (x, y, z) = db.clear_position(match anchor {
+        Some(a) => Database::generate_position_near(a.position_x, a.position_y, a.position_z, 3.0, &mut random),
+        None => db.generate_position_for_category("other", &mut random),
+    });
+    let now = Utc::now().to_rfc3339();
+
+    let thought = crate::Thought {
+        id: Uuid::new_v4().to_string(),
+        content,
+        role: Some("assistant".to_string()),
+        category: "other".to_string(),
+        importance: 0.5,
+        position_x: x,
+        position_y: y,
+        position_z: z,
+        created_at: now.clone(),
+        last_referenced: now,
+        pinned: false,
+        favorite: false,
+        archived: false,
+    };
+
+    db.insert_thought(&thought).map_err(|e| e.to_string())?;
+    Ok(thought)
 }
 
 fn handle_mind_recall(db: &Database, arguments: &Value) -> Result<String, String> {
     let input: MindRecallInput = serde_json::from_value(arguments.clone())
         .map_err(|e| format!("Invalid arguments: {}", e))?;
     
-    let thoughts = db.search_thoughts(&input.query).map_err(|e| e.to_string())?;
-    
-    if thoughts.is_empty() {
-        return Ok(format!("No thoughts found matching: \"{}\"", input.query));
+    if let Some(role) = &input.role {
+        check_role(role)?;
+    }
+    // "yesterday", "last week" and the like become created_at bounds rather than search terms;
+    // with nothing else left, everything in the range matches
+    let today = (Utc::now() + chrono::Duration::minutes(input.tz_offset_minutes as i64)).date_naive();
+    let (range, query) = match crate::utils::extract_date_range(&input.query, today) {
+        Some((range, rest)) => (Some(range), recall_terms(&rest)),
+        None => (None, input.query.clone()),
+    };
+    let has_terms = range.is_none() || !query.is_empty();
+    let in_range = |t: &crate::Thought| range.is_none_or(|r| r.contains(&t.created_at, input.tz_offset_minutes));
+
+    let mut thoughts = match range {
+        Some(range) => {
+            let (created_after, created_before) = range.bounds(input.tz_offset_minutes);
+            let filter = crate::QueryDefinition {
+                text: has_terms.then(|| query.clone()),
+                created_after: Some(created_after),
+                created_before: Some(created_before),
+                role: input.role.clone(),
+                ..Default::default()
+            };
+            db.run_query(&filter, RANGE_SEARCH_LIMIT).map_err(|e| e.to_string())?
+        }
+        None => db.search_thoughts_by(&query, false, input.role.as_deref()).map_err(|e| e.to_string())?,
+    };
+    // A misspelled query falls back to near-matches; highlighting follows the suggestion
+    let mut suggestion = None;
+    if thoughts.is_empty() && has_terms {
+        if let Some(fuzzy) = crate::search::fuzzy_search(db, &query, false, input.role.as_deref()).map_err(|e| e.to_string())? {
+            thoughts = fuzzy.thoughts.into_iter().filter(|t| in_range(t)).collect();
+            suggestion = Some(fuzzy.suggestion).filter(|_| !thoughts.is_empty());
+        }
+    }
+    let highlight_query = suggestion.as_deref().unwrap_or(if has_terms { &query } else { "" });
+    let (connections, sessions) = if has_terms {
+        (
+            db.search_connections(&query, input.limit as i64).map_err(|e| e.to_string())?,
+            db.search_sessions(&query, input.limit as i64).map_err(|e| e.to_string())?,
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let range_note = range.map(|r| format!("📅 Searching {}", r));
+
+    // Semantic matches are merged in when asked for; any failure leaves the keyword results
+    let mut semantic_note = None;
+    let semantic = if input.semantic && has_terms {
+        match crate::embeddings::HttpProvider::from_settings(db) {
+            Some(provider) => crate::embeddings::search(db, &provider, &query, input.limit)
+                .unwrap_or_else(|e| {
+                    semantic_note = Some(format!("Semantic search unavailable ({}); showing keyword matches only.", e));
+                    Vec::new()
+                }),
+            None => {
+                semantic_note = Some("Semantic search is off (embeddings_enabled); showing keyword matches only.".to_string());
+                Vec::new()
+            }
+        }
+    } else {
+        Vec::new()
+    };
+    // Filtered before merging so the limit counts only thoughts that pass
+    let code_matches = |t: &crate::Thought| {
+        input.has_code.is_none_or(|wanted| crate::utils::detect_code(&t.content).is_some() == wanted)
+    };
+    let semantic = semantic.into_iter()
+        .filter(|(t, _)| input.role.is_none() || t.role == input.role)
+        .filter(|(t, _)| in_range(t))
+        .filter(|(t, _)| code_matches(t))
+        .collect();
+    let thoughts = thoughts.into_iter().filter(|t| code_matches(t)).collect();
+    let thoughts = crate::embeddings::merge_results(thoughts, semantic, input.limit);
+    // Thoughts recalled together strengthen the connections between them
+    let ids: Vec<String> = thoughts.iter().map(|(t, _)| t.id.clone()).collect();
+    if let Err(e) = db.reinforce_connections(&ids) {
+        tracing::warn!("Reinforcing connections failed: {}", e);
+    }
+
+    if thoughts.is_empty() && connections.is_empty() && sessions.is_empty() {
+        let mut response = format!("No thoughts found matching: \"{}\"", input.query);
+        if let Some(note) = &range_note {
+            response.push_str(&format!("\n\n{}", note));
+        }
+        if let Some(note) = semantic_note {
+            response.push_str(&format!("\n\n{}", note));
+        }
+        return Ok(response);
     }
     
+    let locked = db.locked_among(&ids).map_err(|e| e.to_string())?;
     let results: Vec<String> = thoughts.iter()
-        .take(input.limit)
+        .map(|(t, similarity)| {
+            let similarity = similarity.map(|s| format!(", similarity: {:.0}%", s * 100.0)).unwrap_or_default();
+            format!(
+                "• {}{}[{}] {} (importance: {:.0}%{})",
+                if locked.contains(&t.id) { "🔒 " } else { "" },
+                if t.favorite { "⭐ " } else { "" },
+                t.category,
+                crate::search::highlight(&t.content, highlight_query),
+                t.importance * 100.0,
+                similarity
+            )
+        })
+        .collect();
+    
+    // Only connections or sessions matched
+    let mut response = if results.is_empty() {
+        format!("No thoughts found matching: \"{}\"", input.query)
+    } else if let Some(suggestion) = &suggestion {
+        format!(
+            "🧠 No exact matches for \"{}\" — did you mean: {}? Showing {} fuzzy match(es):\n\n{}",
+            input.query,
+            suggestion,
+            results.len(),
+            results.join("\n")
+        )
+    } else {
+        format!(
+            "🧠 Found {} thought(s) matching \"{}\":\n\n{}",
+            results.len(),
+            input.query,
+            results.join("\n")
+        )
+    };
+    if let Some(note) = range_note {
+        response = format!("{}\n\n{}", note, response);
+    }
+    if let Some(note) = semantic_note {
+        response.push_str(&format!("\n\n{}", note));
+    }
+
+    if !connections.is_empty() {
+        let lines: Vec<String> = connections.iter()
+            .map(|m| format!(
+                "• \"{}\" → \"{}\" ({}): {}",
+                snippet(&m.from_content, 50),
+                snippet(&m.to_content, 50),
+                m.connection.kind,
+                m.connection.reason
+            ))
+            .collect();
+        response.push_str(&format!("\n\n🔗 Relevant connections:\n{}", lines.join("\n")));
+    }
+
+    if !sessions.is_empty() {
+        let lines: Vec<String> = sessions.iter()
+            .map(|s| match s.summary.as_deref().filter(|summary| !summary.is_empty()) {
+                Some(summary) => format!("• {} ({}): {}", s.title, s.started_at, snippet(summary, 160)),
+                None => format!("• {} ({})", s.title, s.started_at),
+            })
+            .collect();
+        response.push_str(&format!("\n\n📚 Matching sessions:\n{}", lines.join("\n")));
+    }
+
+    Ok(response)
+}
+
+fn handle_mind_query(db: &Database, arguments: &Value) -> Result<String, String> {
+    let query: crate::QueryDefinition = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+    let query = validation::query(query)?;
+    let limit = query.limit.unwrap_or(validation::DEFAULT_QUERY_LIMIT);
+    let thoughts = db.run_query(&query, limit).map_err(|e| e.to_string())?;
+    if thoughts.is_empty() {
+        return Ok("No thoughts match this query".to_string());
+    }
+
+    let lines: Vec<String> = thoughts.iter()
         .map(|t| format!(
-            "• [{}] {} (importance: {:.0}%)",
+            "• [{}] {} (importance: {:.0}%, created: {}, id: {})",
+            t.category,
+            snippet(&t.content, 200),
+            t.importance * 100.0,
+            t.created_at,
+            t.id
+        ))
+        .collect();
+    let more = if thoughts.len() == limit { " (limit reached)" } else { "" };
+    Ok(format!("🧠 {} thought(s) match{}:\n\n{}", thoughts.len(), more, lines.join("\n")))
+}
+
+fn handle_mind_similar(db: &Database, arguments: &Value) -> Result<String, String> {
+    let input: MindSimilarInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    let thought = match resolve_endpoint(db, &input.thought, false)? {
+        EndpointMatch::Found(t) => t,
+        EndpointMatch::Missing => return Err(format!("No thought found matching: \"{}\"", input.thought)),
+        EndpointMatch::Ambiguous(candidates) => {
+            let lines: Vec<String> = candidates.iter()
+                .map(|t| format!("  • {} [{}] {}", t.id, t.category, snippet(&t.content, 80)))
+                .collect();
+            return Ok(format!(
+                "🤔 More than one thought matches \"{}\". Retry with the id of the intended thought:\n{}",
+                input.thought,
+                lines.join("\n")
+            ));
+        }
+    };
+
+    let similar = db.get_similar_thoughts(&thought.id, input.limit.max(1)).map_err(|e| e.to_string())?;
+    if similar.is_empty() {
+        return Ok(format!("No unconnected thoughts are similar to \"{}\"", snippet(&thought.content, 80)));
+    }
+
+    let lines: Vec<String> = similar.iter()
+        .map(|s| format!(
+            "• [{}] {} (similarity: {:.0}% by {}, id: {})",
+            s.thought.category,
+            s.thought.content,
+            s.score * 100.0,
+            s.basis,
+            s.thought.id
+        ))
+        .collect();
+    Ok(format!(
+        "🧭 {} thought(s) like \"{}\" and not yet connected to it:\n\n{}",
+        similar.len(),
+        snippet(&thought.content, 80),
+        lines.join("\n")
+    ))
+}
+
+fn handle_mind_review(db: &Database, arguments: &Value) -> Result<String, String> {
+    let input: MindReviewInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    let queue = db.get_review_queue(input.limit.max(1), false).map_err(|e| e.to_string())?;
+    if queue.is_empty() {
+        return Ok("Nothing to review yet — The Mind is empty.".to_string());
+    }
+
+    let lines: Vec<String> = queue.iter()
+        .map(|(t, days, _)| format!(
+            "• [{}] {} (importance: {:.0}%, last referenced {:.0} day(s) ago, id: {})",
             t.category,
             t.content,
-            t.importance * 100.0
+            t.importance * 100.0,
+            days,
+            t.id
         ))
         .collect();
-    
+
     Ok(format!(
-        "🧠 Found {} thought(s) matching \"{}\":\n\n{}",
-        results.len(),
-        input.query,
-        results.join("\n")
+        "🔁 You haven't revisited these in a while — worth bringing back up?\n\n{}",
+        lines.join("\n")
     ))
 }
 
+fn handle_mind_digest(db: &Database, arguments: &Value) -> Result<String, String> {
+    let input: MindDigestInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    crate::digest::daily_digest(db, input.date.as_deref(), input.tz_offset_minutes)
+}
+
+fn handle_mind_weekly_reflection(db: &Database, arguments: &Value) -> Result<String, String> {
+    let input: MindWeeklyReflectionInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    crate::reflection::weekly_reflection(db, input.end_date.as_deref(), input.tz_offset_minutes, input.max_chars)
+}
+
+fn handle_mind_export_graph(db: &Database, arguments: &Value) -> Result<String, String> {
+    let input: MindExportGraphInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    let since = input.since.as_deref().map(crate::utils::parse_since).transpose()?;
+    let snapshot = db.get_graph_snapshot(input.category.as_deref(), since.as_deref(), input.max_nodes)
+        .map_err(|e| e.to_string())?;
+    serde_json::to_string(&snapshot).map_err(|e| e.to_string())
+}
+
 fn handle_mind_summarize(db: &Database, arguments: &Value) -> Result<String, String> {
     let input: MindSummarizeInput = serde_json::from_value(arguments.clone())
         .map_err(|e| format!("Invalid arguments: {}", e))?;
 
-    let id = Uuid::new_v4().to_string();
     let now = Utc::now().to_rfc3339();
 
-    // Store in the sessions table (not as a fake thought)
-    db.insert_session(&id, &input.title, &input.summary, &now, &now)
-        .map_err(|e| e.to_string())?;
+    // An auto-detected session in progress gets this summary and is closed;
+    // otherwise the summary becomes a session of its own
+    match crate::auto_session::take_active_session(db).map_err(|e| e.to_string())? {
+        Some(id) => {
+            db.update_session(&id, Some(&input.title), Some(&input.summary)).map_err(|e| e.to_string())?;
+            db.end_session(&id, &now).map_err(|e| e.to_string())?;
+        }
+        None => {
+            // Store in the sessions table (not as a fake thought)
+            let id = Uuid::new_v4().to_string();
+            db.insert_session(&id, &input.title, &input.summary, &now, &now)
+                .map_err(|e| e.to_string())?;
+        }
+    }
 
     Ok(format!(
         "📝 Session summarized and logged to The Mind!\n\nTitle: {}\nSummary: {}",
         input.title, input.summary
     ))
 }
+
+fn handle_mind_session_recall(db: &Database, arguments: &Value) -> Result<String, String> {
+    let input: MindSessionRecallInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    let detail = db.get_session_detail(&input.id_or_title)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No session found matching \"{}\"", input.id_or_title))?;
+
+    let mut response = format!("📚 Session: {}\nStarted: {}", detail.session.title, detail.session.started_at);
+    if let Some(minutes) = detail.stats.duration_seconds.map(|s| s / 60).filter(|m| *m > 0) {
+        response.push_str(&format!(" ({} min)", minutes));
+    }
+    if let Some(summary) = detail.session.summary.as_deref().filter(|s| !s.is_empty()) {
+        response.push_str(&format!("\n\nSummary: {}", summary));
+    }
+
+    if detail.thoughts.is_empty() {
+        response.push_str("\n\nNo thoughts were logged during this session.");
+    } else {
+        let lines: Vec<String> = detail.thoughts.iter()
+            .map(|t| format!("• [{}] {}", t.category, t.content))
+            .collect();
+        response.push_str(&format!(
+            "\n\n💭 {} thought(s) across {}:\n{}",
+            detail.stats.thought_count,
+            detail.stats.categories.join(", "),
+            lines.join("\n")
+        ));
+    }
+
+    if !detail.connections.is_empty() {
+        let lines: Vec<String> = detail.connections.iter()
+            .map(|c| format!("• ({}) {}", c.kind, c.reason))
+            .collect();
+        response.push_str(&format!("\n\n🔗 {} connection(s) made:\n{}", detail.stats.connection_count, lines.join("\n")));
+    }
+
+    Ok(response)
+}
+
+fn handle_mind_session_update(db: &Database, arguments: &Value) -> Result<String, String> {
+    let input: MindSessionUpdateInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    let title = input.title.as_deref().map(str::trim);
+    if title.is_some_and(str::is_empty) {
+        return Err("Session title cannot be empty".to_string());
+    }
+    if title.is_none() && input.summary.is_none() {
+        return Err("Nothing to update: pass a title and/or summary".to_string());
+    }
+
+    let session = db.find_session(&input.id_or_title)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No session found matching \"{}\"", input.id_or_title))?;
+
+    db.update_session(&session.id, title, input.summary.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    Ok(format!(
+        "✏️ Session updated.\n\nTitle: {}\nSummary: {}",
+        title.unwrap_or(&session.title),
+        input.summary.as_deref().or(session.summary.as_deref()).unwrap_or("")
+    ))
+}
+
+fn handle_mind_forge_import(db: &Database, arguments: &Value, progress: Progress) -> Result<String, String> {
+    let input: MindForgeImportInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    let result = crate::forge_import::import_forge(db, &input.kinds, progress)?;
+    let lines: Vec<String> = result.kinds.iter()
+        .map(|k| format!("• {}: {} imported, {} updated, {} unchanged", k.kind, k.imported, k.updated, k.skipped))
+        .collect();
+
+    let mut text = format!(
+        "🔨 Imported session-forge entries into The Mind:\n\n{}\n\n🔗 {} decision ↔ dead-end connection(s) created",
+        lines.join("\n"),
+        result.connections_created
+    );
+    if !result.warnings.is_empty() {
+        text.push_str(&format!("\n\n⚠️ Skipped malformed entries ({}):", result.warnings.len()));
+        for w in &result.warnings {
+            text.push_str(&format!("\n• {} #{}: {}", w.file, w.index.map_or("?".to_string(), |i| i.to_string()), w.message));
+        }
+    }
+    Ok(text)
+}
+
+fn handle_mind_log_decision(db: &Database, arguments: &Value) -> Result<String, String> {
+    let input: MindLogDecisionInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    let entry = crate::session_forge::DecisionEntry {
+        timestamp: Utc::now().to_rfc3339(),
+        choice: input.choice,
+        alternatives: input.alternatives,
+        reasoning: input.reasoning,
+        outcome: None,
+        project: input.project,
+        tags: input.tags,
+    };
+    let forge_timestamp = entry.timestamp.clone();
+    let (importance, importance_note) = validation::unit_interval("importance", input.importance)?;
+    let thought = crate::forge_import::log_decision(db, entry, importance)?;
+
+    let outcome = auto_connect(db, &thought, &AutoConnectConfig::load(db)).unwrap_or_default();
+    crate::embeddings::embed_new_thought(db, &thought);
+
+    let mut response = format!(
+        "⚖️ Decision recorded in The Mind and session-forge.\n\nThought ID: {}\nForge entry: decisions.json @ {}\nCategory: {}\nContent: \"{}\"",
+        thought.id, forge_timestamp, thought.category, thought.content
+    );
+    if !outcome.connected.is_empty() {
+        response.push_str(&format!("\n\n🔗 Auto-connected to {} existing thought(s)", outcome.connected.len()));
+    }
+    if let Some(note) = importance_note {
+        response.push_str(&format!("\n⚠️ Note: {}", note));
+    }
+
+    Ok(response)
+}
+
+fn handle_mind_import_transcript(db: &Database, arguments: &Value, progress: Progress) -> Result<String, String> {
+    let input: MindImportTranscriptInput = serde_json::from_value(arguments.clone())
+        .map_err(|e| format!("Invalid arguments: {}", e))?;
+
+    let result = crate::transcript::import_transcript(db, &input.text, &input.title, progress)
+        .map_err(|e| e.to_string())?;
+
+    Ok(format!(
+        "📥 Transcript imported into The Mind!\n\nSession: {} (id: {})\nTurns found: {}\nThoughts created: {}\nShort turns skipped: {}\nAuto-connections: {}",
+        input.title, result.session_id, result.turns_found, result.thoughts_created, result.turns_skipped, result.connections_created
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use serde_json::{json, Value};
+    use super::{handle_line, negotiate, progress_notification, serve, McpSession, PROTOCOL_VERSIONS};
+    use crate::database::testing::TempDb;
+
+    /// Call a tool through the JSON-RPC handler; the text of its result and whether it's an error
+    fn call(db: &TempDb, tool: &str, arguments: Value) -> (String, bool) {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": tool, "arguments": arguments },
+        });
+        let response: Value = serde_json::from_str(&handle_line(db, &McpSession::default(), &request.to_string()).unwrap()).unwrap();
+        let result = &response["result"];
+        (result["content"][0]["text"].as_str().unwrap().to_string(), result["isError"] == json!(true))
+    }
+
+    fn log(db: &TempDb, content: &str, force: bool) -> String {
+        let (text, is_error) = call(db, "mind_log", json!({ "content": content, "category": "technical", "importance": 0.5, "force": force }));
+        assert!(!is_error, "{}", text);
+        text
+    }
+
+    #[test]
+    fn connect_cuts_multibyte_content_on_char_boundaries() {
+        let db = TempDb::new();
+        let from = format!("{}é and more", "a".repeat(49));
+        let to = "日本語のテキスト".repeat(10);
+        log(&db, &from, false);
+        log(&db, &to, false);
+
+        let (text, is_error) = call(&db, "mind_connect", json!({ "from": from, "to": to, "reason": "test", "exact": true }));
+
+        assert!(!is_error, "{}", text);
+        assert!(text.contains(&format!("From: \"{}é\"", "a".repeat(49))), "{}", text);
+        assert!(text.contains(&format!("To: \"{}\"", to.chars().take(50).collect::<String>())), "{}", text);
+    }
+
+    /// The thought id from a mind_log response
+    fn logged_id(text: &str) -> String {
+        text.lines().find_map(|line| line.strip_prefix("ID: ")).expect("response names the thought").to_string()
+    }
+
+    fn position(db: &TempDb, id: &str) -> (f64, f64, f64) {
+        let t = db.get_thought_by_id(id).unwrap().unwrap();
+        (t.position_x, t.position_y, t.position_z)
+    }
+
+    fn session_thought_ids(db: &TempDb, session_id: &str) -> Vec<String> {
+        db.get_session_thoughts(session_id).unwrap().into_iter().map(|t| t.id).collect()
+    }
+
+    #[test]
+    fn log_appends_to_the_given_session() {
+        let db = TempDb::new();
+        db.open_session("s1", "Planning", "2026-03-01T12:00:00+00:00").unwrap();
+
+        let (first, is_error) = call(&db, "mind_log", json!({ "content": "Ship the graph view first", "category": "technical", "importance": 0.5, "session_id": "s1" }));
+        assert!(!is_error, "{}", first);
+        assert!(first.contains("\nSession: s1"), "{}", first);
+        let (second, _) = call(&db, "mind_log", json!({ "content": "Kubernetes pods restart on failed probes", "category": "technical", "importance": 0.5, "session_id": "s1" }));
+
+        assert_eq!(session_thought_ids(&db, "s1"), [logged_id(&first), logged_id(&second)]);
+    }
+
+    #[test]
+    fn log_refuses_an_unknown_session_without_adding_the_thought() {
+        let db = TempDb::new();
+
+        let (text, is_error) = call(&db, "mind_log", json!({ "content": "An orphaned idea", "category": "technical", "importance": 0.5, "session_id": "nope" }));
+
+        assert!(is_error);
+        assert_eq!(text, "Error: No session with id nope");
+        assert_eq!(db.get_thought_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn log_places_a_thought_at_the_given_point() {
+        let db = TempDb::new();
+
+        let (text, _) = call(&db, "mind_log", json!({ "content": "Pinned to a spot", "category": "technical", "importance": 0.5, "near": { "x": 1.5, "y": -2.0, "z": 30.25 } }));
+
+        assert_eq!(position(&db, &logged_id(&text)), (1.5, -2.0, 30.25));
+    }
+
+    #[test]
+    fn log_places_a_thought_next_to_another() {
+        let db = TempDb::new();
+        let (anchor, _) = call(&db, "mind_log", json!({ "content": "Far out anchor", "category": "technical", "importance": 0.5, "near": { "x": 500.0, "y": 500.0, "z": 500.0 } }));
+        let anchor = logged_id(&anchor);
+
+        let (text, _) = call(&db, "mind_log", json!({ "content": "Kubernetes pods restart on failed probes", "category": "technical", "importance": 0.5, "near": anchor }));
+
+        let (x, y, z) = position(&db, &logged_id(&text));
+        let distance = ((x - 500.0).powi(2) + (y - 500.0).powi(2) + (z - 500.0).powi(2)).sqrt();
+        // Within the jitter plus any nudges off the anchor itself
+        assert!(distance < 15.0, "{} from the anchor", distance);
+        assert!(!text.contains("to place it near"), "{}", text);
+    }
+
+    #[test]
+    fn log_near_an_unknown_thought_notes_it_and_still_logs() {
+        let db = TempDb::new();
+
+        let (text, is_error) = call(&db, "mind_log", json!({ "content": "Nowhere in particular", "category": "technical", "importance": 0.5, "near": "missing-id" }));
+
+        assert!(!is_error, "{}", text);
+        assert!(text.contains("No thought with id missing-id to place it near"), "{}", text);
+        assert_eq!(db.get_thought_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn log_applies_session_and_placement_together() {
+        let db = TempDb::new();
+        db.open_session("s1", "Planning", "2026-03-01T12:00:00+00:00").unwrap();
+
+        let (text, _) = call(&db, "mind_log", json!({ "content": "Both at once", "category": "technical", "importance": 0.5, "session_id": "s1", "near": { "x": 7.0, "y": 8.0, "z": 9.0 } }));
+
+        let id = logged_id(&text);
+        assert_eq!(position(&db, &id), (7.0, 8.0, 9.0));
+        assert_eq!(session_thought_ids(&db, "s1"), [id]);
+    }
+
+    #[test]
+    fn a_reinforced_duplicate_joins_the_given_session() {
+        let db = TempDb::new();
+        db.open_session("s1", "Planning", "2026-03-01T12:00:00+00:00").unwrap();
+        let id = logged_id(&log(&db, "Use WAL mode so readers never block the writer", false));
+
+        let (text, _) = call(&db, "mind_log", json!({ "content": "Use WAL mode so readers never block the writer", "category": "technical", "importance": 0.5, "session_id": "s1", "near": { "x": 7.0, "y": 8.0, "z": 9.0 } }));
+
+        assert!(text.starts_with("♻️ Reinforced existing thought"), "{}", text);
+        assert_eq!(session_thought_ids(&db, "s1"), std::slice::from_ref(&id));
+        // The existing node stays where it was
+        assert_ne!(position(&db, &id), (7.0, 8.0, 9.0));
+    }
+
+    fn initialize(params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": params });
+        serde_json::from_str(&handle_line(&TempDb::new(), &McpSession::default(), &request.to_string()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn negotiate_picks_the_newest_version_not_after_the_request() {
+        for version in PROTOCOL_VERSIONS {
+            assert_eq!(negotiate(version), Some(version));
+        }
+        // Between two supported versions, the older one
+        assert_eq!(negotiate("2025-05-01"), Some("2025-03-26"));
+        // A future version gets the newest we have
+        assert_eq!(negotiate("2099-01-01"), Some(PROTOCOL_VERSIONS[0]));
+        // Older than anything supported, or not a version at all
+        assert_eq!(negotiate("2024-01-01"), None);
+        assert_eq!(negotiate("latest"), None);
+        assert_eq!(negotiate(""), None);
+    }
+
+    #[test]
+    fn initialize_answers_with_the_negotiated_version() {
+        let response = initialize(json!({ "protocolVersion": "2024-11-05", "clientInfo": { "name": "test", "version": "1" } }));
+        assert_eq!(response["result"]["protocolVersion"], "2024-11-05");
+
+        let response = initialize(json!({ "protocolVersion": "2099-01-01" }));
+        assert_eq!(response["result"]["protocolVersion"], PROTOCOL_VERSIONS[0]);
+    }
+
+    #[test]
+    fn initialize_refuses_an_unsupported_or_missing_version() {
+        for params in [json!({ "protocolVersion": "2020-01-01" }), json!({}), Value::Null] {
+            let response = initialize(params.clone());
+            assert!(response.get("result").is_none(), "{}", response);
+            assert_eq!(response["error"]["code"], -32602);
+            assert_eq!(response["error"]["data"]["supported"], json!(PROTOCOL_VERSIONS));
+            let requested = params.get("protocolVersion").cloned().unwrap_or(json!(""));
+            assert_eq!(response["error"]["data"]["requested"], requested);
+        }
+    }
+
+    /// A client that has gone away: every write fails
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    #[test]
+    fn serve_stops_quietly_when_the_client_is_gone() {
+        let db = TempDb::new();
+        let requests: String = (1..=20)
+            .map(|id| format!("{}\n", json!({ "jsonrpc": "2.0", "id": id, "method": "tools/list" })))
+            .collect();
+
+        serve(&db, requests.as_bytes(), &mut ClosedPipe, None);
+    }
+
+    #[test]
+    fn progress_leaves_out_an_unknown_total() {
+        let token = json!("import-1");
+
+        let known = progress_notification(Some(&token), 3, Some(10), "Imported 3 of 10");
+        let unknown = progress_notification(Some(&token), 3, None, "Imported 3");
+
+        assert_eq!(known["params"]["total"], json!(10));
+        assert_eq!(unknown["params"]["progress"], json!(3));
+        assert!(unknown["params"].get("total").is_none(), "{}", unknown);
+    }
+
+    #[test]
+    fn an_exact_duplicate_reinforces_the_existing_thought() {
+        let db = TempDb::new();
+        log(&db, "Use WAL mode so readers never block the writer", false);
+
+        let text = log(&db, "  use wal mode so READERS never block the writer ", false);
+
+        assert!(text.starts_with("♻️ Reinforced existing thought"), "{}", text);
+        assert_eq!(db.get_thought_count().unwrap(), 1);
+        let thought = &db.get_all_thoughts().unwrap()[0];
+        assert!((thought.importance - 0.55).abs() < 1e-9, "{}", thought.importance);
+    }
+
+    #[test]
+    fn a_near_duplicate_reinforces_the_existing_thought() {
+        let db = TempDb::new();
+        log(&db, "Sqlite readers never block the writer in wal journal mode", false);
+
+        let text = log(&db, "Sqlite readers never block the writer in wal journal mode either", false);
+
+        assert!(text.starts_with("♻️ Reinforced existing thought"), "{}", text);
+        assert_eq!(db.get_thought_count().unwrap(), 1);
+
+        log(&db, "Kubernetes pods restart on failed health probes", false);
+        assert_eq!(db.get_thought_count().unwrap(), 2);
+    }
+
+    #[test]
+    fn force_logs_a_duplicate_as_a_new_thought() {
+        let db = TempDb::new();
+        log(&db, "Use WAL mode so readers never block the writer", false);
+
+        let text = log(&db, "Use WAL mode so readers never block the writer", true);
+
+        assert!(!text.contains("Reinforced"), "{}", text);
+        assert_eq!(db.get_thought_count().unwrap(), 2);
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, WebviewUrl, WebviewWindowBuilder};
+use crate::wallpaper;
+
+// Overlay windows are labeled identify-<call>-<monitor id>; the call number keeps a second
+// invocation's windows apart from the first's while those are still closing
+const LABEL_PREFIX: &str = "identify-";
+// Overlay edge length in logical pixels
+const OVERLAY_SIZE: f64 = 240.0;
+
+pub const DEFAULT_DURATION_MS: u64 = 2000;
+const MAX_DURATION_MS: u64 = 30_000;
+
+static CALLS: AtomicU64 = AtomicU64::new(0);
+
+/// Show each monitor's number in a small overlay centered on it, closing them after
+/// `duration_ms`. Overlays from an earlier call are closed first. Returns the monitor count.
+pub fn identify(app: &AppHandle, duration_ms: u64) -> Result<usize, String> {
+    close_overlays(app, LABEL_PREFIX);
+
+    let call = CALLS.fetch_add(1, Ordering::Relaxed) + 1;
+    let prefix = format!("{}{}-", LABEL_PREFIX, call);
+    let monitors = wallpaper::get_monitors();
+
+    for monitor in &monitors {
+        let label = format!("{}{}", prefix, monitor.id);
+        // The same bundle renders the number from the hash (see main.tsx)
+        let url = WebviewUrl::App(format!("index.html#identify-{}", monitor.id + 1).into());
+        let window = WebviewWindowBuilder::new(app, &label, url)
+            .title(format!("Monitor {}", monitor.id + 1))
+            .decorations(false)
+            .resizable(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .focused(false)
+            .visible(false)
+            .build();
+        let window = match window {
+            Ok(window) => window,
+            Err(e) => {
+                close_overlays(app, &prefix);
+                return Err(e.to_string());
+            }
+        };
+
+        // Monitor bounds are physical pixels, so place and size the overlay in physical pixels too
+        let size = (OVERLAY_SIZE * monitor.scale_factor).round() as i32;
+        let x = monitor.x + (monitor.width - size) / 2;
+        let y = monitor.y + (monitor.height - size) / 2;
+        let placed = window.set_size(PhysicalSize::new(size as u32, size as u32))
+            .and_then(|_| window.set_position(PhysicalPosition::new(x, y)))
+            .and_then(|_| window.set_ignore_cursor_events(true))
+            .and_then(|_| window.show());
+        if let Err(e) = placed {
+            close_overlays(app, &prefix);
+            return Err(e.to_string());
+        }
+    }
+
+    let app = app.clone();
+    let duration = Duration::from_millis(duration_ms.clamp(1, MAX_DURATION_MS));
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(duration).await;
+        close_overlays(&app, &prefix);
+    });
+
+    Ok(monitors.len())
+}
+
+/// Destroy every overlay window whose label starts with `prefix`
+fn close_overlays(app: &AppHandle, prefix: &str) {
+    for (label, window) in app.webview_windows() {
+        if label.starts_with(prefix) {
+            if let Err(e) = window.destroy() {
+                tracing::warn!("Failed to close monitor overlay {}: {}", label, e);
+            }
+        }
+    }
+}
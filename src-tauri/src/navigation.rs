@@ -0,0 +1,116 @@
+// Deep links: the-mind://thought/<id>, the-mind://session/<id>, the-mind://search/<query>
+
+use crate::database::Database;
+use crate::utils::lock_recovering;
+use serde::Serialize;
+use std::sync::Mutex;
+
+pub const DEEP_LINK_SCHEME: &str = "the-mind";
+
+/// Where a deep link asks the frontend to go (payload of the `mind://navigate` event)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum NavigateTarget {
+    Thought { id: String },
+    Session { id: String },
+    Search { query: String },
+}
+
+/// Parse a the-mind:// URL into a navigation target
+pub fn parse_deep_link(url: &str) -> Result<NavigateTarget, String> {
+    let rest = url
+        .strip_prefix(DEEP_LINK_SCHEME)
+        .and_then(|r| r.strip_prefix(':'))
+        .ok_or_else(|| format!("Not a {}:// link: {}", DEEP_LINK_SCHEME, url))?
+        .trim_start_matches('/');
+
+    // Query strings and fragments are not part of the target
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    let (kind, value) = rest.split_once('/').unwrap_or((rest, ""));
+    let value = percent_decode(value.trim_end_matches('/'))?;
+    let value = value.trim();
+
+    if value.is_empty() {
+        return Err(format!("Deep link is missing a target: {}", url));
+    }
+
+    match kind.to_lowercase().as_str() {
+        "thought" => Ok(NavigateTarget::Thought { id: value.to_string() }),
+        "session" => Ok(NavigateTarget::Session { id: value.to_string() }),
+        "search" => Ok(NavigateTarget::Search { query: value.to_string() }),
+        other => Err(format!("Unknown deep link target '{}' (expected thought, session or search)", other)),
+    }
+}
+
+/// Check that a thought/session target still exists before sending the frontend there
+pub fn validate_target(db: &Database, target: NavigateTarget) -> Result<NavigateTarget, String> {
+    let exists = match &target {
+        NavigateTarget::Thought { id } => db.get_thought_by_id(id).map(|t| t.is_some()),
+        NavigateTarget::Session { id } => db.session_exists(id),
+        NavigateTarget::Search { .. } => Ok(true),
+    }
+    .map_err(|e| e.to_string())?;
+
+    if exists {
+        Ok(target)
+    } else {
+        Err(format!("Deep link target not found: {:?}", target))
+    }
+}
+
+fn percent_decode(value: &str) -> Result<String, String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = value.get(i + 1..i + 3).ok_or("Truncated percent escape in deep link")?;
+                let byte = u8::from_str_radix(hex, 16).map_err(|_| format!("Invalid percent escape %{}", hex))?;
+                decoded.push(byte);
+                i += 3;
+            }
+            b => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| "Deep link is not valid UTF-8".to_string())
+}
+
+#[derive(Default)]
+struct QueueState {
+    frontend_ready: bool,
+    pending: Vec<NavigateTarget>,
+}
+
+/// Holds navigation targets that arrive before the frontend is listening
+/// (e.g. the app was launched cold by a link).
+#[derive(Default)]
+pub struct NavigationQueue {
+    state: Mutex<QueueState>,
+}
+
+impl NavigationQueue {
+    /// Deliver `target` now if the frontend is ready, otherwise queue it
+    pub fn dispatch(&self, target: NavigateTarget, emit: impl Fn(&NavigateTarget)) {
+        let mut state = lock_recovering(&self.state);
+        if state.frontend_ready {
+            emit(&target);
+        } else {
+            state.pending.push(target);
+        }
+    }
+
+    /// Mark the frontend ready and flush anything queued, in arrival order
+    pub fn mark_ready(&self, emit: impl Fn(&NavigateTarget)) -> usize {
+        let mut state = lock_recovering(&self.state);
+        state.frontend_ready = true;
+        let pending = std::mem::take(&mut state.pending);
+        pending.iter().for_each(&emit);
+        pending.len()
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri_plugin_notification::NotificationExt;
+use crate::database::Database;
+use crate::utils::snippet;
+use crate::Thought;
+
+// Settings keys (also readable/writable through get_setting/set_setting)
+pub const SETTING_ENABLED: &str = "notify_enabled";
+pub const SETTING_MIN_IMPORTANCE: &str = "notify_min_importance";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// At most one toast per this interval; anything in between is summarized as "and N more"
+const MIN_NOTIFY_GAP: Duration = Duration::from_secs(30);
+const CONTENT_PREVIEW_CHARS: usize = 120;
+
+/// Notification settings, re-read every poll so changes apply without a restart
+#[derive(Debug, Clone)]
+pub struct NotifyConfig {
+    pub enabled: bool,
+    /// Only thoughts at or above this importance produce a toast
+    pub min_importance: f64,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        NotifyConfig {
+            enabled: true,
+            min_importance: 0.8,
+        }
+    }
+}
+
+impl NotifyConfig {
+    /// Load from the settings table, using defaults for anything unset
+    pub fn load(db: &Database) -> Self {
+        let defaults = NotifyConfig::default();
+        NotifyConfig {
+            enabled: db.setting_or(SETTING_ENABLED, defaults.enabled),
+            min_importance: db.setting_or(SETTING_MIN_IMPORTANCE, defaults.min_importance),
+        }
+    }
+}
+
+/// Watch for thoughts written by other processes (the MCP server) and toast the important ones.
+/// Polls the same cheap rowid high-water mark the frontend sync uses.
+pub fn spawn_watcher(app: tauri::AppHandle, db: Arc<Database>) {
+    std::thread::spawn(move || {
+        // Start from what's already there; only thoughts logged while we run are news
+        let mut last_seen = db.get_max_thought_rowid().unwrap_or(0);
+        let mut pending: Vec<Thought> = Vec::new();
+        let mut last_notified: Option<Instant> = None;
+
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+
+            let config = NotifyConfig::load(&db);
+            let max_rowid = match db.get_max_thought_rowid() {
+                Ok(rowid) => rowid,
+                Err(e) => {
+                    tracing::warn!("Notification watcher failed to read thoughts: {}", e);
+                    continue;
+                }
+            };
+
+            if max_rowid > last_seen {
+                if config.enabled {
+                    match db.get_thoughts_in_rowid_range(last_seen, max_rowid, config.min_importance) {
+                        Ok(thoughts) => pending.extend(thoughts),
+                        Err(e) => tracing::warn!("Notification watcher failed to read thoughts: {}", e),
+                    }
+                }
+                last_seen = max_rowid;
+            }
+
+            if !config.enabled {
+                pending.clear();
+                continue;
+            }
+
+            let gap_elapsed = last_notified.is_none_or(|at| at.elapsed() >= MIN_NOTIFY_GAP);
+            if pending.is_empty() || !gap_elapsed {
+                continue;
+            }
+
+            notify(&app, &pending);
+            pending.clear();
+            last_notified = Some(Instant::now());
+        }
+    });
+}
+
+/// One toast for the oldest pending thought, summarizing the rest
+fn notify(app: &tauri::AppHandle, pending: &[Thought]) {
+    let Some(first) = pending.first() else {
+        return;
+    };
+
+    let mut body = snippet(&first.content, CONTENT_PREVIEW_CHARS);
+    if first.content.chars().count() > CONTENT_PREVIEW_CHARS {
+        body.push('…');
+    }
+    if pending.len() > 1 {
+        body.push_str(&format!("\nand {} more", pending.len() - 1));
+    }
+
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title(format!("New {} thought", first.category))
+        .body(body)
+        .show()
+    {
+        tracing::warn!("Failed to show notification: {}", e);
+    }
+}
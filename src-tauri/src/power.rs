@@ -0,0 +1,215 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use serde::Serialize;
+use crate::utils::lock_recovering;
+
+pub const POWER_CHANGED_EVENT: &str = "mind://power-changed";
+pub const PRESENTATION_EVENT: &str = "mind://presentation-mode";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// A new reading must repeat on this many polls in a row before it's reported, so a
+/// flapping charger or a brief fullscreen video doesn't produce a burst of events
+const STABLE_POLLS: u32 = 2;
+/// Battery level changes smaller than this aren't worth an event
+const PERCENT_STEP: u8 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    Unknown,
+}
+
+/// Payload of mind://power-changed and the get_power_status result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PowerStatus {
+    pub source: PowerSource,
+    /// None without a battery, or when the level can't be read
+    pub battery_percent: Option<u8>,
+}
+
+impl PowerStatus {
+    const UNKNOWN: PowerStatus = PowerStatus { source: PowerSource::Unknown, battery_percent: None };
+
+    /// Whether going from `self` to `next` is worth telling the frontend about
+    fn differs_notably(&self, next: &PowerStatus) -> bool {
+        match (self.battery_percent, next.battery_percent) {
+            _ if self.source != next.source => true,
+            (Some(a), Some(b)) => a.abs_diff(b) >= PERCENT_STEP,
+            (a, b) => a.is_some() != b.is_some(),
+        }
+    }
+}
+
+/// Current power source and battery level
+#[cfg(windows)]
+pub fn power_status() -> PowerStatus {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return PowerStatus::UNKNOWN;
+    }
+    let source = match status.ACLineStatus {
+        0 => PowerSource::Battery,
+        1 => PowerSource::Ac,
+        _ => PowerSource::Unknown,
+    };
+    // 128 = no system battery; 255 = level unknown
+    let battery_percent = (status.BatteryFlag & 128 == 0 && status.BatteryLifePercent <= 100)
+        .then_some(status.BatteryLifePercent);
+    PowerStatus { source, battery_percent }
+}
+
+/// Current power source and battery level, from /sys/class/power_supply
+#[cfg(target_os = "linux")]
+pub fn power_status() -> PowerStatus {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return PowerStatus::UNKNOWN;
+    };
+    let read = |dir: &std::path::Path, file: &str| {
+        std::fs::read_to_string(dir.join(file)).map(|v| v.trim().to_string()).ok()
+    };
+
+    let mut mains_online = None;
+    let mut battery: Option<(u8, bool)> = None; // (percent, discharging)
+    for supply in supplies.flatten() {
+        let dir = supply.path();
+        match read(&dir, "type").as_deref() {
+            Some("Mains") => {
+                let online = read(&dir, "online").as_deref() == Some("1");
+                mains_online = Some(mains_online.unwrap_or(false) || online);
+            }
+            // Only the system battery; peripherals (mice, headsets) report scope "Device"
+            Some("Battery") if read(&dir, "scope").as_deref() != Some("Device") => {
+                if let Some(percent) = read(&dir, "capacity").and_then(|c| c.parse::<u8>().ok()) {
+                    let discharging = read(&dir, "status").as_deref() == Some("Discharging");
+                    battery = Some((percent.min(100), discharging));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let source = match (mains_online, battery) {
+        (Some(true), _) => PowerSource::Ac,
+        (Some(false), Some(_)) => PowerSource::Battery,
+        (None, Some((_, discharging))) => if discharging { PowerSource::Battery } else { PowerSource::Ac },
+        // Desktops often have no power_supply entries at all
+        (None, None) => PowerSource::Unknown,
+        (Some(false), None) => PowerSource::Unknown,
+    };
+    PowerStatus { source, battery_percent: battery.map(|(percent, _)| percent) }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+pub fn power_status() -> PowerStatus {
+    PowerStatus::UNKNOWN
+}
+
+/// Whether the user is presenting, running something fullscreen, or in quiet hours
+#[cfg(windows)]
+pub fn presentation_active() -> bool {
+    use windows_sys::Win32::UI::Shell::{
+        SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME,
+        QUNS_RUNNING_D3D_FULL_SCREEN,
+    };
+
+    let mut state = 0;
+    if unsafe { SHQueryUserNotificationState(&mut state) } != 0 {
+        return false;
+    }
+    matches!(state, QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_PRESENTATION_MODE | QUNS_QUIET_TIME)
+}
+
+#[cfg(not(windows))]
+pub fn presentation_active() -> bool {
+    false
+}
+
+/// Holds a changed reading back until it has been seen STABLE_POLLS times in a row
+struct Debounced<T> {
+    reported: T,
+    candidate: Option<(T, u32)>,
+}
+
+impl<T: Copy + PartialEq> Debounced<T> {
+    fn new(initial: T) -> Self {
+        Debounced { reported: initial, candidate: None }
+    }
+
+    /// Feed one poll's reading; returns it once it's stable and `notable` against the last report
+    fn update(&mut self, reading: T, notable: impl Fn(&T, &T) -> bool) -> Option<T> {
+        if !notable(&self.reported, &reading) {
+            self.candidate = None;
+            return None;
+        }
+        let seen = match self.candidate {
+            Some((candidate, seen)) if candidate == reading => seen + 1,
+            _ => 1,
+        };
+        if seen < STABLE_POLLS {
+            self.candidate = Some((reading, seen));
+            return None;
+        }
+        self.candidate = None;
+        self.reported = reading;
+        Some(reading)
+    }
+}
+
+/// Background poller for power and presentation state. Stops when dropped or on stop().
+pub struct PowerWatcher {
+    stop: Mutex<Option<Sender<()>>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl PowerWatcher {
+    /// Stop polling and wait for the thread to exit. Safe to call more than once.
+    pub fn stop(&self) {
+        // Dropping the sender wakes the thread immediately
+        lock_recovering(&self.stop).take();
+        if let Some(thread) = lock_recovering(&self.thread).take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PowerWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Poll power and presentation state, calling `on_power` / `on_presentation(active)` on
+/// debounced transitions. The state at startup is the baseline and isn't reported.
+pub fn spawn(
+    on_power: impl Fn(&PowerStatus) + Send + 'static,
+    on_presentation: impl Fn(bool) + Send + 'static,
+) -> PowerWatcher {
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let thread = std::thread::spawn(move || {
+        let mut power = Debounced::new(power_status());
+        let mut presentation = Debounced::new(presentation_active());
+
+        loop {
+            match stopped.recv_timeout(POLL_INTERVAL) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => return,
+            }
+
+            if let Some(status) = power.update(power_status(), PowerStatus::differs_notably) {
+                on_power(&status);
+            }
+            if let Some(active) = presentation.update(presentation_active(), |a, b| a != b) {
+                on_presentation(active);
+            }
+        }
+    });
+
+    PowerWatcher { stop: Mutex::new(Some(stop)), thread: Mutex::new(Some(thread)) }
+}
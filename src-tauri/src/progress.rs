@@ -0,0 +1,37 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Items a long import handles between progress reports and cancellation checks; also the
+/// size of each transaction, so a cancelled import stops on a batch boundary
+pub const BATCH_SIZE: usize = 10;
+
+/// Receives (done, total if known, message)
+pub type ReportFn<'a> = dyn Fn(usize, Option<usize>, &str) + 'a;
+
+/// Lets a long operation report how far along it is and notice when it should stop.
+/// Both hooks are optional; Progress::none() has neither.
+#[derive(Clone, Copy)]
+pub struct Progress<'a> {
+    report: Option<&'a ReportFn<'a>>,
+    cancelled: Option<&'a AtomicBool>,
+}
+
+impl<'a> Progress<'a> {
+    pub fn none() -> Self {
+        Progress { report: None, cancelled: None }
+    }
+
+    pub fn new(report: Option<&'a ReportFn<'a>>, cancelled: Option<&'a AtomicBool>) -> Self {
+        Progress { report, cancelled }
+    }
+
+    /// `done` of `total` items (when the total is known so far)
+    pub fn report(&self, done: usize, total: Option<usize>, message: &str) {
+        if let Some(report) = self.report {
+            report(done, total, message);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, HashSet};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use crate::database::Database;
+use crate::session_forge;
+use crate::utils::snippet;
+
+pub const DEFAULT_MAX_CHARS: usize = 8000;
+// Smallest budget accepted; below this not even the headings fit
+pub const MIN_MAX_CHARS: usize = 500;
+
+// Days covered, ending with (and including) the chosen day
+const WEEK_DAYS: i64 = 7;
+
+// Items without an importance of their own: sessions and journal entries are the week's
+// outline and go last, forge decisions rank like a decision logged through mind_log_decision
+const SESSION_PRIORITY: f64 = 1.0;
+const JOURNAL_PRIORITY: f64 = 1.0;
+const DECISION_PRIORITY: f64 = 0.7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Thoughts,
+    Connections,
+    Sessions,
+    Journal,
+    Decisions,
+    Orphans,
+}
+
+impl Section {
+    fn heading(self) -> &'static str {
+        match self {
+            Section::Thoughts => "Thoughts by category",
+            Section::Connections => "New connections",
+            Section::Sessions => "Sessions",
+            Section::Journal => "Forge journal",
+            Section::Decisions => "Decisions",
+            Section::Orphans => "Threads left open",
+        }
+    }
+}
+
+/// One line of the reflection. `group` is the category heading within Thoughts.
+struct Item {
+    section: Section,
+    group: Option<String>,
+    priority: f64,
+    line: String,
+}
+
+fn render(title: &str, summary: &str, items: &[Item], omitted: usize, max_chars: usize) -> String {
+    let mut out = format!("# {}\n\n{}\n", title, summary);
+    let mut section = None;
+    let mut group = None;
+    for item in items {
+        if section != Some(item.section) {
+            section = Some(item.section);
+            group = None;
+            out.push_str(&format!("\n## {}\n", item.section.heading()));
+            if item.group.is_none() {
+                out.push('\n');
+            }
+        }
+        if item.group.is_some() && group != item.group.as_ref() {
+            group = item.group.as_ref();
+            out.push_str(&format!("\n### {}\n\n", item.group.as_deref().unwrap_or_default()));
+        }
+        out.push_str(&item.line);
+        out.push('\n');
+    }
+    if omitted > 0 {
+        out.push_str(&format!(
+            "\n_{} lower-importance item(s) left out to stay within {} characters._\n",
+            omitted, max_chars
+        ));
+    }
+    out
+}
+
+/// Markdown reflection on the 7 local days ending with `end_date` (YYYY-MM-DD, default today
+/// at `tz_offset_minutes` from UTC): the week's thoughts by category, new connections with
+/// their reasons, sessions, forge journal entries and decisions from the same days, and
+/// the week's thoughts that nothing connects to. Kept within `max_chars` by leaving out the
+/// lowest-importance items first.
+pub fn weekly_reflection(db: &Database, end_date: Option<&str>, tz_offset_minutes: i32, max_chars: usize) -> Result<String, String> {
+    if max_chars < MIN_MAX_CHARS {
+        return Err(format!("Invalid: max_chars must be at least {}", MIN_MAX_CHARS));
+    }
+    let last = match end_date.map(str::trim).filter(|d| !d.is_empty()) {
+        Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date: {} (expected YYYY-MM-DD)", d))?,
+        None => (Utc::now() + Duration::minutes(tz_offset_minutes as i64)).date_naive(),
+    };
+    let first = last - Duration::days(WEEK_DAYS - 1);
+    let (first_date, last_date) = (first.to_string(), last.to_string());
+
+    let thoughts = db.get_thoughts_between(&first_date, &last_date, tz_offset_minutes).map_err(|e| e.to_string())?;
+    let connections = db.get_connections_between(&first_date, &last_date, tz_offset_minutes).map_err(|e| e.to_string())?;
+    let sessions = db.get_sessions_between(&first_date, &last_date, tz_offset_minutes).map_err(|e| e.to_string())?;
+
+    // Any connection counts, not only this week's: an old idea picked up again isn't left open
+    let ids: Vec<String> = thoughts.iter().map(|t| t.id.clone()).collect();
+    let touching = db.get_connections_touching(&ids).map_err(|e| e.to_string())?;
+    let connected: HashSet<&str> = touching.iter()
+        .flat_map(|m| [m.connection.from_thought.as_str(), m.connection.to_thought.as_str()])
+        .collect();
+
+    // Local midnight on the first day and the day after the last, as instants
+    let local_midnight = |day: NaiveDate| -> DateTime<Utc> {
+        day.and_time(chrono::NaiveTime::MIN).and_utc() - Duration::minutes(tz_offset_minutes as i64)
+    };
+    let mut forge_note = None;
+    let (journals, decisions) = if session_forge::forge_dir().is_some() {
+        session_forge::entries_between(local_midnight(first), local_midnight(last + Duration::days(1)))
+            .unwrap_or_else(|e| {
+                forge_note = Some(format!("Forge data unavailable: {}", e));
+                Default::default()
+            })
+    } else {
+        Default::default()
+    };
+
+    let mut items = Vec::new();
+    let mut by_category: BTreeMap<&str, Vec<&crate::Thought>> = BTreeMap::new();
+    for t in &thoughts {
+        by_category.entry(t.category.as_str()).or_default().push(t);
+    }
+    for (category, group) in by_category {
+        for t in group {
+            items.push(Item {
+                section: Section::Thoughts,
+                group: Some(category.to_string()),
+                priority: t.importance,
+                line: format!("- {} _(importance {:.0}%)_", snippet(&t.content, 200), t.importance * 100.0),
+            });
+        }
+    }
+    for m in &connections {
+        items.push(Item {
+            section: Section::Connections,
+            group: None,
+            priority: m.connection.strength,
+            line: format!(
+                "- \"{}\" → \"{}\" ({}): {}",
+                snippet(&m.from_content, 60),
+                snippet(&m.to_content, 60),
+                m.connection.kind,
+                m.connection.reason
+            ),
+        });
+    }
+    for s in &sessions {
+        let line = match s.summary.as_deref().map(str::trim).filter(|summary| !summary.is_empty()) {
+            Some(summary) => format!("- **{}** — {}", s.title, snippet(summary, 300)),
+            None => format!("- **{}**", s.title),
+        };
+        items.push(Item { section: Section::Sessions, group: None, priority: SESSION_PRIORITY, line });
+    }
+    for j in &journals {
+        items.push(Item {
+            section: Section::Journal,
+            group: None,
+            priority: JOURNAL_PRIORITY,
+            line: format!("- {}: {}", j.timestamp.get(..10).unwrap_or(&j.timestamp), snippet(&j.session_summary, 300)),
+        });
+    }
+    for d in &decisions {
+        items.push(Item {
+            section: Section::Decisions,
+            group: None,
+            priority: DECISION_PRIORITY,
+            line: format!(
+                "- **{}** ({}) — {}",
+                d.choice,
+                d.timestamp.get(..10).unwrap_or(&d.timestamp),
+                snippet(&d.reasoning, 160)
+            ),
+        });
+    }
+    for t in thoughts.iter().filter(|t| !connected.contains(t.id.as_str())) {
+        items.push(Item {
+            section: Section::Orphans,
+            group: None,
+            priority: t.importance,
+            line: format!("- {} _({})_", snippet(&t.content, 160), t.category),
+        });
+    }
+    let title = format!("Weekly reflection — {} to {}", first.format("%B %-d"), last.format("%B %-d, %Y"));
+    let mut summary = format!(
+        "{} thought(s), {} new connection(s), {} session(s), {} journal {}, {} decision(s).",
+        thoughts.len(),
+        connections.len(),
+        sessions.len(),
+        journals.len(),
+        if journals.len() == 1 { "entry" } else { "entries" },
+        decisions.len()
+    );
+    if items.is_empty() {
+        summary.push_str("\n\nNothing logged to The Mind or session-forge this week.");
+    }
+    if let Some(note) = forge_note {
+        summary.push_str(&format!("\n\n_{}_", note));
+    }
+
+    // Drop the least important items until it fits, as many at a time as the overshoot
+    // needs; ties drop the later item in the document first
+    let mut omitted = 0;
+    loop {
+        let out = render(&title, &summary, &items, omitted, max_chars);
+        let length = out.chars().count();
+        if length <= max_chars || items.is_empty() {
+            return Ok(out);
+        }
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        order.sort_by(|&i, &j| items[i].priority.total_cmp(&items[j].priority).then(j.cmp(&i)));
+        let mut freed = 0;
+        let mut dropped = HashSet::new();
+        for i in order {
+            if freed >= length - max_chars {
+                break;
+            }
+            freed += items[i].line.chars().count() + 1;
+            dropped.insert(i);
+        }
+        omitted += dropped.len();
+        items = items.into_iter()
+            .enumerate()
+            .filter(|(i, _)| !dropped.contains(i))
+            .map(|(_, item)| item)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{weekly_reflection, DEFAULT_MAX_CHARS};
+    use crate::database::testing::{connection, thought, TempDb};
+
+    /// A week (Monday 2026-03-09 to Sunday 2026-03-15 in UTC) with a thought on either side
+    fn seeded_week() -> TempDb {
+        let db = TempDb::new();
+        for (id, content, created_at) in [
+            ("before", "Sunday night sketch of the graph", "2026-03-08T22:30:00+00:00"),
+            ("first", "Monday morning plan for the wallpaper", "2026-03-09T00:00:00+00:00"),
+            ("middle", "Midweek idea nobody followed up", "2026-03-11T12:00:00+00:00"),
+            ("last", "Sunday wrap-up of the release", "2026-03-15T23:59:00+00:00"),
+            ("after", "Next Monday's first note", "2026-03-16T00:00:00+00:00"),
+        ] {
+            db.insert_thought(&thought(id, content, "technical", 0.5, created_at)).unwrap();
+        }
+        db.insert_connection(&connection("old", "before", "first", "2026-03-08T23:00:00+00:00")).unwrap();
+        db.insert_connection(&connection("new", "first", "last", "2026-03-12T09:00:00+00:00")).unwrap();
+        db.insert_session("s1", "Release planning", "Settled the release scope", "2026-03-10T09:00:00+00:00", "2026-03-10T10:00:00+00:00").unwrap();
+        db
+    }
+
+    #[test]
+    fn the_week_runs_monday_through_the_end_date() {
+        let db = seeded_week();
+
+        let out = weekly_reflection(&db, Some("2026-03-15"), 0, DEFAULT_MAX_CHARS).unwrap();
+
+        assert!(out.starts_with("# Weekly reflection — March 9 to March 15, 2026\n"), "{}", out);
+        assert!(out.contains("3 thought(s), 1 new connection(s), 1 session(s)"), "{}", out);
+        for inside in ["Monday morning plan", "Midweek idea", "Sunday wrap-up", "**Release planning** — Settled the release scope"] {
+            assert!(out.contains(inside), "missing {:?} in {}", inside, out);
+        }
+        for outside in ["Sunday night sketch", "Next Monday's"] {
+            assert!(!out.contains(outside), "unexpected {:?} in {}", outside, out);
+        }
+        // Only the thought with no connection at all is left open
+        let open = out.split("## Threads left open").nth(1).expect("an open threads section");
+        assert!(open.contains("Midweek idea") && !open.contains("Monday morning plan"), "{}", open);
+    }
+
+    #[test]
+    fn the_week_boundaries_follow_the_local_offset() {
+        let db = seeded_week();
+
+        // Two hours ahead of UTC, Sunday night's thought is Monday's and the last one is next week's
+        let out = weekly_reflection(&db, Some("2026-03-15"), 120, DEFAULT_MAX_CHARS).unwrap();
+
+        let thoughts = out.split("## New connections").next().unwrap();
+        assert!(thoughts.contains("Sunday night sketch"), "{}", out);
+        assert!(!thoughts.contains("Sunday wrap-up"), "{}", out);
+        // The connection made late on Sunday UTC falls on the local Monday as well
+        assert!(out.contains("3 thought(s), 2 new connection(s)"), "{}", out);
+    }
+
+    #[test]
+    fn the_previous_week_has_none_of_it() {
+        let db = seeded_week();
+
+        let out = weekly_reflection(&db, Some("2026-03-08"), 0, DEFAULT_MAX_CHARS).unwrap();
+
+        assert!(out.starts_with("# Weekly reflection — March 2 to March 8, 2026\n"), "{}", out);
+        assert!(out.contains("1 thought(s), 1 new connection(s), 0 session(s)"), "{}", out);
+        assert!(out.contains("Sunday night sketch"), "{}", out);
+    }
+
+    #[test]
+    fn a_small_budget_leaves_out_the_least_important_items_first() {
+        let db = seeded_week();
+        db.insert_thought(&thought("key", "Decided to ship wallpaper mode in the spring release", "technical", 0.95, "2026-03-13T10:00:00+00:00")).unwrap();
+        db.insert_thought(&thought("minor", &"Stray musing about font kerning on the settings page ".repeat(3), "personal", 0.05, "2026-03-13T11:00:00+00:00")).unwrap();
+        // Connected before the week, so neither is left open and each has one line
+        db.insert_connection(&connection("earlier", "key", "minor", "2026-03-01T09:00:00+00:00")).unwrap();
+        let full = weekly_reflection(&db, Some("2026-03-15"), 0, DEFAULT_MAX_CHARS).unwrap();
+        let full_length = full.chars().count();
+        assert!(full_length > 600 && full.contains("Stray musing"), "{}", full);
+
+        // Just over budget: only the lowest-importance line goes
+        let out = weekly_reflection(&db, Some("2026-03-15"), 0, full_length - 1).unwrap();
+        assert!(out.chars().count() < full_length, "{}", out);
+        assert!(!out.contains("Stray musing"), "{}", out);
+        for kept in ["Decided to ship", "Monday morning plan", "Midweek idea", "Sunday wrap-up", "Release planning"] {
+            assert!(out.contains(kept), "missing {:?} in {}", kept, out);
+        }
+        assert!(out.contains(&format!("_1 lower-importance item(s) left out to stay within {} characters._", full_length - 1)), "{}", out);
+
+        // The smallest budget still fits and keeps the most important thought
+        let out = weekly_reflection(&db, Some("2026-03-15"), 0, 500).unwrap();
+        assert!(out.chars().count() <= 500, "{} chars: {}", out.chars().count(), out);
+        assert!(out.contains("Decided to ship"), "{}", out);
+        assert!(!out.contains("Stray musing"), "{}", out);
+    }
+
+    #[test]
+    fn bad_arguments_are_refused() {
+        let db = seeded_week();
+        assert_eq!(
+            weekly_reflection(&db, Some("2026-03-15"), 0, 100).unwrap_err(),
+            "Invalid: max_chars must be at least 500"
+        );
+        assert_eq!(
+            weekly_reflection(&db, Some("15/03/2026"), 0, DEFAULT_MAX_CHARS).unwrap_err(),
+            "Invalid date: 15/03/2026 (expected YYYY-MM-DD)"
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, HashSet};
+use chrono::DateTime;
+use crate::database::Database;
+use crate::session_forge::{self, ForgeFilter};
+use crate::utils::{snippet, KeywordConfig};
+
+// Forge decisions listed under "Related decisions"
+const RELATED_DECISIONS: usize = 5;
+
+fn time_of(timestamp: &str) -> String {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+fn duration_text(seconds: i64) -> String {
+    let minutes = seconds.max(0) / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{} min", m),
+        (h, m) => format!("{} h {} min", h, m),
+    }
+}
+
+/// Markdown write-up of one session: its summary, its thoughts in the order they were logged,
+/// every connection touching them grouped by reason, forge decisions sharing keywords with
+/// the summary, and the thoughts nothing connects to yet ("threads left open").
+/// `session_id` may also be a title, as with get_session_detail.
+pub fn session_report(db: &Database, session_id: &str) -> Result<String, String> {
+    let detail = db.get_session_detail(session_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("NotFound: session {}", session_id))?;
+    let session = &detail.session;
+
+    let mut thoughts = detail.thoughts.clone();
+    thoughts.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+    let ids: Vec<String> = thoughts.iter().map(|t| t.id.clone()).collect();
+    let connections = db.get_connections_touching(&ids).map_err(|e| e.to_string())?;
+
+    let mut out = format!("# Session report — {}\n", session.title);
+    let ended = session.ended_at.as_deref().filter(|end| !end.is_empty());
+    out.push_str(&format!(
+        "\n{} → {}{}\n",
+        time_of(&session.started_at),
+        ended.map(time_of).unwrap_or_else(|| "ongoing".to_string()),
+        detail.stats.duration_seconds.map(|s| format!(" ({})", duration_text(s))).unwrap_or_default()
+    ));
+    out.push_str(&format!(
+        "\n{} thought(s), {} connection(s), categories: {}.\n",
+        thoughts.len(),
+        connections.len(),
+        if detail.stats.categories.is_empty() { "none".to_string() } else { detail.stats.categories.join(", ") }
+    ));
+
+    let summary = session.summary.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    out.push_str("\n## Summary\n\n");
+    out.push_str(summary.unwrap_or("_No summary recorded._"));
+    out.push('\n');
+
+    out.push_str("\n## Thoughts\n\n");
+    if thoughts.is_empty() {
+        out.push_str("_No thoughts in this session._\n");
+    }
+    for t in &thoughts {
+        out.push_str(&format!(
+            "- {} **{}** — {} _(importance {:.0}%)_\n",
+            time_of(&t.created_at),
+            t.category,
+            t.content,
+            t.importance * 100.0
+        ));
+    }
+
+    if !connections.is_empty() {
+        let mut by_reason: BTreeMap<&str, Vec<&crate::ConnectionMatch>> = BTreeMap::new();
+        for m in &connections {
+            by_reason.entry(m.connection.reason.as_str()).or_default().push(m);
+        }
+        out.push_str("\n## Connections\n");
+        for (reason, items) in by_reason {
+            out.push_str(&format!("\n### {} ({})\n\n", reason, items.len()));
+            for m in items {
+                out.push_str(&format!(
+                    "- \"{}\" → \"{}\" ({})\n",
+                    snippet(&m.from_content, 60),
+                    snippet(&m.to_content, 60),
+                    m.connection.kind
+                ));
+            }
+        }
+    }
+
+    // Keyword overlap with the summary, or the title when there is none; no forge directory
+    // just means no decisions
+    let query = summary.unwrap_or(&session.title);
+    let decisions = session_forge::search_forge_context(
+        query,
+        RELATED_DECISIONS,
+        None,
+        &ForgeFilter::default(),
+        &KeywordConfig::load(db),
+    )
+    .map(|context| context.decisions)
+    .unwrap_or_default();
+    if !decisions.is_empty() {
+        out.push_str("\n## Related decisions\n\n");
+        for d in &decisions {
+            out.push_str(&format!(
+                "- **{}** ({}) — {}\n",
+                d.entry.choice,
+                time_of(&d.entry.timestamp),
+                snippet(&d.entry.reasoning, 160)
+            ));
+        }
+    }
+
+    let connected: HashSet<&str> = connections.iter()
+        .flat_map(|m| [m.connection.from_thought.as_str(), m.connection.to_thought.as_str()])
+        .collect();
+    let open: Vec<&crate::Thought> = thoughts.iter().filter(|t| !connected.contains(t.id.as_str())).collect();
+    if !open.is_empty() {
+        out.push_str("\n## Threads left open\n\n");
+        for t in open {
+            out.push_str(&format!("- {} _({})_\n", snippet(&t.content, 160), t.category));
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::session_report;
+    use crate::database::testing::{connection, thought, TempDb};
+
+    fn seeded_session() -> TempDb {
+        let db = TempDb::new();
+        db.insert_session("s1", "Release planning", "Settled the wallpaper release scope", "2026-03-10T09:00:00+00:00", "2026-03-10T10:30:00+00:00").unwrap();
+        for (position, (id, content, category, minute)) in [
+            ("a", "Ship the wallpaper mode first", "technical", 5),
+            ("b", "Installer needs signing before release", "technical", 20),
+            ("c", "Ask users about multi-monitor setups", "question", 40),
+            ("d", "Maybe a light theme later", "idea", 55),
+        ].into_iter().enumerate() {
+            let created_at = format!("2026-03-10T09:{:02}:00+00:00", minute);
+            db.insert_thought(&thought(id, content, category, 0.5, &created_at)).unwrap();
+            db.link_session_thought("s1", id, position as i64).unwrap();
+        }
+        db.insert_connection(&connection("c1", "a", "b", "2026-03-10T09:30:00+00:00")).unwrap();
+        let mut follow_up = connection("c2", "b", "c", "2026-03-10T09:45:00+00:00");
+        follow_up.reason = "follow-up".to_string();
+        db.insert_connection(&follow_up).unwrap();
+        db
+    }
+
+    #[test]
+    fn the_report_has_every_section_for_a_seeded_session() {
+        let db = seeded_session();
+
+        let out = session_report(&db, "s1").unwrap();
+
+        assert!(out.starts_with("# Session report — Release planning\n\n2026-03-10 09:00 → 2026-03-10 10:30 (1 h 30 min)\n"), "{}", out);
+        assert!(out.contains("\n4 thought(s), 2 connection(s), categories: "), "{}", out);
+        assert!(out.contains("\n## Summary\n\nSettled the wallpaper release scope\n"), "{}", out);
+        let thoughts = out.split("## Thoughts\n\n").nth(1).unwrap();
+        let lines: Vec<&str> = thoughts.lines().take(4).collect();
+        assert_eq!(lines[0], "- 2026-03-10 09:05 **technical** — Ship the wallpaper mode first _(importance 50%)_");
+        assert!(lines[3].contains("Maybe a light theme later"), "{}", out);
+        assert!(out.contains("\n### follow-up (1)\n\n- \"Installer needs signing before release\" → \"Ask users about multi-monitor setups\" (related)\n"), "{}", out);
+        assert!(out.contains("\n### test (1)\n"), "{}", out);
+        let open = out.split("## Threads left open\n\n").nth(1).expect("an open threads section");
+        assert_eq!(open, "- Maybe a light theme later _(idea)_\n");
+        // A title finds the same session
+        assert_eq!(session_report(&db, "Release planning").unwrap(), out);
+    }
+
+    #[test]
+    fn an_unknown_session_is_not_found() {
+        let db = seeded_session();
+        assert_eq!(session_report(&db, "missing").unwrap_err(), "NotFound: session missing");
+    }
+}
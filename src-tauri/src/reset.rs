@@ -0,0 +1,50 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use crate::utils::lock_recovering;
+
+pub const RESET_EVENT: &str = "mind://reset";
+
+/// How long a reset_mind token can be confirmed
+pub const TOKEN_TTL: Duration = Duration::from_secs(60);
+
+/// The one outstanding reset confirmation token, if any. Issuing a new token replaces the old.
+#[derive(Default)]
+pub struct ResetTokens {
+    pending: Mutex<Option<(String, Instant)>>,
+}
+
+impl ResetTokens {
+    pub fn issue(&self) -> String {
+        let token = Uuid::new_v4().to_string();
+        *lock_recovering(&self.pending) = Some((token.clone(), Instant::now()));
+        token
+    }
+
+    /// Spend `token`. A token that doesn't match leaves the outstanding one alone; a matching
+    /// one is used up even if it has expired.
+    pub fn redeem(&self, token: &str) -> Result<(), String> {
+        let mut pending = lock_recovering(&self.pending);
+        match pending.as_ref() {
+            Some((expected, _)) if expected == token => {}
+            _ => return Err("Invalid: unknown or already used reset token".to_string()),
+        }
+        let (_, issued) = pending.take().expect("checked above");
+        if issued.elapsed() > TOKEN_TTL {
+            return Err("Invalid: reset token expired; call reset_mind again".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The backups folder next to the database at `db_path`
+pub fn backup_dir(db_path: &Path) -> PathBuf {
+    db_path.parent().unwrap_or(Path::new(".")).join("backups")
+}
+
+/// Where a pre-reset backup of the database at `db_path` goes: a timestamped copy in the
+/// backups folder
+pub fn backup_path(db_path: &Path) -> PathBuf {
+    backup_dir(db_path).join(format!("mind-{}.db", chrono::Local::now().format("%Y%m%d-%H%M%S")))
+}
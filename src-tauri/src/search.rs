@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use crate::database::Database;
+use crate::Thought;
+
+/// Settings for the fuzzy fallback used when a search finds nothing: the most edits a query
+/// word may be from a word in the mind, and how many thoughts are scanned for those words
+pub const SETTING_FUZZY_MAX_DISTANCE: &str = "fuzzy_max_distance";
+pub const SETTING_FUZZY_MAX_CANDIDATES: &str = "fuzzy_max_candidates";
+const DEFAULT_FUZZY_MAX_DISTANCE: usize = 2;
+const DEFAULT_FUZZY_MAX_CANDIDATES: i64 = 2000;
+// Characters of each scanned thought that are read for words
+const FUZZY_CONTENT_CHARS: i64 = 2000;
+// Query words shorter than this are never corrected
+const FUZZY_MIN_WORD_CHARS: usize = 3;
+
+// Rows fetched from each table before results are scored and merged
+const PER_KIND_LIMIT: i64 = 50;
+// Context kept on each side of the match in a result snippet, in characters
+const SNIPPET_CONTEXT: usize = 60;
+
+// How much a match counts for, by where it was found
+const THOUGHT_WEIGHT: f64 = 1.0;
+const SESSION_TITLE_WEIGHT: f64 = 1.0;
+const SESSION_SUMMARY_WEIGHT: f64 = 0.8;
+const CONNECTION_WEIGHT: f64 = 0.6;
+
+/// One search_everything hit
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    /// "thought", "session", or "connection" (matched on its reason)
+    pub kind: String,
+    pub id: String,
+    /// The matched text around the query, with "…" where it was cut
+    pub snippet: String,
+    /// 0..1; higher is a better match
+    pub score: f64,
+}
+
+/// Search thought content, session titles and summaries, and connection reasons at once,
+/// best matches first. A match scores higher when the query makes up more of the text and
+/// when it's in a more telling place (a thought or session title over a connection reason);
+/// a thought's importance breaks near-ties.
+pub fn search_everything(db: &Database, query: &str, limit: usize) -> rusqlite::Result<Vec<SearchResult>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut results = Vec::new();
+    for thought in db.search_thoughts(query)? {
+        results.push(SearchResult {
+            kind: "thought".to_string(),
+            score: THOUGHT_WEIGHT * coverage(&thought.content, query) * (0.9 + 0.1 * thought.importance),
+            snippet: snippet_around(&thought.content, query),
+            id: thought.id,
+        });
+    }
+    for session in db.search_sessions(query, PER_KIND_LIMIT)? {
+        let summary = session.summary.as_deref().unwrap_or_default();
+        let (text, score) = if contains_ignore_case(&session.title, query) {
+            (session.title.as_str(), SESSION_TITLE_WEIGHT * coverage(&session.title, query))
+        } else {
+            (summary, SESSION_SUMMARY_WEIGHT * coverage(summary, query))
+        };
+        results.push(SearchResult {
+            kind: "session".to_string(),
+            snippet: snippet_around(text, query),
+            score,
+            id: session.id,
+        });
+    }
+    for m in db.search_connections(query, PER_KIND_LIMIT)? {
+        results.push(SearchResult {
+            kind: "connection".to_string(),
+            score: CONNECTION_WEIGHT * coverage(&m.connection.reason, query),
+            snippet: snippet_around(&m.connection.reason, query),
+            id: m.connection.id,
+        });
+    }
+
+    results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+    results.truncate(limit);
+    Ok(results)
+}
+
+fn contains_ignore_case(text: &str, query: &str) -> bool {
+    text.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// 0.5 for any match, rising to 1.0 as the query makes up all of the text
+fn coverage(text: &str, query: &str) -> f64 {
+    let text_len = text.chars().count().max(1) as f64;
+    let query_len = query.chars().count() as f64;
+    0.5 + 0.5 * (query_len / text_len).min(1.0)
+}
+
+/// Up to SNIPPET_CONTEXT characters either side of the first match, or the start of the
+/// text when the query isn't found in it (e.g. a case-folding mismatch)
+fn snippet_around(text: &str, query: &str) -> String {
+    excerpt(text, &match_spans(text, query), false)
+}
+
+/// [start, end) character ranges of every case-insensitive, non-overlapping occurrence of `query`
+fn match_spans(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let fold = |s: &str| -> Vec<char> { s.chars().map(|c| c.to_lowercase().next().unwrap_or(c)).collect() };
+    let (haystack, needle) = (fold(text), fold(query));
+    let mut spans = Vec::new();
+    if needle.is_empty() {
+        return spans;
+    }
+    let mut i = 0;
+    while i + needle.len() <= haystack.len() {
+        if haystack[i..i + needle.len()] == needle[..] {
+            spans.push((i, i + needle.len()));
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+    spans
+}
+
+/// The text around the first span (the start when there are none), "…" where it was cut,
+/// with spans inside the window wrapped in HIGHLIGHT markers when `mark` is set
+fn excerpt(text: &str, spans: &[(usize, usize)], mark: bool) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let (first_start, first_end) = spans.first().copied().unwrap_or((0, 0));
+    let start = first_start.saturating_sub(SNIPPET_CONTEXT);
+    let end = (first_end + SNIPPET_CONTEXT).min(chars.len());
+
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let mut at = start;
+    for &(s, e) in spans.iter().filter(|(s, e)| mark && *s >= start && *e <= end) {
+        out.extend(&chars[at..s]);
+        out.push_str(HIGHLIGHT.0);
+        out.extend(&chars[s..e]);
+        out.push_str(HIGHLIGHT.1);
+        at = e;
+    }
+    out.extend(&chars[at..end]);
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// Markers wrapped around each match in SearchHit snippets and highlight() (Markdown bold)
+pub const HIGHLIGHT: (&str, &str) = ("**", "**");
+
+/// A search_thoughts_detailed hit: the thought plus where the query matched its content
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub thought: Thought,
+    /// [start, end) of each match, in UTF-16 code units so they index JavaScript strings
+    /// directly. Empty when the thought matched some other way.
+    pub matches: Vec<(usize, usize)>,
+    /// The content around the first match with every match in it wrapped in HIGHLIGHT markers
+    pub snippet: String,
+    /// A near-match for a misspelled query; matches and snippet are for the suggestion
+    pub fuzzy: bool,
+    /// The corrected query fuzzy hits matched ("did you mean")
+    pub suggestion: Option<String>,
+}
+
+/// search_thoughts_by's results, each with its match offsets and a highlighted snippet. When
+/// nothing matches, the fuzzy_search near-matches instead, flagged and with the suggestion.
+pub fn search_thoughts_detailed(db: &Database, query: &str, by_computed: bool, role: Option<&str>) -> rusqlite::Result<Vec<SearchHit>> {
+    let thoughts = db.search_thoughts_by(query, by_computed, role)?;
+    if !thoughts.is_empty() {
+        return Ok(thoughts.into_iter().map(|thought| hit(thought, query)).collect());
+    }
+    let Some(fuzzy) = fuzzy_search(db, query, by_computed, role)? else {
+        return Ok(Vec::new());
+    };
+    Ok(fuzzy.thoughts.into_iter()
+        .map(|thought| SearchHit {
+            fuzzy: true,
+            suggestion: Some(fuzzy.suggestion.clone()),
+            ..hit(thought, &fuzzy.suggestion)
+        })
+        .collect())
+}
+
+/// Where `query` matches in a thought already known to match it
+pub fn hit(thought: Thought, query: &str) -> SearchHit {
+    let spans = match_spans(&thought.content, query.trim());
+    // Character index -> UTF-16 offset
+    let mut utf16 = Vec::with_capacity(thought.content.len() + 1);
+    let mut offset = 0;
+    for c in thought.content.chars() {
+        utf16.push(offset);
+        offset += c.len_utf16();
+    }
+    utf16.push(offset);
+
+    SearchHit {
+        matches: spans.iter().map(|&(s, e)| (utf16[s], utf16[e])).collect(),
+        snippet: excerpt(&thought.content, &spans, true),
+        thought,
+        fuzzy: false,
+        suggestion: None,
+    }
+}
+
+/// `text` with every case-insensitive occurrence of `query` wrapped in HIGHLIGHT markers
+pub fn highlight(text: &str, query: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut at = 0;
+    for (s, e) in match_spans(text, query.trim()) {
+        out.extend(&chars[at..s]);
+        out.push_str(HIGHLIGHT.0);
+        out.extend(&chars[s..e]);
+        out.push_str(HIGHLIGHT.1);
+        at = e;
+    }
+    out.extend(&chars[at..]);
+    out
+}
+
+/// A fuzzy_search result: the corrected query and the thoughts matching it
+pub struct FuzzyMatches {
+    pub suggestion: String,
+    pub thoughts: Vec<Thought>,
+}
+
+/// Search again with each misspelled query word replaced by the closest word found in recent
+/// thoughts (within fuzzy_max_distance edits; the more common word wins ties). None when no
+/// word could be corrected or the corrected query matches nothing either.
+pub fn fuzzy_search(db: &Database, query: &str, by_computed: bool, role: Option<&str>) -> rusqlite::Result<Option<FuzzyMatches>> {
+    let max_distance = db.setting_or(SETTING_FUZZY_MAX_DISTANCE, DEFAULT_FUZZY_MAX_DISTANCE);
+    let max_candidates = db.setting_or(SETTING_FUZZY_MAX_CANDIDATES, DEFAULT_FUZZY_MAX_CANDIDATES);
+    if max_distance == 0 || max_candidates <= 0 {
+        return Ok(None);
+    }
+
+    let mut vocabulary: HashMap<String, usize> = HashMap::new();
+    for content in db.recent_thought_contents(max_candidates, FUZZY_CONTENT_CHARS)? {
+        for word in words(&content) {
+            *vocabulary.entry(word).or_default() += 1;
+        }
+    }
+
+    // Rebuilt word by word so punctuation and spacing in the query are kept
+    let mut suggestion = String::with_capacity(query.len());
+    let mut corrected = false;
+    let mut rest = query.trim();
+    while let Some(start) = rest.find(|c: char| c.is_alphanumeric()) {
+        suggestion.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(|c: char| !c.is_alphanumeric()).unwrap_or(rest.len());
+        let word = rest[..end].to_lowercase();
+        match closest_word(&word, &vocabulary, max_distance) {
+            Some(better) => {
+                suggestion.push_str(better);
+                corrected = true;
+            }
+            None => suggestion.push_str(&rest[..end]),
+        }
+        rest = &rest[end..];
+    }
+    suggestion.push_str(rest);
+    if !corrected {
+        return Ok(None);
+    }
+
+    let thoughts = db.search_thoughts_by(&suggestion, by_computed, role)?;
+    Ok((!thoughts.is_empty()).then_some(FuzzyMatches { suggestion, thoughts }))
+}
+
+/// Lowercased alphanumeric runs
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// The vocabulary word nearest a misspelled `word`, or None when it's spelled like one already,
+/// too short to correct, or nothing is close enough. Short words get at most one edit.
+fn closest_word<'a>(word: &str, vocabulary: &'a HashMap<String, usize>, max_distance: usize) -> Option<&'a str> {
+    let len = word.chars().count();
+    if len < FUZZY_MIN_WORD_CHARS || vocabulary.contains_key(word) {
+        return None;
+    }
+    let max_distance = if len <= 4 { max_distance.min(1) } else { max_distance };
+    vocabulary.iter()
+        .filter_map(|(candidate, count)| {
+            edit_distance(word, candidate, max_distance).map(|d| (d, *count, candidate.as_str()))
+        })
+        .min_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)).then(a.2.cmp(b.2)))
+        .map(|(_, _, candidate)| candidate)
+}
+
+/// Levenshtein distance between `a` and `b`, or None once it's certain to exceed `max`
+fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(ca != cb);
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        // Every later row is at least this row's minimum
+        if cur.iter().min().is_some_and(|&m| m > max) {
+            return None;
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    Some(prev[b.len()]).filter(|&d| d <= max)
+}
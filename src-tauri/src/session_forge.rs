@@ -1,6 +1,11 @@
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
-use crate::utils::{extract_keywords, count_shared_keywords};
+use crate::database::Database;
+use crate::utils::{extract_keywords, extract_keywords_with, lock_recovering, KeywordConfig};
 
 // ---- Types matching session-forge's JSON schema ----
 
@@ -18,12 +23,6 @@ pub struct JournalEntry {
     pub collaboration_notes: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-struct JournalData {
-    #[serde(default)]
-    sessions: Vec<JournalEntry>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DecisionEntry {
     pub timestamp: String,
@@ -37,12 +36,6 @@ pub struct DecisionEntry {
     pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-struct DecisionsData {
-    #[serde(default)]
-    decisions: Vec<DecisionEntry>,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeadEndEntry {
     pub timestamp: String,
@@ -56,25 +49,60 @@ pub struct DeadEndEntry {
     pub tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-struct DeadEndsData {
-    #[serde(default)]
-    dead_ends: Vec<DeadEndEntry>,
+/// A search hit: the entry's own fields plus how strongly it matched the query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scored<T> {
+    #[serde(flatten)]
+    pub entry: T,
+    pub score: f64,
 }
 
+/// A problem found while reading a forge file. `index` is the entry's position in its
+/// array; None means the whole file could not be read.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForgeWarning {
+    pub file: String,
+    pub index: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ForgeContext {
-    pub journals: Vec<JournalEntry>,
-    pub decisions: Vec<DecisionEntry>,
-    pub dead_ends: Vec<DeadEndEntry>,
+    pub journals: Vec<Scored<JournalEntry>>,
+    pub decisions: Vec<Scored<DecisionEntry>>,
+    pub dead_ends: Vec<Scored<DeadEndEntry>>,
+    /// Entries (or whole files) that were skipped because they failed to parse
+    pub warnings: Vec<ForgeWarning>,
 }
 
 // ---- File system helpers ----
 
-/// Get the session-forge data directory.
+/// Environment variable that overrides every other forge location
+pub const FORGE_DIR_ENV: &str = "SESSION_FORGE_DIR";
+/// Settings key for a user-chosen forge directory (e.g. a synced folder)
+pub const SETTING_FORGE_DIR: &str = "forge_dir";
+
+// The forge_dir setting, loaded at startup and updated by set_forge_dir
+static CONFIGURED_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Where the forge directory came from, and which of its files exist
+#[derive(Debug, Clone, Serialize)]
+pub struct ForgeStatus {
+    pub dir: Option<String>,
+    /// "env", "setting" or "default"
+    pub source: String,
+    pub available: bool,
+    pub journal: bool,
+    pub decisions: bool,
+    pub dead_ends: bool,
+    /// Parse problems in the files that were found
+    pub warnings: Vec<ForgeWarning>,
+}
+
+/// Default session-forge data directory.
 /// Windows: %APPDATA%/session-forge
 /// Other: ~/.session-forge
-fn get_session_forge_dir() -> Option<PathBuf> {
+fn default_session_forge_dir() -> Option<PathBuf> {
     #[cfg(windows)]
     {
         std::env::var("APPDATA")
@@ -87,106 +115,663 @@ fn get_session_forge_dir() -> Option<PathBuf> {
     }
 }
 
-/// Check if session-forge data directory exists
-pub fn is_available() -> bool {
-    get_session_forge_dir()
-        .map(|d| d.exists())
-        .unwrap_or(false)
+/// Resolve the forge directory: $SESSION_FORGE_DIR, then the forge_dir setting, then the default
+fn resolve_forge_dir() -> (Option<PathBuf>, &'static str) {
+    if let Some(dir) = std::env::var_os(FORGE_DIR_ENV).filter(|d| !d.is_empty()) {
+        return (Some(PathBuf::from(dir)), "env");
+    }
+    if let Some(dir) = lock_recovering(&CONFIGURED_DIR).clone() {
+        return (Some(dir), "setting");
+    }
+    (default_session_forge_dir(), "default")
+}
+
+fn get_session_forge_dir() -> Option<PathBuf> {
+    resolve_forge_dir().0
 }
 
-fn read_json_file<T: serde::de::DeserializeOwned>(path: &PathBuf) -> Option<T> {
+/// Pick up the forge_dir setting. Call once at startup, before any forge access.
+pub fn load_configured_dir(db: &Database) {
+    let dir = db.get_setting(SETTING_FORGE_DIR)
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to read {} setting: {}", SETTING_FORGE_DIR, e);
+            None
+        })
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from);
+    *lock_recovering(&CONFIGURED_DIR) = dir;
+}
+
+/// The resolved forge directory, if one could be determined
+pub fn forge_dir() -> Option<String> {
+    get_session_forge_dir().map(|d| d.display().to_string())
+}
+
+/// Which forge files exist in the resolved directory
+pub fn status() -> ForgeStatus {
+    let (dir, source) = resolve_forge_dir();
+    let has = |file: &str| dir.as_ref().is_some_and(|d| d.join(file).is_file());
+    let (journal, decisions, dead_ends) = (has(JOURNAL_FILE), has(DECISIONS_FILE), has(DEAD_ENDS_FILE));
+    let warnings = dir.as_ref().map(|d| forge_warnings(d)).unwrap_or_default();
+    ForgeStatus {
+        dir: dir.map(|d| d.display().to_string()),
+        source: source.to_string(),
+        available: journal || decisions || dead_ends,
+        journal,
+        decisions,
+        dead_ends,
+        warnings,
+    }
+}
+
+/// Use `path` as the forge directory (persisted in settings). The directory must contain
+/// at least one forge file. An empty path clears the setting and goes back to the default.
+pub fn set_forge_dir(db: &Database, path: &str) -> Result<ForgeStatus, String> {
+    let path = path.trim();
+    if path.is_empty() {
+        db.set_setting(SETTING_FORGE_DIR, "").map_err(|e| e.to_string())?;
+        *lock_recovering(&CONFIGURED_DIR) = None;
+        return Ok(status());
+    }
+
+    let dir = PathBuf::from(path);
+    if !dir.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+    if ![JOURNAL_FILE, DECISIONS_FILE, DEAD_ENDS_FILE].iter().any(|f| dir.join(f).is_file()) {
+        return Err(format!(
+            "No session-forge data in {} (expected {}, {} or {})",
+            path, JOURNAL_FILE, DECISIONS_FILE, DEAD_ENDS_FILE
+        ));
+    }
+
+    db.set_setting(SETTING_FORGE_DIR, path).map_err(|e| e.to_string())?;
+    *lock_recovering(&CONFIGURED_DIR) = Some(dir);
+    Ok(status())
+}
+
+/// Read a forge JSON file as a raw value; a file that exists and fails to parse is an error
+fn load_json_file(path: &PathBuf) -> Result<Option<serde_json::Value>, String> {
     if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Deserialize the array under `key` one entry at a time, so a single malformed entry
+/// is reported and skipped instead of hiding the whole file. A missing key means no entries.
+fn parse_entries<T: serde::de::DeserializeOwned>(
+    file: &str,
+    data: serde_json::Value,
+    key: &str,
+) -> Result<(Vec<T>, Vec<ForgeWarning>), String> {
+    let serde_json::Value::Object(mut data) = data else {
+        return Err(format!("{} is not a JSON object", file));
+    };
+    let items = match data.remove(key) {
+        None | Some(serde_json::Value::Null) => return Ok((Vec::new(), Vec::new())),
+        Some(serde_json::Value::Array(items)) => items,
+        Some(_) => return Err(format!("\"{}\" in {} is not an array", key, file)),
+    };
+
+    let mut entries = Vec::with_capacity(items.len());
+    let mut warnings = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        match serde_json::from_value(item) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warnings.push(ForgeWarning {
+                file: file.to_string(),
+                index: Some(index),
+                message: e.to_string(),
+            }),
+        }
+    }
+    Ok((entries, warnings))
+}
+
+fn forge_file(name: &str) -> Result<PathBuf, String> {
+    get_session_forge_dir()
+        .map(|dir| dir.join(name))
+        .ok_or_else(|| "session-forge directory not found".to_string())
+}
+
+// ---- Full reads (oldest first, as stored) ----
+
+pub const JOURNAL_FILE: &str = "journal.json";
+pub const DECISIONS_FILE: &str = "decisions.json";
+pub const DEAD_ENDS_FILE: &str = "dead-ends.json";
+
+// Key of the entry array inside each file
+const JOURNAL_KEY: &str = "sessions";
+const DECISIONS_KEY: &str = "decisions";
+const DEAD_ENDS_KEY: &str = "dead_ends";
+
+/// Every parseable entry of a file plus warnings for the ones skipped.
+/// Unlike search, a file that can't be read at all is an error here.
+fn all_entries<T: Clone>(parsed: Option<Arc<ParsedFile<T>>>) -> Result<(Vec<T>, Vec<ForgeWarning>), String> {
+    let Some(parsed) = parsed else {
+        return Ok((Vec::new(), Vec::new()));
+    };
+    if let Some(failed) = parsed.warnings.iter().find(|w| w.index.is_none()) {
+        return Err(failed.message.clone());
+    }
+    Ok((
+        parsed.entries.iter().map(|e| e.entry.clone()).collect(),
+        parsed.warnings.clone(),
+    ))
+}
+
+pub fn journal_entries() -> Result<(Vec<JournalEntry>, Vec<ForgeWarning>), String> {
+    all_entries(cached_entries(&JOURNAL_CACHE, &forge_file(JOURNAL_FILE)?, JOURNAL_KEY, journal_text))
+}
+
+pub fn decision_entries() -> Result<(Vec<DecisionEntry>, Vec<ForgeWarning>), String> {
+    all_entries(cached_entries(&DECISIONS_CACHE, &forge_file(DECISIONS_FILE)?, DECISIONS_KEY, decision_text))
+}
+
+pub fn dead_end_entries() -> Result<(Vec<DeadEndEntry>, Vec<ForgeWarning>), String> {
+    all_entries(cached_entries(&DEAD_ENDS_CACHE, &forge_file(DEAD_ENDS_FILE)?, DEAD_ENDS_KEY, dead_end_text))
+}
+
+/// Journal entries and decisions timestamped in [after, before), oldest first. Entries whose
+/// timestamp can't be parsed are left out, since there's no telling which window they're in.
+pub fn entries_between(after: DateTime<Utc>, before: DateTime<Utc>) -> Result<(Vec<JournalEntry>, Vec<DecisionEntry>), String> {
+    let in_window = |timestamp: &str| parse_timestamp(timestamp).is_some_and(|at| after <= at && at < before);
+    let by_time = |timestamp: &str| parse_timestamp(timestamp);
+
+    let (journals, _) = journal_entries()?;
+    let mut journals: Vec<JournalEntry> = journals.into_iter().filter(|e| in_window(&e.timestamp)).collect();
+    journals.sort_by_key(|e| by_time(&e.timestamp));
+    let (decisions, _) = decision_entries()?;
+    let mut decisions: Vec<DecisionEntry> = decisions.into_iter().filter(|e| in_window(&e.timestamp)).collect();
+    decisions.sort_by_key(|e| by_time(&e.timestamp));
+    Ok((journals, decisions))
+}
+
+/// Parse problems across all three files in `dir`
+fn forge_warnings(dir: &std::path::Path) -> Vec<ForgeWarning> {
+    let mut warnings = Vec::new();
+    let mut collect = |w: Option<&Vec<ForgeWarning>>| warnings.extend(w.into_iter().flatten().cloned());
+    collect(cached_entries(&JOURNAL_CACHE, &dir.join(JOURNAL_FILE), JOURNAL_KEY, journal_text).as_ref().map(|p| &p.warnings));
+    collect(cached_entries(&DECISIONS_CACHE, &dir.join(DECISIONS_FILE), DECISIONS_KEY, decision_text).as_ref().map(|p| &p.warnings));
+    collect(cached_entries(&DEAD_ENDS_CACHE, &dir.join(DEAD_ENDS_FILE), DEAD_ENDS_KEY, dead_end_text).as_ref().map(|p| &p.warnings));
+    warnings
+}
+
+// ---- Write-back ----
+
+// How long to wait for another writer's lock file before giving up
+const LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Exclusive lock on a forge file, held via a sibling `.lock` file and released on drop
+struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    fn acquire(target: &std::path::Path) -> Result<Self, String> {
+        let path = target.with_extension("json.lock");
+        let started = std::time::Instant::now();
+        loop {
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(FileLock { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if started.elapsed() >= LOCK_TIMEOUT {
+                        return Err(format!(
+                            "{} is locked by another writer (remove {} if it is stale)",
+                            target.display(),
+                            path.display()
+                        ));
+                    }
+                    std::thread::sleep(std::time::Duration::from_millis(50));
+                }
+                Err(e) => return Err(format!("Failed to lock {}: {}", target.display(), e)),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Append `entry` to the array under `key` in a forge JSON file.
+/// Other fields in the file are preserved; a missing file is created. The new content is
+/// written to a temp file and renamed into place so readers never see a partial write.
+fn append_entry<T: Serialize>(file: &str, key: &str, entry: &T) -> Result<(), String> {
+    let path = forge_file(file)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+
+    let _lock = FileLock::acquire(&path)?;
+
+    let mut data = load_json_file(&path)?.unwrap_or_else(|| serde_json::json!({}));
+    let entries = data
+        .as_object_mut()
+        .ok_or_else(|| format!("{} is not a JSON object; refusing to overwrite it", path.display()))?
+        .entry(key)
+        .or_insert_with(|| serde_json::json!([]))
+        .as_array_mut()
+        .ok_or_else(|| format!("\"{}\" in {} is not an array; refusing to overwrite it", key, path.display()))?;
+    entries.push(serde_json::to_value(entry).map_err(|e| e.to_string())?);
+
+    let json = serde_json::to_string_pretty(&data).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+pub fn append_decision(entry: &DecisionEntry) -> Result<(), String> {
+    append_entry(DECISIONS_FILE, DECISIONS_KEY, entry)
+}
+
+pub fn append_dead_end(entry: &DeadEndEntry) -> Result<(), String> {
+    append_entry(DEAD_ENDS_FILE, DEAD_ENDS_KEY, entry)
+}
+
+// ---- Parsed-file cache ----
+
+/// A forge entry with its keywords extracted once, at parse time
+struct IndexedEntry<T> {
+    entry: T,
+    keywords: HashSet<String>,
+}
+
+impl<T> IndexedEntry<T> {
+    fn shared_keywords(&self, query: &[String]) -> usize {
+        query.iter().filter(|k| self.keywords.contains(*k)).count()
+    }
+}
+
+/// Parsed entries of one file, plus warnings for anything that couldn't be parsed
+struct ParsedFile<T> {
+    entries: Vec<IndexedEntry<T>>,
+    warnings: Vec<ForgeWarning>,
+}
+
+/// A parsed file, valid while its modified time and size are unchanged
+struct CachedFile<T> {
+    modified: SystemTime,
+    len: u64,
+    parsed: Arc<ParsedFile<T>>,
+}
+
+type FileCache<T> = OnceLock<Mutex<HashMap<PathBuf, CachedFile<T>>>>;
+
+static JOURNAL_CACHE: FileCache<JournalEntry> = OnceLock::new();
+static DECISIONS_CACHE: FileCache<DecisionEntry> = OnceLock::new();
+static DEAD_ENDS_CACHE: FileCache<DeadEndEntry> = OnceLock::new();
+
+/// A forge file's entries, re-parsed only when the file changed since the last call.
+/// `key` names the entry array inside the file and `text` gives the searchable text of an
+/// entry. A file that can't be read or parsed at all yields no entries and a single warning
+/// with no index. Missing files yield None.
+fn cached_entries<T: serde::de::DeserializeOwned>(
+    cache: &FileCache<T>,
+    path: &PathBuf,
+    key: &str,
+    text: fn(&T) -> String,
+) -> Option<Arc<ParsedFile<T>>> {
+    let mut files = lock_recovering(cache.get_or_init(Default::default));
+
+    let Ok(meta) = std::fs::metadata(path) else {
+        files.remove(path);
         return None;
+    };
+    let modified = meta.modified().ok()?;
+
+    if let Some(cached) = files.get(path) {
+        if cached.modified == modified && cached.len == meta.len() {
+            return Some(Arc::clone(&cached.parsed));
+        }
+    }
+
+    let file = path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+    let parsed = match load_json_file(path).and_then(|data| parse_entries::<T>(&file, data.unwrap_or_default(), key)) {
+        Ok((entries, warnings)) => ParsedFile {
+            entries: entries.into_iter()
+                .map(|entry| IndexedEntry {
+                    keywords: extract_keywords(&text(&entry)).into_iter().collect(),
+                    entry,
+                })
+                .collect(),
+            warnings,
+        },
+        Err(message) => ParsedFile {
+            entries: Vec::new(),
+            warnings: vec![ForgeWarning { file, index: None, message }],
+        },
+    };
+    if !parsed.warnings.is_empty() {
+        tracing::warn!("session-forge: {} problem(s) reading {}", parsed.warnings.len(), path.display());
+    }
+
+    let parsed = Arc::new(parsed);
+    files.insert(path.clone(), CachedFile { modified, len: meta.len(), parsed: Arc::clone(&parsed) });
+    Some(parsed)
+}
+
+/// Drop cached parses of `file` (one of the *_FILE names) so the next read goes to disk
+pub fn invalidate_cache(file: &str) {
+    fn clear<T>(cache: &FileCache<T>) {
+        if let Some(files) = cache.get() {
+            lock_recovering(files).clear();
+        }
+    }
+    match file {
+        JOURNAL_FILE => clear(&JOURNAL_CACHE),
+        DECISIONS_FILE => clear(&DECISIONS_CACHE),
+        DEAD_ENDS_FILE => clear(&DEAD_ENDS_CACHE),
+        _ => {}
+    }
+}
+
+fn journal_text(j: &JournalEntry) -> String {
+    format!(
+        "{} {} {} {}",
+        j.session_summary,
+        j.key_moments.join(" "),
+        j.breakthroughs.join(" "),
+        j.frustrations.join(" ")
+    )
+}
+
+fn decision_text(d: &DecisionEntry) -> String {
+    format!(
+        "{} {} {} {}",
+        d.choice,
+        d.reasoning,
+        d.alternatives.join(" "),
+        d.tags.join(" ")
+    )
+}
+
+fn dead_end_text(d: &DeadEndEntry) -> String {
+    format!(
+        "{} {} {} {}",
+        d.attempted,
+        d.why_failed,
+        d.lesson,
+        d.tags.join(" ")
+    )
+}
+
+/// Entries scoring at least `min_score` against the query and accepted by `keep`,
+/// as (score, recency rank, entry).
+/// Later entries in a file are more recent, so the rank is the file position.
+/// The file's parse warnings are appended to `warnings`.
+fn matching<T: Clone>(
+    parsed: Option<Arc<ParsedFile<T>>>,
+    keywords: &[String],
+    min_score: f64,
+    keep: impl Fn(&T) -> bool,
+    warnings: &mut Vec<ForgeWarning>,
+) -> Vec<(f64, usize, T)> {
+    let Some(parsed) = parsed else {
+        return Vec::new();
+    };
+    warnings.extend(parsed.warnings.iter().cloned());
+    parsed.entries.iter()
+        .enumerate()
+        .filter_map(|(i, e)| {
+            let score = e.shared_keywords(keywords) as f64;
+            (score > 0.0 && score >= min_score && keep(&e.entry)).then(|| (score, i, e.entry.clone()))
+        })
+        .collect()
+}
+
+/// A hit of any kind, so the three files can be ranked together under one limit
+enum Hit {
+    Journal(JournalEntry),
+    Decision(DecisionEntry),
+    DeadEnd(DeadEndEntry),
+}
+
+impl Hit {
+    fn timestamp(&self) -> &str {
+        match self {
+            Hit::Journal(e) => &e.timestamp,
+            Hit::Decision(e) => &e.timestamp,
+            Hit::DeadEnd(e) => &e.timestamp,
+        }
     }
-    let content = std::fs::read_to_string(path).ok()?;
-    serde_json::from_str(&content).ok()
 }
 
 // ---- Search ----
 
+pub const DEFAULT_FORGE_LIMIT: usize = 20;
+pub const MAX_FORGE_LIMIT: usize = 200;
+
+/// Optional narrowing of a forge search
+#[derive(Debug, Clone, Default)]
+pub struct ForgeFilter {
+    /// Decisions and dead ends must have this project (case-insensitive); journals have no
+    /// project field, so they are kept only when their summary mentions it
+    pub project: Option<String>,
+    /// Entries at or after this time
+    pub after: Option<String>,
+    /// Entries strictly before this time
+    pub before: Option<String>,
+}
+
+/// Parsed form of ForgeFilter
+struct EntryFilter {
+    project: Option<String>,
+    after: Option<DateTime<Utc>>,
+    before: Option<DateTime<Utc>>,
+}
+
+impl EntryFilter {
+    fn new(filter: &ForgeFilter) -> Result<Self, String> {
+        let bound = |value: &Option<String>, name: &str| -> Result<Option<DateTime<Utc>>, String> {
+            match value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                Some(v) => parse_timestamp(v)
+                    .map(Some)
+                    .ok_or_else(|| format!("Invalid {} date: {} (expected YYYY-MM-DD or RFC 3339)", name, v)),
+                None => Ok(None),
+            }
+        };
+        Ok(EntryFilter {
+            project: filter.project.as_deref()
+                .map(|p| p.trim().to_lowercase())
+                .filter(|p| !p.is_empty()),
+            after: bound(&filter.after, "after")?,
+            before: bound(&filter.before, "before")?,
+        })
+    }
+
+    /// Entries whose timestamp can't be parsed are kept: a bad date shouldn't hide an entry
+    fn in_range(&self, timestamp: &str) -> bool {
+        let Some(at) = parse_timestamp(timestamp) else {
+            return true;
+        };
+        self.after.is_none_or(|after| at >= after) && self.before.is_none_or(|before| at < before)
+    }
+
+    fn project_is(&self, project: Option<&str>) -> bool {
+        match &self.project {
+            Some(wanted) => project.is_some_and(|p| p.trim().to_lowercase() == *wanted),
+            None => true,
+        }
+    }
+
+    fn mentions_project(&self, text: &str) -> bool {
+        match &self.project {
+            Some(wanted) => text.to_lowercase().contains(wanted.as_str()),
+            None => true,
+        }
+    }
+}
+
+/// Forge tools don't all write timestamps the same way: accept RFC 3339, naive date-times
+/// (taken as UTC), plain dates, and Unix seconds or milliseconds.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Some(t.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(t) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(t.and_utc());
+        }
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return d.and_hms_opt(0, 0, 0).map(|t| t.and_utc());
+    }
+    let number: i64 = value.parse().ok()?;
+    // Anything past year ~2286 in seconds is really milliseconds
+    if number.abs() >= 10_000_000_000 {
+        DateTime::from_timestamp_millis(number)
+    } else {
+        DateTime::from_timestamp(number, 0)
+    }
+}
+
 /// Search session-forge data for entries related to the given query text.
-/// Uses keyword extraction and overlap filtering (>= 1 shared keyword).
-/// Returns up to 10 entries per type, most recent first.
-pub fn search_forge_context(query: &str) -> Result<ForgeContext, String> {
+/// Each entry is scored by how many query keywords it shares; entries below `min_score`
+/// (default 1) or outside `filter` are dropped. The best `limit` entries across all three
+/// files are returned, highest score first with the most recent winning ties. Parsed files
+/// are cached and only re-read when they change on disk; user stopwords are dropped from
+/// the query, so they never contribute to a score.
+pub fn search_forge_context(
+    query: &str,
+    limit: usize,
+    min_score: Option<f64>,
+    filter: &ForgeFilter,
+    keyword_config: &KeywordConfig,
+) -> Result<ForgeContext, String> {
+    let filter = EntryFilter::new(filter)?;
     let dir = get_session_forge_dir()
         .ok_or_else(|| "session-forge directory not found".to_string())?;
 
     if !dir.exists() {
-        return Ok(ForgeContext {
-            journals: vec![],
-            decisions: vec![],
-            dead_ends: vec![],
-        });
+        return Ok(ForgeContext::default());
     }
 
-    let keywords = extract_keywords(query);
+    let keywords = extract_keywords_with(query, keyword_config);
+    let limit = limit.min(MAX_FORGE_LIMIT);
+    if keywords.is_empty() || limit == 0 {
+        return Ok(ForgeContext::default());
+    }
+    let min_score = min_score.unwrap_or(1.0);
+
+    let mut warnings = Vec::new();
+    let journals = matching(
+        cached_entries(&JOURNAL_CACHE, &dir.join(JOURNAL_FILE), JOURNAL_KEY, journal_text),
+        &keywords,
+        min_score,
+        |j| filter.in_range(&j.timestamp) && filter.mentions_project(&j.session_summary),
+        &mut warnings,
+    );
+    let decisions = matching(
+        cached_entries(&DECISIONS_CACHE, &dir.join(DECISIONS_FILE), DECISIONS_KEY, decision_text),
+        &keywords,
+        min_score,
+        |d| filter.in_range(&d.timestamp) && filter.project_is(d.project.as_deref()),
+        &mut warnings,
+    );
+    let dead_ends = matching(
+        cached_entries(&DEAD_ENDS_CACHE, &dir.join(DEAD_ENDS_FILE), DEAD_ENDS_KEY, dead_end_text),
+        &keywords,
+        min_score,
+        |d| filter.in_range(&d.timestamp) && filter.project_is(d.project.as_deref()),
+        &mut warnings,
+    );
+
+    let mut hits: Vec<(f64, Option<DateTime<Utc>>, usize, Hit)> = journals.into_iter()
+        .map(|(s, i, e)| (s, i, Hit::Journal(e)))
+        .chain(decisions.into_iter().map(|(s, i, e)| (s, i, Hit::Decision(e))))
+        .chain(dead_ends.into_iter().map(|(s, i, e)| (s, i, Hit::DeadEnd(e))))
+        .map(|(score, i, hit)| (score, parse_timestamp(hit.timestamp()), i, hit))
+        .collect();
+    // Score first; then newer timestamp (unparseable ones last), then later file position
+    hits.sort_by(|(sa, ta, ia, _), (sb, tb, ib, _)| {
+        sb.total_cmp(sa)
+            .then_with(|| tb.cmp(ta))
+            .then_with(|| ib.cmp(ia))
+    });
+    hits.truncate(limit);
+
+    let mut context = ForgeContext { warnings, ..Default::default() };
+    for (score, _, _, hit) in hits {
+        match hit {
+            Hit::Journal(entry) => context.journals.push(Scored { entry, score }),
+            Hit::Decision(entry) => context.decisions.push(Scored { entry, score }),
+            Hit::DeadEnd(entry) => context.dead_ends.push(Scored { entry, score }),
+        }
+    }
+    Ok(context)
+}
+
+/// The decision sharing the most keywords with `text`, if it shares at least `min_shared`
+/// (most recent wins ties). Reads through the parsed-file cache, so it is cheap enough to
+/// run for every logged thought.
+pub fn related_decision(text: &str, min_shared: usize, keyword_config: &KeywordConfig) -> Option<Scored<DecisionEntry>> {
+    let dir = get_session_forge_dir()?;
+    let keywords = extract_keywords_with(text, keyword_config);
     if keywords.is_empty() {
-        return Ok(ForgeContext {
-            journals: vec![],
-            decisions: vec![],
-            dead_ends: vec![],
-        });
-    }
-
-    // Search journals
-    let journals = read_json_file::<JournalData>(&dir.join("journal.json"))
-        .map(|data| {
-            let mut matches: Vec<JournalEntry> = data.sessions.into_iter().filter(|j| {
-                let text = format!(
-                    "{} {} {} {}",
-                    j.session_summary,
-                    j.key_moments.join(" "),
-                    j.breakthroughs.join(" "),
-                    j.frustrations.join(" ")
-                );
-                let entry_keywords = extract_keywords(&text);
-                count_shared_keywords(&keywords, &entry_keywords) >= 1
-            }).collect();
-            matches.reverse(); // most recent first
-            matches.truncate(10);
-            matches
-        })
-        .unwrap_or_default();
-
-    // Search decisions
-    let decisions = read_json_file::<DecisionsData>(&dir.join("decisions.json"))
-        .map(|data| {
-            let mut matches: Vec<DecisionEntry> = data.decisions.into_iter().filter(|d| {
-                let text = format!(
-                    "{} {} {} {}",
-                    d.choice,
-                    d.reasoning,
-                    d.alternatives.join(" "),
-                    d.tags.join(" ")
-                );
-                let entry_keywords = extract_keywords(&text);
-                count_shared_keywords(&keywords, &entry_keywords) >= 1
-            }).collect();
-            matches.reverse();
-            matches.truncate(10);
-            matches
-        })
-        .unwrap_or_default();
-
-    // Search dead ends
-    let dead_ends = read_json_file::<DeadEndsData>(&dir.join("dead-ends.json"))
-        .map(|data| {
-            let mut matches: Vec<DeadEndEntry> = data.dead_ends.into_iter().filter(|d| {
-                let text = format!(
-                    "{} {} {} {}",
-                    d.attempted,
-                    d.why_failed,
-                    d.lesson,
-                    d.tags.join(" ")
-                );
-                let entry_keywords = extract_keywords(&text);
-                count_shared_keywords(&keywords, &entry_keywords) >= 1
-            }).collect();
-            matches.reverse();
-            matches.truncate(10);
-            matches
-        })
-        .unwrap_or_default();
+        return None;
+    }
+
+    let parsed = cached_entries(&DECISIONS_CACHE, &dir.join(DECISIONS_FILE), DECISIONS_KEY, decision_text)?;
+    parsed.entries.iter()
+        .map(|e| (e.shared_keywords(&keywords), e))
+        .filter(|(shared, _)| *shared >= min_shared.max(1))
+        // max_by_key keeps the last maximum, i.e. the most recent entry
+        .max_by_key(|(shared, _)| *shared)
+        .map(|(shared, e)| Scored { entry: e.entry.clone(), score: shared as f64 })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+    use super::{cached_entries, decision_text, DecisionEntry, FileCache, DECISIONS_KEY};
 
-    Ok(ForgeContext { journals, decisions, dead_ends })
+    fn write_decisions(path: &PathBuf, choices: &[&str]) {
+        let decisions: Vec<_> = choices.iter()
+            .map(|c| serde_json::json!({ "timestamp": "2026-03-01T12:00:00Z", "choice": c, "reasoning": "because" }))
+            .collect();
+        std::fs::write(path, serde_json::json!({ "decisions": decisions }).to_string()).unwrap();
+    }
+
+    fn choices(cache: &FileCache<DecisionEntry>, path: &PathBuf) -> Option<Vec<String>> {
+        cached_entries(cache, path, DECISIONS_KEY, decision_text)
+            .map(|p| p.entries.iter().map(|e| e.entry.choice.clone()).collect())
+    }
+
+    #[test]
+    fn the_cache_picks_up_writes_to_the_file() {
+        let dir = std::env::temp_dir().join(format!("the-mind-forge-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("decisions.json");
+        let cache: FileCache<DecisionEntry> = Default::default();
+
+        write_decisions(&path, &["sqlite"]);
+        assert_eq!(choices(&cache, &path).unwrap(), ["sqlite"]);
+        let first = cached_entries(&cache, &path, DECISIONS_KEY, decision_text).unwrap();
+        let again = cached_entries(&cache, &path, DECISIONS_KEY, decision_text).unwrap();
+        assert!(std::sync::Arc::ptr_eq(&first, &again), "an unchanged file is served from the cache");
+
+        write_decisions(&path, &["sqlite", "tauri"]);
+        assert_eq!(choices(&cache, &path).unwrap(), ["sqlite", "tauri"]);
+
+        // Same length, so only the modification time tells the versions apart
+        write_decisions(&path, &["sqlite", "webgl"]);
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5)).unwrap();
+        assert_eq!(choices(&cache, &path).unwrap(), ["sqlite", "webgl"]);
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(choices(&cache, &path).is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use uuid::Uuid;
+use chrono::Utc;
+use crate::auto_connect::connection_reason;
+use crate::database::{Database, ThoughtSort, AUTO_CONNECTION_KIND};
+use crate::utils::{extract_terms, shared_keywords};
+use crate::{Connection, ConnectionSuggestion, SimilarThought, Thought};
+
+// Most recently referenced thoughts checked for partners on each pass
+const SCAN_THOUGHTS: i64 = 500;
+// Similar thoughts considered per scanned thought
+const PER_THOUGHT: usize = 5;
+// A pair needs at least this similarity (0..1) to be worth suggesting
+const MIN_SCORE: f64 = 0.2;
+
+/// Queue up to `limit` new suggestions for similar, unconnected pairs (see
+/// Database::get_similar_thoughts), skipping pairs suggested before, then return the best
+/// `limit` pending suggestions.
+pub fn suggest_connections(db: &Database, limit: usize) -> rusqlite::Result<Vec<ConnectionSuggestion>> {
+    // Each pair once, keyed smaller id first
+    let mut pairs: HashMap<(String, String), (Thought, SimilarThought)> = HashMap::new();
+    for thought in db.get_thoughts_page(0, SCAN_THOUGHTS, ThoughtSort::LastReferenced, false)? {
+        for similar in db.get_similar_thoughts(&thought.id, PER_THOUGHT)? {
+            if similar.score < MIN_SCORE {
+                continue;
+            }
+            let key = if thought.id < similar.thought.id {
+                (thought.id.clone(), similar.thought.id.clone())
+            } else {
+                (similar.thought.id.clone(), thought.id.clone())
+            };
+            if pairs.contains_key(&key) || db.suggestion_exists(&key.0, &key.1)? {
+                continue;
+            }
+            pairs.insert(key, (thought.clone(), similar));
+        }
+    }
+
+    let mut pairs: Vec<(Thought, SimilarThought)> = pairs.into_values().collect();
+    pairs.sort_by(|a, b| b.1.score.total_cmp(&a.1.score).then_with(|| a.0.id.cmp(&b.0.id)));
+    pairs.truncate(limit);
+
+    let now = Utc::now().to_rfc3339();
+    let proposed: Vec<Connection> = pairs.iter()
+        .map(|(from, similar)| {
+            let (kind, reason) = describe(from, similar);
+            Connection {
+                id: Uuid::new_v4().to_string(),
+                from_thought: from.id.clone(),
+                to_thought: similar.thought.id.clone(),
+                strength: similar.score,
+                reason,
+                created_at: now.clone(),
+                kind,
+            }
+        })
+        .collect();
+    db.insert_suggestions(&proposed)?;
+
+    db.get_pending_suggestions(limit as i64)
+}
+
+/// Kind and reason for the connection a pair would get: keyword overlap reads like an
+/// auto-connection; embedding similarity is "related"
+fn describe(from: &Thought, similar: &SimilarThought) -> (String, String) {
+    if similar.basis == "embedding" {
+        let reason = format!("Similar in meaning ({:.0}%)", similar.score * 100.0);
+        return (crate::default_connection_kind(), reason);
+    }
+    let a = extract_terms(&from.content);
+    let b = extract_terms(&similar.thought.content);
+    let shared = shared_keywords(&a.keywords, &b.keywords);
+    let phrases: Vec<String> = a.phrases.iter()
+        .filter(|p| b.phrases.iter().any(|q| q.key == p.key))
+        .map(|p| p.text.clone())
+        .collect();
+    (AUTO_CONNECTION_KIND.to_string(), connection_reason(&shared, &phrases))
+}
@@ -0,0 +1,165 @@
+use uuid::Uuid;
+use chrono::Utc;
+use crate::auto_connect::{auto_connect, AutoConnectConfig};
+use crate::database::Database;
+use crate::progress::{Progress, BATCH_SIZE};
+use crate::Thought;
+
+// Turns shorter than this (after trimming) are greetings/acks, not thoughts
+const MIN_TURN_CHARS: usize = 40;
+
+/// One speaker turn parsed from a pasted conversation
+#[derive(Debug, Clone)]
+pub struct Turn {
+    pub role: Option<String>,
+    pub content: String,
+}
+
+/// Split a transcript into turns on "Human:" / "Assistant:" markers at the start of a line.
+/// Text with no markers falls back to blank-line separated paragraphs with no role.
+pub fn parse_turns(text: &str) -> Vec<Turn> {
+    let mut turns = Vec::new();
+    let mut current: Option<Turn> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let marker = [("Human:", "user"), ("User:", "user"), ("Assistant:", "assistant"), ("Claude:", "assistant")]
+            .iter()
+            .find(|(prefix, _)| trimmed.starts_with(prefix));
+
+        match marker {
+            Some((prefix, role)) => {
+                if let Some(turn) = current.take() {
+                    turns.push(turn);
+                }
+                current = Some(Turn {
+                    role: Some(role.to_string()),
+                    content: trimmed[prefix.len()..].trim_start().to_string(),
+                });
+            }
+            None => {
+                if let Some(turn) = current.as_mut() {
+                    turn.content.push('\n');
+                    turn.content.push_str(line);
+                }
+            }
+        }
+    }
+    if let Some(turn) = current {
+        turns.push(turn);
+    }
+
+    if turns.is_empty() {
+        turns = text.split("\n\n")
+            .map(|paragraph| Turn { role: None, content: paragraph.to_string() })
+            .collect();
+    }
+
+    for turn in turns.iter_mut() {
+        turn.content = turn.content.trim().to_string();
+    }
+    turns
+}
+
+/// Create a session from a transcript: one thought per substantive turn, linked to the
+/// session in order, each run through the normal auto-connection pass. Turns are saved in
+/// transactions of BATCH_SIZE; progress is reported and cancellation checked between them,
+/// so a cancelled import keeps whole batches only and its session is still closed.
+pub fn import_transcript(db: &Database, text: &str, title: &str, progress: Progress) -> rusqlite::Result<crate::TranscriptImport> {
+    let _exclusive = crate::maintenance::exclusive();
+    let turns = parse_turns(text);
+    let session_id = Uuid::new_v4().to_string();
+    let started = Utc::now().to_rfc3339();
+    let config = AutoConnectConfig::load(db);
+
+    let mut result = crate::TranscriptImport {
+        session_id: session_id.clone(),
+        turns_found: turns.len(),
+        thoughts_created: 0,
+        turns_skipped: 0,
+        connections_created: 0,
+        cancelled: false,
+    };
+
+    db.insert_session(&session_id, title, &format!("Imported transcript ({} turns)", turns.len()), &started, &started)?;
+
+    let (turns, skipped): (Vec<Turn>, Vec<Turn>) = turns.into_iter()
+        .partition(|turn| turn.content.chars().count() >= MIN_TURN_CHARS);
+    result.turns_skipped = skipped.len();
+    let total = turns.len();
+
+    // Consecutive turns are placed near each other so a conversation reads as a trail
+    let mut previous: Option<(f64, f64, f64)> = None;
+    let mut turns = turns.into_iter().peekable();
+    while turns.peek().is_some() {
+        if progress.is_cancelled() {
+            result.cancelled = true;
+            break;
+        }
+
+        let batch: Vec<Thought> = turns.by_ref().take(BATCH_SIZE).map(|turn| {
+            let mut random = db.position_random(&turn.content, "other");
+            let (x, y, z) = db.clear_position(match previous {
+                Some((px, py, pz)) => Database::generate_position_near(px, py, pz, 4.0, &mut random),
+                None => Database::generate_position(&mut random),
+            });
+            previous = Some((x, y, z));
+
+            let now = Utc::now().to_rfc3339();
+            Thought {
+                id: Uuid::new_v4().to_string(),
+                content: turn.content,
+                role: turn.role,
+                category: "other".to_string(),
+                importance: 0.5,
+                position_x: x,
+                position_y: y,
+                position_z: z,
+                created_at: now.clone(),
+                last_referenced: now,
+                pinned: false,
+                favorite: false,
+                archived: false,
+            }
+        }).collect();
+
+        db.insert_session_thoughts(&session_id, &batch, result.thoughts_created as i64)?;
+        result.thoughts_created += batch.len();
+        for thought in &batch {
+            result.connections_created += auto_connect(db, thought, &config)?.connected.len();
+        }
+        progress.report(result.thoughts_created, Some(total), "Importing transcript turns");
+    }
+
+    db.end_session(&session_id, &Utc::now().to_rfc3339())?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use super::import_transcript;
+    use crate::database::testing::{session_positions, TempDb};
+    use crate::progress::{Progress, BATCH_SIZE};
+
+    #[test]
+    fn a_cancelled_import_keeps_whole_batches_and_closes_its_session() {
+        let db = TempDb::new();
+        let text: String = (0..BATCH_SIZE * 2 + 5)
+            .map(|i| format!("Human: turn {} of the pasted conversation, long enough to count as a thought\n", i))
+            .collect();
+        let cancel = AtomicBool::new(false);
+        // Asks to stop as soon as the first batch has been reported
+        let report = |_: usize, _: Option<usize>, _: &str| cancel.store(true, Ordering::Relaxed);
+
+        let result = import_transcript(&db, &text, "Pasted", Progress::new(Some(&report), Some(&cancel))).unwrap();
+
+        assert!(result.cancelled);
+        assert_eq!(result.thoughts_created, BATCH_SIZE);
+        assert_eq!(db.get_thought_count().unwrap(), BATCH_SIZE as i64);
+        let positions = session_positions(&db, &result.session_id);
+        assert_eq!(positions, (0..BATCH_SIZE as i64).collect::<Vec<_>>());
+        let session = db.find_session(&result.session_id).unwrap().expect("the session is kept");
+        assert!(session.ended_at.is_some());
+    }
+}
@@ -1,6 +1,20 @@
-/// Extract keywords from text (simple word extraction, filtering stopwords)
-pub fn extract_keywords(text: &str) -> Vec<String> {
-    let stopwords = [
+use std::collections::HashSet;
+use std::sync::{Mutex, MutexGuard};
+
+/// Settings key for user stopwords, comma-separated (applied in every language)
+pub const SETTING_EXTRA_STOPWORDS: &str = "extra_stopwords";
+
+/// Languages with a built-in stopword list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+const STOPWORDS: [(Language, &[&str]); 4] = [
+    (Language::English, &[
         "the", "a", "an", "is", "are", "was", "were", "be", "been", "being",
         "have", "has", "had", "do", "does", "did", "will", "would", "could",
         "should", "may", "might", "must", "shall", "can", "need", "dare",
@@ -12,20 +26,872 @@ pub fn extract_keywords(text: &str) -> Vec<String> {
         "there", "when", "where", "why", "how", "all", "each", "every", "any",
         "few", "more", "most", "other", "some", "such", "no", "none", "this",
         "that", "these", "those", "i", "you", "he", "she", "it", "we", "they",
-        "what", "which", "who", "whom", "this", "that", "am", "about", "up",
-    ];
+        "what", "which", "who", "whom", "am", "about", "up",
+    ]),
+    (Language::German, &[
+        "der", "die", "das", "und", "oder", "aber", "ist", "sind", "war", "waren",
+        "ein", "eine", "einer", "eines", "einem", "einen", "nicht", "mit", "von",
+        "für", "auf", "auch", "dass", "wie", "bei", "aus", "nach", "noch", "nur",
+        "ich", "du", "er", "sie", "es", "wir", "ihr", "zu", "im", "in", "den",
+        "dem", "des", "sich", "zum", "zur", "wird", "werden", "hat", "haben",
+        "kann", "können", "muss", "sehr", "schon", "mal", "dann", "wenn", "als",
+        "so", "um", "über", "unter", "vor", "durch", "doch", "jetzt", "hier",
+        "dort", "man", "mein", "dein", "sein", "kein", "keine", "alle", "diese",
+        "dieser", "dieses", "was", "wer", "wo", "warum", "immer", "heute",
+    ]),
+    (Language::French, &[
+        "le", "la", "les", "un", "une", "des", "du", "de", "et", "ou", "mais",
+        "est", "sont", "était", "être", "avoir", "avec", "pour", "par", "sur",
+        "dans", "pas", "plus", "que", "qui", "quoi", "comme", "ce", "cette",
+        "ces", "il", "elle", "ils", "elles", "nous", "vous", "je", "tu", "on",
+        "mon", "ton", "son", "leur", "leurs", "aussi", "très", "tout", "tous",
+        "toute", "toutes", "encore", "déjà", "donc", "alors", "quand", "où",
+        "ici", "fait", "faire", "sans", "sous", "entre", "vers", "chez",
+    ]),
+    (Language::Spanish, &[
+        "el", "la", "los", "las", "un", "una", "unos", "unas", "y", "o", "pero",
+        "es", "son", "era", "ser", "estar", "está", "están", "con", "para", "por",
+        "sobre", "en", "de", "del", "al", "que", "qué", "como", "cómo", "este",
+        "esta", "estos", "estas", "ese", "esa", "yo", "tú", "él", "ella", "ellos",
+        "nosotros", "vosotros", "mi", "tu", "su", "sus", "también", "muy", "todo",
+        "todos", "toda", "todas", "ya", "aún", "cuando", "donde", "dónde", "aquí",
+        "hacer", "sin", "entre", "hasta", "desde", "porque", "más", "menos",
+    ]),
+];
+
+fn stopwords(language: Language) -> &'static [&'static str] {
+    STOPWORDS.iter()
+        .find(|(l, _)| *l == language)
+        .map(|(_, words)| *words)
+        .unwrap_or_default()
+}
+
+/// Guess the language of Latin-script text by which stopword list it hits most.
+/// Defaults to English when nothing matches.
+pub fn detect_language(text: &str) -> Language {
+    let lowered = text.to_lowercase();
+    let words: Vec<String> = tokenize(&lowered)
+        .into_iter()
+        .filter_map(|t| match t {
+            Token::Word(w) if is_latin(&w) => Some(w),
+            _ => None,
+        })
+        .collect();
+
+    let mut best = (Language::English, 0);
+    for (language, list) in STOPWORDS {
+        let hits = words.iter().filter(|w| list.contains(&w.as_str())).count();
+        if hits > best.1 {
+            best = (language, hits);
+        }
+    }
+    best.0
+}
+
+/// Keyword extraction options that come from user settings
+#[derive(Debug, Clone, Default)]
+pub struct KeywordConfig {
+    /// Extra stopwords, each stored as typed and as its stem so every form is caught
+    pub extra_stopwords: HashSet<String>,
+}
+
+impl KeywordConfig {
+    /// Parse a comma-separated stopword list (the extra_stopwords setting format)
+    pub fn parse(list: &str) -> Self {
+        let mut extra_stopwords = HashSet::new();
+        for word in list.split(',').map(|w| w.trim().to_lowercase()).filter(|w| !w.is_empty()) {
+            extra_stopwords.insert(normalize_keyword(&word));
+            extra_stopwords.insert(word);
+        }
+        KeywordConfig { extra_stopwords }
+    }
+
+    /// Load from the settings table
+    pub fn load(db: &crate::database::Database) -> Self {
+        Self::parse(&db.setting_or(SETTING_EXTRA_STOPWORDS, String::new()))
+    }
+
+    fn is_extra(&self, word: &str) -> bool {
+        self.extra_stopwords.contains(word)
+    }
+}
+
+/// Extract keywords with no user stopwords; see extract_keywords_with
+pub fn extract_keywords(text: &str) -> Vec<String> {
+    extract_keywords_with(text, &KeywordConfig::default())
+}
+
+/// Extract keywords and phrases with no user stopwords; see extract_terms_with
+pub fn extract_terms(text: &str) -> Terms {
+    extract_terms_with(text, &KeywordConfig::default())
+}
+
+/// A pair of adjacent keywords, such as "machine learning"
+#[derive(Debug, Clone)]
+pub struct Phrase {
+    /// Both stems joined by a space ("machin learn"); what phrases are matched and counted by
+    pub key: String,
+    /// The words as written (lowercased), for display
+    pub text: String,
+}
+
+/// Keywords and phrases from one pass over a text
+#[derive(Debug, Clone, Default)]
+pub struct Terms {
+    pub keywords: Vec<String>,
+    pub phrases: Vec<Phrase>,
+}
+
+/// Phrase keys contain a space; single keywords never do
+pub fn is_phrase(term: &str) -> bool {
+    term.contains(' ')
+}
+
+/// Extract keywords from text: words of 3+ characters, minus stopwords, stemmed with
+/// normalize_keyword so "cluster", "clusters" and "clustering" count as one keyword.
+/// Any character that isn't a letter, digit or combining mark separates words, so
+/// "auto-connect" yields "auto" and "connect" while "café" stays whole. Chinese, Japanese
+/// and Korean have no spaces to split on, so their runs become overlapping character
+/// bigrams instead. Each keyword appears once, in order of first occurrence.
+///
+/// Stopwords are those of the text's detected language plus English (technical terms
+/// are often English even in other languages), plus `config`'s extra stopwords.
+pub fn extract_keywords_with(text: &str, config: &KeywordConfig) -> Vec<String> {
+    extract_terms_with(text, config).keywords
+}
+
+/// Keywords as extract_keywords_with finds them, plus phrases: each pair of keywords that
+/// are adjacent in the text, so "machine learning" and "learning to machine-knit" are told
+/// apart. A stopword, dropped short word or sentence punctuation between two words breaks
+/// the pair. Each phrase appears once, in order of first occurrence. The inside of ```
+/// code fences is skipped, so identifiers don't count as shared topics.
+pub fn extract_terms_with(text: &str, config: &KeywordConfig) -> Terms {
+    let prose = strip_code_fences(text);
+    let text = prose.as_str();
+    let language = detect_language(text);
+    let is_stopword = |word: &str| {
+        stopwords(Language::English).contains(&word) || stopwords(language).contains(&word)
+    };
+
+    let mut seen = HashSet::new();
+    let mut seen_phrases = HashSet::new();
+    let mut terms = Terms::default();
+    // The previous token, if it was a kept word: (as written, stem)
+    let mut previous: Option<(String, String)> = None;
+    for token in tokenize(&text.to_lowercase()) {
+        let follows = previous.take();
+        match token {
+            Token::Word(word) => {
+                if word.chars().count() < 3 || config.is_extra(&word) {
+                    continue;
+                }
+                // The built-in lists are all Latin-script, so only those words are checked against them
+                if is_latin(&word) && is_stopword(&word) {
+                    continue;
+                }
+                let stem = normalize_keyword(&word);
+                if config.is_extra(&stem) {
+                    continue;
+                }
+                if seen.insert(stem.clone()) {
+                    terms.keywords.push(stem.clone());
+                }
+                if let Some((prev_word, prev_stem)) = follows.filter(|(_, prev_stem)| *prev_stem != stem) {
+                    let key = [prev_stem.as_str(), stem.as_str()].join(" ");
+                    if !seen_phrases.contains(&key) {
+                        let text = [prev_word.as_str(), word.as_str()].join(" ");
+                        seen_phrases.insert(key.clone());
+                        terms.phrases.push(Phrase { key, text });
+                    }
+                }
+                previous = Some((word, stem));
+            }
+            Token::Break => {}
+            Token::Cjk(run) => {
+                let bigrams: Vec<String> = if run.len() == 2 {
+                    vec![run.iter().collect()]
+                } else {
+                    run.windows(2).map(|pair| pair.iter().collect()).collect()
+                };
+                for bigram in bigrams {
+                    if !config.is_extra(&bigram) && seen.insert(bigram.clone()) {
+                        terms.keywords.push(bigram);
+                    }
+                }
+            }
+        }
+    }
+    terms
+}
+
+enum Token {
+    Word(String),
+    /// A run of one CJK script (single characters are dropped)
+    Cjk(Vec<char>),
+    /// Sentence or clause punctuation, which ends a phrase
+    Break,
+}
 
-    text.to_lowercase()
-        .split(|c: char| !c.is_alphanumeric())
-        .filter(|word| word.len() >= 3)
-        .filter(|word| !stopwords.contains(word))
-        .map(|s| s.to_string())
+#[derive(Clone, Copy, PartialEq)]
+enum CharClass {
+    Word,
+    Han,
+    Hiragana,
+    Katakana,
+    Hangul,
+    Punctuation,
+    Separator,
+}
+
+fn char_class(c: char) -> CharClass {
+    match c as u32 {
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2EBEF => CharClass::Han,
+        0x3040..=0x309F => CharClass::Hiragana,
+        0x30A0..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => CharClass::Katakana,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => CharClass::Hangul,
+        _ if c.is_alphanumeric() || is_combining_mark(c) => CharClass::Word,
+        _ if matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | '(' | ')' | '[' | ']' | '"' | '\n' | '。' | '、' | '，') => {
+            CharClass::Punctuation
+        }
+        _ => CharClass::Separator,
+    }
+}
+
+/// Combining diacritics, so decomposed accents ("e" + U+0301) don't split a word
+fn is_combining_mark(c: char) -> bool {
+    matches!(c as u32, 0x0300..=0x036F | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F)
+}
+
+/// Latin letters (with accents), digits and combining marks only
+fn is_latin(word: &str) -> bool {
+    word.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c as u32, 0x00C0..=0x024F | 0x1E00..=0x1EFF) || is_combining_mark(c))
+}
+
+/// Split lowercase text into words, CJK runs and punctuation breaks; a change of script also ends a token,
+/// so mixed text like "rustのコード" separates cleanly. Hiragana runs are mostly particles
+/// and inflections, so they only act as separators.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current: Vec<char> = Vec::new();
+    let mut current_class = CharClass::Separator;
+
+    let mut flush = |chars: &mut Vec<char>, class: CharClass| {
+        match class {
+            CharClass::Word if !chars.is_empty() => tokens.push(Token::Word(chars.iter().collect())),
+            CharClass::Han | CharClass::Katakana | CharClass::Hangul if chars.len() >= 2 => {
+                tokens.push(Token::Cjk(std::mem::take(chars)))
+            }
+            CharClass::Punctuation => tokens.push(Token::Break),
+            _ => {}
+        }
+        chars.clear();
+    };
+
+    for c in text.chars() {
+        let class = char_class(c);
+        // Combining marks stay attached to whatever they follow
+        let class = if is_combining_mark(c) && !matches!(current_class, CharClass::Separator | CharClass::Punctuation) { current_class } else { class };
+        if class != current_class {
+            flush(&mut current, current_class);
+            current_class = class;
+        }
+        if !matches!(class, CharClass::Separator | CharClass::Punctuation) {
+            current.push(c);
+        }
+    }
+    flush(&mut current, current_class);
+    tokens
+}
+
+// Words whose trailing "s", "ing" or "ed" is not a suffix
+const STEM_EXCEPTIONS: &[&str] = &[
+    "news", "series", "species", "always", "perhaps", "thus", "yes", "lens", "canvas",
+    "alias", "atlas", "bias", "chaos", "axis", "basis", "analysis", "status", "focus",
+    "bonus", "virus", "campus", "corpus", "census", "minus", "plus", "various",
+    "previous", "serious", "famous", "nervous", "obvious", "during", "anything",
+    "everything", "nothing", "something",
+];
+
+/// Light suffix-stripping stemmer for a single lowercase word: folds plurals
+/// (s/es/ies), "-ing" and "-ed" verb forms, and "-ion" nouns after s/t, so
+/// "connected", "connecting" and "connections" all become "connect".
+/// Stems are only for comparing words, not for display.
+pub fn normalize_keyword(word: &str) -> String {
+    let word = word.to_lowercase();
+    if word.len() < 4 || !word.is_ascii() || STEM_EXCEPTIONS.contains(&word.as_str()) {
+        return word;
+    }
+    strip_ion(strip_verb_suffix(strip_plural(word)))
+}
+
+fn is_vowel(c: u8) -> bool {
+    matches!(c, b'a' | b'e' | b'i' | b'o' | b'u')
+}
+
+fn has_vowel(word: &str) -> bool {
+    word.bytes().enumerate().any(|(i, c)| is_vowel(c) || (c == b'y' && i > 0))
+}
+
+fn strip_plural(word: String) -> String {
+    if word.ends_with("ss") || word.ends_with("us") || word.ends_with("is") {
+        return word;
+    }
+    if let Some(base) = word.strip_suffix("ies") {
+        if base.len() >= 2 {
+            return format!("{}y", base);
+        }
+    }
+    if let Some(base) = word.strip_suffix("es") {
+        if ["ss", "x", "z", "ch", "sh"].iter().any(|end| base.ends_with(end)) {
+            return base.to_string();
+        }
+    }
+    match word.strip_suffix('s') {
+        Some(base) if base.len() >= 3 => base.to_string(),
+        _ => word,
+    }
+}
+
+fn strip_verb_suffix(word: String) -> String {
+    if word.ends_with("eed") {
+        return word; // "speed", "need", "feed"
+    }
+    let Some(base) = word.strip_suffix("ing").or_else(|| word.strip_suffix("ed")) else {
+        return word;
+    };
+    if base.len() < 3 || !has_vowel(base) {
+        return word; // "string", "thing", "red"
+    }
+
+    let bytes = base.as_bytes();
+    let (last, prev) = (bytes[bytes.len() - 1], bytes[bytes.len() - 2]);
+    if last == prev && !is_vowel(last) && !matches!(last, b'l' | b's' | b'z') {
+        // "running" -> "run", "logged" -> "log"
+        return base[..base.len() - 1].to_string();
+    }
+    if base.ends_with("at") || base.ends_with("bl") || base.ends_with("iz") {
+        // "created" -> "create", "enabling" -> "enable"
+        return format!("{}e", base);
+    }
+    if bytes.len() == 3 && !is_vowel(bytes[0]) && is_vowel(bytes[1]) && !is_vowel(last) && !matches!(last, b'w' | b'x' | b'y') {
+        // "making" -> "make", "coding" -> "code"
+        return format!("{}e", base);
+    }
+    base.to_string()
+}
+
+fn strip_ion(word: String) -> String {
+    match word.strip_suffix("ion") {
+        Some(base) if base.len() >= 4 && (base.ends_with('s') || base.ends_with('t')) => base.to_string(),
+        _ => word,
+    }
+}
+
+/// Keywords present in both lists, in `keywords1` order (each once)
+pub fn shared_keywords(keywords1: &[String], keywords2: &[String]) -> Vec<String> {
+    let other: HashSet<&str> = keywords2.iter().map(String::as_str).collect();
+    let mut seen = HashSet::new();
+    keywords1.iter()
+        .filter(|k| other.contains(k.as_str()) && seen.insert(k.as_str()))
+        .cloned()
         .collect()
 }
 
 /// Count shared keywords between two keyword lists
 pub fn count_shared_keywords(keywords1: &[String], keywords2: &[String]) -> usize {
-    keywords1.iter()
-        .filter(|k| keywords2.contains(k))
-        .count()
+    shared_keywords(keywords1, keywords2).len()
+}
+
+
+/// FNV-1a: a hash that stays the same across runs, builds and platforms (std's hasher
+/// makes no such promise)
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Reproducible random numbers (SplitMix64): the same seed always gives the same sequence
+#[derive(Debug, Clone)]
+pub struct SeededRandom(u64);
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        SeededRandom(seed)
+    }
+
+    /// Seeded from a thought's category and content, so the same thought always draws the same numbers
+    pub fn for_thought(content: &str, category: &str) -> Self {
+        Self::new(stable_hash(format!("{}\0{}", category, content).as_bytes()))
+    }
+
+    /// Next number in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// First `max_chars` characters of `text` (char-boundary safe)
+pub fn snippet(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+/// `text` without the contents of ``` fenced blocks (fence lines included). An unclosed
+/// fence runs to the end of the text.
+pub fn strip_code_fences(text: &str) -> String {
+    let mut in_fence = false;
+    text.lines()
+        .filter(|line| {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                return false;
+            }
+            !in_fence
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Code found in a thought's content
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeInfo {
+    /// From the first fence's info string (```rust), else guessed from the code itself
+    pub language: Option<String>,
+}
+
+// An indented block needs this many lines, and at least half the text's non-blank lines,
+// before the text counts as containing code
+const MIN_INDENTED_CODE_LINES: usize = 2;
+
+/// Whether `text` contains code: a ``` fenced block, or indented (4 spaces or a tab) lines
+/// making up most of it
+pub fn detect_code(text: &str) -> Option<CodeInfo> {
+    let mut fenced: Option<(Option<String>, Vec<&str>)> = None;
+    let mut in_fence = false;
+    for line in text.lines() {
+        if let Some(info) = line.trim_start().strip_prefix("```") {
+            in_fence = !in_fence;
+            if in_fence && fenced.is_none() {
+                let language = info.split_whitespace().next().map(str::to_lowercase);
+                fenced = Some((language, Vec::new()));
+            }
+            continue;
+        }
+        if in_fence {
+            if let Some((_, code)) = fenced.as_mut() {
+                code.push(line);
+            }
+        }
+    }
+    if let Some((language, code)) = fenced {
+        let language = language.or_else(|| guess_language(&code.join("\n")));
+        return Some(CodeInfo { language });
+    }
+
+    let non_blank: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let indented: Vec<&str> = non_blank.iter()
+        .copied()
+        .filter(|l| l.starts_with("    ") || l.starts_with('\t'))
+        .collect();
+    if indented.len() >= MIN_INDENTED_CODE_LINES && indented.len() * 2 >= non_blank.len() {
+        return Some(CodeInfo { language: guess_language(&indented.join("\n")) });
+    }
+    None
+}
+
+/// A rough guess from telltale tokens; None when nothing stands out
+fn guess_language(code: &str) -> Option<String> {
+    const TELLTALES: [(&str, &[&str]); 7] = [
+        ("rust", &["fn ", "let mut ", "impl ", "::new(", "pub struct "]),
+        ("python", &["def ", "import ", "self.", "elif ", "print("]),
+        ("typescript", &["interface ", ": string", ": number", "export type "]),
+        ("javascript", &["function ", "const ", "=> ", "console.log", "require("]),
+        ("go", &["func ", "package ", ":= ", "fmt."]),
+        ("sql", &["SELECT ", "FROM ", "WHERE ", "INSERT INTO", "CREATE TABLE"]),
+        ("shell", &["#!/bin/", "echo ", "sudo ", "cd ", "export "]),
+    ];
+    TELLTALES.iter()
+        .map(|(language, tokens)| (language, tokens.iter().filter(|t| code.contains(*t)).count()))
+        .filter(|(_, hits)| *hits > 0)
+        // The first listed language wins a tie
+        .fold(None, |best: Option<(&&str, usize)>, (language, hits)| match best {
+            Some((_, most)) if most >= hits => best,
+            _ => Some((language, hits)),
+        })
+        .map(|(language, _)| language.to_string())
+}
+
+/// Every http(s) URL in `text`, in order, without repeats. Trailing sentence punctuation
+/// and a closing bracket with no opening partner (as in a markdown link) aren't part of it.
+pub fn extract_urls(text: &str) -> Vec<String> {
+    // ASCII lowercasing keeps byte offsets, so matches index straight into `text`
+    let lower = text.to_ascii_lowercase();
+    let mut urls: Vec<String> = Vec::new();
+    let mut from = 0;
+    while let Some(found) = ["https://", "http://"].iter()
+        .filter_map(|scheme| lower[from..].find(scheme).map(|i| (from + i, scheme.len())))
+        .min()
+    {
+        let (start, scheme_len) = found;
+        let end = text[start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`'))
+            .map_or(text.len(), |i| start + i);
+        from = end;
+
+        let mut url = &text[start..end];
+        loop {
+            let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '*']);
+            let unpaired = |open: char, close: char| trimmed.matches(close).count() > trimmed.matches(open).count();
+            let stripped = match trimmed.chars().last() {
+                Some(')') if unpaired('(', ')') => &trimmed[..trimmed.len() - 1],
+                Some(']') if unpaired('[', ']') => &trimmed[..trimmed.len() - 1],
+                _ => trimmed,
+            };
+            if stripped.len() == url.len() {
+                break;
+            }
+            url = stripped;
+        }
+        if url.len() > scheme_len && !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+    }
+    urls
+}
+
+/// Host (lowercase, without "www.") and path (without a trailing slash) of a URL; the query
+/// and fragment are dropped. Two URLs with the same key point at the same page.
+pub fn link_key(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = host.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    format!("{}/{}", host, path).trim_end_matches('/').to_string()
+}
+
+/// A `since` filter as RFC3339 (UTC): accepts an RFC3339 timestamp or a plain
+/// YYYY-MM-DD date, which means midnight UTC
+pub fn parse_since(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(t.with_timezone(&chrono::Utc).to_rfc3339());
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|t| t.and_utc().to_rfc3339())
+        .ok_or_else(|| format!("Invalid: since '{}' (expected YYYY-MM-DD or an RFC3339 timestamp)", value))
+}
+
+/// A span of whole days named in a query, such as "yesterday" or "in March"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    pub start: chrono::NaiveDate,
+    /// The day after the last one in the range
+    pub end: chrono::NaiveDate,
+}
+
+impl DateRange {
+    fn days(start: chrono::NaiveDate, days: i64) -> Self {
+        DateRange { start, end: start + chrono::Duration::days(days) }
+    }
+
+    /// Local midnight at `tz_offset_minutes` from UTC on the first day and on `end`, as RFC3339
+    pub fn bounds(&self, tz_offset_minutes: i32) -> (String, String) {
+        let offset = chrono::FixedOffset::east_opt(tz_offset_minutes * 60)
+            .unwrap_or_else(|| chrono::FixedOffset::east_opt(0).expect("UTC is a valid offset"));
+        let midnight = |day: chrono::NaiveDate| day.and_time(chrono::NaiveTime::MIN).and_local_timezone(offset).unwrap().to_rfc3339();
+        (midnight(self.start), midnight(self.end))
+    }
+
+    /// Whether an RFC3339 timestamp falls on one of the days, at `tz_offset_minutes` from UTC
+    pub fn contains(&self, timestamp: &str, tz_offset_minutes: i32) -> bool {
+        chrono::DateTime::parse_from_rfc3339(timestamp).is_ok_and(|t| {
+            let day = (t.with_timezone(&chrono::Utc) + chrono::Duration::minutes(tz_offset_minutes as i64)).date_naive();
+            self.start <= day && day < self.end
+        })
+    }
+}
+
+impl std::fmt::Display for DateRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} to {}", self.start, self.end)
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june",
+    "july", "august", "september", "october", "november", "december",
+];
+// Dropped along with the date phrase they introduce ("in march", "from yesterday")
+const DATE_PREPOSITIONS: [&str; 4] = ["in", "on", "from", "during"];
+
+/// Find the first relative-date phrase in `query`: today, yesterday, last (or past) week and
+/// month (the 7 and 30 days up to today), "N days ago", and month names, optionally with a
+/// year (otherwise the latest such month not after today; "may" needs "in" before it or a
+/// year after it). Returns the days it names and the query without it (or a preposition
+/// leading into it), or None when there is no such phrase.
+pub fn extract_date_range(query: &str, today: chrono::NaiveDate) -> Option<(DateRange, String)> {
+    use chrono::Datelike;
+
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let bare: Vec<String> = words.iter()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .collect();
+    let word = |i: usize| bare.get(i).map(String::as_str).unwrap_or_default();
+
+    for i in 0..bare.len() {
+        let found = match word(i) {
+            "today" => Some((DateRange::days(today, 1), 1)),
+            "yesterday" => Some((DateRange::days(today - chrono::Duration::days(1), 1), 1)),
+            "last" | "past" if word(i + 1) == "week" => Some((DateRange::days(today - chrono::Duration::days(7), 8), 2)),
+            "last" | "past" if word(i + 1) == "month" => Some((DateRange::days(today - chrono::Duration::days(30), 31), 2)),
+            n if matches!(word(i + 1), "day" | "days") && word(i + 2) == "ago" => n.parse::<i64>().ok()
+                .filter(|n| (0..=36_500).contains(n))
+                .map(|n| (DateRange::days(today - chrono::Duration::days(n), 1), 3)),
+            name => MONTHS.iter().position(|m| *m == name).and_then(|index| {
+                let month = index as u32 + 1;
+                let given_year = Some(word(i + 1)).filter(|y| y.len() == 4).and_then(|y| y.parse::<i32>().ok());
+                if name == "may" && given_year.is_none() && !(i > 0 && word(i - 1) == "in") {
+                    return None;
+                }
+                let year = given_year.unwrap_or(if month > today.month() { today.year() - 1 } else { today.year() });
+                let start = chrono::NaiveDate::from_ymd_opt(year, month, 1)?;
+                let end = if month == 12 {
+                    chrono::NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+                } else {
+                    chrono::NaiveDate::from_ymd_opt(year, month + 1, 1)?
+                };
+                Some((DateRange { start, end }, if given_year.is_some() { 2 } else { 1 }))
+            }),
+        };
+        if let Some((range, len)) = found {
+            let first = if i > 0 && DATE_PREPOSITIONS.contains(&word(i - 1)) { i - 1 } else { i };
+            let rest: Vec<&str> = words[..first].iter().chain(&words[i + len..]).copied().collect();
+            return Some((range, rest.join(" ")));
+        }
+    }
+    None
+}
+
+// Most recent panic message, kept so lock recovery can report what went wrong
+static LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+/// Remember a panic message (called from the process-wide panic hook)
+pub fn record_panic(message: String) {
+    if let Ok(mut last) = LAST_PANIC.lock() {
+        *last = Some(message);
+    }
+}
+
+pub fn last_panic_message() -> Option<String> {
+    LAST_PANIC.lock().ok().and_then(|p| p.clone())
+}
+
+/// Lock a mutex, recovering the guard if an earlier holder panicked.
+/// Everything we guard this way is backed by SQLite (transactions protect the data)
+/// or is a plain cache, so a poisoned lock is safe to reuse rather than failing
+/// every later caller.
+pub fn lock_recovering<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        tracing::warn!(
+            "Lock was poisoned by a panic ({}); recovering",
+            last_panic_message().unwrap_or_else(|| "unknown panic".to_string())
+        );
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_date_range, extract_keywords, extract_urls, link_key, normalize_keyword, stable_hash, DateRange, SeededRandom};
+
+    fn stems(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| normalize_keyword(w)).collect()
+    }
+
+    #[test]
+    fn plurals_fold_to_the_singular() {
+        assert_eq!(stems(&["clusters", "queries", "boxes", "branches", "uses"]), ["cluster", "query", "box", "branch", "use"]);
+        // Trailing s that isn't a plural
+        assert_eq!(stems(&["class", "status", "analysis", "news"]), ["class", "status", "analysis", "news"]);
+    }
+
+    #[test]
+    fn ing_and_ed_forms_fold_to_the_base_word() {
+        assert_eq!(stems(&["clustering", "running", "coding", "enabling"]), ["cluster", "run", "code", "enable"]);
+        assert_eq!(stems(&["connected", "logged", "created", "tested"]), ["connect", "log", "create", "test"]);
+        assert_eq!(stems(&["connecting", "connections"]), ["connect", "connect"]);
+        // Endings that only look like suffixes
+        assert_eq!(stems(&["string", "thing", "speed", "need", "during"]), ["string", "thing", "speed", "need", "during"]);
+    }
+
+    #[test]
+    fn short_words_are_left_alone() {
+        assert_eq!(stems(&["bus", "gas", "red", "bed", "ing"]), ["bus", "gas", "red", "bed", "ing"]);
+        assert_eq!(normalize_keyword("Was"), "was");
+    }
+
+    #[test]
+    fn keywords_are_stemmed_and_deduplicated() {
+        assert_eq!(extract_keywords("Clusters and clustering: the cluster view"), ["cluster", "view"]);
+    }
+
+    #[test]
+    fn cjk_runs_become_overlapping_bigrams() {
+        assert_eq!(extract_keywords("機械学習"), ["機械", "械学", "学習"]);
+        assert_eq!(extract_keywords("데이터"), ["데이", "이터"]);
+        // A two-character run is its own single bigram
+        assert_eq!(extract_keywords("学習"), ["学習"]);
+    }
+
+    #[test]
+    fn mixed_scripts_split_at_the_script_change() {
+        // Hiragana particles separate without becoming keywords
+        assert_eq!(extract_keywords("rustのコード"), ["rust", "コー", "ード"]);
+        assert_eq!(extract_keywords("Tauri 应用 with SQLite"), ["tauri", "应用", "sqlite"]);
+    }
+
+    #[test]
+    fn single_cjk_characters_are_dropped() {
+        assert!(extract_keywords("猫").is_empty());
+        assert_eq!(extract_keywords("猫 and database"), ["database"]);
+    }
+
+    #[test]
+    fn hyphens_split_words() {
+        assert_eq!(extract_keywords("auto-connect"), ["auto", "connect"]);
+        assert_eq!(extract_keywords("auto-connected thoughts"), ["auto", "connect", "thought"]);
+    }
+
+    #[test]
+    fn accented_words_stay_whole() {
+        // "déjà" is a French stopword
+        let first = extract_keywords("Déjà-vu au café élégant");
+        assert_eq!(first, ["café", "élégant"]);
+        assert_eq!(extract_keywords("Déjà-vu au café élégant"), first);
+        // A decomposed accent is part of the word too
+        assert_eq!(extract_keywords("cafe\u{301} noir"), ["cafe\u{301}", "noir"]);
+    }
+
+    #[test]
+    fn urls_drop_trailing_sentence_punctuation() {
+        assert_eq!(extract_urls("See https://example.com/docs."), ["https://example.com/docs"]);
+        assert_eq!(extract_urls("Really? https://example.com/a?b=1!?"), ["https://example.com/a?b=1"]);
+        assert_eq!(extract_urls("'https://example.com/x', then"), ["https://example.com/x"]);
+    }
+
+    #[test]
+    fn urls_keep_balanced_parentheses_only() {
+        assert_eq!(extract_urls("(see https://example.com/page)"), ["https://example.com/page"]);
+        assert_eq!(extract_urls("[docs](https://example.com/guide)."), ["https://example.com/guide"]);
+        assert_eq!(
+            extract_urls("https://en.wikipedia.org/wiki/Rust_(programming_language)"),
+            ["https://en.wikipedia.org/wiki/Rust_(programming_language)"]
+        );
+    }
+
+    #[test]
+    fn several_urls_come_back_in_order_without_repeats() {
+        assert_eq!(
+            extract_urls("HTTP://b.example/one and https://a.example/two, then http://b.example/one again"),
+            ["HTTP://b.example/one", "https://a.example/two", "http://b.example/one"]
+        );
+        assert_eq!(extract_urls("https://a.example https://a.example"), ["https://a.example"]);
+    }
+
+    #[test]
+    fn only_http_urls_are_extracted() {
+        assert!(extract_urls("ftp://files.example/x mailto:me@example.com file:///etc/hosts").is_empty());
+        // A bare scheme is not a URL
+        assert!(extract_urls("https:// is the prefix").is_empty());
+    }
+
+    #[test]
+    fn link_keys_ignore_case_www_query_and_trailing_slash() {
+        assert_eq!(link_key("https://WWW.Example.com/Docs/?page=2#intro"), "example.com/Docs");
+        assert_eq!(link_key("http://example.com"), link_key("https://www.example.com/"));
+    }
+
+    fn range(query: &str, today: &str) -> Option<(String, String, String)> {
+        extract_date_range(query, today.parse().unwrap())
+            .map(|(r, rest)| (r.start.to_string(), r.end.to_string(), rest))
+    }
+
+    fn owned(start: &str, end: &str, rest: &str) -> Option<(String, String, String)> {
+        Some((start.to_string(), end.to_string(), rest.to_string()))
+    }
+
+    #[test]
+    fn last_month_is_the_thirty_days_up_to_today_even_at_a_month_end() {
+        assert_eq!(range("wal notes from last month", "2026-03-31"), owned("2026-03-01", "2026-04-01", "wal notes"));
+        // Across a short February and into the previous year
+        assert_eq!(range("last month", "2026-03-01"), owned("2026-01-30", "2026-03-02", ""));
+        assert_eq!(range("past month", "2026-01-15"), owned("2025-12-16", "2026-01-16", ""));
+    }
+
+    #[test]
+    fn named_months_and_days_resolve_against_today() {
+        assert_eq!(range("graph ideas in march", "2026-03-31"), owned("2026-03-01", "2026-04-01", "graph ideas"));
+        // A month after today means last year's
+        assert_eq!(range("december plans", "2026-03-31"), owned("2025-12-01", "2026-01-01", "plans"));
+        assert_eq!(range("february 2024", "2026-03-31"), owned("2024-02-01", "2024-03-01", ""));
+        assert_eq!(range("yesterday", "2026-03-01"), owned("2026-02-28", "2026-03-01", ""));
+        assert_eq!(range("last week", "2026-03-30"), owned("2026-03-23", "2026-03-31", ""));
+        assert_eq!(range("3 days ago", "2026-03-02"), owned("2026-02-27", "2026-02-28", ""));
+        assert_eq!(range("may we try", "2026-06-01"), None);
+    }
+
+    #[test]
+    fn range_bounds_use_the_given_offset_across_a_dst_change() {
+        // The day Central Europe moves from +01:00 to +02:00
+        let day = DateRange { start: "2026-03-29".parse().unwrap(), end: "2026-03-30".parse().unwrap() };
+
+        assert_eq!(day.bounds(120), ("2026-03-29T00:00:00+02:00".to_string(), "2026-03-30T00:00:00+02:00".to_string()));
+        assert!(day.contains("2026-03-28T22:30:00+00:00", 120));
+        assert!(!day.contains("2026-03-28T22:30:00+00:00", 60));
+        assert!(day.contains("2026-03-29T22:30:00+00:00", 60));
+        assert!(!day.contains("2026-03-29T22:30:00+00:00", 120));
+    }
+
+    fn draws(mut random: SeededRandom, n: usize) -> Vec<f64> {
+        (0..n).map(|_| random.next_f64()).collect()
+    }
+
+    #[test]
+    fn stable_hash_is_fnv1a() {
+        assert_eq!(stable_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stable_hash(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        assert_eq!(draws(SeededRandom::new(42), 20), draws(SeededRandom::new(42), 20));
+        assert_eq!(
+            draws(SeededRandom::for_thought("Use WAL mode", "technical"), 5),
+            draws(SeededRandom::for_thought("Use WAL mode", "technical"), 5)
+        );
+        assert!(draws(SeededRandom::new(7), 1000).iter().all(|n| (0.0..1.0).contains(n)));
+    }
+
+    #[test]
+    fn different_thoughts_get_different_numbers() {
+        let base = draws(SeededRandom::for_thought("Use WAL mode", "technical"), 5);
+        assert_ne!(base, draws(SeededRandom::for_thought("Use WAL mode!", "technical"), 5));
+        assert_ne!(base, draws(SeededRandom::for_thought("Use WAL mode", "insight"), 5));
+        // Category and content are kept apart, so moving text between them changes the seed
+        assert_ne!(
+            draws(SeededRandom::for_thought("b", "a"), 5),
+            draws(SeededRandom::for_thought("", "ab"), 5)
+        );
+        assert_ne!(draws(SeededRandom::new(1), 5), draws(SeededRandom::new(2), 5));
+    }
 }
@@ -0,0 +1,210 @@
+use crate::database::Database;
+use crate::{Connection, QueryDefinition, Thought};
+
+/// Settings key for the longest thought content accepted, in characters
+pub const SETTING_MAX_CONTENT_CHARS: &str = "max_content_chars";
+const DEFAULT_MAX_CONTENT_CHARS: usize = 10_000;
+
+/// Trimmed content; "Invalid" if that leaves nothing or more than `max_chars` characters
+pub fn content(text: &str, max_chars: usize) -> Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Invalid: content is empty".to_string());
+    }
+    let chars = text.chars().count();
+    if chars > max_chars {
+        return Err(format!("Invalid: content is {} characters (max {})", chars, max_chars));
+    }
+    Ok(text.to_string())
+}
+
+pub fn max_content_chars(db: &Database) -> usize {
+    db.setting_or(SETTING_MAX_CONTENT_CHARS, DEFAULT_MAX_CONTENT_CHARS)
+}
+
+/// `value` clamped to [0, 1], with a note saying so when it had to be. `field` names it in
+/// the note and in the "Invalid" error for NaN or infinity.
+pub fn unit_interval(field: &str, value: f64) -> Result<(f64, Option<String>), String> {
+    if !value.is_finite() {
+        return Err(format!("Invalid: {} must be a number between 0 and 1", field));
+    }
+    let clamped = value.clamp(0.0, 1.0);
+    let note = (clamped != value).then(|| format!("{} {} was clamped to {}", field, value, clamped));
+    Ok((clamped, note))
+}
+
+/// Trimmed, lowercased category, with a note when it isn't one of `known`. Unknown categories
+/// are still allowed (they show up in the graph with the default color).
+pub fn category(name: &str, known: &[String]) -> Result<(String, Option<String>), String> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return Err("Invalid: category is empty".to_string());
+    }
+    let note = (!known.contains(&name)).then(|| format!("category '{}' is not a known category", name));
+    Ok((name, note))
+}
+
+/// Apply the content, importance, and category rules to a thought in place. Returns a note
+/// for each value that was adjusted or flagged.
+pub fn thought(db: &Database, thought: &mut Thought) -> Result<Vec<String>, String> {
+    thought.content = content(&thought.content, max_content_chars(db))?;
+
+    let known: Vec<String> = db.list_categories()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|c| c.name)
+        .collect();
+    let (name, category_note) = category(&thought.category, &known)?;
+    thought.category = name;
+
+    let (importance, importance_note) = unit_interval("importance", thought.importance)?;
+    thought.importance = importance;
+
+    Ok(importance_note.into_iter().chain(category_note).collect())
+}
+
+/// Clamp a connection's strength in place; the note says when it had to be
+pub fn connection(connection: &mut Connection) -> Result<Option<String>, String> {
+    let (strength, note) = unit_interval("strength", connection.strength)?;
+    connection.strength = strength;
+    Ok(note)
+}
+
+// Longest category icon accepted, in characters (an emoji or a short icon name)
+const MAX_ICON_CHARS: usize = 32;
+
+/// A category color as "#RRGGBB" in uppercase; "#RGB" is expanded. "Invalid" for anything else.
+pub fn hex_color(color: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid: color must be a hex color like #3B82F6 (got '{}')", color);
+    let digits = color.trim().strip_prefix('#').ok_or_else(invalid)?;
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    match digits.len() {
+        6 => Ok(format!("#{}", digits.to_ascii_uppercase())),
+        3 => Ok(format!("#{}", digits.chars().flat_map(|c| [c, c]).collect::<String>().to_ascii_uppercase())),
+        _ => Err(invalid()),
+    }
+}
+
+/// Trimmed category icon; "Invalid" if empty, too long, or containing control characters
+pub fn icon(icon: &str) -> Result<String, String> {
+    let icon = icon.trim();
+    if icon.is_empty() {
+        return Err("Invalid: icon is empty".to_string());
+    }
+    if icon.chars().count() > MAX_ICON_CHARS {
+        return Err(format!("Invalid: icon is longer than {} characters", MAX_ICON_CHARS));
+    }
+    if icon.chars().any(char::is_control) {
+        return Err("Invalid: icon contains control characters".to_string());
+    }
+    Ok(icon.to_string())
+}
+
+/// Results a query returns when its definition sets no limit, and the most it may set
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+pub const MAX_QUERY_LIMIT: usize = 1000;
+
+/// A query definition with categories and tags trimmed and lowercased and blank text dropped;
+/// "Invalid" for an unknown role, unparseable dates, importances outside 0-1 or crossed, a
+/// non-positive within_days, or a limit outside 1..=MAX_QUERY_LIMIT
+pub fn query(mut query: QueryDefinition) -> Result<QueryDefinition, String> {
+    query.categories = query.categories.iter().filter_map(|c| crate::database::normalize_category(c)).collect();
+    query.tags = query.tags.iter().map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()).collect();
+    query.text = query.text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+
+    if let Some(role) = &query.role {
+        if !crate::database::THOUGHT_ROLES.contains(&role.as_str()) {
+            return Err(format!("Invalid: role must be one of {}", crate::database::THOUGHT_ROLES.join(", ")));
+        }
+    }
+    for (field, date) in [("created_after", &query.created_after), ("created_before", &query.created_before)] {
+        let valid = date.as_deref().is_none_or(|d| {
+            chrono::DateTime::parse_from_rfc3339(d).is_ok() || chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok()
+        });
+        if !valid {
+            return Err(format!("Invalid: {} must be an RFC 3339 timestamp or a YYYY-MM-DD date", field));
+        }
+    }
+    for (field, importance) in [("min_importance", query.min_importance), ("max_importance", query.max_importance)] {
+        if importance.is_some_and(|i| !(0.0..=1.0).contains(&i)) {
+            return Err(format!("Invalid: {} must be between 0 and 1", field));
+        }
+    }
+    if let (Some(min), Some(max)) = (query.min_importance, query.max_importance) {
+        if min > max {
+            return Err("Invalid: min_importance is above max_importance".to_string());
+        }
+    }
+    if query.within_days.is_some_and(|days| !(days.is_finite() && days > 0.0)) {
+        return Err("Invalid: within_days must be a positive number".to_string());
+    }
+    if query.limit.is_some_and(|limit| !(1..=MAX_QUERY_LIMIT).contains(&limit)) {
+        return Err(format!("Invalid: limit must be between 1 and {}", MAX_QUERY_LIMIT));
+    }
+    Ok(query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{category, connection, content, thought, unit_interval, SETTING_MAX_CONTENT_CHARS};
+    use crate::database::testing::{connection as test_connection, thought as test_thought, TempDb};
+
+    #[test]
+    fn content_is_trimmed_and_limited_in_characters() {
+        assert_eq!(content("  an idea \n", 10).unwrap(), "an idea");
+        // The limit counts characters, not bytes
+        assert_eq!(content("日本語テキスト", 7).unwrap(), "日本語テキスト");
+        assert_eq!(content("日本語テキスト!", 7).unwrap_err(), "Invalid: content is 8 characters (max 7)");
+        assert_eq!(content(" \t\n", 10).unwrap_err(), "Invalid: content is empty");
+    }
+
+    #[test]
+    fn unit_interval_clamps_with_a_note() {
+        assert_eq!(unit_interval("importance", 0.0).unwrap(), (0.0, None));
+        assert_eq!(unit_interval("importance", 1.0).unwrap(), (1.0, None));
+        assert_eq!(unit_interval("importance", 1.5).unwrap(), (1.0, Some("importance 1.5 was clamped to 1".to_string())));
+        assert_eq!(unit_interval("strength", -0.25).unwrap(), (0.0, Some("strength -0.25 was clamped to 0".to_string())));
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(unit_interval("strength", value).unwrap_err(), "Invalid: strength must be a number between 0 and 1");
+        }
+    }
+
+    #[test]
+    fn unknown_categories_are_allowed_with_a_note() {
+        let known = vec!["technical".to_string()];
+        assert_eq!(category(" Technical ", &known).unwrap(), ("technical".to_string(), None));
+        assert_eq!(
+            category("Made Up", &known).unwrap(),
+            ("made up".to_string(), Some("category 'made up' is not a known category".to_string()))
+        );
+        assert_eq!(category("  ", &known).unwrap_err(), "Invalid: category is empty");
+    }
+
+    #[test]
+    fn thought_applies_every_rule_and_the_content_setting() {
+        let db = TempDb::new();
+        let mut t = test_thought("t1", "  An idea  ", " Technical", 2.0, "2026-03-01T12:00:00+00:00");
+
+        let notes = thought(&db, &mut t).unwrap();
+
+        assert_eq!((t.content.as_str(), t.category.as_str(), t.importance), ("An idea", "technical", 1.0));
+        assert_eq!(notes, ["importance 2 was clamped to 1"]);
+
+        db.set_setting(SETTING_MAX_CONTENT_CHARS, "5").unwrap();
+        let mut long = test_thought("t2", "Too long", "technical", 0.5, "2026-03-01T12:00:00+00:00");
+        assert_eq!(thought(&db, &mut long).unwrap_err(), "Invalid: content is 8 characters (max 5)");
+    }
+
+    #[test]
+    fn connection_strength_is_clamped() {
+        let mut c = test_connection("c1", "a", "b", "2026-03-01T12:00:00+00:00");
+        c.strength = 3.0;
+        assert_eq!(connection(&mut c).unwrap(), Some("strength 3 was clamped to 1".to_string()));
+        assert_eq!(c.strength, 1.0);
+
+        c.strength = f64::NAN;
+        assert!(connection(&mut c).unwrap_err().starts_with("Invalid: strength"));
+    }
+}
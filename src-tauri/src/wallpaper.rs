@@ -8,6 +8,7 @@ use std::ffi::c_void;
 use windows_sys::Win32::{
     Foundation::{BOOL, HWND, LPARAM, POINT, RECT},
     UI::WindowsAndMessaging::*,
+    UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI},
     Graphics::Gdi::*,
 };
 
@@ -16,9 +17,24 @@ extern "system" {
     fn MapWindowPoints(hWndFrom: HWND, hWndTo: HWND, lpPoints: *mut POINT, cPoints: u32) -> i32;
 }
 
+use std::sync::Mutex;
 use serde::{Deserialize, Serialize};
+use crate::utils::lock_recovering;
 
-/// Monitor information
+// Setting key (also readable/writable through get_setting/set_setting): false makes the
+// embedded wallpaper click-through so clicks reach the desktop icons
+pub const SETTING_INTERACTIVE: &str = "wallpaper_interactive";
+// Setting key: re-enter the last wallpaper configuration when the app starts
+pub const SETTING_AUTO: &str = "auto_wallpaper";
+// Setting key holding the last SavedWallpaper as JSON (written on every enter/exit)
+pub const SETTING_LAST: &str = "wallpaper_last";
+
+pub const AUTO_STARTED_EVENT: &str = "mind://wallpaper-auto-started";
+
+// Used when the display driver doesn't report a refresh rate
+const DEFAULT_REFRESH_RATE: u32 = 60;
+
+/// Monitor information. Position and size are in physical pixels.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonitorInfo {
     pub id: u32,
@@ -28,6 +44,76 @@ pub struct MonitorInfo {
     pub width: i32,
     pub height: i32,
     pub is_primary: bool,
+    /// Physical pixels per logical pixel (1.5 at 150% scaling)
+    pub scale_factor: f64,
+    /// In Hz
+    pub refresh_rate: u32,
+    pub orientation: MonitorOrientation,
+}
+
+/// Display rotation
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorOrientation {
+    Landscape,
+    Portrait,
+    LandscapeFlipped,
+    PortraitFlipped,
+}
+
+#[cfg(windows)]
+impl MonitorOrientation {
+    /// From DEVMODE's dmDisplayOrientation (DMDO_*: clockwise quarter turns)
+    fn from_dmdo(value: u32) -> Self {
+        match value {
+            DMDO_90 => MonitorOrientation::Portrait,
+            DMDO_180 => MonitorOrientation::LandscapeFlipped,
+            DMDO_270 => MonitorOrientation::PortraitFlipped,
+            _ => MonitorOrientation::Landscape,
+        }
+    }
+}
+
+/// A window's screen rectangle, kept so it can be put back after wallpaper mode
+#[derive(Debug, Clone, Copy)]
+pub struct WindowPlacement {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Which window is embedded as the wallpaper, if any, and where it was before
+#[derive(Default)]
+pub struct WallpaperState {
+    active: Mutex<Option<(isize, Option<WindowPlacement>)>>,
+}
+
+impl WallpaperState {
+    pub fn is_active(&self) -> bool {
+        lock_recovering(&self.active).is_some()
+    }
+
+    /// Record `window_hwnd` as embedded. Switching monitors while already embedded keeps
+    /// the placement from before the first embed.
+    pub fn activate(&self, window_hwnd: isize, original: Option<WindowPlacement>) {
+        let mut active = lock_recovering(&self.active);
+        let original = match *active {
+            Some((hwnd, previous)) if hwnd == window_hwnd => previous,
+            _ => original,
+        };
+        *active = Some((window_hwnd, original));
+    }
+
+    /// The embedded window, if any
+    pub fn active_window(&self) -> Option<isize> {
+        lock_recovering(&self.active).map(|(hwnd, _)| hwnd)
+    }
+
+    /// Clear the record, returning the embedded window and its original placement
+    pub fn take(&self) -> Option<(isize, Option<WindowPlacement>)> {
+        lock_recovering(&self.active).take()
+    }
 }
 
 /// Wallpaper display mode
@@ -35,6 +121,88 @@ pub struct MonitorInfo {
 pub enum WallpaperDisplayMode {
     AllMonitors,
     SingleMonitor(u32), // Monitor ID
+    MonitorSubset(Vec<u32>), // Monitor IDs; spans their bounding rectangle
+}
+
+/// How the window was last embedded, in terms that survive a restart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum WallpaperConfig {
+    AllMonitors,
+    Monitor { id: u32 },
+    Monitors { ids: Vec<u32> },
+    Bounds { x: i32, y: i32, width: i32, height: i32 },
+}
+
+impl WallpaperConfig {
+    /// Embed `window_hwnd` this way
+    pub fn embed(&self, window_hwnd: isize) -> Result<(), String> {
+        match self {
+            WallpaperConfig::AllMonitors => set_as_wallpaper(window_hwnd),
+            WallpaperConfig::Monitor { id } => {
+                set_as_wallpaper_on_monitors(window_hwnd, WallpaperDisplayMode::SingleMonitor(*id))
+            }
+            WallpaperConfig::Monitors { ids } => {
+                set_as_wallpaper_on_monitors(window_hwnd, WallpaperDisplayMode::MonitorSubset(ids.clone()))
+            }
+            WallpaperConfig::Bounds { x, y, width, height } => {
+                set_as_wallpaper_with_bounds(window_hwnd, *x, *y, *width, *height)
+            }
+        }
+    }
+
+    /// Whether this still makes sense on `monitors`: referenced monitors exist, and saved
+    /// bounds lie within the desktop and overlap at least one monitor
+    pub fn fits(&self, monitors: &[MonitorInfo]) -> bool {
+        let exists = |id: &u32| monitors.iter().any(|m| m.id == *id);
+        match self {
+            WallpaperConfig::AllMonitors => !monitors.is_empty(),
+            WallpaperConfig::Monitor { id } => exists(id),
+            WallpaperConfig::Monitors { ids } => !ids.is_empty() && ids.iter().all(exists),
+            WallpaperConfig::Bounds { x, y, width, height } => {
+                let (right, bottom) = (x + width, y + height);
+                let overlaps = monitors.iter()
+                    .any(|m| *x < m.x + m.width && right > m.x && *y < m.y + m.height && bottom > m.y);
+                let ids: Vec<u32> = monitors.iter().map(|m| m.id).collect();
+                let within = monitor_set_bounds(&ids).is_ok_and(|(dx, dy, dw, dh)| {
+                    *x >= dx && *y >= dy && right <= dx + dw && bottom <= dy + dh
+                });
+                *width > 0 && *height > 0 && overlaps && within
+            }
+        }
+    }
+}
+
+/// The last configuration entered and whether wallpaper mode was still on. Exiting wallpaper
+/// mode clears `active`; quitting while embedded leaves it set so auto-start picks it up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedWallpaper {
+    pub active: bool,
+    pub config: WallpaperConfig,
+}
+
+/// Bounding rectangle (x, y, width, height) of the given monitors. Monitors that aren't
+/// adjacent still work: the rectangle simply covers the gap between them too.
+pub fn monitor_set_bounds(monitor_ids: &[u32]) -> Result<(i32, i32, i32, i32), String> {
+    if monitor_ids.is_empty() {
+        return Err("Invalid: no monitors selected".to_string());
+    }
+
+    let monitors = get_monitors();
+    let mut bounds: Option<(i32, i32, i32, i32)> = None; // left, top, right, bottom
+    for id in monitor_ids {
+        let m = monitors.iter()
+            .find(|m| m.id == *id)
+            .ok_or_else(|| format!("NotFound: monitor {}", id))?;
+        let (left, top, right, bottom) = (m.x, m.y, m.x + m.width, m.y + m.height);
+        bounds = Some(match bounds {
+            Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+            None => (left, top, right, bottom),
+        });
+    }
+
+    let (left, top, right, bottom) = bounds.expect("monitor_ids is not empty");
+    Ok((left, top, right - left, bottom - top))
 }
 
 /// Find the WorkerW window that sits behind the desktop icons
@@ -128,6 +296,25 @@ pub fn get_monitors() -> Vec<MonitorInfo> {
             let name_len = name_slice.iter().position(|&c| c == 0).unwrap_or(name_slice.len());
             let name = String::from_utf16_lossy(&name_slice[..name_len]);
 
+            let mut mode: DEVMODEW = std::mem::zeroed();
+            mode.dmSize = std::mem::size_of::<DEVMODEW>() as u16;
+            let (refresh_rate, orientation) =
+                if EnumDisplaySettingsW(info.szDevice.as_ptr(), ENUM_CURRENT_SETTINGS, &mut mode) != 0 {
+                    // 0 and 1 mean "hardware default"
+                    let rate = if mode.dmDisplayFrequency > 1 { mode.dmDisplayFrequency } else { DEFAULT_REFRESH_RATE };
+                    (rate, MonitorOrientation::from_dmdo(mode.Anonymous1.Anonymous2.dmDisplayOrientation))
+                } else {
+                    (DEFAULT_REFRESH_RATE, MonitorOrientation::Landscape)
+                };
+
+            // Effective DPI includes the user's scaling setting; 96 DPI is 100%
+            let (mut dpi_x, mut dpi_y) = (0u32, 0u32);
+            let scale_factor = if GetDpiForMonitor(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) == 0 && dpi_x > 0 {
+                dpi_x as f64 / 96.0
+            } else {
+                1.0
+            };
+
             if let Ok(mut monitors) = MONITORS.lock() {
                 // Temporarily use index as ID - will be reassigned after sorting
                 monitors.push(MonitorInfo {
@@ -138,6 +325,9 @@ pub fn get_monitors() -> Vec<MonitorInfo> {
                     width: rc.right - rc.left,
                     height: rc.bottom - rc.top,
                     is_primary,
+                    scale_factor,
+                    refresh_rate,
+                    orientation,
                 });
             }
         }
@@ -191,6 +381,39 @@ fn get_virtual_screen_bounds() -> (i32, i32, i32, i32) {
     }
 }
 
+/// A window's current screen rectangle
+#[cfg(windows)]
+pub fn window_placement(window_hwnd: isize) -> Option<WindowPlacement> {
+    unsafe {
+        let mut rect: RECT = std::mem::zeroed();
+        if GetWindowRect(window_hwnd as *mut c_void, &mut rect) == 0 {
+            return None;
+        }
+        Some(WindowPlacement {
+            x: rect.left,
+            y: rect.top,
+            width: rect.right - rect.left,
+            height: rect.bottom - rect.top,
+        })
+    }
+}
+
+/// Make Explorer repaint the desktop. A window destroyed while parented to WorkerW can
+/// otherwise leave a black rectangle behind until Explorer redraws on its own.
+#[cfg(windows)]
+pub fn refresh_desktop() {
+    unsafe {
+        // Re-applying the current wallpaper makes Explorer redraw the whole desktop layer
+        let mut path = [0u16; 260];
+        if SystemParametersInfoW(SPI_GETDESKWALLPAPER, path.len() as u32, path.as_mut_ptr() as *mut c_void, 0) != 0 {
+            SystemParametersInfoW(SPI_SETDESKWALLPAPER, 0, path.as_mut_ptr() as *mut c_void, SPIF_SENDCHANGE);
+        }
+        if let Some(worker_w) = find_worker_window() {
+            RedrawWindow(worker_w, std::ptr::null(), std::ptr::null_mut(), RDW_INVALIDATE | RDW_ERASE | RDW_ALLCHILDREN);
+        }
+    }
+}
+
 /// Embed a window as the desktop wallpaper on all monitors
 #[cfg(windows)]
 pub fn set_as_wallpaper(window_hwnd: isize) -> Result<(), String> {
@@ -200,6 +423,12 @@ pub fn set_as_wallpaper(window_hwnd: isize) -> Result<(), String> {
 /// Embed a window as the desktop wallpaper with specific monitor configuration
 #[cfg(windows)]
 pub fn set_as_wallpaper_on_monitors(window_hwnd: isize, mode: WallpaperDisplayMode) -> Result<(), String> {
+    // A set of monitors is just a rectangle, so it goes through the bounds-based embedding
+    if let WallpaperDisplayMode::MonitorSubset(ids) = &mode {
+        let (x, y, width, height) = monitor_set_bounds(ids)?;
+        return set_as_wallpaper_with_bounds(window_hwnd, x, y, width, height);
+    }
+
     unsafe {
         let hwnd: HWND = window_hwnd as *mut c_void;
 
@@ -241,6 +470,7 @@ pub fn set_as_wallpaper_on_monitors(window_hwnd: isize, mode: WallpaperDisplayMo
                     }
                 }
             }
+            WallpaperDisplayMode::MonitorSubset(_) => unreachable!("handled above"),
         };
 
         // Position the window
@@ -265,13 +495,13 @@ pub fn set_as_wallpaper_with_bounds(window_hwnd: isize, x: i32, y: i32, width: i
     unsafe {
         let hwnd: HWND = window_hwnd as *mut c_void;
 
-        eprintln!("set_as_wallpaper_with_bounds: target x={}, y={}, width={}, height={}", x, y, width, height);
+        tracing::debug!("set_as_wallpaper_with_bounds: target x={}, y={}, width={}, height={}", x, y, width, height);
 
         // Find the WorkerW window
         let worker_w = find_worker_window()
             .ok_or_else(|| "Failed to find WorkerW window".to_string())?;
 
-        eprintln!("Found WorkerW: {:?}", worker_w);
+        tracing::debug!("Found WorkerW: {:?}", worker_w);
 
         // Step 1: Position window at target screen coordinates BEFORE setting parent
         // This is how Lively does it - position first, then reparent
@@ -296,11 +526,11 @@ pub fn set_as_wallpaper_with_bounds(window_hwnd: isize, x: i32, y: i32, width: i
         // Step 3: Map window coordinates to WorkerW-relative coordinates
         // This converts screen coords to parent-relative coords
         MapWindowPoints(hwnd, worker_w, &mut rect as *mut _ as *mut _, 2);
-        eprintln!("MapWindowPoints result: left={}, top={}", rect.left, rect.top);
+        tracing::debug!("MapWindowPoints result: left={}, top={}", rect.left, rect.top);
 
         // Step 4: Make the window a child of WorkerW
         let old_parent = SetParent(hwnd, worker_w);
-        eprintln!("SetParent result (old parent): {:?}", old_parent);
+        tracing::debug!("SetParent result (old parent): {:?}", old_parent);
 
         // Step 5: Remove window decorations
         let style = GetWindowLongW(hwnd, GWL_STYLE);
@@ -320,18 +550,40 @@ pub fn set_as_wallpaper_with_bounds(window_hwnd: isize, x: i32, y: i32, width: i
             height,
             SWP_SHOWWINDOW | SWP_NOACTIVATE | SWP_NOZORDER,
         );
-        eprintln!("SetWindowPos result: {}", result);
+        tracing::debug!("SetWindowPos result: {}", result);
 
         Ok(())
     }
 }
 
-/// Restore a window from wallpaper mode
+/// Let mouse clicks pass through the window to whatever is beneath it (the desktop icons),
+/// or make it take input again
+#[cfg(windows)]
+pub fn set_click_through(window_hwnd: isize, click_through: bool) -> Result<(), String> {
+    unsafe {
+        let hwnd: HWND = window_hwnd as *mut c_void;
+        let flags = (WS_EX_TRANSPARENT | WS_EX_NOACTIVATE) as i32;
+        let style = GetWindowLongW(hwnd, GWL_EXSTYLE);
+        let updated = if click_through { style | flags } else { style & !flags };
+        if updated != style {
+            SetWindowLongW(hwnd, GWL_EXSTYLE, updated);
+            // Style changes only take effect once the frame is refreshed
+            SetWindowPos(hwnd, std::ptr::null_mut(), 0, 0, 0, 0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE | SWP_FRAMECHANGED);
+        }
+        Ok(())
+    }
+}
+
+/// Restore a window from wallpaper mode, back to `original` if known
 #[cfg(windows)]
-pub fn restore_from_wallpaper(window_hwnd: isize) -> Result<(), String> {
+pub fn restore_from_wallpaper(window_hwnd: isize, original: Option<WindowPlacement>) -> Result<(), String> {
     unsafe {
         let hwnd: HWND = window_hwnd as *mut c_void;
 
+        // A click-through window would be unusable as a normal window
+        set_click_through(window_hwnd, false)?;
+
         // Remove parent (set to desktop/null)
         SetParent(hwnd, std::ptr::null_mut());
 
@@ -343,14 +595,15 @@ pub fn restore_from_wallpaper(window_hwnd: isize) -> Result<(), String> {
             style | (WS_CAPTION as i32) | (WS_THICKFRAME as i32) | (WS_BORDER as i32)
         );
 
-        // Restore window size
+        // Restore window position and size
+        let placement = original.unwrap_or(WindowPlacement { x: 100, y: 100, width: 1200, height: 800 });
         SetWindowPos(
             hwnd,
             HWND_TOP,
-            100,
-            100,
-            1200,
-            800,
+            placement.x,
+            placement.y,
+            placement.width,
+            placement.height,
             SWP_SHOWWINDOW,
         );
 
@@ -369,6 +622,9 @@ pub fn get_monitors() -> Vec<MonitorInfo> {
         width: 1920,
         height: 1080,
         is_primary: true,
+        scale_factor: 1.0,
+        refresh_rate: DEFAULT_REFRESH_RATE,
+        orientation: MonitorOrientation::Landscape,
     }]
 }
 
@@ -388,6 +644,19 @@ pub fn set_as_wallpaper_with_bounds(_window_hwnd: isize, _x: i32, _y: i32, _widt
 }
 
 #[cfg(not(windows))]
-pub fn restore_from_wallpaper(_window_hwnd: isize) -> Result<(), String> {
+pub fn restore_from_wallpaper(_window_hwnd: isize, _original: Option<WindowPlacement>) -> Result<(), String> {
+    Err("Wallpaper mode is only supported on Windows".to_string())
+}
+
+#[cfg(not(windows))]
+pub fn set_click_through(_window_hwnd: isize, _click_through: bool) -> Result<(), String> {
     Err("Wallpaper mode is only supported on Windows".to_string())
 }
+
+#[cfg(not(windows))]
+pub fn window_placement(_window_hwnd: isize) -> Option<WindowPlacement> {
+    None
+}
+
+#[cfg(not(windows))]
+pub fn refresh_desktop() {}
@@ -11,7 +11,7 @@ import MindChamber from './components/MindChamber'
 import CommandCenter from './components/CommandCenter'
 import ThoughtDetail from './components/ThoughtDetail'
 import { useMindStore } from './stores/mindStore'
-import { useDatabaseSync } from './hooks/useDatabaseSync'
+import { useDatabaseSync, useForgeChangeEvents, useNavigationEvents, useResetEvents, useWallpaperAutoStartEvents } from './hooks/useDatabaseSync'
 import { useForgeStore } from './stores/forgeStore'
 import SetupWizard3D from './components/SetupWizard3D'
 import { dofSettings, cameraControl } from './components/DebugBridge'
@@ -510,6 +510,7 @@ export default function App() {
   const [showSettings, setShowSettings] = useState(false)
   const [showCommandCenter, setShowCommandCenter] = useState(false)
   const [selectedThought, setSelectedThought] = useState<Thought | null>(null) // For detail view
+  const [commandCenterQuery, setCommandCenterQuery] = useState<string | undefined>(undefined) // Prefilled search (deep links)
   const canvasRef = useRef<HTMLCanvasElement | null>(null) // Ref to canvas for re-locking
   const { thoughts } = useMindStore()
 
@@ -526,10 +527,70 @@ export default function App() {
   // Start database sync
   useDatabaseSync()
 
+  // All data was wiped (reset_mind_confirm): drop the scene and reload what's left (default categories)
+  useResetEvents(() => {
+    console.log('🧹 The Mind was reset')
+    useMindStore.setState({ thoughts: [], connections: [], sessions: [], clusters: [], currentSession: null })
+    useMindStore.getState().loadFromDatabase()
+  })
+
+  // Teleport the camera next to a thought; false if it isn't loaded
+  const flyToThought = useCallback((thoughtId: string) => {
+    const thought = thoughts.find(t => t.id === thoughtId)
+    if (!thought) return false
+
+    // Teleport camera to position (close enough to see the info panel)
+    // PANEL_FULL threshold is 8 units, so we go to 6 units back
+    cameraControl.teleportTo = {
+      x: thought.position.x,
+      y: thought.position.y,
+      z: thought.position.z + 6  // Close enough to see panel (< 8 units)
+    }
+    // Look at the thought
+    cameraControl.lookAt = {
+      x: thought.position.x,
+      y: thought.position.y,
+      z: thought.position.z
+    }
+    console.log('🚀 Navigating to thought:', thought.content.substring(0, 50))
+    return true
+  }, [thoughts])
+
+  // Rust re-entered wallpaper mode on startup (auto_wallpaper); attached before frontend_ready
+  useWallpaperAutoStartEvents(({ config, fallback_from }) => {
+    if (fallback_from) {
+      console.warn('🖼️ Saved wallpaper monitors are gone, using the primary monitor instead of', fallback_from)
+    }
+    console.log('🖼️ Wallpaper mode auto-started:', config)
+    useWallpaperStore.setState({ isWallpaperMode: true })
+  })
+
+  // the-mind:// deep links
+  useNavigationEvents((target) => {
+    if (target.kind === 'thought') {
+      if (!flyToThought(target.id)) console.warn('Deep link thought is not loaded:', target.id)
+      return
+    }
+    // Sessions have no dedicated view yet; search for the id/query in the Command Center
+    setCommandCenterQuery(target.kind === 'search' ? target.query : target.id)
+    setShowCommandCenter(true)
+    if (document.pointerLockElement) {
+      document.exitPointerLock()
+    }
+  })
+
   // Check if session-forge data is available
   const checkForgeAvailability = useForgeStore(s => s.checkAvailability)
   useEffect(() => { checkForgeAvailability() }, [checkForgeAvailability])
 
+  // Another tool wrote to session-forge: cached context is stale
+  const clearForgeCache = useForgeStore(s => s.clearCache)
+  useForgeChangeEvents((file) => {
+    console.log(`🔗 session-forge ${file} changed`)
+    clearForgeCache()
+    checkForgeAvailability()
+  })
+
   // Idle detection — reset on any input, check every 5 seconds
   useEffect(() => {
     const onInput = () => resetActivity()
@@ -795,26 +856,13 @@ export default function App() {
       {/* Command Center modal */}
       {showCommandCenter && (
         <CommandCenter 
-          onClose={() => setShowCommandCenter(false)} 
+          onClose={() => {
+            setShowCommandCenter(false)
+            setCommandCenterQuery(undefined)
+          }}
+          initialQuery={commandCenterQuery}
           onNavigateToThought={(thoughtId) => {
-            // Find the thought by ID
-            const thought = thoughts.find(t => t.id === thoughtId)
-            if (thought) {
-              // Teleport camera to position (close enough to see the info panel)
-              // PANEL_FULL threshold is 8 units, so we go to 6 units back
-              cameraControl.teleportTo = {
-                x: thought.position.x,
-                y: thought.position.y,
-                z: thought.position.z + 6  // Close enough to see panel (< 8 units)
-              }
-              // Look at the thought
-              cameraControl.lookAt = {
-                x: thought.position.x,
-                y: thought.position.y,
-                z: thought.position.z
-              }
-              console.log('🚀 Navigating to thought:', thought.content.substring(0, 50))
-              
+            if (flyToThought(thoughtId)) {
               // Close the Command Center so the panel can show
               setShowCommandCenter(false)
             }
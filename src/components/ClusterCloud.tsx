@@ -10,13 +10,15 @@ interface ClusterCloudProps {
   category: string
   center: [number, number, number]
   thoughtCount: number
+  // The category's color from the backend; built-in palette when missing
+  color?: string
 }
 
 /**
  * Translucent nebula sphere representing a cluster of related thoughts.
  * Sized by thought count, colored by category, gently pulsing.
  */
-export default function ClusterCloud({ name: _name, category, center, thoughtCount }: ClusterCloudProps) {
+export default function ClusterCloud({ name: _name, category, center, thoughtCount, color: categoryColor }: ClusterCloudProps) {
   const meshRef = useRef<THREE.Mesh>(null)
   const phaseOffset = useMemo(() => Math.random() * Math.PI * 2, [])
 
@@ -25,7 +27,7 @@ export default function ClusterCloud({ name: _name, category, center, thoughtCou
     return Math.min(25, Math.max(5, Math.sqrt(thoughtCount) * 3))
   }, [thoughtCount])
 
-  const color = CATEGORY_COLORS[category as ThoughtCategory] || CATEGORY_COLORS.other
+  const color = categoryColor || CATEGORY_COLORS[category as ThoughtCategory] || CATEGORY_COLORS.other
 
   // Gentle pulsing animation
   useFrame(({ clock }) => {
@@ -11,6 +11,7 @@ import type { Thought, Connection } from '../types'
 interface CommandCenterProps {
   onClose: () => void
   onNavigateToThought?: (thoughtId: string) => void
+  initialQuery?: string // Opens on the search tab with this query (deep links)
 }
 
 // Check if running in Tauri
@@ -21,9 +22,9 @@ const isTauri = () => {
 type TabId = 'overview' | 'search' | 'export' | 'connect' | 'thinking' | 'dream' | 'timeline' | 'settings'
 type SortOption = 'recent' | 'oldest' | 'importance' | 'connections' | 'alphabetical'
 
-export default function CommandCenter({ onClose, onNavigateToThought }: CommandCenterProps) {
-  const [activeTab, setActiveTab] = useState<TabId>('overview')
-  const [searchQuery, setSearchQuery] = useState('')
+export default function CommandCenter({ onClose, onNavigateToThought, initialQuery }: CommandCenterProps) {
+  const [activeTab, setActiveTab] = useState<TabId>(initialQuery ? 'search' : 'overview')
+  const [searchQuery, setSearchQuery] = useState(initialQuery ?? '')
   const [copied, setCopied] = useState(false)
   const [exePath, setExePath] = useState('C:\\\\Program Files\\\\The Mind\\\\the-mind.exe')
   const searchInputRef = useRef<HTMLInputElement>(null)
@@ -35,6 +36,14 @@ export default function CommandCenter({ onClose, onNavigateToThought }: CommandC
   const [selectedIndex, setSelectedIndex] = useState(0)
   
   const { thoughts, connections } = useMindStore()
+
+  // A new deep link while open replaces the current search
+  useEffect(() => {
+    if (initialQuery) {
+      setActiveTab('search')
+      setSearchQuery(initialQuery)
+    }
+  }, [initialQuery])
   
   // Keyboard shortcuts
   useEffect(() => {
@@ -105,14 +114,14 @@ export default function CommandCenter({ onClose, onNavigateToThought }: CommandC
       )
     }
     
-    // Category filter
+    // Category filter (pinned thoughts always stay visible)
     if (selectedCategories.size > 0) {
-      results = results.filter(t => selectedCategories.has(t.category))
+      results = results.filter(t => t.pinned || selectedCategories.has(t.category))
     }
     
     // Importance filter
     if (minImportance > 0) {
-      results = results.filter(t => t.importance >= minImportance)
+      results = results.filter(t => t.pinned || t.importance >= minImportance)
     }
     
     // Sorting
@@ -1584,7 +1593,8 @@ function WallpaperSettings() {
     setDisplayMode,
     setOrbitSpeed,
     setDriftAmount,
-    refreshMonitors
+    refreshMonitors,
+    identifyMonitors
   } = useWallpaperStore()
 
   // Check if a monitor's bounds match the selected bounds
@@ -1614,12 +1624,21 @@ function WallpaperSettings() {
       <div className="mb-4">
         <div className="flex justify-between mb-2">
           <span className="text-sm text-white/70">Display</span>
-          <button
-            onClick={() => refreshMonitors()}
-            className="text-xs text-blue-400 hover:text-blue-300"
-          >
-            Refresh
-          </button>
+          <div className="flex gap-3">
+            <button
+              onClick={() => identifyMonitors()}
+              className="text-xs text-blue-400 hover:text-blue-300"
+              title="Show each monitor's number on its screen"
+            >
+              Identify
+            </button>
+            <button
+              onClick={() => refreshMonitors()}
+              className="text-xs text-blue-400 hover:text-blue-300"
+            >
+              Refresh
+            </button>
+          </div>
         </div>
         <div className="space-y-2">
           {/* All Monitors Option */}
@@ -1,5 +1,5 @@
 import { useState } from 'react'
-import type { ForgeContext, ForgeJournalEntry, ForgeDecisionEntry, ForgeDeadEndEntry } from '../types'
+import type { ForgeContext, ForgeJournalEntry, ForgeDecisionEntry, ForgeDeadEndEntry, ForgeStatus, ForgeWarning, ScoredForgeEntry } from '../types'
 
 // ---- Time formatting ----
 
@@ -50,7 +50,15 @@ function CollapsibleSection({ title, icon, count, children }: {
 
 // ---- Entry Cards ----
 
-function JournalCard({ entry }: { entry: ForgeJournalEntry }) {
+function MatchBadge({ score }: { score: number }) {
+  return (
+    <span className="text-xs text-white/30" title="Keywords shared with this thought">
+      {score} match{score === 1 ? '' : 'es'}
+    </span>
+  )
+}
+
+function JournalCard({ entry }: { entry: ScoredForgeEntry<ForgeJournalEntry> }) {
   const [expanded, setExpanded] = useState(false)
 
   return (
@@ -60,6 +68,7 @@ function JournalCard({ entry }: { entry: ForgeJournalEntry }) {
     >
       <div className="flex items-center justify-between mb-1">
         <span className="text-xs text-blue-400/70 font-mono">{timeAgo(entry.timestamp)}</span>
+        <MatchBadge score={entry.score} />
       </div>
       <p className="text-sm text-white/70 leading-relaxed" style={{
         display: expanded ? 'block' : '-webkit-box',
@@ -95,7 +104,7 @@ function JournalCard({ entry }: { entry: ForgeJournalEntry }) {
   )
 }
 
-function DecisionCard({ entry }: { entry: ForgeDecisionEntry }) {
+function DecisionCard({ entry }: { entry: ScoredForgeEntry<ForgeDecisionEntry> }) {
   const [expanded, setExpanded] = useState(false)
 
   return (
@@ -105,9 +114,12 @@ function DecisionCard({ entry }: { entry: ForgeDecisionEntry }) {
     >
       <div className="flex items-center justify-between mb-1">
         <span className="text-xs text-purple-400/70 font-mono">{timeAgo(entry.timestamp)}</span>
-        {entry.project && (
-          <span className="text-xs px-1.5 py-0.5 rounded bg-purple-500/20 text-purple-300/70">{entry.project}</span>
-        )}
+        <div className="flex items-center gap-2">
+          {entry.project && (
+            <span className="text-xs px-1.5 py-0.5 rounded bg-purple-500/20 text-purple-300/70">{entry.project}</span>
+          )}
+          <MatchBadge score={entry.score} />
+        </div>
       </div>
       <p className="text-sm text-white/80 font-medium">{entry.choice}</p>
       {expanded && (
@@ -139,7 +151,7 @@ function DecisionCard({ entry }: { entry: ForgeDecisionEntry }) {
   )
 }
 
-function DeadEndCard({ entry }: { entry: ForgeDeadEndEntry }) {
+function DeadEndCard({ entry }: { entry: ScoredForgeEntry<ForgeDeadEndEntry> }) {
   const [expanded, setExpanded] = useState(false)
 
   return (
@@ -149,6 +161,7 @@ function DeadEndCard({ entry }: { entry: ForgeDeadEndEntry }) {
     >
       <div className="flex items-center justify-between mb-1">
         <span className="text-xs text-red-400/70 font-mono">{timeAgo(entry.timestamp)}</span>
+        <MatchBadge score={entry.score} />
       </div>
       <p className="text-sm text-white/70">{entry.attempted}</p>
       {expanded && (
@@ -171,15 +184,47 @@ function DeadEndCard({ entry }: { entry: ForgeDeadEndEntry }) {
   )
 }
 
+// ---- Forge files found ----
+
+function ForgeFiles({ status }: { status: ForgeStatus }) {
+  const files: [string, boolean][] = [
+    ['journal', status.journal],
+    ['decisions', status.decisions],
+    ['dead-ends', status.dead_ends],
+  ]
+  return (
+    <p className="text-white/30 text-xs font-mono" title={status.dir ?? undefined}>
+      {files.map(([name, found]) => `${name} ${found ? '✓' : '✗'}`).join(', ')}
+    </p>
+  )
+}
+
+// ---- Parse warnings ----
+
+function ForgeWarnings({ warnings }: { warnings: ForgeWarning[] }) {
+  if (warnings.length === 0) return null
+  return (
+    <div className="mx-4 mb-3 px-3 py-2 rounded-lg bg-yellow-500/10 text-xs text-yellow-300/80 space-y-1">
+      <div className="font-medium">⚠️ Some session-forge data could not be read</div>
+      {warnings.map((w, i) => (
+        <div key={i} className="font-mono text-yellow-300/60 break-all">
+          {w.file}{w.index !== null ? ` #${w.index}` : ''}: {w.message}
+        </div>
+      ))}
+    </div>
+  )
+}
+
 // ---- Main Pane ----
 
 interface ForgeContextPaneProps {
   forgeContext: ForgeContext | null
   available: boolean
   loading: boolean
+  status?: ForgeStatus | null
 }
 
-export default function ForgeContextPane({ forgeContext, available, loading }: ForgeContextPaneProps) {
+export default function ForgeContextPane({ forgeContext, available, loading, status }: ForgeContextPaneProps) {
   if (!available) {
     return (
       <div className="flex flex-col items-center justify-center py-12 px-6 text-center">
@@ -188,6 +233,9 @@ export default function ForgeContextPane({ forgeContext, available, loading }: F
           Install <span className="text-white/70 font-mono">session-forge</span> to see related context from past sessions, decisions, and dead ends.
         </p>
         <p className="text-white/30 text-xs mt-2 font-mono">npx session-forge</p>
+        {status?.dir && (
+          <p className="text-white/20 text-xs mt-2 font-mono break-all">Looked in {status.dir}</p>
+        )}
       </div>
     )
   }
@@ -203,15 +251,24 @@ export default function ForgeContextPane({ forgeContext, available, loading }: F
   const total = forgeContext.journals.length + forgeContext.decisions.length + forgeContext.dead_ends.length
   if (total === 0) {
     return (
-      <div className="flex flex-col items-center justify-center py-12 px-6 text-center">
-        <div className="text-3xl mb-3">🔍</div>
-        <p className="text-white/40 text-sm">No related session-forge context found for this thought.</p>
+      <div className="py-3">
+        <ForgeWarnings warnings={forgeContext.warnings} />
+        <div className="flex flex-col items-center justify-center py-12 px-6 text-center">
+          <div className="text-3xl mb-3">🔍</div>
+          <p className="text-white/40 text-sm">No related session-forge context found for this thought.</p>
+        </div>
       </div>
     )
   }
 
   return (
     <div className="px-4 py-3">
+      {status && (
+        <div className="px-4 mb-2">
+          <ForgeFiles status={status} />
+        </div>
+      )}
+      <ForgeWarnings warnings={forgeContext.warnings} />
       {forgeContext.journals.length > 0 && (
         <CollapsibleSection title="Sessions" icon="📓" count={forgeContext.journals.length}>
           {forgeContext.journals.map((j, i) => (
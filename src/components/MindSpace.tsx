@@ -120,6 +120,7 @@ export default function MindSpace({ isUIOpen = false, onThoughtClick }: MindSpac
           category={cluster.category}
           center={[cluster.center.x, cluster.center.y, cluster.center.z]}
           thoughtCount={cluster.thoughtCount}
+          color={cluster.color}
         />
       ))}
 
@@ -0,0 +1,12 @@
+/**
+ * Big monitor number shown in the identify-monitors overlay windows.
+ * The windows are opened and closed from Rust; this only renders the number.
+ */
+export default function MonitorIdentify({ number }: { number: string }) {
+  return (
+    <div className="h-screen w-screen bg-gray-900/90 border-4 border-purple-500 rounded-2xl flex flex-col items-center justify-center select-none">
+      <span className="text-white text-9xl font-bold leading-none">{number}</span>
+      <span className="text-white/50 text-sm mt-2">Monitor {number}</span>
+    </div>
+  )
+}
@@ -0,0 +1,75 @@
+import { useEffect, useRef, useState } from 'react'
+import type { ThoughtCategory } from '../types'
+import { CATEGORY_COLORS } from '../types'
+
+const CATEGORIES: ThoughtCategory[] = ['work', 'personal', 'technical', 'creative', 'other']
+
+/**
+ * Minimal capture form rendered in the always-on-top quick-capture window.
+ * Enter saves, Shift+Enter adds a newline, Escape closes without saving.
+ */
+export default function QuickCapture() {
+  const [content, setContent] = useState('')
+  const [category, setCategory] = useState<ThoughtCategory>('other')
+  const [error, setError] = useState<string | null>(null)
+  const inputRef = useRef<HTMLTextAreaElement>(null)
+
+  const close = async () => {
+    const { invoke } = await import('@tauri-apps/api/core')
+    await invoke('close_quick_capture')
+  }
+
+  const submit = async () => {
+    if (!content.trim()) return
+    try {
+      const { invoke } = await import('@tauri-apps/api/core')
+      await invoke('quick_capture', { content, category })
+      await close()
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  useEffect(() => {
+    inputRef.current?.focus()
+    const onKeyDown = (e: KeyboardEvent) => {
+      if (e.key === 'Escape') close()
+    }
+    window.addEventListener('keydown', onKeyDown)
+    return () => window.removeEventListener('keydown', onKeyDown)
+  }, [])
+
+  return (
+    <div className="h-screen w-screen bg-gray-900/95 text-white p-3 flex flex-col gap-2 border border-gray-700 rounded-lg">
+      <textarea
+        ref={inputRef}
+        value={content}
+        onChange={(e) => setContent(e.target.value)}
+        onKeyDown={(e) => {
+          if (e.key === 'Enter' && !e.shiftKey) {
+            e.preventDefault()
+            submit()
+          }
+        }}
+        placeholder="Capture a thought..."
+        className="flex-1 resize-none bg-transparent outline-none text-sm"
+      />
+      <div className="flex items-center gap-2">
+        {CATEGORIES.map((c) => (
+          <button
+            key={c}
+            onClick={() => setCategory(c)}
+            className="text-xs px-2 py-0.5 rounded-full border"
+            style={{
+              borderColor: CATEGORY_COLORS[c],
+              backgroundColor: category === c ? CATEGORY_COLORS[c] : 'transparent',
+            }}
+          >
+            {c}
+          </button>
+        ))}
+        {error && <span className="text-xs text-red-400 ml-auto">{error}</span>}
+      </div>
+    </div>
+  )
+}
@@ -39,7 +39,7 @@ type TabId = 'details' | 'context' | 'connections'
 
 export default function ThoughtDetail({ thought, onClose, onNavigateToConnected }: ThoughtDetailProps) {
   const { thoughts, connections } = useMindStore()
-  const { available: forgeAvailable, fetchContext, cache, loading: forgeLoading } = useForgeStore()
+  const { available: forgeAvailable, status: forgeStatus, fetchContext, cache, loading: forgeLoading } = useForgeStore()
   const [activeTab, setActiveTab] = useState<TabId>('details')
   const [copiedField, setCopiedField] = useState<string | null>(null)
 
@@ -50,9 +50,9 @@ export default function ThoughtDetail({ thought, onClose, onNavigateToConnected
   // Fetch forge context when switching to context tab
   useEffect(() => {
     if (activeTab === 'context' && forgeAvailable && !forgeContext && !isForgeLoading) {
-      fetchContext(thought.id, thought.content)
+      fetchContext(thought.id)
     }
-  }, [activeTab, thought.id, thought.content, forgeAvailable, forgeContext, isForgeLoading, fetchContext])
+  }, [activeTab, thought.id, forgeAvailable, forgeContext, isForgeLoading, fetchContext])
 
   // Count forge results for badge
   const forgeTotal = forgeContext
@@ -195,6 +195,7 @@ export default function ThoughtDetail({ thought, onClose, onNavigateToConnected
             <ForgeContextPane
               forgeContext={forgeContext}
               available={forgeAvailable}
+              status={forgeStatus}
               loading={isForgeLoading}
             />
           )}
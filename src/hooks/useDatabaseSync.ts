@@ -1,5 +1,6 @@
 import { useEffect, useCallback, useRef } from 'react'
 import { useMindStore } from '../stores/mindStore'
+import type { NavigateTarget, WallpaperAutoStarted } from '../types'
 
 // Check if running in Tauri
 const isTauri = () => {
@@ -10,6 +11,10 @@ const isTauri = () => {
 interface DbVersion {
   thought_max_id: number
   connection_max_id: number
+  session_max_id: number
+  cluster_generation: number
+  // Changes on any write (inserts, edits, deletes); absent from older binaries
+  mutation_counter?: number
 }
 
 /**
@@ -18,8 +23,14 @@ interface DbVersion {
  * When spatial loading is active, triggers loadNearCamera instead of full load.
  */
 export function useDatabaseSync(intervalMs = 500) {
-  const { loadFromDatabase, loadNearCamera, useSpatialLoading } = useMindStore()
-  const lastVersionRef = useRef<DbVersion>({ thought_max_id: -1, connection_max_id: -1 })
+  const { loadFromDatabase, loadNearCamera, applyGraphDelta, useSpatialLoading } = useMindStore()
+  const lastVersionRef = useRef<DbVersion>({
+    thought_max_id: -1,
+    connection_max_id: -1,
+    session_max_id: -1,
+    cluster_generation: -1,
+    mutation_counter: -1,
+  })
   // Track spatial loading flag in a ref so the callback stays stable
   const spatialRef = useRef(useSpatialLoading)
   spatialRef.current = useSpatialLoading
@@ -30,20 +41,23 @@ export function useDatabaseSync(intervalMs = 500) {
     try {
       const { invoke } = await import('@tauri-apps/api/core')
 
-      // Cheap check — just gets max rowids
+      // Cheap check — max rowids and change counters
       const version = await invoke<DbVersion>('get_db_version')
 
       const prev = lastVersionRef.current
-      if (version.thought_max_id !== prev.thought_max_id ||
-          version.connection_max_id !== prev.connection_max_id) {
-        console.log(`Database changed: thoughts ${prev.thought_max_id}->${version.thought_max_id}, connections ${prev.connection_max_id}->${version.connection_max_id}`)
+      const changed = version.mutation_counter !== undefined
+        ? version.mutation_counter !== prev.mutation_counter
+        : version.thought_max_id !== prev.thought_max_id ||
+          version.connection_max_id !== prev.connection_max_id
+      if (changed) {
+        console.log(`Database changed: thoughts ${prev.thought_max_id}->${version.thought_max_id}, connections ${prev.connection_max_id}->${version.connection_max_id}, mutations ${prev.mutation_counter}->${version.mutation_counter}`)
         lastVersionRef.current = version
 
         if (spatialRef.current) {
           // In spatial mode, reload at origin (MindSpace's useFrame will handle camera-position reloads)
           // This ensures new MCP-added thoughts at least appear if they're nearby
           await loadNearCamera(0, 0, 50)
-        } else {
+        } else if (!(await applyGraphDelta())) {
           await loadFromDatabase()
         }
       }
@@ -55,7 +69,7 @@ export function useDatabaseSync(intervalMs = 500) {
         console.error('Failed to check for updates:', err)
       }
     }
-  }, [loadFromDatabase, loadNearCamera])
+  }, [loadFromDatabase, loadNearCamera, applyGraphDelta])
 
   useEffect(() => {
     if (!isTauri()) return
@@ -71,7 +85,9 @@ export function useDatabaseSync(intervalMs = 500) {
 }
 
 /**
- * Hook to listen for Tauri events (for future real-time updates)
+ * Hook to listen for thoughts and connections added outside this window (MCP server, imports,
+ * auto-connect). Thoughts arrive before the connections that link them; the window's own
+ * add_thought/add_connection writes are not echoed back.
  */
 export function useTauriEvents() {
   const { addThought, addConnection } = useMindStore()
@@ -85,12 +101,13 @@ export function useTauriEvents() {
       try {
         const { listen } = await import('@tauri-apps/api/event')
 
-        const unlistenThought = await listen<unknown>('thought-added', (event: { payload: unknown }) => {
-          console.log('New thought from MCP:', event.payload)
+        const unlistenThought = await listen<{ id: string }[]>('mind://thoughts-added', (event) => {
+          console.log(`${event.payload.length} thought(s) added elsewhere`)
         })
 
-        const unlistenConnection = await listen<unknown>('connection-added', (event: { payload: unknown }) => {
-          console.log('New connection from MCP:', event.payload)
+        const unlistenConnection = await listen<{ id: string; auto_created: boolean }[]>('mind://connections-added', (event) => {
+          const auto = event.payload.filter(c => c.auto_created).length
+          console.log(`${event.payload.length} connection(s) added elsewhere, ${auto} automatic`)
         })
 
         unlisten = () => {
@@ -109,3 +126,163 @@ export function useTauriEvents() {
     }
   }, [addThought, addConnection])
 }
+
+/**
+ * Listen for deep-link navigation (the-mind://thought/<id> etc.).
+ * Links that arrived before the listener was attached are queued in Rust
+ * and flushed once we call frontend_ready.
+ */
+export function useNavigationEvents(onNavigate: (target: NavigateTarget) => void) {
+  const handlerRef = useRef(onNavigate)
+  handlerRef.current = onNavigate
+
+  useEffect(() => {
+    if (!isTauri()) return
+
+    let unlisten: (() => void) | undefined
+    let cancelled = false
+
+    const setup = async () => {
+      try {
+        const { listen } = await import('@tauri-apps/api/event')
+        const { invoke } = await import('@tauri-apps/api/core')
+
+        const stop = await listen<NavigateTarget>('mind://navigate', (event) => {
+          handlerRef.current(event.payload)
+        })
+        if (cancelled) {
+          stop()
+          return
+        }
+        unlisten = stop
+
+        await invoke('frontend_ready')
+      } catch (err) {
+        console.error('Failed to set up navigation listener:', err)
+      }
+    }
+
+    setup()
+
+    return () => {
+      cancelled = true
+      if (unlisten) unlisten()
+    }
+  }, [])
+}
+
+/**
+ * Listen for changes to session-forge files made by other tools
+ * (e.g. a decision appended while the app is open).
+ */
+export function useForgeChangeEvents(onChange: (file: string) => void) {
+  const handlerRef = useRef(onChange)
+  handlerRef.current = onChange
+
+  useEffect(() => {
+    if (!isTauri()) return
+
+    let unlisten: (() => void) | undefined
+    let cancelled = false
+
+    const setup = async () => {
+      try {
+        const { listen } = await import('@tauri-apps/api/event')
+        const stop = await listen<{ file: string }>('mind://forge-changed', (event) => {
+          handlerRef.current(event.payload.file)
+        })
+        if (cancelled) {
+          stop()
+          return
+        }
+        unlisten = stop
+      } catch (err) {
+        console.error('Failed to set up forge change listener:', err)
+      }
+    }
+
+    setup()
+
+    return () => {
+      cancelled = true
+      if (unlisten) unlisten()
+    }
+  }, [])
+}
+
+/**
+ * Listen for wallpaper mode being re-entered at startup (auto_wallpaper setting).
+ * Fires during frontend_ready, so call this before useNavigationEvents.
+ */
+export function useWallpaperAutoStartEvents(onStart: (event: WallpaperAutoStarted) => void) {
+  const handlerRef = useRef(onStart)
+  handlerRef.current = onStart
+
+  useEffect(() => {
+    if (!isTauri()) return
+
+    let unlisten: (() => void) | undefined
+    let cancelled = false
+
+    const setup = async () => {
+      try {
+        const { listen } = await import('@tauri-apps/api/event')
+        const stop = await listen<WallpaperAutoStarted>('mind://wallpaper-auto-started', (event) => {
+          handlerRef.current(event.payload)
+        })
+        if (cancelled) {
+          stop()
+          return
+        }
+        unlisten = stop
+      } catch (err) {
+        console.error('Failed to set up wallpaper auto-start listener:', err)
+      }
+    }
+
+    setup()
+
+    return () => {
+      cancelled = true
+      if (unlisten) unlisten()
+    }
+  }, [])
+}
+
+/**
+ * Listen for reset_mind_confirm wiping the database, so the scene can be cleared.
+ */
+export function useResetEvents(onReset: () => void) {
+  const handlerRef = useRef(onReset)
+  handlerRef.current = onReset
+
+  useEffect(() => {
+    if (!isTauri()) return
+
+    let unlisten: (() => void) | undefined
+    let cancelled = false
+
+    const setup = async () => {
+      try {
+        const { listen } = await import('@tauri-apps/api/event')
+        const stop = await listen('mind://reset', () => {
+          handlerRef.current()
+        })
+        if (cancelled) {
+          stop()
+          return
+        }
+        unlisten = stop
+      } catch (err) {
+        console.error('Failed to set up reset listener:', err)
+      }
+    }
+
+    setup()
+
+    return () => {
+      cancelled = true
+      if (unlisten) unlisten()
+    }
+  }, [])
+}
@@ -1,10 +1,17 @@
 import React from 'react'
 import ReactDOM from 'react-dom/client'
 import App from './App'
+import QuickCapture from './components/QuickCapture'
+import MonitorIdentify from './components/MonitorIdentify'
 import './index.css'
 
+// The quick-capture window loads the same bundle with a #capture hash,
+// and each identify-monitors overlay with #identify-<number>
+const isCaptureWindow = window.location.hash === '#capture'
+const identifyNumber = window.location.hash.match(/^#identify-(\d+)$/)?.[1]
+
 ReactDOM.createRoot(document.getElementById('root')!).render(
   <React.StrictMode>
-    <App />
+    {isCaptureWindow ? <QuickCapture /> : identifyNumber ? <MonitorIdentify number={identifyNumber} /> : <App />}
   </React.StrictMode>,
 )
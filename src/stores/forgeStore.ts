@@ -1,5 +1,5 @@
 import { create } from 'zustand'
-import type { ForgeContext } from '../types'
+import type { ForgeContext, ForgeStatus } from '../types'
 
 const isTauri = () => {
   return typeof window !== 'undefined' &&
@@ -16,16 +16,19 @@ async function invoke<T>(cmd: string, args?: Record<string, unknown>): Promise<T
 
 interface ForgeState {
   available: boolean
+  status: ForgeStatus | null
   cache: Map<string, ForgeContext>
   loading: Set<string>
 
   checkAvailability: () => Promise<void>
-  fetchContext: (thoughtId: string, content: string) => Promise<ForgeContext | null>
+  setForgeDir: (path: string) => Promise<ForgeStatus>
+  fetchContext: (thoughtId: string) => Promise<ForgeContext | null>
   clearCache: () => void
 }
 
 export const useForgeStore = create<ForgeState>((set, get) => ({
   available: false,
+  status: null,
   cache: new Map(),
   loading: new Set(),
 
@@ -35,9 +38,9 @@ export const useForgeStore = create<ForgeState>((set, get) => ({
       return
     }
     try {
-      const available = await invoke<boolean>('get_forge_available')
-      set({ available })
-      if (available) {
+      const status = await invoke<ForgeStatus>('get_forge_available')
+      set({ available: status.available, status })
+      if (status.available) {
         console.log('🔗 session-forge data detected')
       }
     } catch {
@@ -45,7 +48,14 @@ export const useForgeStore = create<ForgeState>((set, get) => ({
     }
   },
 
-  fetchContext: async (thoughtId: string, content: string) => {
+  setForgeDir: async (path: string) => {
+    const status = await invoke<ForgeStatus>('set_forge_dir', { path })
+    // Results came from the old directory
+    set({ available: status.available, status, cache: new Map() })
+    return status
+  },
+
+  fetchContext: async (thoughtId: string) => {
     const { cache, loading } = get()
 
     // Return cached result
@@ -62,7 +72,7 @@ export const useForgeStore = create<ForgeState>((set, get) => ({
     })
 
     try {
-      const result = await invoke<ForgeContext>('get_forge_context', { query: content })
+      const result = await invoke<ForgeContext>('get_forge_context_for_thought', { thoughtId })
 
       set(s => {
         const newCache = new Map(s.cache)
@@ -1,5 +1,6 @@
 import { create } from 'zustand'
-import type { MindState, Thought, Connection, Session, Cluster, ThoughtCategory } from '../types'
+import type { MindState, Thought, Connection, ConnectionKind, Session, Cluster, ThoughtCategory } from '../types'
+import { CATEGORY_COLORS } from '../types'
 import { useThinkingStore } from './thinkingStore'
 import { useActivationStore } from './activationStore'
 
@@ -30,6 +31,9 @@ interface RustThought {
   position_z: number
   created_at: string
   last_referenced: string
+  pinned?: boolean
+  favorite?: boolean
+  archived?: boolean
 }
 
 interface RustConnection {
@@ -39,6 +43,7 @@ interface RustConnection {
   strength: number
   reason: string
   created_at: string
+  kind?: string
 }
 
 interface RustCluster {
@@ -50,6 +55,11 @@ interface RustCluster {
   center_z: number
   thought_count: number
   created_at: string
+  keywords?: string[]
+  parent_id?: string | null
+  child_count?: number
+  manual?: boolean
+  color?: string | null
 }
 
 function rustToCluster(rust: RustCluster): Cluster {
@@ -61,13 +71,36 @@ function rustToCluster(rust: RustCluster): Cluster {
       y: rust.center_y,
       z: rust.center_z,
     },
-    color: rust.category, // We'll resolve to actual color in the component
+    color: rust.color ?? CATEGORY_COLORS[rust.category as ThoughtCategory] ?? CATEGORY_COLORS.other,
     createdAt: new Date(rust.created_at),
     category: rust.category,
     thoughtCount: rust.thought_count,
+    keywords: rust.keywords ?? [],
+    parentId: rust.parent_id ?? null,
+    childCount: rust.child_count ?? 0,
+    manual: rust.manual ?? false,
   }
 }
 
+interface RustGraphDelta {
+  seq: number
+  stale: boolean
+  thoughts: RustThought[]
+  connections: RustConnection[]
+  clusters: RustCluster[]
+  deleted: {
+    thoughts: string[]
+    connections: string[]
+    clusters: string[]
+  }
+}
+
+// Replace items with matching ids and append the rest, after dropping removed ids
+function mergeById<T extends { id: string }>(items: T[], changed: T[], removed: string[]): T[] {
+  const gone = new Set([...removed, ...changed.map(item => item.id)])
+  return [...items.filter(item => !gone.has(item.id)), ...changed]
+}
+
 // Convert Rust thought to frontend thought
 function rustToThought(rust: RustThought): Thought {
   return {
@@ -83,6 +116,9 @@ function rustToThought(rust: RustThought): Thought {
     },
     createdAt: new Date(rust.created_at),
     lastReferenced: new Date(rust.last_referenced),
+    pinned: rust.pinned ?? false,
+    favorite: rust.favorite ?? false,
+    archived: rust.archived ?? false,
   }
 }
 
@@ -99,6 +135,9 @@ function thoughtToRust(thought: Thought): RustThought {
     position_z: thought.position.z,
     created_at: thought.createdAt.toISOString(),
     last_referenced: thought.lastReferenced.toISOString(),
+    pinned: thought.pinned ?? false,
+    favorite: thought.favorite ?? false,
+    archived: thought.archived ?? false,
   }
 }
 
@@ -111,6 +150,7 @@ function rustToConnection(rust: RustConnection): Connection {
     strength: rust.strength,
     reason: rust.reason,
     createdAt: new Date(rust.created_at),
+    kind: (rust.kind ?? 'related') as ConnectionKind,
   }
 }
 
@@ -123,6 +163,7 @@ function connectionToRust(conn: Connection): RustConnection {
     strength: conn.strength,
     reason: conn.reason,
     created_at: conn.createdAt.toISOString(),
+    kind: conn.kind ?? 'related',
   }
 }
 
@@ -265,6 +306,7 @@ export const useMindStore = create<MindState>((set, _get) => ({
   currentSession: null,
   useSpatialLoading: false,
   totalThoughtCount: 0,
+  changeSeq: null,
   
   addThought: async (thought: Thought) => {
     const state = _get()
@@ -357,9 +399,17 @@ export const useMindStore = create<MindState>((set, _get) => ({
 
         const shouldUseSpatial = totalCount > SPATIAL_THRESHOLD
 
+        // Taken before loading so changes made while it runs come through the next delta
+        let changeSeq: number | null = null
+        try {
+          changeSeq = (await invoke<{ change_seq?: number }>('get_db_version')).change_seq ?? null
+        } catch {
+          // Old binary without change tracking, keep doing full loads
+        }
+
         if (shouldUseSpatial) {
           console.log(`${totalCount} thoughts detected — spatial loading enabled (threshold: ${SPATIAL_THRESHOLD})`)
-          set({ useSpatialLoading: true, totalThoughtCount: totalCount })
+          set({ useSpatialLoading: true, totalThoughtCount: totalCount, changeSeq: null })
           // Don't load all — let MindSpace trigger loadNearCamera based on camera position
           return
         }
@@ -383,14 +433,12 @@ export const useMindStore = create<MindState>((set, _get) => ({
 
         console.log(`Loaded ${thoughts.length} thoughts, ${connections.length} connections, ${clusters.length} clusters`)
 
-        set({ thoughts, connections, clusters, useSpatialLoading: false, totalThoughtCount: totalCount })
+        set({ thoughts, connections, clusters, useSpatialLoading: false, totalThoughtCount: totalCount, changeSeq })
 
         // If no data, seed with demo data
         if (thoughts.length === 0) {
           console.log('No data found, seeding with demo data...')
-          for (const thought of demoThoughts) {
-            await invoke('add_thought', { thought: thoughtToRust(thought) })
-          }
+          await invoke('add_thoughts', { thoughts: demoThoughts.map(thoughtToRust) })
           for (const conn of demoConnections) {
             await invoke('add_connection', { connection: connectionToRust(conn) })
           }
@@ -437,6 +485,37 @@ export const useMindStore = create<MindState>((set, _get) => ({
       set({ thoughts, connections, useSpatialLoading: false })
     }
   },
+
+  applyGraphDelta: async () => {
+    const { changeSeq, useSpatialLoading } = _get()
+    // Spatial loading holds only part of the graph, so it reloads around the camera instead
+    if (!isTauri() || changeSeq === null || useSpatialLoading) return false
+
+    try {
+      const delta = await invoke<RustGraphDelta>('get_graph_delta', { sinceSeq: changeSeq })
+      if (delta.stale) return false
+
+      const clusters = delta.clusters.filter(c => !c.parent_id).map(rustToCluster)
+      const removedClusters = [...delta.deleted.clusters, ...delta.clusters.filter(c => c.parent_id).map(c => c.id)]
+      set((state) => {
+        const thoughts = mergeById(state.thoughts, delta.thoughts.map(rustToThought), delta.deleted.thoughts)
+        const remaining = new Set(thoughts.map(t => t.id))
+        const connections = mergeById(state.connections, delta.connections.map(rustToConnection), delta.deleted.connections)
+          .filter(c => remaining.has(c.fromThought) && remaining.has(c.toThought))
+        return {
+          thoughts,
+          connections,
+          clusters: mergeById(state.clusters, clusters, removedClusters),
+          changeSeq: delta.seq,
+        }
+      })
+      console.log(`Applied delta to seq ${delta.seq}: ${delta.thoughts.length} thoughts, ${delta.connections.length} connections, ${clusters.length} clusters changed`)
+      return true
+    } catch (err) {
+      console.error('Failed to apply graph delta:', err)
+      return false
+    }
+  },
 }))
 
 // Export a function to search thoughts (used by UI)
@@ -21,6 +21,9 @@ export interface MonitorInfo {
   width: number
   height: number
   is_primary: boolean
+  scale_factor: number
+  refresh_rate: number
+  orientation: 'landscape' | 'portrait' | 'landscape_flipped' | 'portrait_flipped'
 }
 
 export type DisplayMode = 'all' | number // 'all' for all monitors, or specific monitor ID
@@ -50,6 +53,7 @@ interface WallpaperState {
   setDriftAmount: (amount: number) => void
   setDisplayMode: (mode: DisplayMode, bounds?: { x: number; y: number; width: number; height: number } | null) => void
   refreshMonitors: () => Promise<void>
+  identifyMonitors: () => Promise<void>
 }
 
 export const useWallpaperStore = create<WallpaperState>()(
@@ -134,6 +138,14 @@ export const useWallpaperStore = create<WallpaperState>()(
         set({ displayMode: mode, selectedMonitorBounds: bounds || null })
       },
 
+      identifyMonitors: async () => {
+        try {
+          await invoke('identify_monitors')
+        } catch (err) {
+          console.error('Failed to identify monitors:', err)
+        }
+      },
+
       refreshMonitors: async () => {
         try {
           const monitors = await invoke<MonitorInfo[]>('get_monitors')
@@ -1,5 +1,7 @@
 // Shared types for The Mind
 
+import type { MonitorInfo } from './stores/wallpaperStore';
+
 // Categories for thoughts
 export type ThoughtCategory = 'work' | 'personal' | 'technical' | 'creative' | 'other';
 
@@ -21,10 +23,24 @@ export interface Thought {
   };
   createdAt: Date;
   lastReferenced: Date;
+  pinned?: boolean; // Anchor: never moved by layout, never hidden by filters
+  favorite?: boolean;
+  archived?: boolean;
   metadata?: Record<string, unknown>;
 }
 
 // Connection between two thoughts
+// How two thoughts relate; 'keyword-overlap' and 'shares-link' mark auto-connections
+export type ConnectionKind =
+  | 'related'
+  | 'supports'
+  | 'contradicts'
+  | 'caused-by'
+  | 'leads-to'
+  | 'example-of'
+  | 'keyword-overlap'
+  | 'shares-link';
+
 export interface Connection {
   id: string;
   fromThought: string;
@@ -32,6 +48,7 @@ export interface Connection {
   strength: number; // 0-1, visual line thickness
   reason: string;
   createdAt: Date;
+  kind?: ConnectionKind;
 }
 
 // A conversation session
@@ -57,6 +74,12 @@ export interface Cluster {
   color: string;
   thoughtCount: number;
   createdAt: Date;
+  keywords: string[];
+  // Set on sub-clusters of an oversized cluster (get_cluster_children)
+  parentId: string | null;
+  childCount: number;
+  // Made with create_cluster; kept (with its locked thoughts) when clusters are recomputed
+  manual: boolean;
 }
 
 // MCP Tool input types
@@ -91,6 +114,8 @@ export interface MindState {
   currentSession: Session | null;
   useSpatialLoading: boolean;
   totalThoughtCount: number;
+  // change_log seq the loaded graph is current to (null until a full load records it)
+  changeSeq: number | null;
 
   // Actions
   addThought: (thought: Thought) => void;
@@ -99,6 +124,89 @@ export interface MindState {
   setCurrentSession: (session: Session | null) => void;
   loadFromDatabase: () => Promise<void>;
   loadNearCamera: (x: number, y: number, z: number, radius?: number, limit?: number) => Promise<void>;
+  // Apply get_graph_delta since changeSeq; false when a full reload is needed instead
+  applyGraphDelta: () => Promise<boolean>;
+}
+
+// Payload of the mind://navigate event (from the-mind:// deep links)
+export type NavigateTarget =
+  | { kind: 'thought'; id: string }
+  | { kind: 'session'; id: string }
+  | { kind: 'search'; query: string };
+
+// Saved camera viewpoint (list_views / save_view; also the mind://view-saved payload)
+export interface CameraView {
+  id: string;
+  name: string;
+  position_x: number;
+  position_y: number;
+  position_z: number;
+  target_x: number;
+  target_y: number;
+  target_z: number;
+  zoom: number;
+  created_at: string;
+}
+
+// File, image or link attached to a thought (get_attachments / add_attachment)
+export interface Attachment {
+  id: string;
+  thought_id: string;
+  kind: 'file' | 'image' | 'url';
+  // Absolute path for files and images, the URL for links
+  path_or_url: string;
+  label: string | null;
+  created_at: string;
+}
+
+// Result of add_thoughts; skipped rows had an id that was already taken
+export interface BatchInsert {
+  inserted: number;
+  skipped: number;
+}
+
+// get_app_info result (mirrors AppInfo in app_info.rs)
+export interface AppInfo {
+  version: string;
+  git_commit: string;
+  build_timestamp: string;
+  schema_version: number;
+  database_schema_version: number | null;
+  platform: string;
+}
+
+// get_top_keywords entry; keyword is the display form, stem what thoughts are matched by
+export interface TopKeyword {
+  keyword: string;
+  stem: string;
+  doc_count: number;
+  importance_sum: number;
+  // Rising topics have more in the last 7 days than the 7 before
+  last_7_days: number;
+  previous_7_days: number;
+}
+
+// One get_timeline bucket; bucket_start is a local YYYY-MM-DD date
+export interface TimelineBucket {
+  bucket_start: string;
+  count: number;
+  thought_ids: string[];
+  counts: Record<string, number>;
+  representative: Thought | null;
+}
+
+// Saved wallpaper configuration (mirrors WallpaperConfig in wallpaper.rs)
+export type WallpaperConfig =
+  | { mode: 'all_monitors' }
+  | { mode: 'monitor'; id: number }
+  | { mode: 'monitors'; ids: number[] }
+  | { mode: 'bounds'; x: number; y: number; width: number; height: number };
+
+// Payload of the mind://wallpaper-auto-started event; fallback_from is set when the saved
+// monitors were missing and the primary monitor was used instead
+export interface WallpaperAutoStarted {
+  config: WallpaperConfig;
+  fallback_from: WallpaperConfig | null;
 }
 
 // Session-forge integration types
@@ -132,10 +240,32 @@ export interface ForgeDeadEndEntry {
   tags: string[];
 }
 
+// An entry (index set) or whole file (index null) that failed to parse
+export interface ForgeWarning {
+  file: string;
+  index: number | null;
+  message: string;
+}
+
+// Resolved session-forge directory and which of its files exist
+export interface ForgeStatus {
+  dir: string | null;
+  source: 'env' | 'setting' | 'default';
+  available: boolean;
+  journal: boolean;
+  decisions: boolean;
+  dead_ends: boolean;
+  warnings: ForgeWarning[];
+}
+
+// Search hits carry how many query keywords they matched
+export type ScoredForgeEntry<T> = T & { score: number };
+
 export interface ForgeContext {
-  journals: ForgeJournalEntry[];
-  decisions: ForgeDecisionEntry[];
-  dead_ends: ForgeDeadEndEntry[];
+  journals: ScoredForgeEntry<ForgeJournalEntry>[];
+  decisions: ScoredForgeEntry<ForgeDecisionEntry>[];
+  dead_ends: ScoredForgeEntry<ForgeDeadEndEntry>[];
+  warnings: ForgeWarning[];
 }
 
 // Category colors
@@ -146,3 +276,110 @@ export const CATEGORY_COLORS: Record<ThoughtCategory, string> = {
   creative: '#F59E0B',  // Orange
   other: '#6B7280',     // Gray
 };
+
+// Result of get_app_mode; editing UI should be disabled while read_only
+export interface AppMode {
+  read_only: boolean;
+  read_only_reason: 'flag' | 'setting' | null;
+}
+
+export interface RecentError {
+  at: string;
+  source: string;
+  message: string;
+}
+
+// Result of get_diagnostics (also printed by `--diagnose`)
+export interface Diagnostics {
+  app_version: string;
+  database_path: string;
+  database_size_bytes: number;
+  read_only: boolean;
+  table_counts: Record<string, number>;
+  pending_suggestions: number;
+  journal_mode: string;
+  wal: { size_bytes: number; pending_frames: number | null; healthy: boolean } | null;
+  forge: ForgeStatus;
+  monitors: MonitorInfo[];
+  wallpaper_active: boolean;
+  last_backup: string | null;
+  recent_errors: RecentError[];
+}
+
+// One search_everything hit; score is 0-1, higher is better
+export interface SearchResult {
+  kind: 'thought' | 'session' | 'connection';
+  id: string;
+  snippet: string;
+  score: number;
+}
+
+export interface SearchHit {
+  thought: Thought;
+  // [start, end) of each match in thought.content, in string indices
+  matches: [number, number][];
+  // Content around the first match, matches wrapped in **
+  snippet: string;
+  // Near-match for a misspelled query; matches/snippet are for suggestion
+  fuzzy: boolean;
+  suggestion: string | null;
+}
+
+// get_similar_thoughts result; score is 0-1, higher is more alike
+export interface SimilarThought {
+  thought: Thought;
+  score: number;
+  basis: 'embedding' | 'keywords';
+}
+
+// A proposed connection awaiting accept_suggestion / reject_suggestion
+export interface ConnectionSuggestion {
+  connection: Connection;
+  from_content: string;
+  to_content: string;
+}
+
+// decay_connection_strength result
+export interface ConnectionDecay {
+  decayed: number;
+  // Automatic connections below prune_threshold (prune_connections with autoOnly)
+  prune_candidates: number;
+  prune_threshold: number;
+}
+
+// get_overconnected_thoughts result
+export interface OverconnectedThought {
+  thought: Thought;
+  connections: number;
+  auto_connections: number;
+}
+
+// get_cluster_detail result; thoughts are the cluster's members, most important first
+export interface ClusterDetail {
+  cluster: Cluster;
+  thoughts: Thought[];
+}
+
+// get_nearest_cluster / get_cluster_for_thought result; distance is to the cluster centroid
+export interface ClusterMatch {
+  cluster: Cluster;
+  distance: number;
+  // The thought belongs to the cluster rather than just being nearest to it
+  member: boolean;
+}
+
+// run_maintenance result
+export interface MaintenanceStep {
+  name: string;
+  duration_ms: number;
+  detail: string;
+}
+
+export interface MaintenanceReport {
+  steps: MaintenanceStep[];
+  size_before_bytes: number;
+  size_after_bytes: number;
+  wal_before_bytes: number;
+  wal_after_bytes: number;
+  vacuumed: boolean;
+}